@@ -97,6 +97,11 @@ initImageCache();
 // Use a promise cache to prevent concurrent processing of the same image
 const processingCache = new Map<string, Promise<string>>();
 
+// convertFileSrc 生成的地址：asset://localhost/...（macOS/Linux）或 http(s)://asset.localhost/...（Windows/Android）
+function isLocalAssetUrl(url: string) {
+  return /^(asset:|https?:\/\/asset\.localhost\/)/.test(url);
+}
+
 /**
  * Cache an image URL to local filesystem and return the local path.
  * @param url The remote URL of the image
@@ -305,7 +310,8 @@ export function useCachedImage(url: MaybeRef<string | undefined>, cacheKey?: May
         return;
       }
 
-      if (isCacheDisabled.value) {
+      // 本地 asset 协议地址（如缩略图）已在磁盘上，无需再缓存
+      if (isCacheDisabled.value || isLocalAssetUrl(newUrl)) {
         cachedSrc.value = newUrl;
         return;
       }
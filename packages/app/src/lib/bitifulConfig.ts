@@ -3,6 +3,25 @@ import { load } from '@tauri-apps/plugin-store'
 import { isTauri } from '@/constants'
 import { isLocalMode } from './serviceRouter'
 
+// S3 兼容服务商，决定寻址方式、默认 region 等差异
+export type S3Provider = 'bitiful' | 'r2' | 'oss' | 'minio' | 'b2'
+// 空字符串表示使用服务商默认寻址方式
+export type S3AddressingStyle = '' | 'path' | 'virtual_hosted'
+
+export const s3ProviderOptions: { text: string, value: S3Provider }[] = [
+  { text: 'Bitiful', value: 'bitiful' },
+  { text: 'Cloudflare R2', value: 'r2' },
+  { text: '阿里云 OSS', value: 'oss' },
+  { text: 'MinIO', value: 'minio' },
+  { text: 'Backblaze B2', value: 'b2' },
+]
+
+export const s3AddressingStyleOptions: { text: string, value: S3AddressingStyle }[] = [
+  { text: '服务商默认', value: '' },
+  { text: 'Path-style', value: 'path' },
+  { text: 'Virtual-hosted', value: 'virtual_hosted' },
+]
+
 export interface BitifulConfig {
   accessKey: string
   secretKey: string
@@ -14,6 +33,9 @@ export interface BitifulConfig {
   albumStyle: string
   region: string
   endpoint: string
+  provider: S3Provider
+  addressingStyle: S3AddressingStyle
+  pathPrefix: string
 }
 
 export const defaultBitifulConfig: BitifulConfig = {
@@ -27,6 +49,18 @@ export const defaultBitifulConfig: BitifulConfig = {
   albumStyle: '',
   region: 'cn-east-1',
   endpoint: 'https://s3.bitiful.net',
+  provider: 'bitiful',
+  addressingStyle: '',
+  pathPrefix: '',
+}
+
+// 原生 S3 命令的 profile 参数；未设置的项由 Rust 端按服务商默认值补全
+export function s3Profile(config?: Partial<BitifulConfig> | null) {
+  return {
+    provider: config?.provider || 'bitiful',
+    addressingStyle: config?.addressingStyle || null,
+    pathPrefix: config?.pathPrefix || null,
+  }
 }
 
 export function mergeBitifulConfig(...configs: Array<Partial<BitifulConfig> | null | undefined>): BitifulConfig {
@@ -382,10 +382,72 @@ export function filePath2Name(filePath: string) {
 
 
 export function getFileInfo(filePath: string) {
-  return invoke<{ last_modified: number, creation_time: number, width: number, height: number, file_type: string, md5?: string, size: number }>('get_file_info', { filePath })
+  return invoke<{
+    last_modified: number
+    creation_time: number
+    width: number
+    height: number
+    file_type: string
+    md5?: string
+    size: number
+    orientation?: number | null
+    taken_at?: number | null
+    make?: string | null
+    model?: string | null
+    gps?: { latitude: number, longitude: number, altitude?: number | null } | null
+    duration?: number | null
+  }>('get_file_info', { filePath })
 }
 
 
+/**
+ * 计算图片感知哈希，用于相似照片聚类；不支持的格式返回 undefined
+ */
+export async function getPhash(filePath: string) {
+  try {
+    return await invoke<string>('compute_phash', { filePath })
+  } catch (e) {
+    console.warn('Failed to compute phash:', e)
+    return undefined
+  }
+}
+
+interface ThumbnailCacheUsage {
+  bytes: number
+  count: number
+  budgetBytes: number
+}
+
+/**
+ * 获取本地图片缩略图（原生生成 JPEG / 有损 WebP 并按 md5 缓存），url 走 asset 协议
+ */
+export async function getLocalThumbnail(filePath: string, options: {
+  md5?: string
+  size?: 256 | 512 | 1024
+  format?: 'jpeg' | 'webp'
+} = {}) {
+  const thumb = await invoke<{
+    path: string
+    width: number
+    height: number
+    bytes: number
+    hit: boolean
+  }>('get_thumbnail', { filePath, ...options })
+  return { ...thumb, url: convertFileSrc(thumb.path) }
+}
+
+export function getThumbnailCacheUsage() {
+  return invoke<ThumbnailCacheUsage>('thumbnail_cache_usage')
+}
+
+export function clearThumbnailCache() {
+  return invoke<ThumbnailCacheUsage>('thumbnail_cache_clear')
+}
+
+export function setThumbnailCacheBudget(budgetBytes: number) {
+  return invoke<ThumbnailCacheUsage>('thumbnail_cache_set_budget', { budgetBytes })
+}
+
 /**
  * 尝试获取原生文件信息（尺寸、修改时间、MD5等）
  * 优先使用 Rust Bridge，失败则降级到 fs.lstat
@@ -398,7 +460,9 @@ async function getNativeFileInfo(filePath: string) {
     fileType: '',
     md5: '',
     size: 0,
-    creationTime: 0
+    creationTime: 0,
+    takenAt: 0,
+    gps: null as GpsPosition | null,
   }
 
   try {
@@ -406,6 +470,7 @@ async function getNativeFileInfo(filePath: string) {
     if (info) {
       if (info.last_modified > 0) result.lastModified = info.last_modified
       if (info.creation_time > 0) result.creationTime = info.creation_time
+      if (info.taken_at && info.taken_at > 0) result.takenAt = info.taken_at
       if (info.width > 0 && info.height > 0) {
         result.width = info.width
         result.height = info.height
@@ -413,6 +478,7 @@ async function getNativeFileInfo(filePath: string) {
       result.fileType = info.file_type
       if (info.md5) result.md5 = info.md5
       if (info.size) result.size = info.size
+      if (info.gps) result.gps = info.gps
     }
   } catch (e) {
     console.error('Failed to get file info via Rust:', e)
@@ -470,6 +536,7 @@ export async function parseNativeImageFileUploadInfo(filePath: string) {
   try {
     // 1. 获取基础文件信息
     const nativeInfo = await getNativeFileInfo(filePath)
+    const phash = await getPhash(filePath)
 
     // 2. 读取文件内容
     const _file = await readNativeFile(filePath)
@@ -500,6 +567,9 @@ export async function parseNativeImageFileUploadInfo(filePath: string) {
       file,
       name: originalName,
       md5: nativeInfo.md5,
+      phash,
+      gps: nativeInfo.gps || undefined,
+      takenAt: nativeInfo.takenAt || undefined,
       width: nativeInfo.width,
       height: nativeInfo.height,
       fileType: nativeInfo.fileType,
@@ -561,6 +631,7 @@ export async function parseNativeVideoFileUploadInfo(filePath: string) {
     return {
       file,
       md5: nativeInfo.md5,
+      takenAt: nativeInfo.takenAt || undefined,
       width,
       height,
       fileType,
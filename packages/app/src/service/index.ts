@@ -2,8 +2,9 @@ import { api } from "@/lib/request";
 import ky from "ky";
 import { isTauri } from "@/constants";
 import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 import { isNativeUploadTokenEnabled } from "@/composables/useUploadTokenConfig";
-import { getBitifulConfigLocal } from "@/lib/bitifulConfig";
+import { getBitifulConfigLocal, s3Profile } from "@/lib/bitifulConfig";
 import { showConfirmDialog } from "vant";
 import router from "@/router";
 import { isLocalMode } from "@/lib/serviceRouter";
@@ -53,7 +54,7 @@ export function updatePassword(data: { username: string, operator: string, token
   return api.post<ServerResponse>('user/password', { json: data }).json()
 }
 
-export async function getUploadUrl(key: string) {
+export async function getUploadUrl(key: string, md5?: string) {
   // S3 upload works in both modes via native command
   if (isTauri && (isLocalMode() || isNativeUploadTokenEnabled.value)) {
     const config = await getBitifulConfigLocal();
@@ -78,6 +79,9 @@ export async function getUploadUrl(key: string) {
       endpoint: config.endpoint,
       accessKey: config.accessKey,
       secretKey: config.secretKey,
+      // 签入 Content-MD5，upload_file 上传时需带上同一 md5
+      md5: md5 || null,
+      profile: s3Profile(config),
     }).then((v: any) => {
       return v.url
     })
@@ -146,6 +150,141 @@ export function uploadFile(file: File, url: string, onProgress?: (progress: numb
   })
 }
 
+// 原生整文件上传；带上服务商配置，与 getUploadUrl 签入 Content-MD5 的判断保持一致
+export async function uploadNativeFile(key: string, path: string, url: string, md5?: string) {
+  const config = await getBitifulConfigLocal();
+  await invoke('upload_file', {
+    key,
+    path,
+    url,
+    md5: md5 || null,
+    profile: s3Profile(config),
+  })
+}
+
+// 超过该大小的原生文件走分片上传，中断后可从最后完成的分片续传
+export const MULTIPART_UPLOAD_THRESHOLD = 64 * 1024 * 1024
+
+// 原生分片上传，进度同样通过 upload://progress 推送；未配置本地 S3 参数时返回 false，由调用方回退到普通上传
+export async function uploadNativeFileMultipart(key: string, path: string) {
+  const config = await getBitifulConfigLocal();
+  if (!config || !config.bucket || !config.region || !config.endpoint || !config.accessKey || !config.secretKey) {
+    return false
+  }
+  await invoke('upload_file_multipart', {
+    key,
+    path,
+    bucket: config.bucket,
+    region: config.region,
+    endpoint: config.endpoint,
+    accessKey: config.accessKey,
+    secretKey: config.secretKey,
+    profile: s3Profile(config),
+  })
+  return true
+}
+
+export type UploadJobState = 'pending' | 'running' | 'paused' | 'done' | 'failed' | 'cancelled'
+
+export interface UploadJob {
+  id: string
+  objectKey: string
+  sourcePath: string
+  entityType: 'photo' | 'drive_file' | null
+  entityId: string | null
+  meta: any
+  state: UploadJobState
+  attempts: number
+  lastError: string | null
+  fileSize: number | null
+  nextAttemptAt: string | null
+  createdAt: string
+  updatedAt: string
+}
+
+let uploadQueueStarted = false
+
+// 原生上传队列：任务持久化在本地库，应用重启后继续；进度与状态通过 upload://progress、upload://state 推送
+export async function startUploadQueue(concurrency?: number) {
+  const config = await getBitifulConfigLocal();
+  if (!isTauri || !config || !config.bucket || !config.region || !config.endpoint || !config.accessKey || !config.secretKey) {
+    return false
+  }
+  await invoke('upload_queue_start', {
+    bucket: config.bucket,
+    region: config.region,
+    endpoint: config.endpoint,
+    accessKey: config.accessKey,
+    secretKey: config.secretKey,
+    concurrency: concurrency ?? null,
+    profile: s3Profile(config),
+  })
+  uploadQueueStarted = true
+  return true
+}
+
+// 本地模式下队列已按本机 S3 配置启动，本机文件可交给队列上传
+export function isUploadQueueReady() {
+  return isTauri && isLocalMode() && uploadQueueStarted
+}
+
+export function enqueueUploads(jobs: { objectKey: string, sourcePath: string, entityType?: 'photo' | 'drive_file', entityId?: string, meta?: any }[]) {
+  return invoke<{ code: number, data: UploadJob[] }>('upload_queue_enqueue', { jobs }).then(v => v.data)
+}
+
+// 经上传队列上传本机照片/视频：上传完成后原生端在同一事务中写入照片记录，返回新建的照片
+export async function queuePhotoUpload(body: UploadInfo, sourcePath: string) {
+  const unlisten: UnlistenFn[] = []
+  let settle: { resolve: (row: any) => void, reject: (err: Error) => void }
+  const finished = new Promise<any>((resolve, reject) => { settle = { resolve, reject } })
+  // 先订阅再入队，避免小文件在订阅前就已完成
+  unlisten.push(await listen<{ key: string, entity: any }>('upload://done', (event) => {
+    if (event.payload.key === body.key) settle.resolve(event.payload.entity)
+  }))
+  unlisten.push(await listen<UploadJob>('upload://state', (event) => {
+    const job = event.payload
+    if (job.objectKey !== body.key) return
+    if (job.state === 'failed') settle.reject(new Error(job.lastError || '上传失败'))
+    if (job.state === 'cancelled') settle.reject(new Error('上传已取消'))
+  }))
+  try {
+    const [job] = await enqueueUploads([{
+      objectKey: body.key,
+      sourcePath,
+      entityType: 'photo',
+      meta: local.photoRecord({ ...body, filePath: sourcePath }),
+    }])
+    // 同一 key 复用了之前失败或暂停的任务：重新开始
+    if (job.state === 'failed' || job.state === 'paused') {
+      await resumeUploads([job.id])
+    }
+    return await local.enrichPhotoUrls(await finished)
+  } finally {
+    unlisten.forEach(fn => fn())
+  }
+}
+
+export function getUploadJobs(states?: UploadJobState[]) {
+  return invoke<{ code: number, data: UploadJob[] }>('upload_queue_list', { states: states ?? null }).then(v => v.data)
+}
+
+// 不传 ids 时作用于全部任务
+export function pauseUploads(ids?: string[]) {
+  return invoke<{ code: number, data: UploadJob[] }>('upload_queue_pause', { ids: ids ?? null }).then(v => v.data)
+}
+
+export function resumeUploads(ids?: string[]) {
+  return invoke<{ code: number, data: UploadJob[] }>('upload_queue_resume', { ids: ids ?? null }).then(v => v.data)
+}
+
+export function cancelUploads(ids?: string[]) {
+  return invoke<{ code: number, data: UploadJob[] }>('upload_queue_cancel', { ids: ids ?? null }).then(v => v.data)
+}
+
+export function clearFinishedUploads() {
+  return invoke<{ code: number, data: number }>('upload_queue_clear').then(v => v.data)
+}
+
 export function getPhotos(page: number, pageSize: number, options: {
   likedMode?: boolean,
   albumId?: string,
@@ -172,6 +311,27 @@ export function getPhotos(page: number, pageSize: number, options: {
     })
 }
 
+export interface PhotoPage {
+  list: Photo[]
+  /** 下一页的键集游标；没有时按页码继续 */
+  nextCursor?: string
+}
+
+/** 照片分页：本地模式返回 `nextCursor`，传回 `cursor` 即可避免深翻页的 OFFSET 扫描 */
+export function getPhotoPage(page: number, pageSize: number, options: {
+  likedMode?: boolean,
+  albumId?: string,
+  isDelete?: boolean,
+  type?: string,
+  startDate?: string,
+  endDate?: string,
+  cursor?: string
+}): Promise<PhotoPage> {
+  if (isLocalMode()) return local.getPhotoPage(page, pageSize, options)
+  // 服务端接口只支持页码分页
+  return getPhotos(page, pageSize, options).then(list => ({ list }))
+}
+
 export function updateDescription(id: string, description: string) {
   if (isLocalMode()) return local.updateDescription(id, description)
   return api.put<ServerResponse<Photo>>('file/photo/update/description', {
@@ -366,6 +526,17 @@ export function restorePhotos(ids: string[]) {
   })
 }
 
+// 彻底删除与保留期清理目前只在本地模式提供
+export function purgePhotos(ids: string[]) {
+  if (isLocalMode()) return local.purgePhotos(ids)
+  return Promise.reject(new Error('当前模式不支持彻底删除'))
+}
+
+export function purgeExpiredPhotos(retentionDays?: number) {
+  if (isLocalMode()) return local.purgeExpiredPhotos(retentionDays)
+  return Promise.resolve(undefined)
+}
+
 export function getPhotoListInfo(options: {
   likedMode?: boolean,
   albumId?: string,
@@ -1,7 +1,7 @@
 import { invoke } from '@tauri-apps/api/core'
 import { showConfirmDialog } from 'vant'
 import router from '@/router'
-import { getBitifulConfigLocal } from '@/lib/bitifulConfig'
+import { getBitifulConfigLocal, s3Profile } from '@/lib/bitifulConfig'
 import { buildFileUrl } from './fileUrl'
 import type { DriveFileItem, DriveBreadcrumb } from '../driveFile'
 
@@ -87,6 +87,7 @@ export async function purgeDriveFile(id: string) {
     endpoint: config?.endpoint || null,
     accessKey: config?.accessKey || null,
     secretKey: config?.secretKey || null,
+    profile: s3Profile(config),
   })
 }
 
@@ -99,6 +100,7 @@ export async function purgeAllDriveFiles() {
     endpoint: config?.endpoint || null,
     accessKey: config?.accessKey || null,
     secretKey: config?.secretKey || null,
+    profile: s3Profile(config),
   })
 }
 
@@ -140,6 +142,7 @@ async function nativePresignedUrl(key: string, expiresSeconds: number) {
     accessKey: config.accessKey,
     secretKey: config.secretKey,
     expiresSeconds,
+    profile: s3Profile(config),
   })
   return result.url as string
 }
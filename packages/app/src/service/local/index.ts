@@ -2,10 +2,11 @@ import { invoke } from '@tauri-apps/api/core'
 import dayjs from 'dayjs'
 import { MEMORIAL_PRESET_COVERS } from '@/constants/memorialCovers'
 import { normalizeMemorial } from '@/lib/memorial'
+import { getBitifulConfigLocal, s3Profile } from '@/lib/bitifulConfig'
 import { randomUUID } from '@/lib/util'
 import { buildCoverUrl, buildFileUrl, buildPreviewUrl } from './fileUrl'
 
-async function enrichPhotoUrls(row: any): Promise<any> {
+export async function enrichPhotoUrls(row: any): Promise<any> {
   const photo = mapPhoto(row)
   const s3Key = photo.key
   if (s3Key) {
@@ -128,7 +129,8 @@ export async function getUploadUrl(key: string) {
   throw new Error('Use native upload token in offline mode')
 }
 
-export async function addFileInfo(body: any) {
+// db_photo_add 的参数；上传队列任务把它作为 meta，上传完成时由原生端写入
+export function photoRecord(body: any) {
   const data = JSON.stringify({
     key: body.key,
     name: body.name,
@@ -147,18 +149,29 @@ export async function addFileInfo(body: any) {
     liveVideoKey: body.liveVideoKey || '',
     liveContentId: body.liveContentId || '',
     liveDuration: body.liveDuration ?? 0,
+    gps: body.gps || undefined,
+    // 本机源文件路径：网格优先用它生成本地缩略图，其他设备上读取失败时回退远程封面
+    localPath: body.filePath || undefined,
   })
-  const added = await invoke<any>('db_photo_add', {
-    id: randomUUID(),
+  return {
     isLiked: body.likedMode || false,
     type: body.type || 'image/jpeg',
     lastModified: body.lastModified ? new Date(body.lastModified).toISOString() : new Date().toISOString(),
     md5: body.md5 || '',
+    // 原生元数据的拍摄时间，优先于 lastModified 参与时间线排序
+    takenAt: body.takenAt ? new Date(body.takenAt).toISOString() : undefined,
+    phash: body.phash || undefined,
     data,
-  })
-  if (body.albumId?.length) {
-    await invoke('db_photo_set_albums', { photoId: added.id || added._id, albumIds: body.albumId })
-    added.albumId = body.albumId
+    albumIds: body.albumId || [],
+  }
+}
+
+export async function addFileInfo(body: any) {
+  const { albumIds, ...record } = photoRecord(body)
+  const added = await invoke<any>('db_photo_add', { id: randomUUID(), ...record })
+  if (albumIds.length) {
+    await invoke('db_photo_set_albums', { photoId: added.id || added._id, albumIds })
+    added.albumId = albumIds
   }
   return enrichPhotoUrls(added)
 }
@@ -179,6 +192,8 @@ export async function updateFileInfo(body: any) {
     liveVideoKey: body.liveVideoKey || '',
     liveContentId: body.liveContentId || '',
     liveDuration: body.liveDuration ?? 0,
+    gps: body.gps || undefined,
+    localPath: body.filePath || undefined,
   })
 
   return invoke<any>('db_photo_update', {
@@ -187,23 +202,28 @@ export async function updateFileInfo(body: any) {
     type: body.type,
     lastModified: body.lastModified ? new Date(body.lastModified).toISOString() : undefined,
     md5: body.md5,
+    takenAt: body.takenAt ? new Date(body.takenAt).toISOString() : undefined,
+    phash: body.phash,
     data,
   }).then(enrichPhotoUrls)
 }
 
 // ==================== Photos ====================
 
-export async function getPhotos(page: number, pageSize: number, options: {
+export async function getPhotoPage(page: number, pageSize: number, options: {
   likedMode?: boolean,
   albumId?: string,
   isDelete?: boolean,
   type?: string,
   startDate?: string,
-  endDate?: string
+  endDate?: string,
+  cursor?: string
 }) {
   const result = await invoke<any>('db_photo_list', {
     page,
     pageSize,
+    // 有游标时走键集分页，page 被忽略
+    cursor: options.cursor || undefined,
     likedMode: options.likedMode,
     albumId: options.albumId,
     isDelete: options.isDelete,
@@ -212,8 +232,20 @@ export async function getPhotos(page: number, pageSize: number, options: {
     startDate: options.startDate || undefined,
     endDate: options.endDate || undefined,
   })
-  const mapped = await Promise.all((result.data || []).map(enrichPhotoUrls))
-  return mapped
+  const list = await Promise.all((result.data || []).map(enrichPhotoUrls))
+  return { list, nextCursor: (result.nextCursor as string | null) || undefined }
+}
+
+export async function getPhotos(page: number, pageSize: number, options: {
+  likedMode?: boolean,
+  albumId?: string,
+  isDelete?: boolean,
+  type?: string,
+  startDate?: string,
+  endDate?: string
+}) {
+  const { list } = await getPhotoPage(page, pageSize, options)
+  return list
 }
 
 export async function updateDescription(id: string, description: string) {
@@ -264,6 +296,34 @@ export async function restorePhotos(ids: string[]) {
   return { code: 0 }
 }
 
+async function s3PurgeParams() {
+  const config = await getBitifulConfigLocal()
+  return {
+    bucket: config?.bucket || null,
+    region: config?.region || null,
+    endpoint: config?.endpoint || null,
+    accessKey: config?.accessKey || null,
+    secretKey: config?.secretKey || null,
+    profile: s3Profile(config),
+  }
+}
+
+// 彻底删除：回收站中的照片连同 S3 对象（含 Live 视频）一起清理
+export async function purgePhotos(ids: string[]) {
+  return invoke<{ code: number, data: { id: string, ok: boolean, error?: string }[], message: string }>('db_photo_purge', {
+    ids,
+    ...(await s3PurgeParams()),
+  })
+}
+
+// 清理超过保留期（默认 30 天）的回收站照片
+export async function purgeExpiredPhotos(retentionDays?: number) {
+  return invoke<{ code: number, data: { purged: number }, message: string }>('db_photo_purge_expired', {
+    retentionDays: retentionDays ?? null,
+    ...(await s3PurgeParams()),
+  })
+}
+
 export async function getPhotoListInfo(options: {
   likedMode?: boolean,
   albumId?: string,
@@ -293,6 +353,23 @@ export async function checkDuplicateByMd5(md5: string) {
 
 // ==================== Albums ====================
 
+/**
+ * 智能相册规则：保存在相册 data.smart 中，照片列表与数量按规则实时计算
+ */
+export type SmartAlbumRule =
+  | { field: 'type', value: 'image' | 'video' | 'live' }
+  | { field: 'liked', value: boolean }
+  | { field: 'year', value: number }
+  | { field: 'takenAt', op: 'gt' | 'gte' | 'lt' | 'lte', value: string }
+  | { field: 'size', op: 'gt' | 'gte' | 'lt' | 'lte', value: number }
+  | { field: 'location', value: { latitude: number, longitude: number, radiusKm: number } }
+  | { field: 'name', value: string }
+
+export interface SmartAlbumRules {
+  match?: 'all' | 'any'
+  rules: SmartAlbumRule[]
+}
+
 export async function getAlbums() {
   const result = await invoke<any>('db_album_list')
   const data = result.data || { large: [], small: [] }
@@ -310,7 +387,7 @@ export async function getAlbums() {
   return { large, small }
 }
 
-export async function createAlbum(name: string, description: string, isLarge: boolean, tags: string[], folderId?: string | null) {
+export async function createAlbum(name: string, description: string, isLarge: boolean, tags: string[], folderId?: string | null, smart?: SmartAlbumRules) {
   const now = new Date().toISOString()
   const result = await invoke<any>('db_album_create', {
     name,
@@ -326,6 +403,7 @@ export async function createAlbum(name: string, description: string, isLarge: bo
       createdAt: now,
       updatedAt: now,
       ...(folderId ? { folderId } : {}),
+      ...(smart ? { smart } : {}),
     }),
   })
   return mapAlbum(result)
@@ -337,6 +415,8 @@ export async function updateAlbum(id: string, options: {
   isLarge: boolean,
   tags: string[]
   folderId?: string | null
+  // 不传则保留原规则，传 null 转为普通相册
+  smart?: SmartAlbumRules | null
 }) {
   const data = JSON.stringify({
     name: options.name,
@@ -346,6 +426,7 @@ export async function updateAlbum(id: string, options: {
     ...(options.folderId !== undefined
       ? (options.folderId ? { folderId: options.folderId } : { folderId: null })
       : {}),
+    ...(options.smart !== undefined ? { smart: options.smart } : {}),
   })
   const result = await invoke<any>('db_album_update', { id, data })
   return mapAlbum(result)
@@ -531,6 +612,26 @@ export async function getMemorialCovers() {
   return [...MEMORIAL_PRESET_COVERS]
 }
 
+// ==================== Search ====================
+
+export type SearchHitType = 'photo' | 'album' | 'drive_file' | 'todo' | 'memorial' | 'asset'
+
+export interface SearchHit {
+  type: SearchHitType
+  id: string
+  title: string
+  field: string
+  snippet: string
+  /** [start, end) 高亮区间，JS 字符串下标 */
+  highlights: [number, number][]
+  score: number
+}
+
+export async function search(query: string, options: { types?: SearchHitType[], limit?: number } = {}) {
+  const result = await invoke<{ code: number, data: SearchHit[] }>('db_search', { query, ...options })
+  return result.data
+}
+
 // ==================== Check Update ====================
 
 export async function checkUpdate(params: {
@@ -22,6 +22,9 @@ interface Photo {
   liveVideoKey?: string
   liveContentId?: string
   liveDuration?: number
+  md5?: string
+  /** 导入时的本机源文件路径，用于生成本地缩略图 */
+  localPath?: string
 }
 
 type ServerResponse<T = any> = {
@@ -60,6 +63,12 @@ interface InfoItem {
   label?: string
 }
 
+interface GpsPosition {
+  latitude: number
+  longitude: number
+  altitude?: number | null
+}
+
 interface FileInfoItem {
   file: File
   objectUrl: string
@@ -68,6 +77,10 @@ interface FileInfoItem {
   date: Date
   exif: any
   md5?: string
+  phash?: string
+  gps?: GpsPosition
+  /** 原生元数据读出的拍摄时间（毫秒） */
+  takenAt?: number
   repeat?: boolean
   width?: number
   height?: number
@@ -96,6 +109,9 @@ interface UploadInfo {
   type: string
   likedMode: boolean
   md5?: string
+  phash?: string
+  gps?: GpsPosition
+  takenAt?: number
   albumId?: string[]
   filePath?: string
   isLive?: boolean
@@ -3,6 +3,7 @@ use log::info;
 use serde::{Deserialize, Serialize};
 use futures_util::StreamExt;
 use std::io::Write;
+use crate::error::AppError;
 use crate::command::common::calculate_md5;
 
 #[cfg(target_os = "android")]
@@ -16,11 +17,11 @@ pub struct ProgressPayload {
 }
 
 #[tauri::command]
-pub async fn download_apk(app_handle: tauri::AppHandle, url: String, version: String, md5: Option<String>) -> Result<String, String> {
-    let cache_dir = app_handle.path().app_cache_dir().map_err(|e| e.to_string())?;
+pub async fn download_apk(app_handle: tauri::AppHandle, url: String, version: String, md5: Option<String>) -> Result<String, AppError> {
+    let cache_dir = app_handle.path().app_cache_dir()?;
     
     if !cache_dir.exists() {
-        std::fs::create_dir_all(&cache_dir).map_err(|e| e.to_string())?;
+        std::fs::create_dir_all(&cache_dir)?;
     }
     
     let file_name = format!("echo-trails-{}.apk", version);
@@ -57,10 +58,10 @@ pub async fn download_apk(app_handle: tauri::AppHandle, url: String, version: St
     }
 
     let client = reqwest::Client::new();
-    let res = client.get(&url).send().await.map_err(|e| e.to_string())?;
+    let res = client.get(&url).send().await?;
     let total_size = res.content_length().unwrap_or(0);
 
-    let mut file = std::fs::File::create(&file_path).map_err(|e| e.to_string())?;
+    let mut file = std::fs::File::create(&file_path)?;
     let mut stream = res.bytes_stream();
     let mut downloaded: u64 = 0;
 
@@ -71,13 +72,13 @@ pub async fn download_apk(app_handle: tauri::AppHandle, url: String, version: St
                 // 下载中断：清理半截文件，避免残留坏缓存导致后续无法重新下载
                 drop(file);
                 let _ = std::fs::remove_file(&file_path);
-                return Err(e.to_string());
+                return Err(e.into());
             }
         };
         if let Err(e) = file.write_all(&chunk) {
             drop(file);
             let _ = std::fs::remove_file(&file_path);
-            return Err(e.to_string());
+            return Err(e.into());
         }
         downloaded += chunk.len() as u64;
 
@@ -94,7 +95,7 @@ pub async fn download_apk(app_handle: tauri::AppHandle, url: String, version: St
              let current_md5 = calculate_md5(&file_path)?;
              if !current_md5.eq_ignore_ascii_case(expected_md5) {
                  let _ = std::fs::remove_file(&file_path);
-                 return Err(format!("MD5 mismatch: expected {}, got {}", expected_md5, current_md5));
+                 return Err(AppError::conflict(format!("MD5 mismatch: expected {}, got {}", expected_md5, current_md5)));
              }
         }
     }
@@ -103,44 +104,42 @@ pub async fn download_apk(app_handle: tauri::AppHandle, url: String, version: St
 }
 
 #[tauri::command]
-pub async fn open_apk(_app_handle: tauri::AppHandle, file_path: String) -> Result<(), String> {
+pub async fn open_apk(_app_handle: tauri::AppHandle, file_path: String) -> Result<(), AppError> {
     info!("Opening APK from: {}", file_path);
     #[cfg(target_os = "android")]
     {
         let ctx = ndk_context::android_context();
-        let vm = unsafe { jni::JavaVM::from_raw(ctx.vm().cast()) }.map_err(|e| e.to_string())?;
-        let mut env = vm.attach_current_thread().map_err(|e| e.to_string())?;
+        let vm = unsafe { jni::JavaVM::from_raw(ctx.vm().cast()) }?;
+        let mut env = vm.attach_current_thread()?;
         
         // We need the context object. ndk_context provides it as a raw pointer.
         let context = unsafe { jni::objects::JObject::from_raw(ctx.context().cast()) };
         
         // Use AppHelper
         // Use ClassLoader to find the class
-        let class_loader = env.call_method(&context, "getClassLoader", "()Ljava/lang/ClassLoader;", &[])
-            .map_err(|e| e.to_string())?
-            .l()
-            .map_err(|e| e.to_string())?;
+        let class_loader = env.call_method(&context, "getClassLoader", "()Ljava/lang/ClassLoader;", &[])?
+            .l()?;
         
-        let class_name = env.new_string("com/echo_trails/app/AppHelper").map_err(|e| e.to_string())?;
+        let class_name = env.new_string("com/echo_trails/app/AppHelper")?;
         
         let class_obj = env.call_method(
             class_loader, 
             "loadClass", 
             "(Ljava/lang/String;)Ljava/lang/Class;", 
             &[JValue::Object(&class_name)]
-        ).map_err(|e| e.to_string())?.l().map_err(|e| e.to_string())?;
+        )?.l()?;
 
         let class: jni::objects::JClass = class_obj.into();
         
         // Convert file_path to JString
-        let uri_str = env.new_string(&file_path).map_err(|e| e.to_string())?;
+        let uri_str = env.new_string(&file_path)?;
         
         env.call_static_method(
             class,
             "installApk",
             "(Landroid/content/Context;Ljava/lang/String;)V",
             &[JValue::Object(&context), JValue::Object(&uri_str)]
-        ).map_err(|e| e.to_string())?;
+        )?;
         
         Ok(())
     }
@@ -148,7 +147,7 @@ pub async fn open_apk(_app_handle: tauri::AppHandle, file_path: String) -> Resul
     #[cfg(not(target_os = "android"))]
     {
         use tauri_plugin_opener::OpenerExt;
-        _app_handle.opener().open_path(file_path, None::<&str>).map_err(|e: tauri_plugin_opener::Error| e.to_string())?;
+        _app_handle.opener().open_path(file_path, None::<&str>).map_err(|e: tauri_plugin_opener::Error| AppError::io(e.to_string()))?;
         Ok(())
     }
 }
@@ -275,11 +274,10 @@ async fn is_download_available(client: &reqwest::Client, download_url: &str) ->
 }
 
 #[tauri::command]
-pub async fn check_update(current_version: String, platform: String) -> Result<UpdateInfo, String> {
+pub async fn check_update(current_version: String, platform: String) -> Result<UpdateInfo, AppError> {
     let client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(3))
-        .build()
-        .map_err(|e| e.to_string())?;
+        .build()?;
 
     let mut found_latest: Option<VersionInfo> = None;
 
@@ -2,6 +2,9 @@ use serde::Serialize;
 use std::fs;
 use std::io::Write;
 
+use crate::command::metadata::GpsPosition;
+use crate::error::AppError;
+
 #[cfg(target_os = "android")]
 use jni::objects::JValue;
 
@@ -14,6 +17,14 @@ pub struct FileInfo {
     height: u32,
     file_type: Option<String>,
     md5: Option<String>,
+    orientation: Option<u16>,
+    /// 拍摄时间（毫秒），来自 DateTimeOriginal / QuickTime creationdate
+    taken_at: Option<i64>,
+    make: Option<String>,
+    model: Option<String>,
+    gps: Option<GpsPosition>,
+    /// 视频时长（毫秒）
+    duration: Option<i64>,
 }
 
 #[derive(Serialize, Default)]
@@ -25,7 +36,7 @@ pub struct LivePhotoInfo {
 }
 
 #[tauri::command]
-pub async fn save_to_pictures(app_handle: tauri::AppHandle, file_name: String, data: Vec<u8>) -> Result<String, String> {
+pub async fn save_to_pictures(app_handle: tauri::AppHandle, file_name: String, data: Vec<u8>) -> Result<String, AppError> {
     #[cfg(target_os = "android")]
     let pictures_dir = std::path::PathBuf::from("/storage/emulated/0/Pictures");
 
@@ -35,53 +46,51 @@ pub async fn save_to_pictures(app_handle: tauri::AppHandle, file_name: String, d
         app_handle
             .path()
             .picture_dir()
-            .map_err(|e| format!("获取图片目录失败: {}", e))?
+            .map_err(|e| AppError::io(format!("获取图片目录失败: {}", e)))?
     };
 
     if !pictures_dir.exists() {
-        fs::create_dir_all(&pictures_dir).map_err(|e| format!("创建图片目录失败: {}", e))?;
+        fs::create_dir_all(&pictures_dir).map_err(|e| AppError::io(format!("创建图片目录失败: {}", e)))?;
     }
 
     let file_path = pictures_dir.join(&file_name);
     let file_path_str = file_path.to_string_lossy().to_string();
 
-    let mut file = fs::File::create(&file_path).map_err(|e| format!("创建文件失败: {}", e))?;
+    let mut file = fs::File::create(&file_path).map_err(|e| AppError::io(format!("创建文件失败: {}", e)))?;
     file.write_all(&data)
-        .map_err(|e| format!("写入文件失败: {}", e))?;
+        .map_err(|e| AppError::io(format!("写入文件失败: {}", e)))?;
 
     let _ = &app_handle;
     Ok(file_path_str)
 }
 
 #[tauri::command]
-pub async fn get_file_info(file_path: String) -> Result<FileInfo, String> {
+pub async fn get_file_info(file_path: String) -> Result<FileInfo, AppError> {
     #[cfg(target_os = "android")]
     {
         let ctx = ndk_context::android_context();
-        let vm = unsafe { jni::JavaVM::from_raw(ctx.vm().cast()) }.map_err(|e| e.to_string())?;
-        let mut env = vm.attach_current_thread().map_err(|e| e.to_string())?;
+        let vm = unsafe { jni::JavaVM::from_raw(ctx.vm().cast()) }?;
+        let mut env = vm.attach_current_thread()?;
         
         let context = unsafe { jni::objects::JObject::from_raw(ctx.context().cast()) };
-        // let class = env.get_object_class(&context).map_err(|e| e.to_string())?;
+        // let class = env.get_object_class(&context)?;
         // Update to use FileHelper
         // Use ClassLoader to find the class, as find_class often fails for app classes in JNI threads
-        let class_loader = env.call_method(&context, "getClassLoader", "()Ljava/lang/ClassLoader;", &[])
-            .map_err(|e| e.to_string())?
-            .l()
-            .map_err(|e| e.to_string())?;
+        let class_loader = env.call_method(&context, "getClassLoader", "()Ljava/lang/ClassLoader;", &[])?
+            .l()?;
         
-        let class_name = env.new_string("com/echo_trails/app/FileHelper").map_err(|e| e.to_string())?;
+        let class_name = env.new_string("com/echo_trails/app/FileHelper")?;
         
         let class_obj = env.call_method(
             class_loader, 
             "loadClass", 
             "(Ljava/lang/String;)Ljava/lang/Class;", 
             &[JValue::Object(&class_name)]
-        ).map_err(|e| e.to_string())?.l().map_err(|e| e.to_string())?;
+        )?.l()?;
 
         let class: jni::objects::JClass = class_obj.into();
         
-        let path_jstr = env.new_string(&file_path).map_err(|e| e.to_string())?;
+        let path_jstr = env.new_string(&file_path)?;
         
         // 调用 Java 方法获取文件信息
         // 使用 getFileInfoWithContext 以支持 Content URI
@@ -90,61 +99,45 @@ pub async fn get_file_info(file_path: String) -> Result<FileInfo, String> {
             "getFileInfoWithContext",
             "(Landroid/content/Context;Ljava/lang/String;)Lcom/echo_trails/app/FileInfo;",
             &[JValue::Object(&context), JValue::Object(&path_jstr)]
-        ).map_err(|e| e.to_string())?;
+        )?;
         
-        let file_info_obj = result.l().map_err(|e| e.to_string())?;
+        let file_info_obj = result.l()?;
         
         if file_info_obj.is_null() {
-             return Err(format!("Failed to get file info for path: {}", file_path));
+             return Err(AppError::not_found(format!("Failed to get file info for path: {}", file_path)));
         }
 
         // 获取字段
-        let last_modified = env.get_field(&file_info_obj, "lastModified", "J")
-            .map_err(|e| e.to_string())?
-            .j()
-            .map_err(|e| e.to_string())?;
+        let last_modified = env.get_field(&file_info_obj, "lastModified", "J")?
+            .j()?;
             
-        let creation_time = env.get_field(&file_info_obj, "creationTime", "J")
-            .map_err(|e| e.to_string())?
-            .j()
-            .map_err(|e| e.to_string())?;
+        let creation_time = env.get_field(&file_info_obj, "creationTime", "J")?
+            .j()?;
 
-        let size = env.get_field(&file_info_obj, "size", "J")
-            .map_err(|e| e.to_string())?
-            .j()
-            .map_err(|e| e.to_string())?;
+        let size = env.get_field(&file_info_obj, "size", "J")?
+            .j()?;
 
-        let width = env.get_field(&file_info_obj, "width", "I")
-            .map_err(|e| e.to_string())?
-            .i()
-            .map_err(|e| e.to_string())?;
+        let width = env.get_field(&file_info_obj, "width", "I")?
+            .i()?;
 
-        let height = env.get_field(&file_info_obj, "height", "I")
-            .map_err(|e| e.to_string())?
-            .i()
-            .map_err(|e| e.to_string())?;
+        let height = env.get_field(&file_info_obj, "height", "I")?
+            .i()?;
 
-        let file_type_obj = env.get_field(&file_info_obj, "fileType", "Ljava/lang/String;")
-            .map_err(|e| e.to_string())?
-            .l()
-            .map_err(|e| e.to_string())?;
+        let file_type_obj = env.get_field(&file_info_obj, "fileType", "Ljava/lang/String;")?
+            .l()?;
         
         let file_type: Option<String> = if !file_type_obj.is_null() {
-            Some(env.get_string(&file_type_obj.into())
-                .map_err(|e| e.to_string())?
+            Some(env.get_string(&file_type_obj.into())?
                 .into())
         } else {
             None
         };
 
-        let md5_obj = env.get_field(&file_info_obj, "md5", "Ljava/lang/String;")
-            .map_err(|e| e.to_string())?
-            .l()
-            .map_err(|e| e.to_string())?;
+        let md5_obj = env.get_field(&file_info_obj, "md5", "Ljava/lang/String;")?
+            .l()?;
 
         let md5: Option<String> = if !md5_obj.is_null() {
-            Some(env.get_string(&md5_obj.into())
-                .map_err(|e| e.to_string())?
+            Some(env.get_string(&md5_obj.into())?
                 .into())
         } else {
             None
@@ -157,57 +150,54 @@ pub async fn get_file_info(file_path: String) -> Result<FileInfo, String> {
             width: width as u32,
             height: height as u32,
             file_type,
-            md5
+            md5,
+            orientation: None,
+            taken_at: None,
+            make: None,
+            model: None,
+            gps: None,
+            duration: None,
         })
     }
 
     #[cfg(not(target_os = "android"))]
     {
         use crate::command::common::calculate_md5;
-        let metadata = std::fs::metadata(&file_path).map_err(|e| e.to_string())?;
-        let modified = metadata.modified().map_err(|e| e.to_string())?
-            .duration_since(std::time::UNIX_EPOCH).map_err(|e| e.to_string())?
+        let metadata = std::fs::metadata(&file_path)?;
+        let modified = metadata.modified()?
+            .duration_since(std::time::UNIX_EPOCH).map_err(|e| AppError::io(e.to_string()))?
             .as_millis() as i64;
             
-        let created = metadata.created().map_err(|e| e.to_string())?
-             .duration_since(std::time::UNIX_EPOCH).map_err(|e| e.to_string())?
+        let created = metadata.created()?
+             .duration_since(std::time::UNIX_EPOCH).map_err(|e| AppError::io(e.to_string()))?
              .as_millis() as i64;
 
         let path = std::path::Path::new(&file_path);
-        let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("").to_lowercase();
-        let file_type = match extension.as_str() {
-            "jpg" | "jpeg" => Some("image/jpeg".to_string()),
-            "png" => Some("image/png".to_string()),
-            "gif" => Some("image/gif".to_string()),
-            "webp" => Some("image/webp".to_string()),
-            "mp4" => Some("video/mp4".to_string()),
-            "mov" => Some("video/quicktime".to_string()),
-            "avi" => Some("video/x-msvideo".to_string()),
-            "mkv" => Some("video/x-matroska".to_string()),
-            "webm" => Some("video/webm".to_string()),
-            "mp3" => Some("audio/mpeg".to_string()),
-            "wav" => Some("audio/wav".to_string()),
-            "flac" => Some("audio/flac".to_string()),
-            "aac" => Some("audio/aac".to_string()),
-            "m4a" => Some("audio/mp4".to_string()),
-            "ogg" | "oga" => Some("audio/ogg".to_string()),
-            "opus" => Some("audio/opus".to_string()),
-            _ => None,
-        };
+        // 按文件头识别类型，扩展名仅作兜底
+        let file_type = crate::command::sniff::detect_file(path)?.map(String::from);
         
         // Calculate MD5
         let md5 = calculate_md5(path).ok();
 
-        // Desktop platform width/height fetching logic can be added here if needed
-        // For now returning 0
+        // 解析失败不影响上传，按无元数据处理
+        let media = crate::command::metadata::read_file(path).unwrap_or_default();
+        let (width, height) = media.display_size();
+
+        // 与 Android 端一致：有拍摄时间时用它替代文件系统时间
         Ok(FileInfo {
-            last_modified: modified,
-            creation_time: created,
+            last_modified: media.taken_at.unwrap_or(modified),
+            creation_time: media.taken_at.unwrap_or(created),
             size: metadata.len(),
-            width: 0,
-            height: 0,
+            width,
+            height,
             file_type,
-            md5
+            md5,
+            orientation: media.orientation,
+            taken_at: media.taken_at,
+            make: media.make,
+            model: media.model,
+            gps: media.gps,
+            duration: media.duration_ms,
         })
     }
 }
@@ -215,43 +205,41 @@ pub async fn get_file_info(file_path: String) -> Result<FileInfo, String> {
 /// 解析 Apple Live Photo 配对信息
 /// 给定一张静态图路径，返回同目录的 MOV/MP4 动态部分（若存在），以及 ContentIdentifier
 #[tauri::command]
-pub async fn parse_live_photo(file_path: String) -> Result<Option<LivePhotoInfo>, String> {
+pub async fn parse_live_photo(file_path: String) -> Result<Option<LivePhotoInfo>, AppError> {
     #[cfg(target_os = "android")]
     {
         let ctx = ndk_context::android_context();
-        let vm = unsafe { jni::JavaVM::from_raw(ctx.vm().cast()) }.map_err(|e| e.to_string())?;
-        let mut env = vm.attach_current_thread().map_err(|e| e.to_string())?;
+        let vm = unsafe { jni::JavaVM::from_raw(ctx.vm().cast()) }?;
+        let mut env = vm.attach_current_thread()?;
         let context = unsafe { jni::objects::JObject::from_raw(ctx.context().cast()) };
 
-        let class_loader = env.call_method(&context, "getClassLoader", "()Ljava/lang/ClassLoader;", &[])
-            .map_err(|e| e.to_string())?
-            .l()
-            .map_err(|e| e.to_string())?;
-        let class_name = env.new_string("com/echo_trails/app/FileHelper").map_err(|e| e.to_string())?;
+        let class_loader = env.call_method(&context, "getClassLoader", "()Ljava/lang/ClassLoader;", &[])?
+            .l()?;
+        let class_name = env.new_string("com/echo_trails/app/FileHelper")?;
         let class_obj = env.call_method(
             class_loader,
             "loadClass",
             "(Ljava/lang/String;)Ljava/lang/Class;",
             &[JValue::Object(&class_name)],
-        ).map_err(|e| e.to_string())?.l().map_err(|e| e.to_string())?;
+        )?.l()?;
         let class: jni::objects::JClass = class_obj.into();
 
-        let path_jstr = env.new_string(&file_path).map_err(|e| e.to_string())?;
+        let path_jstr = env.new_string(&file_path)?;
         let result = env.call_static_method(
             class,
             "findLivePhotoVideo",
             "(Landroid/content/Context;Ljava/lang/String;)Ljava/lang/String;",
             &[JValue::Object(&context), JValue::Object(&path_jstr)],
-        ).map_err(|e| e.to_string())?;
+        )?;
 
-        let json_obj = result.l().map_err(|e| e.to_string())?;
+        let json_obj = result.l()?;
         if json_obj.is_null() {
             println!("[LivePhoto:DEBUG] parse_live_photo null path={}", file_path);
             return Ok(None);
         }
-        let json_str: String = env.get_string(&json_obj.into()).map_err(|e| e.to_string())?.into();
+        let json_str: String = env.get_string(&json_obj.into())?.into();
         println!("[LivePhoto:DEBUG] parse_live_photo path={} json={}", file_path, json_str);
-        let parsed: serde_json::Value = serde_json::from_str(&json_str).map_err(|e| e.to_string())?;
+        let parsed: serde_json::Value = serde_json::from_str(&json_str)?;
         let video_path = parsed.get("videoPath").and_then(|v| v.as_str()).unwrap_or("").to_string();
         if video_path.is_empty() {
             println!("[LivePhoto:DEBUG] parse_live_photo miss path={}", file_path);
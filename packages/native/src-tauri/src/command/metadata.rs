@@ -0,0 +1,974 @@
+//! Pure-Rust media metadata reader used by the desktop `get_file_info`.
+//!
+//! Understands JPEG, PNG, GIF, WebP and ISO-BMFF containers (HEIC/AVIF, MP4,
+//! MOV). Only the pieces the app needs are decoded: pixel size, orientation,
+//! capture time, camera make/model, GPS and video duration. Unknown or broken
+//! structures are skipped rather than reported, so a partially readable file
+//! still yields whatever could be found.
+
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use chrono::{FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone};
+use serde::Serialize;
+
+/// EXIF/QuickTime 中的 GPS 坐标（十进制度，南纬/西经为负）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct GpsPosition {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MediaMetadata {
+    /// Stored pixel size, before applying `orientation` / `rotation`.
+    pub width: u32,
+    pub height: u32,
+    /// EXIF orientation (1-8).
+    pub orientation: Option<u16>,
+    /// Clockwise display rotation in degrees (video track matrix / HEIF `irot`).
+    pub rotation: Option<u16>,
+    /// Capture time in milliseconds since the Unix epoch.
+    pub taken_at: Option<i64>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub gps: Option<GpsPosition>,
+    pub duration_ms: Option<i64>,
+}
+
+impl MediaMetadata {
+    /// Size as displayed, i.e. with width and height swapped for 90°/270° turns.
+    pub fn display_size(&self) -> (u32, u32) {
+        let rotated = matches!(self.orientation, Some(5..=8))
+            || (self.orientation.is_none() && matches!(self.rotation, Some(90) | Some(270)));
+        if rotated {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        }
+    }
+}
+
+/// Read metadata from a file on disk.
+pub fn read_file(path: &Path) -> io::Result<MediaMetadata> {
+    let mut file = io::BufReader::new(std::fs::File::open(path)?);
+    read(&mut file)
+}
+
+/// Read metadata from any seekable source. Unrecognised formats yield an
+/// empty [`MediaMetadata`].
+pub fn read<R: Read + Seek>(reader: &mut R) -> io::Result<MediaMetadata> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    let mut src = Source { inner: reader, len };
+    let head = src.read_at(0, 16)?;
+    let mut meta = MediaMetadata::default();
+
+    if head.starts_with(&[0xFF, 0xD8]) {
+        read_jpeg(&mut src, &mut meta)?;
+    } else if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        read_png(&mut src, &mut meta)?;
+    } else if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        if head.len() >= 10 {
+            meta.width = u16::from_le_bytes([head[6], head[7]]) as u32;
+            meta.height = u16::from_le_bytes([head[8], head[9]]) as u32;
+        }
+    } else if head.len() >= 12 && &head[0..4] == b"RIFF" && &head[8..12] == b"WEBP" {
+        read_webp(&mut src, &mut meta)?;
+    } else if head.len() >= 8 && &head[4..8] == b"ftyp" {
+        read_bmff(&mut src, &mut meta)?;
+    }
+    Ok(meta)
+}
+
+// ---------- 随机读取 ----------
+
+struct Source<'a, R> {
+    inner: &'a mut R,
+    len: u64,
+}
+
+impl<R: Read + Seek> Source<'_, R> {
+    /// Read up to `n` bytes at `offset` (short at end of file).
+    fn read_at(&mut self, offset: u64, n: usize) -> io::Result<Vec<u8>> {
+        if offset >= self.len {
+            return Ok(Vec::new());
+        }
+        let n = n.min((self.len - offset) as usize);
+        self.inner.seek(SeekFrom::Start(offset))?;
+        let mut buf = vec![0u8; n];
+        self.inner.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+fn be16(b: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(b.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn be32(b: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(b.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn be64(b: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(b.get(at..at + 8)?.try_into().ok()?))
+}
+
+fn le16(b: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(b.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn le32(b: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(b.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Big-endian unsigned integer of 0, 2, 4 or 8 bytes (ISO-BMFF variable-width fields).
+fn be_n(b: &[u8], at: usize, size: usize) -> Option<u64> {
+    match size {
+        0 => Some(0),
+        2 => be16(b, at).map(u64::from),
+        4 => be32(b, at).map(u64::from),
+        8 => be64(b, at),
+        _ => None,
+    }
+}
+
+// EXIF 载荷最多读 1MB，足够容纳缩略图之外的全部标签
+const MAX_EXIF_LEN: usize = 1024 * 1024;
+
+// ---------- JPEG ----------
+
+fn read_jpeg<R: Read + Seek>(src: &mut Source<R>, meta: &mut MediaMetadata) -> io::Result<()> {
+    let mut pos = 2u64;
+    while pos + 4 <= src.len {
+        let marker = src.read_at(pos, 4)?;
+        if marker[0] != 0xFF {
+            break;
+        }
+        let kind = marker[1];
+        // 填充字节与无长度的标记
+        if kind == 0xFF {
+            pos += 1;
+            continue;
+        }
+        if kind == 0x01 || (0xD0..=0xD7).contains(&kind) {
+            pos += 2;
+            continue;
+        }
+        if kind == 0xDA || kind == 0xD9 {
+            break;
+        }
+        let seg_len = be16(&marker, 2).unwrap_or(0) as u64;
+        if seg_len < 2 {
+            break;
+        }
+        let body = pos + 4;
+        match kind {
+            0xE1 => {
+                let seg = src.read_at(body, (seg_len as usize - 2).min(MAX_EXIF_LEN))?;
+                if seg.starts_with(b"Exif\0\0") {
+                    parse_tiff(&seg[6..], meta);
+                }
+            }
+            0xC0..=0xCF if !matches!(kind, 0xC4 | 0xC8 | 0xCC) => {
+                let sof = src.read_at(body, 5)?;
+                if let (Some(h), Some(w)) = (be16(&sof, 1), be16(&sof, 3)) {
+                    meta.width = w as u32;
+                    meta.height = h as u32;
+                }
+            }
+            _ => {}
+        }
+        pos = body + seg_len - 2;
+    }
+    Ok(())
+}
+
+// ---------- PNG ----------
+
+fn read_png<R: Read + Seek>(src: &mut Source<R>, meta: &mut MediaMetadata) -> io::Result<()> {
+    let mut pos = 8u64;
+    while pos + 8 <= src.len {
+        let header = src.read_at(pos, 8)?;
+        let chunk_len = be32(&header, 0).unwrap_or(0) as u64;
+        match &header[4..8] {
+            b"IHDR" => {
+                let ihdr = src.read_at(pos + 8, 8)?;
+                meta.width = be32(&ihdr, 0).unwrap_or(0);
+                meta.height = be32(&ihdr, 4).unwrap_or(0);
+            }
+            b"eXIf" => {
+                let exif = src.read_at(pos + 8, (chunk_len as usize).min(MAX_EXIF_LEN))?;
+                parse_tiff(&exif, meta);
+            }
+            b"IEND" => break,
+            _ => {}
+        }
+        // length + type + data + crc
+        pos += 12 + chunk_len;
+    }
+    Ok(())
+}
+
+// ---------- WebP ----------
+
+fn read_webp<R: Read + Seek>(src: &mut Source<R>, meta: &mut MediaMetadata) -> io::Result<()> {
+    let mut pos = 12u64;
+    while pos + 8 <= src.len {
+        let header = src.read_at(pos, 8)?;
+        let chunk_len = le32(&header, 4).unwrap_or(0) as u64;
+        let body = pos + 8;
+        match &header[0..4] {
+            b"VP8X" => {
+                let b = src.read_at(body, 10)?;
+                if b.len() == 10 {
+                    meta.width = 1 + u32::from_le_bytes([b[4], b[5], b[6], 0]);
+                    meta.height = 1 + u32::from_le_bytes([b[7], b[8], b[9], 0]);
+                }
+            }
+            b"VP8 " if meta.width == 0 => {
+                let b = src.read_at(body, 10)?;
+                if b.len() == 10 && b[3..6] == [0x9D, 0x01, 0x2A] {
+                    meta.width = (le16(&b, 6).unwrap_or(0) & 0x3FFF) as u32;
+                    meta.height = (le16(&b, 8).unwrap_or(0) & 0x3FFF) as u32;
+                }
+            }
+            b"VP8L" if meta.width == 0 => {
+                let b = src.read_at(body, 5)?;
+                if b.len() == 5 && b[0] == 0x2F {
+                    let bits = le32(&b, 1).unwrap_or(0);
+                    meta.width = (bits & 0x3FFF) + 1;
+                    meta.height = ((bits >> 14) & 0x3FFF) + 1;
+                }
+            }
+            b"EXIF" => {
+                let exif = src.read_at(body, (chunk_len as usize).min(MAX_EXIF_LEN))?;
+                // 部分编码器会保留 JPEG 的 "Exif\0\0" 前缀
+                let tiff = exif.strip_prefix(b"Exif\0\0").unwrap_or(&exif);
+                parse_tiff(tiff, meta);
+            }
+            _ => {}
+        }
+        // RIFF 块按偶数字节对齐
+        pos = body + chunk_len + (chunk_len & 1);
+    }
+    Ok(())
+}
+
+// ---------- TIFF / EXIF ----------
+
+struct Tiff<'a> {
+    data: &'a [u8],
+    little: bool,
+}
+
+struct IfdEntry {
+    tag: u16,
+    kind: u16,
+    count: u32,
+    /// Offset of the value bytes within the TIFF data.
+    value_at: usize,
+}
+
+impl Tiff<'_> {
+    fn u16(&self, at: usize) -> Option<u16> {
+        if self.little {
+            le16(self.data, at)
+        } else {
+            be16(self.data, at)
+        }
+    }
+
+    fn u32(&self, at: usize) -> Option<u32> {
+        if self.little {
+            le32(self.data, at)
+        } else {
+            be32(self.data, at)
+        }
+    }
+
+    fn entries(&self, ifd: usize) -> Vec<IfdEntry> {
+        let count = self.u16(ifd).unwrap_or(0) as usize;
+        (0..count)
+            .filter_map(|i| {
+                let at = ifd + 2 + i * 12;
+                let tag = self.u16(at)?;
+                let kind = self.u16(at + 2)?;
+                let count = self.u32(at + 4)?;
+                let unit = match kind {
+                    1 | 2 | 6 | 7 => 1,
+                    3 | 8 => 2,
+                    4 | 9 | 11 => 4,
+                    5 | 10 | 12 => 8,
+                    _ => return None,
+                };
+                let size = unit * count as usize;
+                let value_at = if size <= 4 {
+                    at + 8
+                } else {
+                    self.u32(at + 8)? as usize
+                };
+                if value_at.checked_add(size)? > self.data.len() {
+                    return None;
+                }
+                Some(IfdEntry {
+                    tag,
+                    kind,
+                    count,
+                    value_at,
+                })
+            })
+            .collect()
+    }
+
+    fn int(&self, e: &IfdEntry) -> Option<u32> {
+        match e.kind {
+            1 | 7 => self.data.get(e.value_at).map(|v| *v as u32),
+            3 => self.u16(e.value_at).map(u32::from),
+            4 => self.u32(e.value_at),
+            _ => None,
+        }
+    }
+
+    fn ascii(&self, e: &IfdEntry) -> Option<String> {
+        if e.kind != 2 {
+            return None;
+        }
+        let raw = self.data.get(e.value_at..e.value_at + e.count as usize)?;
+        let text = String::from_utf8_lossy(raw);
+        let text = text.trim_end_matches('\0').trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+
+    fn rationals(&self, e: &IfdEntry) -> Vec<f64> {
+        if e.kind != 5 && e.kind != 10 {
+            return Vec::new();
+        }
+        (0..e.count as usize)
+            .filter_map(|i| {
+                let at = e.value_at + i * 8;
+                let (n, d) = (self.u32(at)?, self.u32(at + 4)?);
+                if d == 0 {
+                    return None;
+                }
+                Some(if e.kind == 10 {
+                    n as i32 as f64 / d as i32 as f64
+                } else {
+                    n as f64 / d as f64
+                })
+            })
+            .collect()
+    }
+}
+
+/// Parse a TIFF-structured EXIF block (starting at the byte-order mark).
+fn parse_tiff(data: &[u8], meta: &mut MediaMetadata) {
+    let little = match data.get(0..2) {
+        Some(b"II") => true,
+        Some(b"MM") => false,
+        _ => return,
+    };
+    let tiff = Tiff { data, little };
+    if tiff.u16(2) != Some(42) {
+        return;
+    }
+    let Some(ifd0) = tiff.u32(4) else { return };
+
+    let mut date_time = None;
+    let mut date_time_original = None;
+    let mut offset_original = None;
+    let mut exif_ifd = None;
+    let mut gps_ifd = None;
+    let (mut pixel_w, mut pixel_h) = (0, 0);
+
+    for e in tiff.entries(ifd0 as usize) {
+        match e.tag {
+            0x010F => meta.make = meta.make.take().or(tiff.ascii(&e)),
+            0x0110 => meta.model = meta.model.take().or(tiff.ascii(&e)),
+            0x0112 => {
+                meta.orientation = tiff
+                    .int(&e)
+                    .map(|v| v as u16)
+                    .filter(|v| (1..=8).contains(v))
+            }
+            0x0132 => date_time = tiff.ascii(&e),
+            0x8769 => exif_ifd = tiff.int(&e),
+            0x8825 => gps_ifd = tiff.int(&e),
+            _ => {}
+        }
+    }
+    if let Some(ifd) = exif_ifd {
+        for e in tiff.entries(ifd as usize) {
+            match e.tag {
+                0x9003 => date_time_original = tiff.ascii(&e),
+                0x9011 => offset_original = tiff.ascii(&e),
+                0xA002 => pixel_w = tiff.int(&e).unwrap_or(0),
+                0xA003 => pixel_h = tiff.int(&e).unwrap_or(0),
+                _ => {}
+            }
+        }
+    }
+    if let Some(ifd) = gps_ifd {
+        meta.gps = parse_gps(&tiff, ifd as usize).or(meta.gps);
+    }
+
+    if meta.width == 0 || meta.height == 0 {
+        meta.width = pixel_w;
+        meta.height = pixel_h;
+    }
+    // 与 Android 端一致：优先 DateTimeOriginal，其次 DateTime
+    let taken = date_time_original.or(date_time);
+    if let Some(ts) = taken.and_then(|v| parse_exif_datetime(&v, offset_original.as_deref())) {
+        meta.taken_at = Some(ts);
+    }
+}
+
+fn parse_gps(tiff: &Tiff, ifd: usize) -> Option<GpsPosition> {
+    let (mut lat_ref, mut lon_ref) = ("N".to_string(), "E".to_string());
+    let (mut lat, mut lon, mut alt) = (None, None, None);
+    let mut below_sea = false;
+    let to_degrees = |v: Vec<f64>| -> Option<f64> {
+        let d = *v.first()?;
+        Some(
+            d + v.get(1).copied().unwrap_or(0.0) / 60.0 + v.get(2).copied().unwrap_or(0.0) / 3600.0,
+        )
+    };
+    for e in tiff.entries(ifd) {
+        match e.tag {
+            1 => lat_ref = tiff.ascii(&e).unwrap_or(lat_ref),
+            2 => lat = to_degrees(tiff.rationals(&e)),
+            3 => lon_ref = tiff.ascii(&e).unwrap_or(lon_ref),
+            4 => lon = to_degrees(tiff.rationals(&e)),
+            5 => below_sea = tiff.int(&e) == Some(1),
+            6 => alt = tiff.rationals(&e).first().copied(),
+            _ => {}
+        }
+    }
+    let (lat, lon) = (lat?, lon?);
+    Some(GpsPosition {
+        latitude: if lat_ref.starts_with('S') { -lat } else { lat },
+        longitude: if lon_ref.starts_with('W') { -lon } else { lon },
+        altitude: alt.map(|a| if below_sea { -a } else { a }),
+    })
+}
+
+// ---------- 时间解析 ----------
+
+/// `"2023:05:01 10:00:00"` (local wall clock) plus an optional EXIF
+/// `OffsetTimeOriginal` such as `"+08:00"`. Without an offset the device's
+/// local time zone is assumed, matching Android's ExifInterface handling.
+pub fn parse_exif_datetime(value: &str, offset: Option<&str>) -> Option<i64> {
+    to_millis(parse_naive(value)?, offset.and_then(parse_offset))
+}
+
+/// First six digit groups as year, month, day, hour, minute, second.
+fn parse_naive(value: &str) -> Option<NaiveDateTime> {
+    let digits: Vec<u32> = value
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|s| !s.is_empty())
+        .take(6)
+        .map(|s| s.parse().ok())
+        .collect::<Option<_>>()?;
+    if digits.len() < 6 || digits[0] == 0 {
+        return None;
+    }
+    NaiveDate::from_ymd_opt(digits[0] as i32, digits[1], digits[2])?
+        .and_hms_opt(digits[3], digits[4], digits[5])
+}
+
+/// ISO 8601 as written by QuickTime `creationdate`, e.g.
+/// `"2023-05-01T10:00:00+0800"` or `"2023-05-01T02:00:00Z"`.
+fn parse_iso_datetime(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let (date, rest) = value.split_at(value.find('T')?);
+    let rest = &rest[1..];
+    let tz_at = rest.find(['+', '-', 'Z']).unwrap_or(rest.len());
+    let (time, tz) = rest.split_at(tz_at);
+    let time = time.split('.').next()?;
+    let naive = parse_naive(&format!("{} {}", date, time))?;
+    to_millis(
+        naive,
+        if tz.is_empty() {
+            None
+        } else {
+            parse_offset(tz)
+        },
+    )
+}
+
+fn parse_offset(value: &str) -> Option<FixedOffset> {
+    let value = value.trim();
+    if value == "Z" {
+        return FixedOffset::east_opt(0);
+    }
+    let sign = match value.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits: String = value[1..].chars().filter(|c| c.is_ascii_digit()).collect();
+    if digits.len() != 4 {
+        return None;
+    }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits[2..].parse().ok()?;
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+fn to_millis(naive: NaiveDateTime, offset: Option<FixedOffset>) -> Option<i64> {
+    match offset {
+        Some(tz) => tz
+            .from_local_datetime(&naive)
+            .single()
+            .map(|d| d.timestamp_millis()),
+        None => Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|d| d.timestamp_millis()),
+    }
+}
+
+/// `"+37.3349-122.0090+040.000/"` → latitude, longitude, altitude.
+fn parse_iso6709(value: &str) -> Option<GpsPosition> {
+    let value = value.trim().trim_end_matches('/');
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for (i, c) in value.char_indices().skip(1) {
+        if c == '+' || c == '-' {
+            parts.push(&value[start..i]);
+            start = i;
+        }
+    }
+    parts.push(&value[start..]);
+    let nums: Vec<f64> = parts.iter().filter_map(|p| p.parse().ok()).collect();
+    if nums.len() < 2 {
+        return None;
+    }
+    Some(GpsPosition {
+        latitude: nums[0],
+        longitude: nums[1],
+        altitude: nums.get(2).copied(),
+    })
+}
+
+// ---------- ISO-BMFF (HEIC / MP4 / MOV) ----------
+
+// QuickTime 时间以 1904-01-01 为纪元
+const MAC_EPOCH_OFFSET: i64 = 2_082_844_800;
+// 盒子内容读取上限，防止损坏文件声明超大尺寸
+const MAX_BOX_READ: usize = 4 * 1024 * 1024;
+
+struct BoxHeader {
+    kind: [u8; 4],
+    /// Start of the payload.
+    body: u64,
+    /// End of the box (exclusive).
+    end: u64,
+}
+
+fn box_children<R: Read + Seek>(
+    src: &mut Source<R>,
+    start: u64,
+    end: u64,
+) -> io::Result<Vec<BoxHeader>> {
+    let mut boxes = Vec::new();
+    let mut pos = start;
+    while pos + 8 <= end {
+        let h = src.read_at(pos, 16)?;
+        let size32 = be32(&h, 0).unwrap_or(0) as u64;
+        let kind: [u8; 4] = h[4..8].try_into().unwrap_or_default();
+        let (header_len, size) = match size32 {
+            0 => (8, end - pos),
+            1 => (16, be64(&h, 8).unwrap_or(0)),
+            n => (8, n),
+        };
+        // 64 位 largesize 可能声明任意大小，相加需防溢出
+        let box_end = match pos.checked_add(size) {
+            Some(box_end) if size >= header_len && box_end <= end => box_end,
+            _ => break,
+        };
+        boxes.push(BoxHeader {
+            kind,
+            body: pos + header_len,
+            end: box_end,
+        });
+        pos = box_end;
+    }
+    Ok(boxes)
+}
+
+fn box_body<R: Read + Seek>(src: &mut Source<R>, b: &BoxHeader) -> io::Result<Vec<u8>> {
+    src.read_at(b.body, ((b.end - b.body) as usize).min(MAX_BOX_READ))
+}
+
+fn read_bmff<R: Read + Seek>(src: &mut Source<R>, meta: &mut MediaMetadata) -> io::Result<()> {
+    let top = box_children(src, 0, src.len)?;
+    for b in &top {
+        match &b.kind {
+            b"meta" => read_heif_meta(src, b, meta)?,
+            b"moov" => read_moov(src, b, meta)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn read_moov<R: Read + Seek>(
+    src: &mut Source<R>,
+    moov: &BoxHeader,
+    meta: &mut MediaMetadata,
+) -> io::Result<()> {
+    let mut creation = None;
+    for b in box_children(src, moov.body, moov.end)? {
+        match &b.kind {
+            b"mvhd" => {
+                let body = box_body(src, &b)?;
+                let (created, timescale, duration) = if body.first() == Some(&1) {
+                    (be64(&body, 4), be32(&body, 20), be64(&body, 24))
+                } else {
+                    (
+                        be32(&body, 4).map(u64::from),
+                        be32(&body, 12),
+                        be32(&body, 16).map(u64::from),
+                    )
+                };
+                if let (Some(ts), Some(d)) = (timescale.filter(|v| *v > 0), duration) {
+                    meta.duration_ms = Some((d as u128 * 1000 / ts as u128) as i64);
+                }
+                creation = created
+                    .filter(|v| *v as i64 > MAC_EPOCH_OFFSET)
+                    .map(|v| (v as i64 - MAC_EPOCH_OFFSET) * 1000);
+            }
+            b"trak" => read_trak(src, &b, meta)?,
+            b"udta" => read_udta(src, &b, meta)?,
+            b"meta" => read_qt_keys(src, b.body, b.end, meta)?,
+            _ => {}
+        }
+    }
+    // creationdate 带本地时区，优先于 mvhd
+    if meta.taken_at.is_none() {
+        meta.taken_at = creation;
+    }
+    Ok(())
+}
+
+fn read_trak<R: Read + Seek>(
+    src: &mut Source<R>,
+    trak: &BoxHeader,
+    meta: &mut MediaMetadata,
+) -> io::Result<()> {
+    for b in box_children(src, trak.body, trak.end)? {
+        if &b.kind != b"tkhd" {
+            continue;
+        }
+        let body = box_body(src, &b)?;
+        let base = if body.first() == Some(&1) { 32 } else { 20 };
+        // layer, alternate_group, volume, reserved 之后是 3x3 矩阵，再之后是 16.16 定点宽高
+        let matrix_at = base + 4 + 8 + 8;
+        let size_at = matrix_at + 36;
+        let (Some(w), Some(h)) = (be32(&body, size_at), be32(&body, size_at + 4)) else {
+            continue;
+        };
+        let (w, h) = (w >> 16, h >> 16);
+        // 音频轨宽高为 0，只取第一条视频轨
+        if w == 0 || h == 0 || meta.width > 0 {
+            continue;
+        }
+        meta.width = w;
+        meta.height = h;
+        let a = be32(&body, matrix_at).unwrap_or(0) as i32;
+        let bm = be32(&body, matrix_at + 4).unwrap_or(0) as i32;
+        meta.rotation = match (a.signum(), bm.signum()) {
+            (0, 1) => Some(90),
+            (-1, 0) => Some(180),
+            (0, -1) => Some(270),
+            _ => None,
+        };
+    }
+    Ok(())
+}
+
+/// QuickTime user data: `©xyz` location, `©mak` / `©mod` camera.
+fn read_udta<R: Read + Seek>(
+    src: &mut Source<R>,
+    udta: &BoxHeader,
+    meta: &mut MediaMetadata,
+) -> io::Result<()> {
+    for b in box_children(src, udta.body, udta.end)? {
+        let value = match &b.kind {
+            b"\xA9xyz" | b"\xA9mak" | b"\xA9mod" => {
+                let body = box_body(src, &b)?;
+                let len = be16(&body, 0).unwrap_or(0) as usize;
+                body.get(4..4 + len)
+                    .map(|v| String::from_utf8_lossy(v).trim().to_string())
+            }
+            b"meta" => {
+                // udta/meta 是 full box
+                read_qt_keys(src, b.body + 4, b.end, meta)?;
+                None
+            }
+            _ => None,
+        };
+        let Some(value) = value.filter(|v| !v.is_empty()) else {
+            continue;
+        };
+        match &b.kind {
+            b"\xA9xyz" => meta.gps = meta.gps.or(parse_iso6709(&value)),
+            b"\xA9mak" => meta.make = meta.make.take().or(Some(value)),
+            b"\xA9mod" => meta.model = meta.model.take().or(Some(value)),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Apple `meta` box with `keys` + `ilst`, where iPhone videos store make,
+/// model, location and the local-time creation date.
+fn read_qt_keys<R: Read + Seek>(
+    src: &mut Source<R>,
+    start: u64,
+    end: u64,
+    meta: &mut MediaMetadata,
+) -> io::Result<()> {
+    let children = box_children(src, start, end)?;
+    let mut keys: Vec<String> = Vec::new();
+    if let Some(b) = children.iter().find(|b| &b.kind == b"keys") {
+        let body = box_body(src, b)?;
+        let count = be32(&body, 4).unwrap_or(0);
+        let mut at = 8usize;
+        for _ in 0..count {
+            let Some(size) = be32(&body, at).map(|v| v as usize) else {
+                break;
+            };
+            if size < 8 || at + size > body.len() {
+                break;
+            }
+            keys.push(String::from_utf8_lossy(&body[at + 8..at + size]).to_string());
+            at += size;
+        }
+    }
+    let Some(ilst) = children.iter().find(|b| &b.kind == b"ilst") else {
+        return Ok(());
+    };
+    for item in box_children(src, ilst.body, ilst.end)? {
+        let index = u32::from_be_bytes(item.kind) as usize;
+        let Some(key) = index.checked_sub(1).and_then(|i| keys.get(i)) else {
+            continue;
+        };
+        let body = box_body(src, &item)?;
+        // data 子盒：size, "data", type(4), locale(4), value
+        if body.get(4..8) != Some(b"data") {
+            continue;
+        }
+        let size = be32(&body, 0).unwrap_or(0) as usize;
+        let Some(raw) = body.get(16..size.min(body.len())) else {
+            continue;
+        };
+        let value = String::from_utf8_lossy(raw).trim().to_string();
+        match key.as_str() {
+            "com.apple.quicktime.make" => meta.make = Some(value),
+            "com.apple.quicktime.model" => meta.model = Some(value),
+            "com.apple.quicktime.location.ISO6709" => meta.gps = parse_iso6709(&value).or(meta.gps),
+            "com.apple.quicktime.creationdate" => {
+                meta.taken_at = parse_iso_datetime(&value).or(meta.taken_at)
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// HEIF `meta`: primary item size from `ispe`, rotation from `irot`, and the
+/// `Exif` item located through `iloc`.
+fn read_heif_meta<R: Read + Seek>(
+    src: &mut Source<R>,
+    meta_box: &BoxHeader,
+    meta: &mut MediaMetadata,
+) -> io::Result<()> {
+    // meta 是 full box，跳过 version/flags
+    let children = box_children(src, meta_box.body + 4, meta_box.end)?;
+    let mut primary = None;
+    let mut exif_item = None;
+    let mut locations: Vec<(u32, u64, u64)> = Vec::new();
+    let mut properties: Vec<BoxHeader> = Vec::new();
+    let mut associations: Vec<(u32, Vec<usize>)> = Vec::new();
+
+    for b in &children {
+        let body = box_body(src, b)?;
+        match &b.kind {
+            b"pitm" => {
+                primary = if body.first() == Some(&0) {
+                    be16(&body, 4).map(u32::from)
+                } else {
+                    be32(&body, 4)
+                }
+            }
+            b"iinf" => {
+                let skip = if body.first() == Some(&0) { 6 } else { 8 };
+                for infe in box_children(src, b.body + skip, b.end)? {
+                    let e = box_body(src, &infe)?;
+                    let (id, kind) = match e.first() {
+                        Some(2) => (be16(&e, 4).map(u32::from), e.get(8..12)),
+                        Some(3) => (be32(&e, 4), e.get(10..14)),
+                        _ => continue,
+                    };
+                    if kind == Some(b"Exif") {
+                        exif_item = id;
+                    }
+                }
+            }
+            b"iloc" => locations = parse_iloc(&body),
+            b"iprp" => {
+                for p in box_children(src, b.body, b.end)? {
+                    match &p.kind {
+                        b"ipco" => properties = box_children(src, p.body, p.end)?,
+                        b"ipma" => associations = parse_ipma(&box_body(src, &p)?),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(primary) = primary {
+        let assoc = associations
+            .iter()
+            .find(|(id, _)| *id == primary)
+            .map(|(_, v)| v.clone())
+            .unwrap_or_default();
+        for index in assoc {
+            let Some(p) = index.checked_sub(1).and_then(|i| properties.get(i)) else {
+                continue;
+            };
+            let body = box_body(src, p)?;
+            match &p.kind {
+                b"ispe" => {
+                    meta.width = be32(&body, 4).unwrap_or(0);
+                    meta.height = be32(&body, 8).unwrap_or(0);
+                }
+                // irot 为逆时针 90° 的倍数，换算成顺时针角度
+                b"irot" => {
+                    meta.rotation = body
+                        .first()
+                        .map(|v| (4 - (*v & 3) as u16) % 4 * 90)
+                        .filter(|v| *v != 0)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if let Some((_, offset, length)) = exif_item.and_then(|id| locations.iter().find(|l| l.0 == id))
+    {
+        let block = src.read_at(*offset, (*length as usize).min(MAX_EXIF_LEN))?;
+        // 前 4 字节为 TIFF 头相对偏移（通常跳过 "Exif\0\0"）
+        if let Some(skip) = be32(&block, 0) {
+            if let Some(tiff) = block.get(4 + skip as usize..) {
+                // HEIC 的尺寸以 ispe 为准，EXIF 中的 PixelDimension 仅作兜底
+                parse_tiff(tiff, meta);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `(item_id, file_offset, length)` of each item's first extent.
+fn parse_iloc(body: &[u8]) -> Vec<(u32, u64, u64)> {
+    let mut out = Vec::new();
+    let Some(&version) = body.first() else {
+        return out;
+    };
+    let (Some(&sizes), Some(&sizes2)) = (body.get(4), body.get(5)) else {
+        return out;
+    };
+    let offset_size = (sizes >> 4) as usize;
+    let length_size = (sizes & 0xF) as usize;
+    let base_offset_size = (sizes2 >> 4) as usize;
+    let index_size = if version >= 1 {
+        (sizes2 & 0xF) as usize
+    } else {
+        0
+    };
+    let mut at = 6;
+    let count = if version < 2 {
+        at += 2;
+        be16(body, 6).map(u32::from)
+    } else {
+        at += 4;
+        be32(body, 6)
+    };
+    for _ in 0..count.unwrap_or(0) {
+        let id = if version < 2 {
+            at += 2;
+            be16(body, at - 2).map(u32::from)
+        } else {
+            at += 4;
+            be32(body, at - 4)
+        };
+        let mut method = 0;
+        if version >= 1 {
+            method = be16(body, at).unwrap_or(0) & 0xF;
+            at += 2;
+        }
+        at += 2; // data_reference_index
+        let base = be_n(body, at, base_offset_size);
+        at += base_offset_size;
+        let extents = be16(body, at).unwrap_or(0);
+        at += 2;
+        let mut first = None;
+        for _ in 0..extents {
+            at += index_size;
+            let offset = be_n(body, at, offset_size);
+            at += offset_size;
+            let length = be_n(body, at, length_size);
+            at += length_size;
+            if first.is_none() {
+                first = offset.zip(length);
+            }
+        }
+        // 只支持直接文件偏移（construction_method 0）
+        if let (Some(id), Some(base), Some((offset, length)), 0) = (id, base, first, method) {
+            out.push((id, base + offset, length));
+        }
+        if at > body.len() {
+            break;
+        }
+    }
+    out
+}
+
+/// `(item_id, [1-based property indices])` from `ipma`.
+fn parse_ipma(body: &[u8]) -> Vec<(u32, Vec<usize>)> {
+    let mut out = Vec::new();
+    let version = body.first().copied().unwrap_or(0);
+    let wide = body.get(3).map(|f| f & 1 == 1).unwrap_or(false);
+    let count = be32(body, 4).unwrap_or(0);
+    let mut at = 8;
+    for _ in 0..count {
+        let id = if version < 1 {
+            at += 2;
+            be16(body, at - 2).map(u32::from)
+        } else {
+            at += 4;
+            be32(body, at - 4)
+        };
+        let Some(&n) = body.get(at) else { break };
+        at += 1;
+        let mut indices = Vec::new();
+        for _ in 0..n {
+            let index = if wide {
+                at += 2;
+                be16(body, at - 2).map(|v| (v & 0x7FFF) as usize)
+            } else {
+                at += 1;
+                body.get(at - 1).map(|v| (v & 0x7F) as usize)
+            };
+            indices.extend(index);
+        }
+        if let Some(id) = id {
+            out.push((id, indices));
+        }
+    }
+    out
+}
@@ -1,8 +1,16 @@
 pub mod app;
 pub mod common;
 pub mod media;
+pub mod metadata;
+pub mod multipart;
+pub mod phash;
+pub mod s3_client;
 pub mod s3_presign;
+pub mod s3_profile;
+pub mod sniff;
+pub mod thumbnail;
 pub mod upload;
+pub mod upload_queue;
 
 pub use app::*;
 pub use common::*;
@@ -0,0 +1,405 @@
+//! Resumable S3 multipart upload of local files.
+//!
+//! Parts are read straight from disk and PUT to presigned UploadPart URLs.
+//! Each acknowledged ETag is persisted through [`crate::db::multipart`] before
+//! the next part starts, so a retry — even after restarting the app — only
+//! sends the parts S3 has not confirmed yet.
+
+use chrono::Utc;
+use serde_json::{json, Value as JsonValue};
+use tauri::{AppHandle, Emitter, State};
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, SeekFrom};
+
+use super::s3_client::check_response;
+use super::s3_profile::{ProviderConfig, ProviderProfile};
+use super::s3_presign::{
+    complete_multipart_upload_body, presign_abort_multipart_upload_url,
+    presign_complete_multipart_upload_url, presign_create_multipart_upload_url,
+    presign_upload_part_url, xml_tag_text, PresignMultipartParams, S3Credentials,
+};
+use super::upload::open_upload_source;
+use crate::db::multipart::{self, MultipartUpload, UploadedPart};
+use crate::db::TursoDb;
+use crate::error::AppError;
+
+/// 默认分片大小
+pub const DEFAULT_PART_SIZE: u64 = 8 * 1024 * 1024;
+/// S3 要求除最后一片外每片至少 5 MiB
+pub const MIN_PART_SIZE: u64 = 5 * 1024 * 1024;
+/// 上传队列中超过该大小的文件走分片上传（与前端 MULTIPART_UPLOAD_THRESHOLD 一致）
+pub const MULTIPART_THRESHOLD: u64 = 64 * 1024 * 1024;
+const MAX_PARTS: u64 = 10_000;
+const PRESIGN_EXPIRES_SECONDS: u32 = 3600;
+
+/// Part size for `file_size`: `requested` (default 8 MiB, at least 5 MiB),
+/// grown when needed so the file fits in S3's 10,000-part limit.
+pub fn part_size_for(file_size: u64, requested: Option<u64>) -> u64 {
+    let size = requested.unwrap_or(DEFAULT_PART_SIZE).max(MIN_PART_SIZE);
+    size.max(file_size.div_ceil(MAX_PARTS))
+}
+
+fn presign_params<'a>(credentials: &'a S3Credentials, key: &'a str) -> PresignMultipartParams<'a> {
+    PresignMultipartParams {
+        key,
+        bucket: &credentials.bucket,
+        region: &credentials.region,
+        endpoint: &credentials.endpoint,
+        access_key: &credentials.access_key,
+        secret_key: &credentials.secret_key,
+        expires_seconds: PRESIGN_EXPIRES_SECONDS,
+        profile: &credentials.profile,
+    }
+}
+
+async fn create_session(
+    conn: &turso::Connection,
+    client: &reqwest::Client,
+    credentials: &S3Credentials,
+    key: &str,
+    source_path: &str,
+    file_size: u64,
+    source_mtime: Option<i64>,
+    part_size: Option<u64>,
+) -> Result<MultipartUpload, AppError> {
+    let url = presign_create_multipart_upload_url(&presign_params(credentials, key), Utc::now())?;
+    let resp = client
+        .post(&url)
+        .header("Content-Length", 0)
+        .send()
+        .await
+        .map_err(|e| AppError::network(format!("CreateMultipartUpload request failed: {}", e)))?;
+    let body = check_response(resp, "CreateMultipartUpload").await?;
+    let upload_id = xml_tag_text(&body, "UploadId")
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| AppError::s3_status(200, "CreateMultipartUpload returned no UploadId"))?;
+    let upload = MultipartUpload {
+        upload_id,
+        object_key: key.to_string(),
+        bucket: credentials.bucket.clone(),
+        source_path: source_path.to_string(),
+        file_size,
+        source_mtime,
+        part_size: part_size_for(file_size, part_size),
+    };
+    multipart::create_upload(conn, &upload).await?;
+    Ok(upload)
+}
+
+async fn abort_remote(
+    client: &reqwest::Client,
+    credentials: &S3Credentials,
+    upload: &MultipartUpload,
+) -> Result<(), AppError> {
+    let url = presign_abort_multipart_upload_url(
+        &presign_params(credentials, &upload.object_key),
+        &upload.upload_id,
+        Utc::now(),
+    )?;
+    let resp =
+        client.delete(&url).send().await.map_err(|e| {
+            AppError::network(format!("AbortMultipartUpload request failed: {}", e))
+        })?;
+    // 已不存在的上传视为已中止
+    if resp.status().as_u16() == 404 {
+        return Ok(());
+    }
+    check_response(resp, "AbortMultipartUpload").await?;
+    Ok(())
+}
+
+async fn upload_part(
+    client: &reqwest::Client,
+    credentials: &S3Credentials,
+    upload: &MultipartUpload,
+    file: &mut File,
+    part_number: u32,
+) -> Result<UploadedPart, AppError> {
+    let (offset, len) = upload.part_range(part_number);
+    let mut buf = vec![0u8; len as usize];
+    file.seek(SeekFrom::Start(offset))
+        .await
+        .map_err(|e| AppError::io(format!("Failed to seek part {}: {}", part_number, e)))?;
+    file.read_exact(&mut buf)
+        .await
+        .map_err(|e| AppError::io(format!("Failed to read part {}: {}", part_number, e)))?;
+
+    let url = presign_upload_part_url(
+        &presign_params(credentials, &upload.object_key),
+        &upload.upload_id,
+        part_number,
+        Utc::now(),
+    )?;
+    let resp = client
+        .put(&url)
+        .header("Content-Length", len)
+        .body(buf)
+        .send()
+        .await
+        .map_err(|e| {
+            AppError::network(format!("UploadPart {} request failed: {}", part_number, e))
+        })?;
+    let etag = resp
+        .headers()
+        .get("etag")
+        .and_then(|v| v.to_str().ok())
+        .map(String::from);
+    check_response(resp, &format!("UploadPart {}", part_number)).await?;
+    let etag = etag.filter(|v| !v.is_empty()).ok_or_else(|| {
+        AppError::s3_status(200, format!("UploadPart {} returned no ETag", part_number))
+    })?;
+    Ok(UploadedPart {
+        part_number,
+        etag,
+        size: len,
+    })
+}
+
+async fn complete(
+    client: &reqwest::Client,
+    credentials: &S3Credentials,
+    upload: &MultipartUpload,
+    parts: &[UploadedPart],
+) -> Result<(), AppError> {
+    let url = presign_complete_multipart_upload_url(
+        &presign_params(credentials, &upload.object_key),
+        &upload.upload_id,
+        Utc::now(),
+    )?;
+    let pairs: Vec<(u32, String)> = parts
+        .iter()
+        .map(|p| (p.part_number, p.etag.clone()))
+        .collect();
+    let resp = client
+        .post(&url)
+        .header("Content-Type", "application/xml")
+        .body(complete_multipart_upload_body(&pairs))
+        .send()
+        .await
+        .map_err(|e| AppError::network(format!("CompleteMultipartUpload request failed: {}", e)))?;
+    check_response(resp, "CompleteMultipartUpload").await?;
+    Ok(())
+}
+
+/// Upload `file` (read from `source_path`) to `key`, resuming a previous
+/// session for the same file when one is recorded. `on_progress` receives
+/// `(uploaded_bytes, total_bytes)` after every part, starting with the bytes
+/// already on S3.
+pub async fn upload_multipart<F: FnMut(u64, u64)>(
+    conn: &turso::Connection,
+    client: &reqwest::Client,
+    credentials: &S3Credentials,
+    key: &str,
+    source_path: &str,
+    mut file: File,
+    part_size: Option<u64>,
+    mut on_progress: F,
+) -> Result<(), AppError> {
+    let metadata = file
+        .metadata()
+        .await
+        .map_err(|e| AppError::io(format!("Failed to get metadata: {}", e)))?;
+    let file_size = metadata.len();
+    let source_mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64);
+
+    // 同一对象的旧会话只有在源文件一致（路径、大小、修改时间）时才能续传
+    let mut session = match multipart::find_upload(conn, &credentials.bucket, key).await? {
+        Some(old)
+            if old.source_path == source_path
+                && old.file_size == file_size
+                && old.source_mtime == source_mtime =>
+        {
+            Some(old)
+        }
+        Some(old) => {
+            if let Err(e) = abort_remote(client, credentials, &old).await {
+                log::warn!(
+                    "Failed to abort stale multipart upload {}: {}",
+                    old.upload_id,
+                    e.message
+                );
+            }
+            multipart::remove_upload(conn, &old.upload_id).await?;
+            None
+        }
+        None => None,
+    };
+
+    let mut restarted = false;
+    'session: loop {
+        let upload = match session.take() {
+            Some(upload) => upload,
+            None => {
+                create_session(
+                    conn,
+                    client,
+                    credentials,
+                    key,
+                    source_path,
+                    file_size,
+                    source_mtime,
+                    part_size,
+                )
+                .await?
+            }
+        };
+        let mut parts = multipart::list_parts(conn, &upload.upload_id).await?;
+        let mut uploaded: u64 = parts.iter().map(|p| p.size).sum();
+        on_progress(uploaded, file_size);
+
+        for part_number in 1..=upload.part_count() {
+            if parts.iter().any(|p| p.part_number == part_number) {
+                continue;
+            }
+            let part = match upload_part(client, credentials, &upload, &mut file, part_number).await
+            {
+                Ok(part) => part,
+                // 服务端已清理该上传（过期或被中止）：丢弃本地记录，从头开始一次
+                Err(e) if e.s3_status_code() == Some(404) && !restarted => {
+                    log::warn!(
+                        "Multipart upload {} is gone, restarting: {}",
+                        upload.upload_id,
+                        e.message
+                    );
+                    multipart::remove_upload(conn, &upload.upload_id).await?;
+                    restarted = true;
+                    continue 'session;
+                }
+                Err(e) => return Err(e),
+            };
+            multipart::record_part(conn, &upload.upload_id, &part).await?;
+            uploaded += part.size;
+            parts.push(part);
+            on_progress(uploaded, file_size);
+        }
+
+        complete(client, credentials, &upload, &parts).await?;
+        multipart::remove_upload(conn, &upload.upload_id).await?;
+        return Ok(());
+    }
+}
+
+/// Abort the recorded session for `key`, if any; `false` when none exists.
+pub(crate) async fn discard_session(
+    conn: &turso::Connection,
+    client: &reqwest::Client,
+    credentials: &S3Credentials,
+    key: &str,
+) -> Result<bool, AppError> {
+    let Some(upload) = multipart::find_upload(conn, &credentials.bucket, key).await? else {
+        return Ok(false);
+    };
+    abort_remote(client, credentials, &upload).await?;
+    multipart::remove_upload(conn, &upload.upload_id).await?;
+    Ok(true)
+}
+
+/// Credentials from command arguments; like `upload_token`, an endpoint
+/// without a scheme is treated as https and `profile` defaults to Bitiful.
+pub(crate) fn require_credentials(
+    bucket: String,
+    region: String,
+    endpoint: String,
+    access_key: String,
+    secret_key: String,
+    profile: Option<ProviderConfig>,
+) -> Result<S3Credentials, AppError> {
+    let endpoint = if endpoint.is_empty()
+        || endpoint.starts_with("http://")
+        || endpoint.starts_with("https://")
+    {
+        endpoint
+    } else {
+        format!("https://{}", endpoint)
+    };
+    S3Credentials::from_parts(
+        Some(bucket),
+        Some(region),
+        Some(endpoint),
+        Some(access_key),
+        Some(secret_key),
+        ProviderProfile::from_config(profile.as_ref()),
+    )
+    .ok_or_else(|| {
+        AppError::validation(
+            "S3 config is incomplete. Please configure your S3 bucket and credentials.",
+        )
+    })
+}
+
+/// 分片上传本地文件；中断后再次调用同一 key 与文件会从最后完成的分片续传
+#[tauri::command]
+pub async fn upload_file_multipart(
+    app: AppHandle,
+    state: State<'_, TursoDb>,
+    key: String,
+    path: String,
+    bucket: String,
+    region: String,
+    endpoint: String,
+    access_key: String,
+    secret_key: String,
+    part_size: Option<u64>,
+    profile: Option<ProviderConfig>,
+) -> Result<(), AppError> {
+    let credentials =
+        require_credentials(bucket, region, endpoint, access_key, secret_key, profile)?;
+    let conn = state.0.connect()?;
+    let file = open_upload_source(&app, &path).await?;
+    let client = reqwest::Client::new();
+    upload_multipart(
+        &conn,
+        &client,
+        &credentials,
+        &key,
+        &path,
+        file,
+        part_size,
+        |progress, total| {
+            let _ = app.emit(
+                "upload://progress",
+                json!({ "key": key, "progress": progress, "total": total }),
+            );
+        },
+    )
+    .await
+}
+
+/// 放弃分片上传：通知 S3 释放已上传的分片并删除本地记录
+#[tauri::command]
+pub async fn abort_multipart_upload(
+    state: State<'_, TursoDb>,
+    key: String,
+    bucket: String,
+    region: String,
+    endpoint: String,
+    access_key: String,
+    secret_key: String,
+    profile: Option<ProviderConfig>,
+) -> Result<JsonValue, AppError> {
+    let credentials =
+        require_credentials(bucket, region, endpoint, access_key, secret_key, profile)?;
+    let conn = state.0.connect()?;
+    let aborted = discard_session(&conn, &reqwest::Client::new(), &credentials, &key).await?;
+    Ok(json!({ "code": 0, "data": aborted }))
+}
+
+/// 未完成的分片上传及已上传字节数，用于展示可续传任务
+#[tauri::command]
+pub async fn multipart_upload_list(state: State<'_, TursoDb>) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let items: Vec<JsonValue> = multipart::list_uploads(&conn)
+        .await?
+        .into_iter()
+        .map(|(upload, uploaded)| {
+            let mut item = json!(upload);
+            item["uploadedBytes"] = json!(uploaded);
+            item
+        })
+        .collect();
+    Ok(json!({ "code": 0, "data": items }))
+}
@@ -0,0 +1,142 @@
+//! Perceptual hashing for near-duplicate detection.
+//!
+//! Uses a 64-bit difference hash (dHash): the image is reduced to a 9x8
+//! grayscale thumbnail and each bit records whether a pixel is brighter than
+//! its right neighbour. Re-encoded, resized or recompressed copies of the same
+//! photo end up a few bits apart, while unrelated photos differ in ~32 bits.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use image::imageops::FilterType;
+use image::DynamicImage;
+
+use crate::command::metadata;
+use crate::error::AppError;
+
+/// Default Hamming distance under which two photos count as the same shot.
+pub const DEFAULT_THRESHOLD: u32 = 6;
+
+/// dHash of an already decoded image.
+pub fn dhash(img: &DynamicImage) -> u64 {
+    let small = img.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = small.get_pixel(x, y)[0];
+            let right = small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | (left > right) as u64;
+        }
+    }
+    hash
+}
+
+/// Decode an image file and hash it upright, so a rotated-by-EXIF copy
+/// matches a copy that had the rotation baked into its pixels.
+pub fn dhash_file(path: &Path) -> Result<u64, AppError> {
+    let img = image::ImageReader::open(path)?
+        .with_guessed_format()?
+        .decode()
+        .map_err(|e| AppError::validation(format!("Failed to decode image: {}", e)))?;
+    let orientation = metadata::read_file(path)
+        .ok()
+        .and_then(|m| m.orientation)
+        .unwrap_or(1);
+    Ok(dhash(&apply_orientation(img, orientation)))
+}
+
+/// Rotate / flip pixels according to an EXIF orientation value (1-8).
+pub fn apply_orientation(img: DynamicImage, orientation: u16) -> DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
+
+pub fn hamming(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// 16 hex chars, the form stored in `photos.phash`.
+pub fn to_hex(hash: u64) -> String {
+    format!("{:016x}", hash)
+}
+
+pub fn parse_hex(value: &str) -> Option<u64> {
+    if value.len() != 16 {
+        return None;
+    }
+    u64::from_str_radix(value, 16).ok()
+}
+
+/// Group hashes whose Hamming distance is within `threshold` (transitively).
+/// Returns clusters of indices into `hashes`, each with at least two members.
+///
+/// Splitting the 64 bits into `threshold + 1` chunks means any two hashes
+/// within the threshold share at least one identical chunk (pigeonhole), so
+/// only hashes that collide in some chunk are compared.
+pub fn cluster(hashes: &[u64], threshold: u32) -> Vec<Vec<usize>> {
+    let threshold = threshold.min(63);
+    let chunks = threshold as usize + 1;
+    let mut parent: Vec<usize> = (0..hashes.len()).collect();
+
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    for chunk in 0..chunks {
+        // 第 chunk 段的位区间（尽量等分 64 位）
+        let start = chunk * 64 / chunks;
+        let end = (chunk + 1) * 64 / chunks;
+        let width = end - start;
+        let mask = if width == 64 {
+            u64::MAX
+        } else {
+            (1u64 << width) - 1
+        };
+        let mut buckets: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (i, h) in hashes.iter().enumerate() {
+            buckets.entry((h >> start) & mask).or_default().push(i);
+        }
+        for members in buckets.values().filter(|m| m.len() > 1) {
+            for (n, &a) in members.iter().enumerate() {
+                for &b in &members[n + 1..] {
+                    if hamming(hashes[a], hashes[b]) <= threshold {
+                        let (ra, rb) = (find(&mut parent, a), find(&mut parent, b));
+                        if ra != rb {
+                            parent[ra.max(rb)] = ra.min(rb);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..hashes.len() {
+        let root = find(&mut parent, i);
+        groups.entry(root).or_default().push(i);
+    }
+    let mut clusters: Vec<Vec<usize>> = groups.into_values().filter(|g| g.len() > 1).collect();
+    clusters.sort_by_key(|g| g[0]);
+    clusters
+}
+
+/// 计算本地图片的感知哈希（16 位十六进制），导入时写入 photos.phash
+#[tauri::command]
+pub async fn compute_phash(file_path: String) -> Result<String, AppError> {
+    tauri::async_runtime::spawn_blocking(move || dhash_file(Path::new(&file_path)))
+        .await
+        .map_err(|e| AppError::io(e.to_string()))?
+        .map(to_hex)
+}
@@ -0,0 +1,417 @@
+//! Header-signed S3 requests made directly from Rust.
+//!
+//! Unlike the presigned URLs handed to uploads, these calls sign every
+//! request with [`SigV4Signer::sign_headers`], including the payload hash
+//! and operation headers such as `Range` or `x-amz-copy-source`. Objects
+//! are addressed as the credentials' provider profile prescribes.
+
+use chrono::Utc;
+use reqwest::Method;
+
+use super::s3_presign::{
+    base64, sha256_hex, uri_encode, xml_escape, xml_tag_text, S3Credentials, SigV4Signer,
+};
+use crate::error::AppError;
+
+/// DeleteObjects 单次请求最多 1000 个 key
+pub const DELETE_BATCH_SIZE: usize = 1000;
+
+/// HeadObject 返回的对象元信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectHead {
+    pub content_length: u64,
+    pub etag: Option<String>,
+    pub content_type: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// ListObjectsV2 中的单个对象
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectSummary {
+    pub key: String,
+    pub size: u64,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// ListObjectsV2 的一页结果；`next_continuation_token` 为 None 表示已到末页
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectPage {
+    pub objects: Vec<ObjectSummary>,
+    pub next_continuation_token: Option<String>,
+}
+
+/// DeleteObjects 中删除失败的 key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeleteError {
+    pub key: String,
+    pub code: String,
+    pub message: Option<String>,
+}
+
+// S3 出错时即使状态码为 200 也可能返回根元素为 <Error> 的 XML
+pub(crate) async fn check_response(
+    resp: reqwest::Response,
+    action: &str,
+) -> Result<String, AppError> {
+    let status = resp.status();
+    let body = resp
+        .text()
+        .await
+        .map_err(|e| AppError::network(format!("{} response failed: {}", action, e)))?;
+    if !status.is_success() || is_error_document(&body) {
+        let code = xml_tag_text(&body, "Code").unwrap_or_else(|| status.to_string());
+        return Err(AppError::s3_status(
+            status.as_u16(),
+            format!("{} failed: {}", action, code),
+        ));
+    }
+    Ok(body)
+}
+
+// DeleteObjects 的结果里也有 <Error> 子元素，只有根元素是 <Error> 才算整体失败
+fn is_error_document(body: &str) -> bool {
+    let mut body = body.trim_start();
+    if body.starts_with("<?xml") {
+        body = body
+            .find("?>")
+            .map_or("", |end| body[end + 2..].trim_start());
+    }
+    body.starts_with("<Error>")
+}
+
+// 签名并发送请求；`key` 为 None 时请求桶本身（按服务商配置决定寻址方式）
+async fn send(
+    client: &reqwest::Client,
+    credentials: &S3Credentials,
+    method: Method,
+    key: Option<&str>,
+    query: &[(&str, String)],
+    headers: Vec<(&str, String)>,
+    body: Vec<u8>,
+) -> Result<reqwest::Response, AppError> {
+    let target = credentials
+        .profile
+        .locate(&credentials.endpoint, &credentials.bucket, key)?;
+    let payload_hash = sha256_hex(&body);
+    let mut headers = headers;
+    headers.push(("host", target.host.clone()));
+    headers.push(("x-amz-content-sha256", payload_hash.clone()));
+    let signed = SigV4Signer::s3(
+        &credentials.access_key,
+        &credentials.secret_key,
+        &credentials.region,
+    )
+    .sign_headers(
+        method.as_str(),
+        &target.canonical_uri,
+        query,
+        &headers,
+        &payload_hash,
+        Utc::now(),
+    );
+
+    // 查询串与签名时一致：逐项编码后按名称排序
+    let mut pairs: Vec<String> = query
+        .iter()
+        .map(|(name, value)| format!("{}={}", uri_encode(name, true), uri_encode(value, true)))
+        .collect();
+    pairs.sort();
+    let mut url = format!("{}{}", target.base, target.canonical_uri);
+    if !pairs.is_empty() {
+        url.push('?');
+        url.push_str(&pairs.join("&"));
+    }
+
+    let mut request = client.request(method.clone(), &url).body(body);
+    // host 由 reqwest 按 URL 生成
+    for (name, value) in headers.into_iter().chain(signed) {
+        if name != "host" {
+            request = request.header(name, value);
+        }
+    }
+    request
+        .send()
+        .await
+        .map_err(|e| AppError::network(format!("S3 {} request failed: {}", method, e)))
+}
+
+fn header_text(resp: &reqwest::Response, name: &str) -> Option<String> {
+    resp.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from)
+}
+
+/// HeadObject：对象不存在时返回 None
+pub async fn head_object(
+    client: &reqwest::Client,
+    credentials: &S3Credentials,
+    key: &str,
+) -> Result<Option<ObjectHead>, AppError> {
+    let resp = send(
+        client,
+        credentials,
+        Method::HEAD,
+        Some(key),
+        &[],
+        vec![],
+        vec![],
+    )
+    .await?;
+    let status = resp.status();
+    if status.as_u16() == 404 {
+        return Ok(None);
+    }
+    // HEAD 响应没有正文，只能按状态码报错
+    if !status.is_success() {
+        return Err(AppError::s3_status(
+            status.as_u16(),
+            format!("HeadObject failed: {}", status),
+        ));
+    }
+    Ok(Some(ObjectHead {
+        content_length: header_text(&resp, "content-length")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0),
+        etag: header_text(&resp, "etag"),
+        content_type: header_text(&resp, "content-type"),
+        last_modified: header_text(&resp, "last-modified"),
+    }))
+}
+
+/// ListObjectsV2 单页；传入上一页的 `next_continuation_token` 继续列举
+pub async fn list_objects_v2(
+    client: &reqwest::Client,
+    credentials: &S3Credentials,
+    prefix: Option<&str>,
+    continuation_token: Option<&str>,
+    max_keys: Option<u32>,
+) -> Result<ObjectPage, AppError> {
+    let mut query = vec![("list-type", "2".to_string())];
+    if let Some(prefix) = prefix.filter(|p| !p.is_empty()) {
+        query.push(("prefix", prefix.to_string()));
+    }
+    if let Some(token) = continuation_token {
+        query.push(("continuation-token", token.to_string()));
+    }
+    if let Some(max_keys) = max_keys {
+        query.push(("max-keys", max_keys.to_string()));
+    }
+    let resp = send(
+        client,
+        credentials,
+        Method::GET,
+        None,
+        &query,
+        vec![],
+        vec![],
+    )
+    .await?;
+    let body = check_response(resp, "ListObjectsV2").await?;
+
+    let objects = xml_blocks(&body, "Contents")
+        .into_iter()
+        .filter_map(|item| {
+            Some(ObjectSummary {
+                key: xml_tag_text(item, "Key")?,
+                size: xml_tag_text(item, "Size")
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(0),
+                etag: xml_tag_text(item, "ETag"),
+                last_modified: xml_tag_text(item, "LastModified"),
+            })
+        })
+        .collect();
+    let truncated = xml_tag_text(&body, "IsTruncated").as_deref() == Some("true");
+    let next_continuation_token =
+        xml_tag_text(&body, "NextContinuationToken").filter(|token| truncated && !token.is_empty());
+    if truncated && next_continuation_token.is_none() {
+        return Err(AppError::s3_status(
+            200,
+            "ListObjectsV2 truncated without NextContinuationToken",
+        ));
+    }
+    Ok(ObjectPage {
+        objects,
+        next_continuation_token,
+    })
+}
+
+/// 跟随 continuation token 列出前缀下的全部对象
+pub async fn list_all_objects(
+    client: &reqwest::Client,
+    credentials: &S3Credentials,
+    prefix: Option<&str>,
+) -> Result<Vec<ObjectSummary>, AppError> {
+    let mut objects = Vec::new();
+    let mut token: Option<String> = None;
+    loop {
+        let page = list_objects_v2(client, credentials, prefix, token.as_deref(), None).await?;
+        objects.extend(page.objects);
+        match page.next_continuation_token {
+            // 服务端返回相同 token 时停止，避免死循环
+            Some(next) if token.as_deref() != Some(next.as_str()) => token = Some(next),
+            Some(_) => {
+                return Err(AppError::s3_status(
+                    200,
+                    "ListObjectsV2 returned the same continuation token",
+                ))
+            }
+            None => return Ok(objects),
+        }
+    }
+}
+
+/// CopyObject：同桶内复制，返回新对象的 ETag
+pub async fn copy_object(
+    client: &reqwest::Client,
+    credentials: &S3Credentials,
+    source_key: &str,
+    dest_key: &str,
+) -> Result<Option<String>, AppError> {
+    let source = format!(
+        "/{}/{}",
+        uri_encode(&credentials.bucket, true),
+        uri_encode(source_key, false)
+    );
+    let resp = send(
+        client,
+        credentials,
+        Method::PUT,
+        Some(dest_key),
+        &[],
+        vec![("x-amz-copy-source", source)],
+        vec![],
+    )
+    .await?;
+    let body = check_response(resp, "CopyObject").await?;
+    Ok(xml_tag_text(&body, "ETag"))
+}
+
+/// DeleteObjects 请求体（Quiet 模式：响应只列出失败的 key）
+pub fn delete_objects_body(keys: &[String]) -> String {
+    let objects: String = keys
+        .iter()
+        .map(|key| format!("<Object><Key>{}</Key></Object>", xml_escape(key)))
+        .collect();
+    format!(
+        "<Delete xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\"><Quiet>true</Quiet>{}</Delete>",
+        objects
+    )
+}
+
+/// DeleteObjects：按每批 1000 个批量删除，返回删除失败的 key；
+/// 不存在的 key 在 S3 中视为删除成功
+pub async fn delete_objects(
+    client: &reqwest::Client,
+    credentials: &S3Credentials,
+    keys: &[String],
+) -> Result<Vec<DeleteError>, AppError> {
+    let mut errors = Vec::new();
+    for batch in keys.chunks(DELETE_BATCH_SIZE) {
+        let body = delete_objects_body(batch);
+        // DeleteObjects 要求 Content-MD5
+        let content_md5 = base64(&md5::compute(body.as_bytes()).0);
+        let resp = send(
+            client,
+            credentials,
+            Method::POST,
+            None,
+            &[("delete", String::new())],
+            vec![
+                ("content-md5", content_md5),
+                ("content-type", "application/xml".to_string()),
+            ],
+            body.into_bytes(),
+        )
+        .await?;
+        let body = check_response(resp, "DeleteObjects").await?;
+        errors.extend(
+            xml_blocks(&body, "Error")
+                .into_iter()
+                .map(|item| DeleteError {
+                    key: xml_tag_text(item, "Key").unwrap_or_default(),
+                    code: xml_tag_text(item, "Code").unwrap_or_default(),
+                    message: xml_tag_text(item, "Message"),
+                }),
+        );
+    }
+    Ok(errors)
+}
+
+/// 回收站清理用的批量删除：返回失败个数，整批请求失败时该批 key 全部计为失败，不中断调用方
+pub async fn purge_objects(credentials: &S3Credentials, keys: &[String]) -> u32 {
+    let client = reqwest::Client::new();
+    let mut failed = 0usize;
+    for batch in keys.chunks(DELETE_BATCH_SIZE) {
+        match delete_objects(&client, credentials, batch).await {
+            Ok(errors) => failed += errors.len(),
+            Err(e) => {
+                log::warn!("DeleteObjects failed for {} keys: {}", batch.len(), e.message);
+                failed += batch.len();
+            }
+        }
+    }
+    failed as u32
+}
+
+/// GetObject 指定字节范围（含两端）；`end` 为 None 时读到对象末尾
+pub async fn get_object_range(
+    client: &reqwest::Client,
+    credentials: &S3Credentials,
+    key: &str,
+    start: u64,
+    end: Option<u64>,
+) -> Result<Vec<u8>, AppError> {
+    if end.is_some_and(|end| end < start) {
+        return Err(AppError::validation(format!(
+            "Invalid byte range: {}-{}",
+            start,
+            end.unwrap_or_default()
+        )));
+    }
+    let range = match end {
+        Some(end) => format!("bytes={}-{}", start, end),
+        None => format!("bytes={}-", start),
+    };
+    let resp = send(
+        client,
+        credentials,
+        Method::GET,
+        Some(key),
+        &[],
+        vec![("range", range)],
+        vec![],
+    )
+    .await?;
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        let code = xml_tag_text(&body, "Code").unwrap_or_else(|| status.to_string());
+        return Err(AppError::s3_status(
+            status.as_u16(),
+            format!("GetObject failed: {}", code),
+        ));
+    }
+    let bytes = resp
+        .bytes()
+        .await
+        .map_err(|e| AppError::network(format!("GetObject response failed: {}", e)))?;
+    Ok(bytes.to_vec())
+}
+
+// 依次取出 XML 中所有 `<tag>...</tag>` 的内容（不反转义，供 xml_tag_text 继续解析）
+fn xml_blocks<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let mut blocks = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let inner = &rest[start + open.len()..];
+        let Some(end) = inner.find(&close) else { break };
+        blocks.push(&inner[..end]);
+        rest = &inner[end + close.len()..];
+    }
+    blocks
+}
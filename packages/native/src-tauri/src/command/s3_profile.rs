@@ -0,0 +1,219 @@
+//! Provider profiles for S3-compatible storage.
+//!
+//! Each storage config names its provider; the profile decides how object
+//! URLs are addressed (path-style `/{bucket}/{key}` or virtual-hosted
+//! `{bucket}.{host}/{key}`), which region to sign with when none is
+//! configured, an optional path prefix in front of every object path, and
+//! whether uploads sign a Content-MD5 the provider verifies.
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use super::s3_presign::{host_header, normalize_endpoint, uri_encode, PayloadChecksum};
+use crate::error::AppError;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum S3Provider {
+    #[default]
+    Bitiful,
+    /// Cloudflare R2
+    R2,
+    /// 阿里云 OSS（S3 兼容接口）
+    Oss,
+    Minio,
+    /// Backblaze B2
+    B2,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressingStyle {
+    /// `https://host/{bucket}/{key}`
+    #[default]
+    Path,
+    /// `https://{bucket}.host/{key}`
+    VirtualHosted,
+}
+
+/// 前端存储配置中的服务商设置，未填写的项使用服务商默认值
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderConfig {
+    pub provider: Option<S3Provider>,
+    pub addressing_style: Option<AddressingStyle>,
+    pub path_prefix: Option<String>,
+    /// 覆盖服务商是否校验 Content-MD5（如网关会改写请求体时关闭）
+    pub content_md5: Option<bool>,
+}
+
+/// Resolved provider behaviour used by every presign function and by
+/// [`super::s3_client`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderProfile {
+    pub provider: S3Provider,
+    pub addressing_style: AddressingStyle,
+    /// 对象路径前缀（已去掉首尾 `/`），如反向代理下的 `minio`
+    pub path_prefix: String,
+    /// 是否在 PUT 中签入 Content-MD5 并比对 ETag
+    pub content_md5: bool,
+}
+
+impl Default for ProviderProfile {
+    fn default() -> Self {
+        Self::for_provider(S3Provider::default())
+    }
+}
+
+/// 签名与请求共用的对象定位：`base` 为 `scheme://host`，`canonical_uri` 已 URI 编码
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct S3Target {
+    pub base: String,
+    pub host: String,
+    pub canonical_uri: String,
+}
+
+impl ProviderProfile {
+    pub fn for_provider(provider: S3Provider) -> Self {
+        let addressing_style = match provider {
+            S3Provider::Bitiful | S3Provider::R2 | S3Provider::Minio => AddressingStyle::Path,
+            // OSS 禁止 path-style 访问（SecondLevelDomainForbidden）
+            S3Provider::Oss | S3Provider::B2 => AddressingStyle::VirtualHosted,
+        };
+        ProviderProfile {
+            provider,
+            addressing_style,
+            path_prefix: String::new(),
+            // 以上服务商的 PutObject 均校验 Content-MD5，需要时由配置关闭
+            content_md5: true,
+        }
+    }
+
+    /// 服务商默认值叠加配置中的覆盖项；未传配置时为 Bitiful
+    pub fn from_config(config: Option<&ProviderConfig>) -> Self {
+        let Some(config) = config else {
+            return Self::default();
+        };
+        let mut profile = Self::for_provider(config.provider.unwrap_or_default());
+        if let Some(style) = config.addressing_style {
+            profile.addressing_style = style;
+        }
+        if let Some(prefix) = &config.path_prefix {
+            profile.path_prefix = prefix.trim_matches('/').to_string();
+        }
+        if let Some(content_md5) = config.content_md5 {
+            profile.content_md5 = content_md5;
+        }
+        profile
+    }
+
+    /// Region to sign with when the config leaves it empty. OSS and B2
+    /// encode the region in their endpoint host, so it is taken from there.
+    pub fn default_region(&self, endpoint: &str) -> String {
+        let host = normalize_endpoint(endpoint)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+            .unwrap_or_default();
+        let labels: Vec<&str> = host.split('.').collect();
+        match self.provider {
+            S3Provider::Bitiful | S3Provider::Minio => "us-east-1".to_string(),
+            S3Provider::R2 => "auto".to_string(),
+            // oss-cn-hangzhou.aliyuncs.com -> cn-hangzhou
+            S3Provider::Oss => labels
+                .iter()
+                .find_map(|label| label.strip_prefix("oss-"))
+                .filter(|region| !region.is_empty())
+                .unwrap_or("cn-hangzhou")
+                .to_string(),
+            // s3.us-west-004.backblazeb2.com -> us-west-004
+            S3Provider::B2 => match labels.as_slice() {
+                ["s3", region, "backblazeb2", "com"] => region.to_string(),
+                _ => "us-west-004".to_string(),
+            },
+        }
+    }
+
+    /// 配置的 region，为空时取服务商默认值
+    pub fn resolve_region(&self, region: &str, endpoint: &str) -> String {
+        if region.trim().is_empty() {
+            self.default_region(endpoint)
+        } else {
+            region.trim().to_string()
+        }
+    }
+
+    /// 按服务商支持情况决定上传是否签入 Content-MD5
+    pub fn md5_checksum<'a>(&self, md5: Option<&'a str>) -> Option<PayloadChecksum<'a>> {
+        md5.filter(|v| self.content_md5 && !v.is_empty())
+            .map(PayloadChecksum::Md5)
+    }
+
+    /// Locate `key` in `bucket` (the bucket itself when `key` is `None`).
+    /// A path on the endpoint is kept and, like `path_prefix`, goes in
+    /// front of the object path.
+    pub(crate) fn locate(
+        &self,
+        endpoint: &str,
+        bucket: &str,
+        key: Option<&str>,
+    ) -> Result<S3Target, AppError> {
+        let endpoint = normalize_endpoint(endpoint)?;
+        let mut host = host_header(&endpoint)?;
+        let mut uri = self.prefix_path(&endpoint);
+        match self.addressing_style {
+            AddressingStyle::Path => {
+                uri.push('/');
+                uri.push_str(&uri_encode(bucket, true));
+            }
+            AddressingStyle::VirtualHosted => {
+                if !is_dns_compatible_bucket(bucket) {
+                    return Err(AppError::validation(format!(
+                        "Bucket '{}' can't be used with virtual-hosted-style addressing.",
+                        bucket
+                    )));
+                }
+                host = format!("{}.{}", bucket, host);
+            }
+        }
+        match key {
+            Some(key) => {
+                uri.push('/');
+                uri.push_str(&uri_encode(key, false));
+            }
+            // 虚拟主机风格下桶本身的路径为 `/`
+            None if uri.is_empty() => uri.push('/'),
+            None => {}
+        }
+        Ok(S3Target {
+            base: format!("{}://{}", endpoint.scheme(), host),
+            host,
+            canonical_uri: uri,
+        })
+    }
+
+    // endpoint 自带路径与配置前缀合并为 `/a/b`，没有前缀时为空
+    fn prefix_path(&self, endpoint: &Url) -> String {
+        let mut path = String::new();
+        let endpoint_path = endpoint.path().trim_matches('/');
+        if !endpoint_path.is_empty() {
+            path.push('/');
+            path.push_str(endpoint_path);
+        }
+        if !self.path_prefix.is_empty() {
+            path.push('/');
+            path.push_str(&uri_encode(&self.path_prefix, false));
+        }
+        path
+    }
+}
+
+// 虚拟主机风格要求桶名可作为 DNS 标签：小写字母、数字、`-`、`.`
+fn is_dns_compatible_bucket(bucket: &str) -> bool {
+    (3..=63).contains(&bucket.len())
+        && bucket
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'.')
+        && !bucket.starts_with(['-', '.'])
+        && !bucket.ends_with(['-', '.'])
+}
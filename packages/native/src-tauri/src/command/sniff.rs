@@ -0,0 +1,261 @@
+//! Content-based MIME detection from file signatures.
+//!
+//! Used by the desktop `get_file_info` so uploads with a wrong or missing
+//! extension still get the right `type` (the photo list filters on
+//! `type LIKE 'image%'`). The extension map is only a fallback for formats
+//! without a usable signature.
+
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Bytes read from the start of a file; enough for TIFF-based RAW headers.
+pub const SNIFF_LEN: usize = 64 * 1024;
+
+/// Detect the MIME type of a file from its content, falling back to the
+/// extension when the signature is unknown.
+pub fn detect_file(path: &Path) -> io::Result<Option<&'static str>> {
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    std::fs::File::open(path)?
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut head)?;
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+    Ok(sniff(&head).or_else(|| from_extension(ext)))
+}
+
+/// MIME type from the leading bytes of a file.
+pub fn sniff(head: &[u8]) -> Option<&'static str> {
+    let at = |offset: usize, sig: &[u8]| head.get(offset..offset + sig.len()) == Some(sig);
+
+    // ---------- 图片 ----------
+    if at(0, &[0xFF, 0xD8, 0xFF]) {
+        return Some("image/jpeg");
+    }
+    if at(0, b"\x89PNG\r\n\x1a\n") {
+        return Some("image/png");
+    }
+    if at(0, b"GIF87a") || at(0, b"GIF89a") {
+        return Some("image/gif");
+    }
+    if at(0, b"BM") && head.len() >= 14 && at(6, &[0, 0, 0, 0]) {
+        return Some("image/bmp");
+    }
+    if at(0, &[0, 0, 1, 0]) && head.len() >= 6 && head[4] > 0 {
+        return Some("image/x-icon");
+    }
+    if at(0, &[0xFF, 0x0A]) || at(0, b"\0\0\0\x0cJXL \r\n\x87\n") {
+        return Some("image/jxl");
+    }
+    if at(0, b"8BPS") {
+        return Some("image/vnd.adobe.photoshop");
+    }
+    if at(0, b"FUJIFILMCCD-RAW") {
+        return Some("image/x-fuji-raf");
+    }
+    if at(0, b"IIRO") || at(0, b"IIRS") {
+        return Some("image/x-olympus-orf");
+    }
+    if at(0, b"IIU\0") {
+        return Some("image/x-panasonic-rw2");
+    }
+    if at(0, b"II*\0") || at(0, b"MM\0*") {
+        return Some(sniff_tiff(head));
+    }
+
+    // ---------- RIFF 容器 ----------
+    if at(0, b"RIFF") {
+        return match head.get(8..12)? {
+            b"WEBP" => Some("image/webp"),
+            b"AVI " => Some("video/x-msvideo"),
+            b"WAVE" => Some("audio/wav"),
+            _ => None,
+        };
+    }
+
+    // ---------- ISO-BMFF ----------
+    if at(4, b"ftyp") {
+        return sniff_bmff(head);
+    }
+
+    // ---------- 其他视频 ----------
+    if at(0, &[0x1A, 0x45, 0xDF, 0xA3]) {
+        // EBML DocType 决定是 WebM 还是通用 Matroska
+        let window = &head[..head.len().min(64)];
+        return Some(if window.windows(4).any(|w| w == b"webm") {
+            "video/webm"
+        } else {
+            "video/x-matroska"
+        });
+    }
+    if at(0, b"FLV\x01") {
+        return Some("video/x-flv");
+    }
+    if at(0, &[0x30, 0x26, 0xB2, 0x75, 0x8E, 0x66, 0xCF, 0x11]) {
+        return Some("video/x-ms-asf");
+    }
+    if at(0, &[0, 0, 1, 0xBA]) || at(0, &[0, 0, 1, 0xB3]) {
+        return Some("video/mpeg");
+    }
+    // MPEG-TS：每 188 字节一个 0x47 同步字节
+    if head.len() >= 188 * 3 && (0..3).all(|i| head[i * 188] == 0x47) {
+        return Some("video/mp2t");
+    }
+
+    // ---------- 音频 ----------
+    if at(0, b"OggS") {
+        let window = &head[..head.len().min(64)];
+        let has = |sig: &[u8]| window.windows(sig.len()).any(|w| w == sig);
+        return Some(if has(b"OpusHead") {
+            "audio/opus"
+        } else if has(b"\x80theora") {
+            "video/ogg"
+        } else {
+            "audio/ogg"
+        });
+    }
+    if at(0, b"fLaC") {
+        return Some("audio/flac");
+    }
+    if at(0, b"ID3") {
+        return Some("audio/mpeg");
+    }
+    if at(0, b"#!AMR") {
+        return Some("audio/amr");
+    }
+    if at(0, b"FORM") && (at(8, b"AIFF") || at(8, b"AIFC")) {
+        return Some("audio/aiff");
+    }
+    if at(0, b"MThd") {
+        return Some("audio/midi");
+    }
+    if head.len() >= 2 && head[0] == 0xFF && head[1] & 0xE0 == 0xE0 {
+        // 帧同步：layer 位为 00 时是 AAC ADTS，否则是 MPEG 音频
+        return Some(if head[1] & 0x06 == 0 {
+            "audio/aac"
+        } else {
+            "audio/mpeg"
+        });
+    }
+    None
+}
+
+fn sniff_bmff(head: &[u8]) -> Option<&'static str> {
+    let size = u32::from_be_bytes(head.get(0..4)?.try_into().ok()?) as usize;
+    let end = size.clamp(16, head.len().max(16)).min(head.len());
+    // major brand 在前，其后是兼容品牌列表（跳过 minor version）
+    let brands = std::iter::once(head.get(8..12)?)
+        .chain(head.get(16..end).unwrap_or(&[]).chunks_exact(4));
+    let mut fallback = None;
+    for brand in brands {
+        let mime = match brand {
+            b"heic" | b"heix" | b"heim" | b"heis" | b"hevc" | b"hevx" => "image/heic",
+            b"avif" | b"avis" => "image/avif",
+            b"crx " => "image/x-canon-cr3",
+            b"qt  " => "video/quicktime",
+            b"3g2a" | b"3g2b" | b"3g2c" => "video/3gpp2",
+            b"M4V " | b"M4VH" | b"M4VP" => "video/x-m4v",
+            b"M4A " | b"M4B " | b"M4P " => "audio/mp4",
+            b if b.starts_with(b"3gp") || b.starts_with(b"3gs") => "video/3gpp",
+            b"isom" | b"iso2" | b"iso4" | b"iso5" | b"iso6" | b"mp41" | b"mp42" | b"avc1"
+            | b"dash" | b"MSNV" | b"XAVC" | b"f4v " => {
+                // 通用品牌只作兜底，继续看是否有更具体的品牌
+                fallback = fallback.or(Some("video/mp4"));
+                continue;
+            }
+            // HEIF 通用品牌：iPhone 的 HEIC 常以 mif1 为主品牌
+            b"mif1" | b"msf1" => {
+                fallback = Some("image/heif");
+                continue;
+            }
+            _ => continue,
+        };
+        return Some(mime);
+    }
+    fallback
+}
+
+/// Tell the TIFF-based RAW formats apart from plain TIFF.
+fn sniff_tiff(head: &[u8]) -> &'static str {
+    let little = head.starts_with(b"II");
+    let u16_at = |at: usize| {
+        let b: [u8; 2] = head.get(at..at + 2)?.try_into().ok()?;
+        Some(if little {
+            u16::from_le_bytes(b)
+        } else {
+            u16::from_be_bytes(b)
+        })
+    };
+    let u32_at = |at: usize| {
+        let b: [u8; 4] = head.get(at..at + 4)?.try_into().ok()?;
+        Some(if little {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        })
+    };
+
+    if head.get(8..10) == Some(b"CR") {
+        return "image/x-canon-cr2";
+    }
+    let Some(ifd) = u32_at(4).map(|v| v as usize) else {
+        return "image/tiff";
+    };
+    let mut make = String::new();
+    for i in 0..u16_at(ifd).unwrap_or(0) as usize {
+        let entry = ifd + 2 + i * 12;
+        match u16_at(entry) {
+            // DNGVersion
+            Some(0xC612) => return "image/x-adobe-dng",
+            // Make
+            Some(0x010F) => {
+                let count = u32_at(entry + 4).unwrap_or(0) as usize;
+                let value_at = if count <= 4 {
+                    Some(entry + 8)
+                } else {
+                    u32_at(entry + 8).map(|v| v as usize)
+                };
+                if let Some(raw) = value_at.and_then(|at| head.get(at..at + count)) {
+                    make = String::from_utf8_lossy(raw).to_uppercase();
+                }
+            }
+            _ => {}
+        }
+    }
+    if make.starts_with("NIKON") {
+        "image/x-nikon-nef"
+    } else if make.starts_with("SONY") {
+        "image/x-sony-arw"
+    } else if make.starts_with("PENTAX") || make.starts_with("RICOH") {
+        "image/x-pentax-pef"
+    } else {
+        "image/tiff"
+    }
+}
+
+/// Extension-based guess for files whose content has no known signature.
+pub fn from_extension(ext: &str) -> Option<&'static str> {
+    Some(match ext.to_lowercase().as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "heic" => "image/heic",
+        "heif" => "image/heif",
+        "avif" => "image/avif",
+        "dng" => "image/x-adobe-dng",
+        "mp4" => "video/mp4",
+        "m4v" => "video/x-m4v",
+        "mov" => "video/quicktime",
+        "3gp" => "video/3gpp",
+        "avi" => "video/x-msvideo",
+        "mkv" => "video/x-matroska",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "flac" => "audio/flac",
+        "aac" => "audio/aac",
+        "m4a" => "audio/mp4",
+        "ogg" | "oga" => "audio/ogg",
+        "opus" => "audio/opus",
+        _ => return None,
+    })
+}
@@ -0,0 +1,409 @@
+//! Local thumbnail generation and on-disk LRU cache.
+//!
+//! Thumbnails live under `<app_cache_dir>/thumbnails/<md5[..2]>/<md5>_<size>.<ext>`
+//! and are served to the webview through the asset protocol (`$APPCACHE/**`
+//! is in scope), so grid views don't have to download full objects. The cache
+//! keeps its total size under a configurable budget by evicting the least
+//! recently used files; access order is mirrored into file mtimes so it
+//! survives restarts.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::command::common::calculate_md5;
+use crate::command::{metadata, phash};
+use crate::error::AppError;
+
+/// Edge lengths (longest side, px) a thumbnail can be generated at.
+pub const THUMBNAIL_SIZES: [u32; 3] = [256, 512, 1024];
+pub const DEFAULT_THUMBNAIL_SIZE: u32 = 256;
+pub const DEFAULT_BUDGET_BYTES: u64 = 512 * 1024 * 1024;
+
+const JPEG_QUALITY: u8 = 80;
+const WEBP_QUALITY: f32 = 75.0;
+const CONFIG_FILE: &str = "config.json";
+
+/// Encoded format of a thumbnail. WebP is lossy (libwebp); the `image`
+/// crate's own WebP encoder is lossless only and far larger than JPEG.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThumbnailFormat {
+    Jpeg,
+    WebP,
+}
+
+impl ThumbnailFormat {
+    pub fn parse(value: Option<&str>) -> Result<Self, AppError> {
+        match value.map(|v| v.to_ascii_lowercase()).as_deref() {
+            None | Some("jpeg") | Some("jpg") => Ok(Self::Jpeg),
+            Some("webp") => Ok(Self::WebP),
+            Some(other) => Err(AppError::validation(format!(
+                "Unsupported thumbnail format: {}",
+                other
+            ))),
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::WebP => "webp",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Thumbnail {
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+    pub bytes: u64,
+    /// 命中缓存（未重新生成）
+    pub hit: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheUsage {
+    pub bytes: u64,
+    pub count: usize,
+    pub budget_bytes: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CacheConfig {
+    budget_bytes: u64,
+}
+
+struct Entry {
+    bytes: u64,
+    access: u64,
+}
+
+// 内存中的 LRU 索引，首次使用时扫描磁盘建立
+#[derive(Default)]
+struct CacheIndex {
+    loaded: bool,
+    budget: u64,
+    total: u64,
+    tick: u64,
+    entries: HashMap<PathBuf, Entry>,
+}
+
+impl CacheIndex {
+    // 新增或刷新访问顺序（重复插入时修正总大小）
+    fn insert(&mut self, path: PathBuf, bytes: u64) {
+        self.tick += 1;
+        let old = self.entries.insert(
+            path,
+            Entry {
+                bytes,
+                access: self.tick,
+            },
+        );
+        self.total = self.total - old.map(|e| e.bytes).unwrap_or(0) + bytes;
+    }
+
+    /// Drop least recently used files until the total fits the budget.
+    /// `keep` (the thumbnail just served) is never evicted.
+    fn evict(&mut self, keep: Option<&Path>) -> usize {
+        if self.total <= self.budget {
+            return 0;
+        }
+        let mut order: Vec<(u64, PathBuf)> = self
+            .entries
+            .iter()
+            .filter(|(path, _)| Some(path.as_path()) != keep)
+            .map(|(path, entry)| (entry.access, path.clone()))
+            .collect();
+        order.sort();
+        let mut removed = 0;
+        for (_, path) in order {
+            if self.total <= self.budget {
+                break;
+            }
+            if let Some(entry) = self.entries.remove(&path) {
+                let _ = fs::remove_file(&path);
+                self.total -= entry.bytes;
+                removed += 1;
+            }
+        }
+        removed
+    }
+}
+
+/// Thumbnail cache rooted at a directory; cheap to clone, managed as Tauri state.
+#[derive(Clone)]
+pub struct ThumbnailCache {
+    dir: PathBuf,
+    index: Arc<Mutex<CacheIndex>>,
+}
+
+impl ThumbnailCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            index: Arc::new(Mutex::new(CacheIndex::default())),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Return the cached thumbnail for `md5`, generating it from `source` on a miss.
+    pub fn get_or_create(
+        &self,
+        source: &Path,
+        md5: &str,
+        size: u32,
+        format: ThumbnailFormat,
+    ) -> Result<Thumbnail, AppError> {
+        if !THUMBNAIL_SIZES.contains(&size) {
+            return Err(AppError::validation(format!(
+                "Unsupported thumbnail size: {} (expected one of {:?})",
+                size, THUMBNAIL_SIZES
+            )));
+        }
+        let md5 = md5.to_ascii_lowercase();
+        if md5.len() != 32 || !md5.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(AppError::validation(format!("Invalid md5: {}", md5)));
+        }
+        let path =
+            self.dir
+                .join(&md5[..2])
+                .join(format!("{}_{}.{}", md5, size, format.extension()));
+
+        let mut index = self.lock()?;
+        if let Ok(existing) = image::image_dimensions(&path) {
+            // 命中：更新访问顺序，并同步到 mtime 以便重启后仍有效
+            let _ = fs::File::options()
+                .write(true)
+                .open(&path)
+                .and_then(|f| f.set_modified(SystemTime::now()));
+            let bytes = fs::metadata(&path)?.len();
+            index.insert(path.clone(), bytes);
+            return Ok(Thumbnail {
+                path: path.to_string_lossy().to_string(),
+                width: existing.0,
+                height: existing.1,
+                bytes,
+                hit: true,
+            });
+        }
+        drop(index);
+
+        // 解码与编码较慢，不持有锁
+        let (width, height) = render(source, &path, size, format)?;
+        let bytes = fs::metadata(&path)?.len();
+
+        let mut index = self.lock()?;
+        index.insert(path.clone(), bytes);
+        index.evict(Some(&path));
+        Ok(Thumbnail {
+            path: path.to_string_lossy().to_string(),
+            width,
+            height,
+            bytes,
+            hit: false,
+        })
+    }
+
+    pub fn usage(&self) -> Result<CacheUsage, AppError> {
+        let index = self.lock()?;
+        Ok(CacheUsage {
+            bytes: index.total,
+            count: index.entries.len(),
+            budget_bytes: index.budget,
+        })
+    }
+
+    /// Delete every cached thumbnail; returns what was freed.
+    pub fn clear(&self) -> Result<CacheUsage, AppError> {
+        let mut index = self.lock()?;
+        let freed = CacheUsage {
+            bytes: index.total,
+            count: index.entries.len(),
+            budget_bytes: index.budget,
+        };
+        for path in index.entries.keys() {
+            let _ = fs::remove_file(path);
+        }
+        index.entries.clear();
+        index.total = 0;
+        Ok(freed)
+    }
+
+    /// Change the size budget (persisted) and evict down to it right away.
+    pub fn set_budget(&self, budget_bytes: u64) -> Result<CacheUsage, AppError> {
+        let mut index = self.lock()?;
+        fs::create_dir_all(&self.dir)?;
+        fs::write(
+            self.dir.join(CONFIG_FILE),
+            serde_json::to_vec(&CacheConfig { budget_bytes })?,
+        )?;
+        index.budget = budget_bytes;
+        index.evict(None);
+        Ok(CacheUsage {
+            bytes: index.total,
+            count: index.entries.len(),
+            budget_bytes,
+        })
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, CacheIndex>, AppError> {
+        let mut guard = self
+            .index
+            .lock()
+            .map_err(|_| AppError::io("Thumbnail cache lock poisoned"))?;
+        if !guard.loaded {
+            *guard = self.scan();
+        }
+        Ok(guard)
+    }
+
+    // 扫描磁盘：按 mtime 还原访问顺序，读取预算配置
+    fn scan(&self) -> CacheIndex {
+        let budget = fs::read(self.dir.join(CONFIG_FILE))
+            .ok()
+            .and_then(|raw| serde_json::from_slice::<CacheConfig>(&raw).ok())
+            .map(|c| c.budget_bytes)
+            .unwrap_or(DEFAULT_BUDGET_BYTES);
+        let mut files: Vec<(SystemTime, PathBuf, u64)> = Vec::new();
+        let shards = fs::read_dir(&self.dir).into_iter().flatten().flatten();
+        for shard in shards.filter(|e| e.path().is_dir()) {
+            for file in fs::read_dir(shard.path()).into_iter().flatten().flatten() {
+                let Ok(meta) = file.metadata() else { continue };
+                // 上次中断残留的临时文件
+                if file.path().extension().is_some_and(|e| e == "tmp") {
+                    let _ = fs::remove_file(file.path());
+                    continue;
+                }
+                if meta.is_file() {
+                    let mtime = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                    files.push((mtime, file.path(), meta.len()));
+                }
+            }
+        }
+        files.sort();
+        let mut index = CacheIndex {
+            loaded: true,
+            budget,
+            ..Default::default()
+        };
+        for (_, path, bytes) in files {
+            index.insert(path, bytes);
+        }
+        index
+    }
+}
+
+/// Decode `source`, orient it upright, shrink to fit `size` and write it
+/// atomically to `target`. Returns the thumbnail dimensions.
+fn render(
+    source: &Path,
+    target: &Path,
+    size: u32,
+    format: ThumbnailFormat,
+) -> Result<(u32, u32), AppError> {
+    let img = image::ImageReader::open(source)?
+        .with_guessed_format()?
+        .decode()
+        .map_err(|e| AppError::validation(format!("Failed to decode image: {}", e)))?;
+    let orientation = metadata::read_file(source)
+        .ok()
+        .and_then(|m| m.orientation)
+        .unwrap_or(1);
+    let mut img = phash::apply_orientation(img, orientation);
+    // 不放大：原图已小于目标尺寸时只转码
+    if img.width() > size || img.height() > size {
+        img = img.resize(size, size, FilterType::Triangle);
+    }
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // 并发生成同一缩略图时各写各的临时文件，rename 保证原子替换
+    let tmp = target.with_extension(format!("{}.tmp", uuid::Uuid::new_v4().simple()));
+    let result = (|| -> Result<(), AppError> {
+        let mut writer = BufWriter::new(fs::File::create(&tmp)?);
+        match format {
+            ThumbnailFormat::Jpeg => img
+                .to_rgb8()
+                .write_with_encoder(JpegEncoder::new_with_quality(&mut writer, JPEG_QUALITY))
+                .map_err(|e| AppError::io(format!("Failed to encode thumbnail: {}", e)))?,
+            ThumbnailFormat::WebP => {
+                let rgba = img.to_rgba8();
+                let encoded = webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height())
+                    .encode(WEBP_QUALITY);
+                writer.write_all(&encoded)?;
+            }
+        }
+        writer.into_inner().map_err(|e| e.into_error())?;
+        fs::rename(&tmp, target)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result.map(|_| (img.width(), img.height()))
+}
+
+/// 获取本地图片缩略图（按 md5 缓存），返回的 path 通过 convertFileSrc 交给 asset 协议加载
+#[tauri::command]
+pub async fn get_thumbnail(
+    cache: State<'_, ThumbnailCache>,
+    file_path: String,
+    md5: Option<String>,
+    size: Option<u32>,
+    format: Option<String>,
+) -> Result<Thumbnail, AppError> {
+    let cache = cache.inner().clone();
+    let format = ThumbnailFormat::parse(format.as_deref())?;
+    let size = size.unwrap_or(DEFAULT_THUMBNAIL_SIZE);
+    tauri::async_runtime::spawn_blocking(move || {
+        let source = Path::new(&file_path);
+        let md5 = match md5.filter(|m| !m.is_empty()) {
+            Some(m) => m,
+            None => calculate_md5(source)?,
+        };
+        cache.get_or_create(source, &md5, size, format)
+    })
+    .await
+    .map_err(|e| AppError::io(e.to_string()))?
+}
+
+/// 缩略图缓存占用
+#[tauri::command]
+pub async fn thumbnail_cache_usage(
+    cache: State<'_, ThumbnailCache>,
+) -> Result<CacheUsage, AppError> {
+    cache.usage()
+}
+
+/// 清空缩略图缓存，返回释放的大小
+#[tauri::command]
+pub async fn thumbnail_cache_clear(
+    cache: State<'_, ThumbnailCache>,
+) -> Result<CacheUsage, AppError> {
+    cache.clear()
+}
+
+/// 设置缓存上限（字节），超出部分按 LRU 立即淘汰
+#[tauri::command]
+pub async fn thumbnail_cache_set_budget(
+    cache: State<'_, ThumbnailCache>,
+    budget_bytes: u64,
+) -> Result<CacheUsage, AppError> {
+    cache.set_budget(budget_bytes)
+}
@@ -1,20 +1,28 @@
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use tauri::{AppHandle, Emitter};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, SeekFrom};
 use tokio_util::codec::{BytesCodec, FramedRead};
 use reqwest::{Body, Url};
 use futures_util::StreamExt;
 use tauri_plugin_fs::FsExt;
 use tauri_plugin_fs::OpenOptions;
 use tauri_plugin_fs::FilePath;
-use super::s3_presign::{presign_delete_object_url, presign_get_object_url, presign_put_object_url, PresignDeleteObjectParams, PresignGetObjectParams, PresignPutObjectParams};
+use crate::error::AppError;
+use super::s3_profile::{ProviderConfig, ProviderProfile};
+use super::s3_presign::{etag_matches_md5, presign_delete_object_url, presign_get_object_url, presign_put_object_url, xml_tag_text, PayloadChecksum, PresignDeleteObjectParams, PresignGetObjectParams, PresignPutObjectParams};
 
 #[derive(Serialize, Deserialize)]
 pub struct UploadTokenResponse {
     url: String,
     code: i32,
     message: Option<String>,
+    /// 签入 URL 的请求头，PUT 时必须原样携带
+    #[serde(skip_serializing_if = "Option::is_none")]
+    headers: Option<HashMap<String, String>>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -39,16 +47,18 @@ pub async fn upload_token(
     endpoint: String,
     access_key: String,
     secret_key: String,
-) -> Result<UploadTokenResponse, String> {
+    md5: Option<String>,
+    profile: Option<ProviderConfig>,
+) -> Result<UploadTokenResponse, AppError> {
     // Validate inputs before generating the local S3 signature.
     if endpoint.is_empty() {
-        return Err("Endpoint is empty. Please configure your S3 endpoint.".to_string());
+        return Err(AppError::validation("Endpoint is empty. Please configure your S3 endpoint."));
     }
     if bucket.is_empty() {
-        return Err("Bucket is empty. Please configure your S3 bucket.".to_string());
+        return Err(AppError::validation("Bucket is empty. Please configure your S3 bucket."));
     }
     if access_key.is_empty() || secret_key.is_empty() {
-        return Err("Access key or secret key is empty. Please configure your S3 credentials.".to_string());
+        return Err(AppError::validation("Access key or secret key is empty. Please configure your S3 credentials."));
     }
 
     let parsed_endpoint = if endpoint.starts_with("http://") || endpoint.starts_with("https://") {
@@ -59,10 +69,13 @@ pub async fn upload_token(
 
     // Validate URL format
     if Url::parse(&parsed_endpoint).is_err() {
-        return Err(format!("Invalid endpoint URL: '{}'. Please check your S3 endpoint configuration.", parsed_endpoint));
+        return Err(AppError::validation(format!("Invalid endpoint URL: '{}'. Please check your S3 endpoint configuration.", parsed_endpoint)));
     }
 
-    let region_value = if region.is_empty() { "us-east-1".to_string() } else { region.clone() };
+    let profile = ProviderProfile::from_config(profile.as_ref());
+    let region_value = profile.resolve_region(&region, &parsed_endpoint);
+    // 传入文件 md5 且服务商支持时把 Content-MD5 签入 URL，由 S3 校验上传内容
+    let checksum = profile.md5_checksum(md5.as_deref());
     let url = presign_put_object_url(
         PresignPutObjectParams {
             key: &key,
@@ -72,14 +85,24 @@ pub async fn upload_token(
             access_key: &access_key,
             secret_key: &secret_key,
             expires_seconds: 3600,
+            checksum,
+            profile: &profile,
         },
         chrono::Utc::now(),
     )?;
+    let headers = match checksum {
+        Some(checksum) => Some(HashMap::from([(
+            "Content-MD5".to_string(),
+            checksum.header_value()?,
+        )])),
+        None => None,
+    };
 
     Ok(UploadTokenResponse {
         url,
         code: 0,
         message: None,
+        headers,
     })
 }
 
@@ -93,15 +116,16 @@ pub async fn download_url(
     access_key: String,
     secret_key: String,
     expires_seconds: Option<u32>,
-) -> Result<DownloadUrlResponse, String> {
+    profile: Option<ProviderConfig>,
+) -> Result<DownloadUrlResponse, AppError> {
     if endpoint.is_empty() {
-        return Err("Endpoint is empty. Please configure your S3 endpoint.".to_string());
+        return Err(AppError::validation("Endpoint is empty. Please configure your S3 endpoint."));
     }
     if bucket.is_empty() {
-        return Err("Bucket is empty. Please configure your S3 bucket.".to_string());
+        return Err(AppError::validation("Bucket is empty. Please configure your S3 bucket."));
     }
     if access_key.is_empty() || secret_key.is_empty() {
-        return Err("Access key or secret key is empty. Please configure your S3 credentials.".to_string());
+        return Err(AppError::validation("Access key or secret key is empty. Please configure your S3 credentials."));
     }
 
     let parsed_endpoint = if endpoint.starts_with("http://") || endpoint.starts_with("https://") {
@@ -111,10 +135,11 @@ pub async fn download_url(
     };
 
     if Url::parse(&parsed_endpoint).is_err() {
-        return Err(format!("Invalid endpoint URL: '{}'. Please check your S3 endpoint configuration.", parsed_endpoint));
+        return Err(AppError::validation(format!("Invalid endpoint URL: '{}'. Please check your S3 endpoint configuration.", parsed_endpoint)));
     }
 
-    let region_value = if region.is_empty() { "us-east-1".to_string() } else { region.clone() };
+    let profile = ProviderProfile::from_config(profile.as_ref());
+    let region_value = profile.resolve_region(&region, &parsed_endpoint);
     let url = presign_get_object_url(
         PresignGetObjectParams {
             key: &key,
@@ -124,6 +149,7 @@ pub async fn download_url(
             access_key: &access_key,
             secret_key: &secret_key,
             expires_seconds: expires_seconds.unwrap_or(3600),
+            profile: &profile,
         },
         chrono::Utc::now(),
     )?;
@@ -145,15 +171,16 @@ pub async fn delete_object(
     endpoint: String,
     access_key: String,
     secret_key: String,
-) -> Result<(), String> {
+    profile: Option<ProviderConfig>,
+) -> Result<(), AppError> {
     if endpoint.is_empty() {
-        return Err("Endpoint is empty. Please configure your S3 endpoint.".to_string());
+        return Err(AppError::validation("Endpoint is empty. Please configure your S3 endpoint."));
     }
     if bucket.is_empty() {
-        return Err("Bucket is empty. Please configure your S3 bucket.".to_string());
+        return Err(AppError::validation("Bucket is empty. Please configure your S3 bucket."));
     }
     if access_key.is_empty() || secret_key.is_empty() {
-        return Err("Access key or secret key is empty. Please configure your S3 credentials.".to_string());
+        return Err(AppError::validation("Access key or secret key is empty. Please configure your S3 credentials."));
     }
 
     let parsed_endpoint = if endpoint.starts_with("http://") || endpoint.starts_with("https://") {
@@ -163,10 +190,11 @@ pub async fn delete_object(
     };
 
     if Url::parse(&parsed_endpoint).is_err() {
-        return Err(format!("Invalid endpoint URL: '{}'. Please check your S3 endpoint configuration.", parsed_endpoint));
+        return Err(AppError::validation(format!("Invalid endpoint URL: '{}'. Please check your S3 endpoint configuration.", parsed_endpoint)));
     }
 
-    let region_value = if region.is_empty() { "us-east-1".to_string() } else { region.clone() };
+    let profile = ProviderProfile::from_config(profile.as_ref());
+    let region_value = profile.resolve_region(&region, &parsed_endpoint);
     let url = presign_delete_object_url(
         PresignDeleteObjectParams {
             key: &key,
@@ -176,6 +204,7 @@ pub async fn delete_object(
             access_key: &access_key,
             secret_key: &secret_key,
             expires_seconds: 3600,
+            profile: &profile,
         },
         chrono::Utc::now(),
     )?;
@@ -185,20 +214,18 @@ pub async fn delete_object(
         .delete(&url)
         .send()
         .await
-        .map_err(|e| format!("S3 delete request failed: {}", e))?;
+        .map_err(|e| AppError::network(format!("S3 delete request failed: {}", e)))?;
 
     if !resp.status().is_success() {
-        return Err(format!("S3 delete failed with status: {}", resp.status()));
+        return Err(AppError::s3_status(resp.status().as_u16(), format!("S3 delete failed with status: {}", resp.status())));
     }
 
     Ok(())
 }
 
-#[tauri::command]
-pub async fn upload_file(app: AppHandle, key: String, path: String, url: String) -> Result<(), String> {
-    let file_path = PathBuf::from(&path);
-
-    // 尝试直接打开，如果失败则尝试通过 tauri_plugin_fs::FsExt 打开（利用其 Scope 能力处理 Resource/Content URI）
+// 尝试直接打开，如果失败则尝试通过 tauri_plugin_fs::FsExt 打开（利用其 Scope 能力处理 Resource/Content URI）
+pub(crate) async fn open_upload_source(app: &AppHandle, path: &str) -> Result<File, AppError> {
+    let file_path = PathBuf::from(path);
     let file = match File::open(&file_path).await {
         Ok(f) => f,
         Err(_) => {
@@ -207,54 +234,112 @@ pub async fn upload_file(app: AppHandle, key: String, path: String, url: String)
              opts.read(true);
              
              // 尝试解析为 URL (处理 content:// 等)，否则作为普通路径
-             let file_path_wrapper: FilePath = match Url::parse(&path) {
+             let file_path_wrapper: FilePath = match Url::parse(path) {
                  Ok(url) if url.scheme() != "file" => url.into(),
-                 _ => PathBuf::from(path.clone()).into(),
+                 _ => PathBuf::from(path).into(),
              };
 
              let fs_file = app.fs().open(file_path_wrapper, opts)
-                .map_err(|e| format!("Failed to open file via fs plugin: {}", e))?;
+                .map_err(|e| AppError::io(format!("Failed to open file via fs plugin: {}", e)))?;
              
              // 将 std::fs::File 转换为 tokio::fs::File
              File::from_std(fs_file)
         }
     };
+    Ok(file)
+}
+
+/// 上传本地文件到预签名 URL；传入 md5 且服务商支持时携带 Content-MD5 并校验返回的 ETag，
+/// 与 upload_token 签入 URL 的判断一致
+#[tauri::command]
+pub async fn upload_file(app: AppHandle, key: String, path: String, url: String, md5: Option<String>, profile: Option<ProviderConfig>) -> Result<(), AppError> {
+    let file = open_upload_source(&app, &path).await?;
+
+    let file_size = file.metadata().await.map_err(|e| AppError::io(format!("Failed to get metadata: {}", e)))?.len();
 
-    let file_size = file.metadata().await.map_err(|e| format!("Failed to get metadata: {}", e))?.len();
+    let checksum = ProviderProfile::from_config(profile.as_ref()).md5_checksum(md5.as_deref());
+    let client = reqwest::Client::new();
+    put_file(&client, &url, file, file_size, checksum, move |uploaded, total| {
+        // Emit progress
+        let _ = app.emit("upload://progress", ProgressPayload {
+            key: key.clone(),
+            progress: uploaded,
+            total,
+        });
+    })
+    .await
+}
 
+/// 计算文件 MD5（十六进制）并把读取位置复位到开头
+pub(crate) async fn file_md5(file: &mut File) -> Result<String, AppError> {
+    let mut context = md5::Context::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    file.seek(SeekFrom::Start(0)).await?;
+    loop {
+        let count = file.read(&mut buffer).await?;
+        if count == 0 {
+            break;
+        }
+        context.consume(&buffer[..count]);
+    }
+    file.seek(SeekFrom::Start(0)).await?;
+    Ok(format!("{:x}", context.compute()))
+}
+
+// S3 拒绝请求体摘要时返回的错误码
+const CHECKSUM_ERROR_CODES: [&str; 4] = ["BadDigest", "InvalidDigest", "XAmzContentChecksumMismatch", "XAmzContentSHA256Mismatch"];
+
+/// 以流式请求体 PUT 整个文件到预签名 URL，每读出一块回调 (已上传, 总大小)。
+/// `checksum` 须与签入 URL 的一致；MD5 模式下还会比对响应 ETag，
+/// 不一致时返回 `ChecksumMismatch`，调用方不应再记录该对象。
+pub async fn put_file(
+    client: &reqwest::Client,
+    url: &str,
+    file: File,
+    file_size: u64,
+    checksum: Option<PayloadChecksum<'_>>,
+    mut on_progress: impl FnMut(u64, u64) + Send + Sync + 'static,
+) -> Result<(), AppError> {
     let stream = FramedRead::new(file, BytesCodec::new());
     
     let mut uploaded = 0;
-    let app_handle = app.clone();
-    let key_clone = key.clone();
     
     let stream = stream.map(move |chunk| {
-        let chunk = chunk.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        let chunk = chunk.map_err(std::io::Error::other)?;
         let len = chunk.len() as u64;
         uploaded += len;
-        
-        // Emit progress
-        let _ = app_handle.emit("upload://progress", ProgressPayload {
-            key: key_clone.clone(),
-            progress: uploaded,
-            total: file_size,
-        });
-        
+        on_progress(uploaded, file_size);
         Ok::<_, std::io::Error>(chunk.freeze())
     });
 
     let body = Body::wrap_stream(stream);
 
-    let client = reqwest::Client::new();
-    let res = client.put(&url)
-        .header("Content-Length", file_size)
+    let mut request = client.put(url).header("Content-Length", file_size);
+    if let Some(checksum) = checksum {
+        request = request.header(checksum.header_name(), checksum.header_value()?);
+    }
+    let res = request
         .body(body)
         .send()
         .await
-        .map_err(|e| format!("Upload request failed: {}", e))?;
+        .map_err(|e| AppError::network(format!("Upload request failed: {}", e)))?;
+
+    let status = res.status();
+    if !status.is_success() {
+        let body = res.text().await.unwrap_or_default();
+        let code = xml_tag_text(&body, "Code").unwrap_or_default();
+        if CHECKSUM_ERROR_CODES.contains(&code.as_str()) {
+            return Err(AppError::checksum_mismatch(format!("Upload rejected by S3: {}", code)));
+        }
+        return Err(AppError::s3_status(status.as_u16(), format!("Upload failed with status: {}", status)));
+    }
 
-    if !res.status().is_success() {
-        return Err(format!("Upload failed with status: {}", res.status()));
+    if let Some(PayloadChecksum::Md5(expected)) = checksum {
+        let etag = res.headers().get("etag").and_then(|v| v.to_str().ok()).unwrap_or_default();
+        if etag_matches_md5(etag, expected) == Some(false) {
+            return Err(AppError::checksum_mismatch("Uploaded object ETag does not match file MD5")
+                .with_details(json!({ "expected": expected, "actual": etag })));
+        }
     }
 
     Ok(())
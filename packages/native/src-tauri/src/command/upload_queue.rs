@@ -0,0 +1,394 @@
+//! Background upload queue.
+//!
+//! Jobs live in the `upload_jobs` table (see [`crate::db::upload_job`]), so the
+//! queue survives restarts. `upload_queue_start` hands over the S3 config and
+//! spawns N workers; each worker claims the oldest due job, uploads it (files
+//! above [`MULTIPART_THRESHOLD`] go through the resumable multipart path) and
+//! retries transient failures with exponential backoff.
+//!
+//! Events:
+//! - `upload://progress` — `{ key, jobId, progress, total }` in bytes
+//! - `upload://state` — the full job whenever its state changes
+//! - `upload://done` — `{ jobId, key, entityType, entityId, entity }` once a
+//!   job is stored; `entity` is the created photo / drive file row (or `null`)
+
+use std::collections::HashMap;
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::Utc;
+use futures_util::future::{select, Either};
+use serde_json::{json, Value as JsonValue};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::Notify;
+
+use super::multipart::{
+    discard_session, require_credentials, upload_multipart, MULTIPART_THRESHOLD,
+};
+use super::s3_presign::{
+    presign_put_object_url, PresignPutObjectParams, S3Credentials,
+};
+use super::s3_profile::ProviderConfig;
+use super::upload::{file_md5, open_upload_source, put_file};
+use crate::db::upload_job::{self, JobState, NewUploadJob, UploadJob};
+use crate::db::TursoDb;
+use crate::error::{AppError, ErrorCode};
+
+pub const DEFAULT_CONCURRENCY: usize = 3;
+const MAX_CONCURRENCY: usize = 8;
+/// 超过该次数仍失败则标记为 failed，需手动恢复
+pub const MAX_ATTEMPTS: u32 = 5;
+const BACKOFF_BASE_SECONDS: u64 = 2;
+const BACKOFF_MAX_SECONDS: u64 = 300;
+// 空闲时的轮询间隔，用于发现退避到期的任务
+const IDLE_POLL: Duration = Duration::from_secs(2);
+
+/// Delay before retry number `attempts` (1-based): 2s, 4s, 8s, … capped at 5 min.
+pub fn backoff_seconds(attempts: u32) -> u64 {
+    let exp = attempts.saturating_sub(1).min(16);
+    (BACKOFF_BASE_SECONDS << exp).min(BACKOFF_MAX_SECONDS)
+}
+
+/// Network errors, 5xx, 408, 429 and checksum mismatches (the file is hashed
+/// again on every attempt) are worth retrying; anything else (missing file,
+/// bad config, 403 …) fails the job right away.
+pub fn is_retryable(err: &AppError) -> bool {
+    match err.code {
+        ErrorCode::Network | ErrorCode::ChecksumMismatch => true,
+        ErrorCode::S3Status => {
+            let status = err.s3_status_code().unwrap_or(0);
+            status >= 500 || status == 408 || status == 429
+        }
+        _ => false,
+    }
+}
+
+/// Managed state shared by the commands and the workers.
+#[derive(Default)]
+pub struct UploadQueue {
+    inner: Arc<QueueInner>,
+}
+
+#[derive(Default)]
+struct QueueInner {
+    credentials: Mutex<Option<S3Credentials>>,
+    started: AtomicBool,
+    workers: Mutex<usize>,
+    wake: Notify,
+    // 正在上传的任务；通知后 worker 放弃本次上传
+    running: Mutex<HashMap<String, Arc<Notify>>>,
+}
+
+impl QueueInner {
+    fn credentials(&self) -> Option<S3Credentials> {
+        self.credentials.lock().unwrap().clone()
+    }
+
+    fn interrupt(&self, id: &str) {
+        if let Some(cancel) = self.running.lock().unwrap().get(id) {
+            cancel.notify_one();
+        }
+    }
+}
+
+fn emit_state(app: &AppHandle, job: &UploadJob) {
+    let _ = app.emit("upload://state", job);
+}
+
+async fn run_job(
+    app: &AppHandle,
+    conn: &turso::Connection,
+    client: &reqwest::Client,
+    credentials: &S3Credentials,
+    job: &UploadJob,
+) -> Result<(), AppError> {
+    let mut file = open_upload_source(app, &job.source_path).await?;
+    let file_size = file
+        .metadata()
+        .await
+        .map_err(|e| AppError::io(format!("Failed to get metadata: {}", e)))?
+        .len();
+    if job.file_size != Some(file_size) {
+        upload_job::set_file_size(conn, &job.id, file_size).await?;
+    }
+
+    let progress = {
+        let app = app.clone();
+        let key = job.object_key.clone();
+        let job_id = job.id.clone();
+        move |uploaded: u64, total: u64| {
+            let _ = app.emit(
+                "upload://progress",
+                json!({ "key": key, "jobId": job_id, "progress": uploaded, "total": total }),
+            );
+        }
+    };
+
+    if file_size >= MULTIPART_THRESHOLD {
+        return upload_multipart(
+            conn,
+            client,
+            credentials,
+            &job.object_key,
+            &job.source_path,
+            file,
+            None,
+            progress,
+        )
+        .await;
+    }
+    // 整体上传时签入 Content-MD5（服务商支持时），内容损坏的对象不会被标记为完成
+    let md5 = file_md5(&mut file).await?;
+    let checksum = credentials.profile.md5_checksum(Some(&md5));
+    let url = presign_put_object_url(
+        PresignPutObjectParams {
+            key: &job.object_key,
+            bucket: &credentials.bucket,
+            region: &credentials.region,
+            endpoint: &credentials.endpoint,
+            access_key: &credentials.access_key,
+            secret_key: &credentials.secret_key,
+            expires_seconds: 3600,
+            checksum,
+            profile: &credentials.profile,
+        },
+        Utc::now(),
+    )?;
+    put_file(client, &url, file, file_size, checksum, progress).await
+}
+
+/// Record the upload result; returns the created record when the job is done.
+pub async fn finish_job(
+    conn: &turso::Connection,
+    job: &UploadJob,
+    result: Result<(), AppError>,
+) -> Result<Option<JsonValue>, AppError> {
+    match result {
+        // 记录写入失败时对象已在 S3，重试也无济于事，直接标记失败
+        Ok(()) => match upload_job::complete_job(conn, job).await {
+            Ok(record) => return Ok(record),
+            Err(e) => upload_job::mark_failed(conn, &job.id, &e.message).await?,
+        },
+        Err(e) if is_retryable(&e) && job.attempts < MAX_ATTEMPTS => {
+            upload_job::mark_retry(conn, &job.id, &e.message, backoff_seconds(job.attempts)).await?
+        }
+        Err(e) => upload_job::mark_failed(conn, &job.id, &e.message).await?,
+    };
+    Ok(None)
+}
+
+async fn worker_loop(app: AppHandle, inner: Arc<QueueInner>) {
+    let conn = match app.state::<TursoDb>().0.connect() {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::error!("Upload worker failed to connect to database: {}", e);
+            return;
+        }
+    };
+    let _ = conn.execute("PRAGMA busy_timeout=5000", ()).await;
+    let client = reqwest::Client::new();
+
+    loop {
+        let Some(credentials) = inner.credentials() else {
+            inner.wake.notified().await;
+            continue;
+        };
+        let job = match upload_job::claim_next_job(&conn).await {
+            Ok(Some(job)) => job,
+            Ok(None) => {
+                let _ = tokio::time::timeout(IDLE_POLL, inner.wake.notified()).await;
+                continue;
+            }
+            Err(e) => {
+                log::warn!("Upload worker failed to claim a job: {}", e.message);
+                let _ = tokio::time::timeout(IDLE_POLL, inner.wake.notified()).await;
+                continue;
+            }
+        };
+
+        let cancel = Arc::new(Notify::new());
+        inner
+            .running
+            .lock()
+            .unwrap()
+            .insert(job.id.clone(), cancel.clone());
+        emit_state(&app, &job);
+
+        let upload = pin!(run_job(&app, &conn, &client, &credentials, &job));
+        let cancelled = pin!(cancel.notified());
+        let result = match select(upload, cancelled).await {
+            Either::Left((result, _)) => Some(result),
+            Either::Right(_) => None,
+        };
+        inner.running.lock().unwrap().remove(&job.id);
+
+        // 被暂停或取消时状态已由对应命令写入，这里不再改动
+        if let Some(result) = result {
+            match finish_job(&conn, &job, result).await {
+                Ok(Some(entity)) => {
+                    let _ = app.emit(
+                        "upload://done",
+                        json!({
+                            "jobId": job.id,
+                            "key": job.object_key,
+                            "entityType": job.entity_type,
+                            "entityId": job.entity_id,
+                            "entity": entity,
+                        }),
+                    );
+                }
+                Ok(None) => {}
+                Err(e) => log::warn!("Failed to record upload job {}: {}", job.id, e.message),
+            }
+        }
+        if let Ok(Some(job)) = upload_job::get_job(&conn, &job.id).await {
+            emit_state(&app, &job);
+        }
+    }
+}
+
+/// 配置 S3 参数并启动上传队列；首次启动时把上次中断的任务重新排队
+#[tauri::command]
+pub async fn upload_queue_start(
+    app: AppHandle,
+    queue: State<'_, UploadQueue>,
+    state: State<'_, TursoDb>,
+    bucket: String,
+    region: String,
+    endpoint: String,
+    access_key: String,
+    secret_key: String,
+    concurrency: Option<u32>,
+    profile: Option<ProviderConfig>,
+) -> Result<JsonValue, AppError> {
+    let credentials =
+        require_credentials(bucket, region, endpoint, access_key, secret_key, profile)?;
+    if !queue.inner.started.swap(true, Ordering::SeqCst) {
+        let conn = state.0.connect()?;
+        let requeued = upload_job::requeue_interrupted(&conn).await?;
+        if requeued > 0 {
+            log::info!("Requeued {} interrupted upload jobs", requeued);
+        }
+    }
+    *queue.inner.credentials.lock().unwrap() = Some(credentials);
+
+    // worker 只增不减，降低并发在下次启动应用时生效
+    let target = concurrency
+        .map(|n| n as usize)
+        .unwrap_or(DEFAULT_CONCURRENCY)
+        .clamp(1, MAX_CONCURRENCY);
+    let workers = {
+        let mut workers = queue.inner.workers.lock().unwrap();
+        while *workers < target {
+            tauri::async_runtime::spawn(worker_loop(app.clone(), queue.inner.clone()));
+            *workers += 1;
+        }
+        *workers
+    };
+    queue.inner.wake.notify_waiters();
+    Ok(json!({ "code": 0, "data": { "workers": workers } }))
+}
+
+/// 加入上传队列；同一 key 已有未完成任务时返回该任务
+#[tauri::command]
+pub async fn upload_queue_enqueue(
+    app: AppHandle,
+    queue: State<'_, UploadQueue>,
+    state: State<'_, TursoDb>,
+    jobs: Vec<NewUploadJob>,
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let jobs = upload_job::enqueue_jobs(&conn, &jobs).await?;
+    for job in &jobs {
+        emit_state(&app, job);
+    }
+    queue.inner.wake.notify_waiters();
+    Ok(json!({ "code": 0, "data": jobs }))
+}
+
+#[tauri::command]
+pub async fn upload_queue_list(
+    state: State<'_, TursoDb>,
+    states: Option<Vec<String>>,
+) -> Result<JsonValue, AppError> {
+    let states = states
+        .unwrap_or_default()
+        .iter()
+        .map(|s| JobState::parse(s))
+        .collect::<Result<Vec<_>, _>>()?;
+    let conn = state.0.connect()?;
+    let jobs = upload_job::list_jobs(&conn, &states).await?;
+    Ok(json!({ "code": 0, "data": jobs }))
+}
+
+/// 暂停任务（不传 ids 时暂停全部）；上传中的任务立即中断，分片进度保留
+#[tauri::command]
+pub async fn upload_queue_pause(
+    app: AppHandle,
+    queue: State<'_, UploadQueue>,
+    state: State<'_, TursoDb>,
+    ids: Option<Vec<String>>,
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let jobs = upload_job::pause_jobs(&conn, ids.as_deref()).await?;
+    for job in &jobs {
+        queue.inner.interrupt(&job.id);
+        emit_state(&app, job);
+    }
+    Ok(json!({ "code": 0, "data": jobs }))
+}
+
+/// 恢复暂停或失败的任务（不传 ids 时恢复全部）
+#[tauri::command]
+pub async fn upload_queue_resume(
+    app: AppHandle,
+    queue: State<'_, UploadQueue>,
+    state: State<'_, TursoDb>,
+    ids: Option<Vec<String>>,
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let jobs = upload_job::resume_jobs(&conn, ids.as_deref()).await?;
+    for job in &jobs {
+        emit_state(&app, job);
+    }
+    queue.inner.wake.notify_waiters();
+    Ok(json!({ "code": 0, "data": jobs }))
+}
+
+/// 取消任务（不传 ids 时取消全部未完成任务），并尽量释放 S3 上已上传的分片
+#[tauri::command]
+pub async fn upload_queue_cancel(
+    app: AppHandle,
+    queue: State<'_, UploadQueue>,
+    state: State<'_, TursoDb>,
+    ids: Option<Vec<String>>,
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let jobs = upload_job::cancel_jobs(&conn, ids.as_deref()).await?;
+    let credentials = queue.inner.credentials();
+    let client = reqwest::Client::new();
+    for job in &jobs {
+        queue.inner.interrupt(&job.id);
+        if let Some(credentials) = &credentials {
+            if let Err(e) = discard_session(&conn, &client, credentials, &job.object_key).await {
+                log::warn!(
+                    "Failed to abort multipart upload for {}: {}",
+                    job.object_key,
+                    e.message
+                );
+            }
+        }
+        emit_state(&app, job);
+    }
+    Ok(json!({ "code": 0, "data": jobs }))
+}
+
+/// 删除已完成和已取消的任务记录
+#[tauri::command]
+pub async fn upload_queue_clear(state: State<'_, TursoDb>) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let removed = upload_job::clear_finished(&conn).await?;
+    Ok(json!({ "code": 0, "data": removed }))
+}
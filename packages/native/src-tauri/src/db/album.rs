@@ -1,171 +1,69 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 use serde_json::{json, Value as JsonValue};
 use tauri::State;
 
-use super::{ensure_album_folders_table, merge_row, new_id, TursoDb};
+use crate::error::AppError;
+use super::model::{query_model, query_models, Album, Model};
+use super::photo::{album_counts_and_covers, photo_count_and_cover, PhotoFilter};
+use super::search::reindex_one;
+use super::smart_album::SmartRules;
+use super::{
+    ensure_album_folders_table, in_transaction, new_id, placeholders, query_maps, BatchItemResult,
+    TursoDb, BATCH_CHUNK_SIZE,
+};
 
 #[tauri::command]
-pub async fn db_album_list(state: State<'_, TursoDb>) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+pub async fn db_album_list(state: State<'_, TursoDb>) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
 
     let mut albums = Vec::new();
     {
-        let mut rows = conn
-            .query(
-                "SELECT * FROM albums WHERE deleted = 0 ORDER BY updated_at DESC",
-                (),
-            )
-            .await
-            .map_err(|e| e.to_string())?;
-
-        while let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-            let val = row_to_json(&row)?;
-            albums.push(merge_row(&val));
-        }
-    }
-
-    let mut album_photos: HashMap<String, HashSet<String>> = HashMap::new();
-    let mut photo_albums: HashMap<String, HashSet<String>> = HashMap::new();
-
-    match conn
-        .query("SELECT photo_id, album_id FROM photo_albums", ())
-        .await
-    {
-        Ok(mut relation_rows) => loop {
-            let row = match relation_rows.next().await {
-                Ok(Some(row)) => row,
-                Ok(None) => break,
-                Err(e) => {
-                    log::warn!("Failed to read photo_albums row: {}", e);
-                    break;
-                }
-            };
-            let photo_id = row
-                .get_value(0)
-                .ok()
-                .and_then(|v| v.as_text().map(|v| v.to_string()))
-                .unwrap_or_default();
-            let album_id = row
-                .get_value(1)
-                .ok()
-                .and_then(|v| v.as_text().map(|v| v.to_string()))
-                .unwrap_or_default();
-            if photo_id.is_empty() || album_id.is_empty() {
-                continue;
-            }
-            album_photos
-                .entry(album_id.clone())
-                .or_default()
-                .insert(photo_id.clone());
-            photo_albums.entry(photo_id).or_default().insert(album_id);
-        },
-        Err(e) => log::warn!("Failed to enrich albums from photo_albums: {}", e),
-    }
-
-    let mut cover_candidates: HashMap<String, (String, String)> = HashMap::new();
-    let mut pending_relations: Vec<(String, String)> = Vec::new();
-    match conn
-        .query(
-            "SELECT id, last_modified, data FROM photos WHERE deleted = 0",
+        let rows = query_models::<Album>(
+            &conn,
+            "SELECT * FROM albums WHERE deleted = 0 ORDER BY updated_at DESC",
             (),
         )
-        .await
-    {
-        Ok(mut photo_rows) => loop {
-            let row = match photo_rows.next().await {
-                Ok(Some(row)) => row,
-                Ok(None) => break,
-                Err(e) => {
-                    log::warn!("Failed to read photos row for album enrichment: {}", e);
-                    break;
-                }
-            };
-            let photo_id = row
-                .get_value(0)
-                .ok()
-                .and_then(|v| v.as_text().map(|v| v.to_string()))
-                .unwrap_or_default();
-            let last_modified = row
-                .get_value(1)
-                .ok()
-                .and_then(|v| v.as_text().map(|v| v.to_string()))
-                .unwrap_or_default();
-            let data_str = row
-                .get_value(2)
-                .ok()
-                .and_then(|v| v.as_text().map(|v| v.to_string()))
-                .unwrap_or_else(|| "{}".to_string());
-            if photo_id.is_empty() {
-                continue;
-            }
+        .await?;
 
-            let data_json =
-                serde_json::from_str::<JsonValue>(&data_str).unwrap_or_else(|_| json!({}));
-            if let Some(album_ids) = data_json.get("albumId").and_then(|v| v.as_array()) {
-                for album_id in album_ids.iter().filter_map(|v| v.as_str()) {
-                    if album_id.is_empty() {
-                        continue;
-                    }
-                    album_photos
-                        .entry(album_id.to_string())
-                        .or_default()
-                        .insert(photo_id.clone());
-                    photo_albums
-                        .entry(photo_id.clone())
-                        .or_default()
-                        .insert(album_id.to_string());
-                    pending_relations.push((photo_id.clone(), album_id.to_string()));
-                }
-            }
-
-            let cover_key = data_json
-                .get("key")
-                .and_then(|v| v.as_str())
-                .filter(|v| !v.is_empty())
-                .unwrap_or(&photo_id)
-                .to_string();
-
-            if let Some(related_album_ids) = photo_albums.get(&photo_id) {
-                for album_id in related_album_ids {
-                    let should_replace = cover_candidates
-                        .get(album_id)
-                        .map(|(_, existing_time)| last_modified > *existing_time)
-                        .unwrap_or(true);
-                    if should_replace {
-                        cover_candidates
-                            .insert(album_id.clone(), (cover_key.clone(), last_modified.clone()));
-                    }
-                }
-            }
-        },
-        Err(e) => log::warn!("Failed to enrich albums from photos: {}", e),
-    }
-
-    for (photo_id, album_id) in pending_relations {
-        if let Err(e) = conn
-            .execute(
-                "INSERT OR IGNORE INTO photo_albums (photo_id, album_id) VALUES (?1, ?2)",
-                (photo_id, album_id),
-            )
-            .await
-        {
-            log::warn!("Failed to backfill photo album relation: {}", e);
+        for row in rows {
+            albums.push(row.to_json());
         }
     }
 
+    // 普通相册一次分组查询统计（旧 data.albumId 已在 v4 迁移中回填），
+    // 智能相册按规则逐个实时统计；两者都取最新一张作为默认封面
+    let mut linked = album_counts_and_covers(&conn).await?;
     for album in &mut albums {
         let album_id = album
             .get("id")
             .and_then(|v| v.as_str())
             .unwrap_or("")
             .to_string();
-        let count = album_photos.get(&album_id).map(|v| v.len()).unwrap_or(0);
+        let smart = match SmartRules::from_album_data(album) {
+            Ok(smart) => smart,
+            Err(e) => {
+                log::warn!("Invalid smart album rules in {}: {}", album_id, e.message);
+                None
+            }
+        };
+        let (count, cover) = match smart {
+            Some(smart) => {
+                let filter = PhotoFilter {
+                    album_id: Some(album_id),
+                    smart: Some(smart),
+                    ..Default::default()
+                };
+                photo_count_and_cover(&conn, &filter).await?
+            }
+            None => linked.remove(&album_id).unwrap_or((0, None)),
+        };
+        let latest_cover = cover.and_then(|c| c.get("key").and_then(|v| v.as_str()).map(String::from));
         album["count"] = json!(count);
 
         let cover_key = album.get("coverKey").and_then(|v| v.as_str()).unwrap_or("");
         if cover_key.is_empty() {
-            if let Some((key, _)) = cover_candidates.get(&album_id) {
+            if let Some(key) = latest_cover {
                 album["coverKey"] = json!(key);
             }
         }
@@ -190,18 +88,14 @@ pub async fn db_album_list(state: State<'_, TursoDb>) -> Result<JsonValue, Strin
 }
 
 #[tauri::command]
-pub async fn db_album_get(state: State<'_, TursoDb>, id: String) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
-    let mut rows = conn
-        .query("SELECT * FROM albums WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
+pub async fn db_album_get(state: State<'_, TursoDb>, id: String) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let row = query_model::<Album>(&conn, "SELECT * FROM albums WHERE id = ?1", (id,)).await?;
 
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        Ok(merge_row(&val))
+    if let Some(row) = row {
+        Ok(row.to_json())
     } else {
-        Err("Album not found".to_string())
+        Err(AppError::not_found("Album not found"))
     }
 }
 
@@ -215,8 +109,8 @@ pub async fn db_album_create(
     tags: Option<Vec<String>>,
     folder_id: Option<String>,
     data: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
     let album_id = id.unwrap_or_else(new_id);
     let style_val = style.unwrap_or_else(|| "small".to_string());
     let tags_json = tags
@@ -235,23 +129,20 @@ pub async fn db_album_create(
         }
         obj.to_string()
     });
+    validate_album_data(&data_val)?;
 
     conn.execute(
         "INSERT INTO albums (id, data) VALUES (?1, ?2)",
         (album_id.clone(), data_val),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
+    reindex_one(&conn, "albums", &album_id).await?;
 
-    let mut rows = conn
-        .query("SELECT * FROM albums WHERE id = ?1", (album_id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        Ok(merge_row(&val))
+    let row = query_model::<Album>(&conn, "SELECT * FROM albums WHERE id = ?1", (album_id,)).await?;
+    if let Some(row) = row {
+        Ok(row.to_json())
     } else {
-        Err("Failed to retrieve created album".to_string())
+        Err(AppError::storage("Failed to retrieve created album"))
     }
 }
 
@@ -265,16 +156,17 @@ pub async fn db_album_update(
     tags: Option<Vec<String>>,
     folder_id: Option<String>,
     data: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
 
     if let Some(d) = data {
+        validate_album_data(&d)?;
+        let d = keep_smart_rules(&conn, &id, d).await?;
         conn.execute(
-            "UPDATE albums SET data = ?1, updated_at = datetime('now') WHERE id = ?2",
+            "UPDATE albums SET data = ?1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?2",
             (d, id.clone()),
         )
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
     } else {
         // Merge into existing data
         let mut existing = get_album_data(&conn, &id).await?;
@@ -301,22 +193,18 @@ pub async fn db_album_update(
             }
         }
         conn.execute(
-            "UPDATE albums SET data = ?1, updated_at = datetime('now') WHERE id = ?2",
+            "UPDATE albums SET data = ?1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?2",
             (existing.to_string(), id.clone()),
         )
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
     }
+    reindex_one(&conn, "albums", &id).await?;
 
-    let mut rows = conn
-        .query("SELECT * FROM albums WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        Ok(merge_row(&val))
+    let row = query_model::<Album>(&conn, "SELECT * FROM albums WHERE id = ?1", (id,)).await?;
+    if let Some(row) = row {
+        Ok(row.to_json())
     } else {
-        Err("Album not found".to_string())
+        Err(AppError::not_found("Album not found"))
     }
 }
 
@@ -325,8 +213,8 @@ pub async fn db_album_set_folder(
     state: State<'_, TursoDb>,
     id: String,
     folder_id: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
     ensure_album_folder_exists(&conn, folder_id.as_deref()).await?;
     let mut existing = get_album_data(&conn, &id).await?;
     if let Some(obj) = existing.as_object_mut() {
@@ -340,49 +228,87 @@ pub async fn db_album_set_folder(
         }
     }
     conn.execute(
-        "UPDATE albums SET data = ?1, updated_at = datetime('now') WHERE id = ?2",
+        "UPDATE albums SET data = ?1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?2",
         (existing.to_string(), id.clone()),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
 
     Ok(json!({ "code": 0 }))
 }
 
+/// 批量移动相册到文件夹：单事务完成，不存在的相册在结果中标记为跳过
 #[tauri::command]
 pub async fn db_albums_set_folder(
     state: State<'_, TursoDb>,
     album_ids: Vec<String>,
     folder_id: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
     ensure_album_folder_exists(&conn, folder_id.as_deref()).await?;
-    for album_id in &album_ids {
-        let mut existing = get_album_data(&conn, album_id).await?;
-        if let Some(obj) = existing.as_object_mut() {
-            match folder_id.as_ref() {
-                Some(fid) if !fid.is_empty() => {
-                    obj.insert("folderId".to_string(), json!(fid));
-                }
-                _ => {
-                    obj.remove("folderId");
+    let results = set_albums_folder(&conn, &album_ids, folder_id.as_deref()).await?;
+    Ok(json!({ "code": 0, "data": results }))
+}
+
+pub async fn set_albums_folder(
+    conn: &turso::Connection,
+    album_ids: &[String],
+    folder_id: Option<&str>,
+) -> Result<Vec<BatchItemResult>, AppError> {
+    in_transaction(conn, async {
+        let mut results = Vec::with_capacity(album_ids.len());
+        for chunk in album_ids.chunks(BATCH_CHUNK_SIZE) {
+            let params: Vec<turso::Value> = chunk.iter().map(|id| turso::Value::Text(id.clone())).collect();
+            let rows = query_maps(
+                conn,
+                &format!(
+                    "SELECT id, data FROM albums WHERE id IN ({})",
+                    placeholders(1, chunk.len())
+                ),
+                params,
+            )
+            .await?;
+            let existing: HashMap<String, String> = rows
+                .iter()
+                .filter_map(|r| {
+                    let id = r.get("id")?.as_str()?.to_string();
+                    let data = r.get("data").and_then(|v| v.as_str()).unwrap_or("{}").to_string();
+                    Some((id, data))
+                })
+                .collect();
+
+            for album_id in chunk {
+                let Some(data_str) = existing.get(album_id) else {
+                    results.push(BatchItemResult::skipped(album_id, "Album not found"));
+                    continue;
+                };
+                let mut data: JsonValue = serde_json::from_str(data_str).unwrap_or_else(|_| json!({}));
+                if let Some(obj) = data.as_object_mut() {
+                    match folder_id {
+                        Some(fid) if !fid.is_empty() => {
+                            obj.insert("folderId".to_string(), json!(fid));
+                        }
+                        _ => {
+                            obj.remove("folderId");
+                        }
+                    }
                 }
+                conn.execute(
+                    "UPDATE albums SET data = ?1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?2",
+                    (data.to_string(), album_id.clone()),
+                )
+                .await?;
+                results.push(BatchItemResult::ok(album_id));
             }
         }
-        conn.execute(
-            "UPDATE albums SET data = ?1, updated_at = datetime('now') WHERE id = ?2",
-            (existing.to_string(), album_id.clone()),
-        )
-        .await
-        .map_err(|e| e.to_string())?;
-    }
-    Ok(json!({ "code": 0 }))
+        Ok(results)
+    })
+    .await
 }
 
 async fn ensure_album_folder_exists(
     conn: &turso::Connection,
     folder_id: Option<&str>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     let Some(folder_id) = folder_id.filter(|id| !id.is_empty()) else {
         return Ok(());
     };
@@ -394,13 +320,12 @@ async fn ensure_album_folder_exists(
             "SELECT id FROM album_folders WHERE id = ?1 AND deleted = 0 LIMIT 1",
             (folder_id,),
         )
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
-    if rows.next().await.map_err(|e| e.to_string())?.is_some() {
+    if rows.next().await?.is_some() {
         Ok(())
     } else {
-        Err("目标文件夹不存在".to_string())
+        Err(AppError::not_found("目标文件夹不存在"))
     }
 }
 
@@ -409,62 +334,65 @@ pub async fn db_album_update_cover(
     state: State<'_, TursoDb>,
     id: String,
     key: String,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
     let mut existing = get_album_data(&conn, &id).await?;
     existing["coverKey"] = json!(key);
     conn.execute(
-        "UPDATE albums SET data = ?1, updated_at = datetime('now') WHERE id = ?2",
+        "UPDATE albums SET data = ?1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?2",
         (existing.to_string(), id.clone()),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
 
-    let mut rows = conn
-        .query("SELECT * FROM albums WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        Ok(merge_row(&val))
+    let row = query_model::<Album>(&conn, "SELECT * FROM albums WHERE id = ?1", (id,)).await?;
+    if let Some(row) = row {
+        Ok(row.to_json())
     } else {
-        Err("Album not found".to_string())
+        Err(AppError::not_found("Album not found"))
     }
 }
 
-async fn get_album_data(conn: &turso::Connection, id: &str) -> Result<JsonValue, String> {
+async fn get_album_data(conn: &turso::Connection, id: &str) -> Result<JsonValue, AppError> {
     let mut rows = conn
         .query("SELECT data FROM albums WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
+        .await?;
+    if let Some(row) = rows.next().await? {
         let data_str = row
-            .get_value(0)
-            .map_err(|e| e.to_string())?
+            .get_value(0)?
             .as_text()
             .map_or("{}", |v| v)
             .to_string();
-        serde_json::from_str(&data_str).map_err(|e| e.to_string())
+        serde_json::from_str(&data_str).map_err(AppError::from)
     } else {
-        Err("Album not found".to_string())
+        Err(AppError::not_found("Album not found"))
     }
 }
 
-fn row_to_json(row: &turso::Row) -> Result<JsonValue, String> {
-    let mut map = serde_json::Map::new();
-    for i in 0..6 {
-        if let Ok(val) = row.get_value(i as usize) {
-            let key = match i {
-                0 => "id",
-                1 => "remote_id",
-                2 => "sync_status",
-                3 => "updated_at",
-                4 => "deleted",
-                5 => "data",
-                _ => continue,
-            };
-            map.insert(key.to_string(), super::turso_value_to_json(&val));
+// 智能相册规则在保存时校验，避免到列表时才暴露
+fn validate_album_data(data: &str) -> Result<(), AppError> {
+    if let Ok(value) = serde_json::from_str::<JsonValue>(data) {
+        SmartRules::from_album_data(&value)?;
+    }
+    Ok(())
+}
+
+// 整体替换 data 时未带 smart 键则沿用原规则；显式传 null 才变回普通相册
+async fn keep_smart_rules(conn: &turso::Connection, id: &str, data: String) -> Result<String, AppError> {
+    let Ok(mut value) = serde_json::from_str::<JsonValue>(&data) else {
+        return Ok(data);
+    };
+    let Some(obj) = value.as_object_mut().filter(|o| !o.contains_key("smart")) else {
+        return Ok(data);
+    };
+    let existing = match get_album_data(conn, id).await {
+        Ok(existing) => existing,
+        Err(_) => return Ok(data),
+    };
+    match existing.get("smart").filter(|v| !v.is_null()) {
+        Some(smart) => {
+            obj.insert("smart".to_string(), smart.clone());
+            Ok(value.to_string())
         }
+        None => Ok(data),
     }
-    Ok(JsonValue::Object(map))
 }
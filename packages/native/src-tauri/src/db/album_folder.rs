@@ -1,24 +1,24 @@
 use serde_json::{json, Value as JsonValue};
 use tauri::State;
 
-use super::{ensure_album_folders_table, merge_row, new_id, TursoDb};
+use crate::error::AppError;
+use super::model::{query_model, query_models, AlbumFolder, Model};
+use super::{ensure_album_folders_table, new_id, TursoDb};
 
 #[tauri::command]
-pub async fn db_album_folder_list(state: State<'_, TursoDb>) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+pub async fn db_album_folder_list(state: State<'_, TursoDb>) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
     ensure_album_folders_table(&conn).await?;
-    let mut rows = conn
-        .query(
-            "SELECT * FROM album_folders WHERE deleted = 0 ORDER BY updated_at DESC",
-            (),
-        )
-        .await
-        .map_err(|e| e.to_string())?;
+    let rows = query_models::<AlbumFolder>(
+        &conn,
+        "SELECT * FROM album_folders WHERE deleted = 0 ORDER BY updated_at DESC",
+        (),
+    )
+    .await?;
 
     let mut items = Vec::new();
-    while let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        items.push(merge_row(&val));
+    for row in rows {
+        items.push(row.to_json());
     }
 
     Ok(json!({ "code": 0, "data": items }))
@@ -28,17 +28,18 @@ pub async fn db_album_folder_list(state: State<'_, TursoDb>) -> Result<JsonValue
 pub async fn db_album_folder_get(
     state: State<'_, TursoDb>,
     id: String,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
     ensure_album_folders_table(&conn).await?;
-    let mut rows = conn
-        .query("SELECT * FROM album_folders WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
+    let row = query_model::<AlbumFolder>(
+        &conn,
+        "SELECT * FROM album_folders WHERE id = ?1",
+        (id,),
+    )
+    .await?;
 
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        Ok(json!({ "code": 0, "data": merge_row(&val) }))
+    if let Some(row) = row {
+        Ok(json!({ "code": 0, "data": row.to_json() }))
     } else {
         Ok(json!({ "code": 1, "message": "Album folder not found" }))
     }
@@ -50,8 +51,8 @@ pub async fn db_album_folder_create(
     name: String,
     description: Option<String>,
     data: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
     ensure_album_folders_table(&conn).await?;
     let folder_id = new_id();
 
@@ -67,18 +68,18 @@ pub async fn db_album_folder_create(
         "INSERT INTO album_folders (id, data) VALUES (?1, ?2)",
         (folder_id.clone(), data_val),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
 
-    let mut rows = conn
-        .query("SELECT * FROM album_folders WHERE id = ?1", (folder_id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        Ok(json!({ "code": 0, "data": merge_row(&val) }))
+    let row = query_model::<AlbumFolder>(
+        &conn,
+        "SELECT * FROM album_folders WHERE id = ?1",
+        (folder_id,),
+    )
+    .await?;
+    if let Some(row) = row {
+        Ok(json!({ "code": 0, "data": row.to_json() }))
     } else {
-        Err("Failed to retrieve created album folder".to_string())
+        Err(AppError::storage("Failed to retrieve created album folder"))
     }
 }
 
@@ -89,17 +90,16 @@ pub async fn db_album_folder_update(
     name: Option<String>,
     description: Option<String>,
     data: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
     ensure_album_folders_table(&conn).await?;
 
     if let Some(d) = data {
         conn.execute(
-            "UPDATE album_folders SET data = ?1, updated_at = datetime('now') WHERE id = ?2",
+            "UPDATE album_folders SET data = ?1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?2",
             (d, id.clone()),
         )
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
     } else {
         let mut existing = get_album_folder_data(&conn, &id).await?;
         if let Some(n) = name {
@@ -109,20 +109,20 @@ pub async fn db_album_folder_update(
             existing["description"] = json!(d);
         }
         conn.execute(
-            "UPDATE album_folders SET data = ?1, updated_at = datetime('now') WHERE id = ?2",
+            "UPDATE album_folders SET data = ?1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?2",
             (existing.to_string(), id.clone()),
         )
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
     }
 
-    let mut rows = conn
-        .query("SELECT * FROM album_folders WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        Ok(json!({ "code": 0, "data": merge_row(&val) }))
+    let row = query_model::<AlbumFolder>(
+        &conn,
+        "SELECT * FROM album_folders WHERE id = ?1",
+        (id,),
+    )
+    .await?;
+    if let Some(row) = row {
+        Ok(json!({ "code": 0, "data": row.to_json() }))
     } else {
         Ok(json!({ "code": 1, "message": "Album folder not found" }))
     }
@@ -132,18 +132,17 @@ pub async fn db_album_folder_update(
 pub async fn db_album_folder_delete(
     state: State<'_, TursoDb>,
     id: String,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
     ensure_album_folders_table(&conn).await?;
 
     // 把该文件夹下相册的 folderId 置空
     let mut album_rows = conn
         .query("SELECT id, data FROM albums WHERE deleted = 0", ())
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
     let mut album_ids: Vec<String> = Vec::new();
-    while let Some(row) = album_rows.next().await.map_err(|e| e.to_string())? {
+    while let Some(row) = album_rows.next().await? {
         let album_id = row
             .get_value(0)
             .ok()
@@ -171,20 +170,18 @@ pub async fn db_album_folder_delete(
             obj.remove("folderId");
         }
         conn.execute(
-            "UPDATE albums SET data = ?1, updated_at = datetime('now') WHERE id = ?2",
+            "UPDATE albums SET data = ?1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?2",
             (existing.to_string(), album_id.clone()),
         )
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
     }
 
     // 软删文件夹
     conn.execute(
-        "UPDATE album_folders SET deleted = 1, updated_at = datetime('now') WHERE id = ?1",
+        "UPDATE album_folders SET deleted = 1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?1",
         (id,),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
 
     Ok(json!({ "code": 0 }))
 }
@@ -192,57 +189,34 @@ pub async fn db_album_folder_delete(
 async fn get_album_folder_data(
     conn: &turso::Connection,
     id: &str,
-) -> Result<JsonValue, String> {
+) -> Result<JsonValue, AppError> {
     let mut rows = conn
         .query("SELECT data FROM album_folders WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
+        .await?;
+    if let Some(row) = rows.next().await? {
         let data_str = row
-            .get_value(0)
-            .map_err(|e| e.to_string())?
+            .get_value(0)?
             .as_text()
             .map_or("{}", |v| v)
             .to_string();
-        serde_json::from_str(&data_str).map_err(|e| e.to_string())
+        serde_json::from_str(&data_str).map_err(AppError::from)
     } else {
-        Err("Album folder not found".to_string())
+        Err(AppError::not_found("Album folder not found"))
     }
 }
 
-async fn get_album_data(conn: &turso::Connection, id: &str) -> Result<JsonValue, String> {
+async fn get_album_data(conn: &turso::Connection, id: &str) -> Result<JsonValue, AppError> {
     let mut rows = conn
         .query("SELECT data FROM albums WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
+        .await?;
+    if let Some(row) = rows.next().await? {
         let data_str = row
-            .get_value(0)
-            .map_err(|e| e.to_string())?
+            .get_value(0)?
             .as_text()
             .map_or("{}", |v| v)
             .to_string();
-        serde_json::from_str(&data_str).map_err(|e| e.to_string())
+        serde_json::from_str(&data_str).map_err(AppError::from)
     } else {
-        Err("Album not found".to_string())
-    }
-}
-
-fn row_to_json(row: &turso::Row) -> Result<JsonValue, String> {
-    let mut map = serde_json::Map::new();
-    for i in 0..6 {
-        if let Ok(val) = row.get_value(i as usize) {
-            let key = match i {
-                0 => "id",
-                1 => "remote_id",
-                2 => "sync_status",
-                3 => "updated_at",
-                4 => "deleted",
-                5 => "data",
-                _ => continue,
-            };
-            map.insert(key.to_string(), super::turso_value_to_json(&val));
-        }
+        Err(AppError::not_found("Album not found"))
     }
-    Ok(JsonValue::Object(map))
 }
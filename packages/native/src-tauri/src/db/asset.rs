@@ -1,22 +1,26 @@
 use serde_json::{json, Value as JsonValue};
 use tauri::State;
 
-use super::{merge_row, new_id, TursoDb};
+use crate::error::AppError;
+use super::model::{query_model, query_models, Asset, AssetCategory, Model};
+use super::search::reindex_one;
+use super::{new_id, TursoDb};
 
 // ==================== Asset Categories ====================
 
 #[tauri::command]
-pub async fn db_asset_category_list(state: State<'_, TursoDb>) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
-    let mut rows = conn
-        .query("SELECT * FROM asset_categories WHERE deleted = 0", ())
-        .await
-        .map_err(|e| e.to_string())?;
+pub async fn db_asset_category_list(state: State<'_, TursoDb>) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let rows = query_models::<AssetCategory>(
+        &conn,
+        "SELECT * FROM asset_categories WHERE deleted = 0",
+        (),
+    )
+    .await?;
 
     let mut all = Vec::new();
-    while let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row, "asset_categories")?;
-        all.push(merge_row(&val));
+    for row in rows {
+        all.push(row.to_json());
     }
 
     // Build hierarchy: parent categories with sub-categories
@@ -45,8 +49,8 @@ pub async fn db_asset_category_create(
     name: String,
     parent_id: Option<String>,
     data: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
     let id = new_id();
 
     let data_val = data.unwrap_or_else(|| {
@@ -62,30 +66,29 @@ pub async fn db_asset_category_create(
         "INSERT INTO asset_categories (id, data) VALUES (?1, ?2)",
         (id.clone(), data_val),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
 
-    let mut rows = conn
-        .query("SELECT * FROM asset_categories WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row, "asset_categories")?;
-        Ok(json!({ "data": merge_row(&val) }))
+    let row = query_model::<AssetCategory>(
+        &conn,
+        "SELECT * FROM asset_categories WHERE id = ?1",
+        (id,),
+    )
+    .await?;
+    if let Some(row) = row {
+        Ok(json!({ "data": row.to_json() }))
     } else {
-        Err("Failed to create category".to_string())
+        Err(AppError::storage("Failed to create category"))
     }
 }
 
 #[tauri::command]
-pub async fn db_asset_category_delete(state: State<'_, TursoDb>, id: String) -> Result<(), String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+pub async fn db_asset_category_delete(state: State<'_, TursoDb>, id: String) -> Result<(), AppError> {
+    let conn = state.0.connect()?;
     conn.execute(
-        "UPDATE asset_categories SET deleted = 1, updated_at = datetime('now') WHERE id = ?1",
+        "UPDATE asset_categories SET deleted = 1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?1",
         (id,),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
     Ok(())
 }
 
@@ -97,8 +100,8 @@ pub async fn db_asset_list(
     category_id: Option<String>,
     sub_category_id: Option<String>,
     status: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
 
     let mut conditions = vec!["deleted = 0".to_string()];
     let mut params: Vec<turso::Value> = Vec::new();
@@ -139,11 +142,10 @@ pub async fn db_asset_list(
         where_clause
     );
 
-    let mut rows = conn.query(&sql, params).await.map_err(|e| e.to_string())?;
+    let rows = query_models::<Asset>(&conn, &sql, params).await?;
     let mut items = Vec::new();
-    while let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row, "assets")?;
-        let mut merged = merge_row(&val);
+    for row in rows {
+        let mut merged = row.to_json();
         // Compute costPerUse, costPerDay, daysHeld
         let price = merged.get("price").and_then(|v| v.as_f64()).unwrap_or(0.0);
         let purchase_date = merged
@@ -178,26 +180,22 @@ pub async fn db_asset_list(
 }
 
 #[tauri::command]
-pub async fn db_asset_create(state: State<'_, TursoDb>, data: String) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+pub async fn db_asset_create(state: State<'_, TursoDb>, data: String) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
     let id = new_id();
 
     conn.execute(
         "INSERT INTO assets (id, data) VALUES (?1, ?2)",
         (id.clone(), data),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
+    reindex_one(&conn, "assets", &id).await?;
 
-    let mut rows = conn
-        .query("SELECT * FROM assets WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row, "assets")?;
-        Ok(merge_row(&val))
+    let row = query_model::<Asset>(&conn, "SELECT * FROM assets WHERE id = ?1", (id,)).await?;
+    if let Some(row) = row {
+        Ok(row.to_json())
     } else {
-        Err("Failed to create asset".to_string())
+        Err(AppError::storage("Failed to create asset"))
     }
 }
 
@@ -206,8 +204,8 @@ pub async fn db_asset_update(
     state: State<'_, TursoDb>,
     id: String,
     data: String,
-) -> Result<(), String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<(), AppError> {
+    let conn = state.0.connect()?;
     let mut merged_data = get_asset_data(&conn, &id)
         .await
         .unwrap_or_else(|_| json!({}));
@@ -223,44 +221,42 @@ pub async fn db_asset_update(
         }
     }
     conn.execute(
-        "UPDATE assets SET data = ?1, updated_at = datetime('now') WHERE id = ?2",
-        (merged_data.to_string(), id),
+        "UPDATE assets SET data = ?1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?2",
+        (merged_data.to_string(), id.clone()),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
+    reindex_one(&conn, "assets", &id).await?;
     Ok(())
 }
 
 #[tauri::command]
-pub async fn db_asset_delete(state: State<'_, TursoDb>, id: String) -> Result<(), String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+pub async fn db_asset_delete(state: State<'_, TursoDb>, id: String) -> Result<(), AppError> {
+    let conn = state.0.connect()?;
     conn.execute(
-        "UPDATE assets SET deleted = 1, updated_at = datetime('now') WHERE id = ?1",
-        (id,),
+        "UPDATE assets SET deleted = 1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?1",
+        (id.clone(),),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
+    reindex_one(&conn, "assets", &id).await?;
     Ok(())
 }
 
 #[tauri::command]
-pub async fn db_asset_stats(state: State<'_, TursoDb>) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+pub async fn db_asset_stats(state: State<'_, TursoDb>) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
     let mut rows = conn
         .query(
             "SELECT data FROM assets WHERE deleted = 0 AND json_extract(data, '$.status') = 'active'",
             (),
         )
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
     let mut total_value = 0.0f64;
     let mut total_daily_cost = 0.0f64;
 
-    while let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
+    while let Some(row) = rows.next().await? {
         let data_str = row
-            .get_value(0)
-            .map_err(|e| e.to_string())?
+            .get_value(0)?
             .as_text()
             .map_or("{}", |v| v)
             .to_string();
@@ -289,44 +285,18 @@ pub async fn db_asset_stats(state: State<'_, TursoDb>) -> Result<JsonValue, Stri
     }))
 }
 
-async fn get_asset_data(conn: &turso::Connection, id: &str) -> Result<JsonValue, String> {
+async fn get_asset_data(conn: &turso::Connection, id: &str) -> Result<JsonValue, AppError> {
     let mut rows = conn
         .query("SELECT data FROM assets WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
+        .await?;
+    if let Some(row) = rows.next().await? {
         let data_str = row
-            .get_value(0)
-            .map_err(|e| e.to_string())?
+            .get_value(0)?
             .as_text()
             .map_or("{}", |v| v)
             .to_string();
-        serde_json::from_str(&data_str).map_err(|e| e.to_string())
+        serde_json::from_str(&data_str).map_err(AppError::from)
     } else {
-        Err("Asset not found".to_string())
-    }
-}
-
-fn row_to_json(row: &turso::Row, table: &str) -> Result<JsonValue, String> {
-    let mut map = serde_json::Map::new();
-    let col_count = match table {
-        "asset_categories" => 6,
-        "assets" => 6,
-        _ => 6,
-    };
-    for i in 0..col_count {
-        if let Ok(val) = row.get_value(i as usize) {
-            let key = match i {
-                0 => "id",
-                1 => "remote_id",
-                2 => "sync_status",
-                3 => "updated_at",
-                4 => "deleted",
-                5 => "data",
-                _ => continue,
-            };
-            map.insert(key.to_string(), super::turso_value_to_json(&val));
-        }
+        Err(AppError::not_found("Asset not found"))
     }
-    Ok(JsonValue::Object(map))
 }
@@ -0,0 +1,423 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use tauri::{Manager, State};
+
+use crate::error::AppError;
+use super::{migration, TursoDb};
+
+/// Archive layout (little-endian):
+/// `ETBACKUP` | u32 format | u64 db_len | db bytes | u32 manifest_len | manifest JSON
+const BACKUP_MAGIC: &[u8; 8] = b"ETBACKUP";
+const BACKUP_FORMAT: u32 = 1;
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+const DB_FILE_NAME: &str = "echo_trails.db";
+/// 清单只含版本与各表行数，远小于此
+const MAX_MANIFEST_LEN: u32 = 1024 * 1024;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupManifest {
+    pub format: u32,
+    pub app_version: String,
+    pub schema_version: i64,
+    pub created_at: String,
+    /// 每张表的行数，恢复时用于校验
+    pub tables: BTreeMap<String, i64>,
+    pub db_size: u64,
+    pub sha256: String,
+}
+
+/// Location of the live database file.
+pub fn db_file_path(app: &tauri::AppHandle) -> Result<PathBuf, AppError> {
+    let app_dir = app.path().app_data_dir()?;
+    Ok(app_dir.join(DB_FILE_NAME))
+}
+
+fn sidecar(path: &Path, suffix: &str) -> PathBuf {
+    PathBuf::from(format!("{}{}", path.display(), suffix))
+}
+
+/// Staged database waiting to be swapped in on next start.
+pub fn staged_restore_path(db_path: &Path) -> PathBuf {
+    sidecar(db_path, ".restore")
+}
+
+/// Previous database kept after a restore.
+pub fn rollback_path(db_path: &Path) -> PathBuf {
+    sidecar(db_path, ".rollback")
+}
+
+fn remove_db_files(path: &Path) {
+    let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_file(sidecar(path, "-wal"));
+    let _ = std::fs::remove_file(sidecar(path, "-shm"));
+}
+
+/// Row count of every user table.
+async fn table_counts(conn: &turso::Connection) -> Result<BTreeMap<String, i64>, AppError> {
+    let mut names = Vec::new();
+    {
+        let mut rows = conn
+            .query(
+                "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+                (),
+            )
+            .await?;
+        while let Some(row) = rows.next().await? {
+            if let Some(name) = row.get_value(0)?.as_text() {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    let mut counts = BTreeMap::new();
+    for name in names {
+        let mut rows = conn
+            .query(&format!("SELECT COUNT(*) FROM \"{}\"", name), ())
+            .await?;
+        let count = match rows.next().await? {
+            Some(row) => row
+                .get_value(0)?
+                .as_integer()
+                .copied()
+                .unwrap_or(0),
+            None => 0,
+        };
+        counts.insert(name, count);
+    }
+    Ok(counts)
+}
+
+/// Copy `len` bytes from `reader` to `writer`, returning the sha256 of what was copied.
+fn copy_hashed(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    len: u64,
+) -> Result<String, AppError> {
+    let mut ctx = ring::digest::Context::new(&ring::digest::SHA256);
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut remaining = len;
+    while remaining > 0 {
+        let want = remaining.min(buf.len() as u64) as usize;
+        let n = reader.read(&mut buf[..want])?;
+        if n == 0 {
+            return Err(AppError::validation("Unexpected end of database data"));
+        }
+        ctx.update(&buf[..n]);
+        writer.write_all(&buf[..n])?;
+        remaining -= n as u64;
+    }
+    Ok(ctx
+        .finish()
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Write a consistent snapshot of the database at `db_path` to `out_path`.
+/// The database and its WAL are copied while holding the write lock, and the
+/// manifest's version and row counts are read from that copy, so concurrent
+/// writers can't make them disagree with the archived database. The archive
+/// is written to a temp file and renamed into place.
+pub async fn create_backup(
+    conn: &turso::Connection,
+    db_path: &Path,
+    out_path: &Path,
+    app_version: &str,
+) -> Result<BackupManifest, AppError> {
+    let snapshot_path = sidecar(out_path, ".snapshot");
+    remove_db_files(&snapshot_path);
+    let result = async {
+        let (schema_version, tables) = snapshot_database(conn, db_path, &snapshot_path).await?;
+        write_archive(&snapshot_path, out_path, app_version, schema_version, tables)
+    }
+    .await;
+    remove_db_files(&snapshot_path);
+    result
+}
+
+// 持有写锁期间复制主文件与 WAL：其他连接无法在中途提交，两者合起来即完整快照。
+// 版本与行数从快照读取，再在快照上做检查点，归档只需包含主文件
+async fn snapshot_database(
+    conn: &turso::Connection,
+    db_path: &Path,
+    snapshot_path: &Path,
+) -> Result<(i64, BTreeMap<String, i64>), AppError> {
+    conn.execute("BEGIN IMMEDIATE", ()).await?;
+    let copied = (|| {
+        std::fs::copy(db_path, snapshot_path)?;
+        let wal_path = sidecar(db_path, "-wal");
+        if wal_path.exists() {
+            std::fs::copy(&wal_path, sidecar(snapshot_path, "-wal"))?;
+        }
+        Ok::<(), std::io::Error>(())
+    })();
+    let _ = conn.execute("ROLLBACK", ()).await;
+    copied.map_err(|e| AppError::io(format!("Failed to copy database: {}", e)))?;
+
+    let db = turso::Builder::new_local(&snapshot_path.to_string_lossy())
+        .build()
+        .await?;
+    let snapshot = db.connect()?;
+    let schema_version = migration::current_version(&snapshot).await?;
+    let tables = table_counts(&snapshot).await?;
+    let mut rows = snapshot
+        .query("PRAGMA wal_checkpoint(TRUNCATE)", ())
+        .await
+        .map_err(|e| AppError::storage(format!("WAL checkpoint failed: {}", e)))?;
+    while rows.next().await?.is_some() {}
+    Ok((schema_version, tables))
+}
+
+fn write_archive(
+    snapshot_path: &Path,
+    out_path: &Path,
+    app_version: &str,
+    schema_version: i64,
+    tables: BTreeMap<String, i64>,
+) -> Result<BackupManifest, AppError> {
+    let mut db_file = File::open(snapshot_path)
+        .map_err(|e| AppError::io(format!("Failed to open database: {}", e)))?;
+    let db_size = db_file.metadata()?.len();
+
+    let tmp_path = sidecar(out_path, ".tmp");
+    let result = (|| {
+        let mut out = BufWriter::new(
+            File::create(&tmp_path).map_err(|e| AppError::io(format!("Failed to create backup file: {}", e)))?,
+        );
+        out.write_all(BACKUP_MAGIC)?;
+        out.write_all(&BACKUP_FORMAT.to_le_bytes())?;
+        out.write_all(&db_size.to_le_bytes())?;
+        let sha256 = copy_hashed(&mut db_file, &mut out, db_size)?;
+
+        let manifest = BackupManifest {
+            format: BACKUP_FORMAT,
+            app_version: app_version.to_string(),
+            schema_version,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            tables,
+            db_size,
+            sha256,
+        };
+        let manifest_bytes = serde_json::to_vec(&manifest)?;
+        out.write_all(&(manifest_bytes.len() as u32).to_le_bytes())?;
+        out.write_all(&manifest_bytes)?;
+        out.into_inner().map_err(|e| AppError::io(e.to_string()))?
+            .sync_all()?;
+        Ok(manifest)
+    })();
+
+    match result {
+        Ok(manifest) => {
+            std::fs::rename(&tmp_path, out_path)?;
+            info!(
+                "Backup written to {} ({} bytes)",
+                out_path.display(),
+                manifest.db_size
+            );
+            Ok(manifest)
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&tmp_path);
+            Err(e)
+        }
+    }
+}
+
+/// Read and sanity-check the manifest of a backup archive without touching
+/// the embedded database.
+pub fn read_manifest(archive_path: &Path) -> Result<BackupManifest, AppError> {
+    let mut file = File::open(archive_path).map_err(|e| AppError::io(format!("Failed to open backup: {}", e)))?;
+    let db_len = read_header(&mut file)?;
+    file.seek(SeekFrom::Current(db_len as i64))?;
+    let mut len_buf = [0u8; 4];
+    file.read_exact(&mut len_buf)
+        .map_err(|_| AppError::validation("Backup file is truncated"))?;
+    // 长度来自文件本身，分配前先设上限
+    let manifest_len = u32::from_le_bytes(len_buf);
+    if manifest_len > MAX_MANIFEST_LEN {
+        return Err(AppError::validation(format!(
+            "Backup manifest is too large: {} bytes",
+            manifest_len
+        )));
+    }
+    let mut manifest_bytes = vec![0u8; manifest_len as usize];
+    file.read_exact(&mut manifest_bytes)
+        .map_err(|_| AppError::validation("Backup file is truncated"))?;
+    let manifest: BackupManifest = serde_json::from_slice(&manifest_bytes)
+        .map_err(|e| AppError::validation(format!("Invalid backup manifest: {}", e)))?;
+
+    if manifest.format != BACKUP_FORMAT {
+        return Err(AppError::validation(format!("Unsupported backup format: {}", manifest.format)));
+    }
+    if manifest.db_size != db_len {
+        return Err(AppError::validation("Backup manifest does not match archive size"));
+    }
+    if manifest.schema_version > migration::latest_version() {
+        return Err(AppError::validation(format!(
+            "Backup schema version {} is newer than this app supports (latest known: {}). Please upgrade the app.",
+            manifest.schema_version,
+            migration::latest_version()
+        )));
+    }
+    Ok(manifest)
+}
+
+fn read_header(file: &mut File) -> Result<u64, AppError> {
+    let mut magic = [0u8; 8];
+    file.read_exact(&mut magic)
+        .map_err(|_| AppError::validation("Not an Echo Trails backup file"))?;
+    if &magic != BACKUP_MAGIC {
+        return Err(AppError::validation("Not an Echo Trails backup file"));
+    }
+    let mut format = [0u8; 4];
+    file.read_exact(&mut format)
+        .map_err(|_| AppError::validation("Backup file is truncated"))?;
+    let mut db_len = [0u8; 8];
+    file.read_exact(&mut db_len)
+        .map_err(|_| AppError::validation("Backup file is truncated"))?;
+    Ok(u64::from_le_bytes(db_len))
+}
+
+/// Validate `archive_path` and stage its database next to `db_path`. The live
+/// database is left untouched; [`apply_pending_restore`] swaps it in on the
+/// next start, before the database is opened.
+pub async fn stage_restore(archive_path: &Path, db_path: &Path) -> Result<BackupManifest, AppError> {
+    let manifest = read_manifest(archive_path)?;
+
+    let tmp_path = sidecar(db_path, ".restore.tmp");
+    remove_db_files(&tmp_path);
+    let result = async {
+        {
+            let mut file = BufReader::new(
+                File::open(archive_path).map_err(|e| AppError::io(format!("Failed to open backup: {}", e)))?,
+            );
+            read_header(file.get_mut())?;
+            let mut out = BufWriter::new(File::create(&tmp_path)?);
+            let sha256 = copy_hashed(&mut file, &mut out, manifest.db_size)?;
+            if sha256 != manifest.sha256 {
+                return Err(AppError::validation("Backup checksum mismatch, the file may be corrupted"));
+            }
+            out.into_inner().map_err(|e| AppError::io(e.to_string()))?
+                .sync_all()?;
+        }
+
+        let mut header = [0u8; 16];
+        File::open(&tmp_path)
+            .and_then(|mut f| f.read_exact(&mut header))
+            .map_err(|_| AppError::validation("Backup does not contain a valid database"))?;
+        if &header != SQLITE_HEADER {
+            return Err(AppError::validation("Backup does not contain a valid database"));
+        }
+
+        // 打开暂存库核对 schema 版本与各表行数
+        let db = turso::Builder::new_local(&tmp_path.to_string_lossy())
+            .build()
+            .await
+            .map_err(|e| AppError::validation(format!("Backup database cannot be opened: {}", e)))?;
+        let conn = db.connect()?;
+        let version = migration::current_version(&conn).await?;
+        if version != manifest.schema_version {
+            return Err(AppError::validation(format!(
+                "Backup schema version mismatch: manifest {}, database {}",
+                manifest.schema_version, version
+            )));
+        }
+        let counts = table_counts(&conn).await?;
+        for (table, expected) in &manifest.tables {
+            let actual = counts.get(table).copied().unwrap_or(-1);
+            if actual != *expected {
+                return Err(AppError::validation(format!(
+                    "Backup row count mismatch for {}: manifest {}, database {}",
+                    table, expected, actual
+                )));
+            }
+        }
+        drop(conn);
+        drop(db);
+        Ok(())
+    }
+    .await;
+
+    let _ = std::fs::remove_file(sidecar(&tmp_path, "-wal"));
+    let _ = std::fs::remove_file(sidecar(&tmp_path, "-shm"));
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+    std::fs::rename(&tmp_path, staged_restore_path(db_path))?;
+    Ok(manifest)
+}
+
+/// Swap a staged restore into place. Must run before the database is opened.
+/// The current database (with its WAL/SHM) is moved to the rollback path; if
+/// the swap fails it is moved back. Returns whether a restore was applied.
+pub fn apply_pending_restore(db_path: &Path) -> Result<bool, AppError> {
+    let staged = staged_restore_path(db_path);
+    if !staged.exists() {
+        return Ok(false);
+    }
+
+    let rollback = rollback_path(db_path);
+    remove_db_files(&rollback);
+    let moves: Vec<(PathBuf, PathBuf)> = ["", "-wal", "-shm"]
+        .iter()
+        .map(|suffix| (sidecar(db_path, suffix), sidecar(&rollback, suffix)))
+        .filter(|(from, _)| from.exists())
+        .collect();
+    for (i, (from, to)) in moves.iter().enumerate() {
+        if let Err(e) = std::fs::rename(from, to) {
+            for (from, to) in &moves[..i] {
+                let _ = std::fs::rename(to, from);
+            }
+            return Err(AppError::io(format!("Failed to keep rollback copy: {}", e)));
+        }
+    }
+
+    if let Err(e) = std::fs::rename(&staged, db_path) {
+        for (from, to) in &moves {
+            let _ = std::fs::rename(to, from);
+        }
+        return Err(AppError::io(format!("Failed to swap in restored database: {}", e)));
+    }
+    info!(
+        "Restored database swapped in, previous copy kept at {}",
+        rollback.display()
+    );
+    Ok(true)
+}
+
+/// Write a WAL-checkpointed snapshot of the database plus manifest to `path`.
+#[tauri::command]
+pub async fn db_backup_create(
+    app: tauri::AppHandle,
+    state: State<'_, TursoDb>,
+    path: String,
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let db_path = db_file_path(&app)?;
+    let app_version = app.package_info().version.to_string();
+    let manifest = create_backup(&conn, &db_path, Path::new(&path), &app_version).await?;
+    Ok(json!({ "code": 0, "data": manifest }))
+}
+
+/// Validate a backup and stage it; the swap happens on the next app start.
+#[tauri::command]
+pub async fn db_backup_restore(app: tauri::AppHandle, path: String) -> Result<JsonValue, AppError> {
+    let db_path = db_file_path(&app)?;
+    let manifest = stage_restore(Path::new(&path), &db_path).await?;
+    Ok(json!({
+        "code": 0,
+        "data": {
+            "manifest": manifest,
+            "restartRequired": true,
+        }
+    }))
+}
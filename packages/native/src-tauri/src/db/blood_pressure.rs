@@ -2,7 +2,9 @@ use serde_json::{json, Value as JsonValue};
 use tauri::State;
 use turso::Value as TursoValue;
 
-use super::{merge_row, new_id, TursoDb};
+use crate::error::AppError;
+use super::model::{query_model, query_models, BloodPressure, Model};
+use super::{new_id, TursoDb};
 
 #[tauri::command]
 pub async fn db_bp_list(
@@ -12,8 +14,8 @@ pub async fn db_bp_list(
     end_time: Option<String>,
     page: Option<i64>,
     page_size: Option<i64>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
     let page = page.unwrap_or(1);
     let page_size = page_size.unwrap_or(50);
     let offset = (page - 1) * page_size;
@@ -45,11 +47,10 @@ pub async fn db_bp_list(
         where_clause, page_size, offset
     );
 
-    let mut rows = conn.query(&sql, params).await.map_err(|e| e.to_string())?;
+    let rows = query_models::<BloodPressure>(&conn, &sql, params).await?;
     let mut items = Vec::new();
-    while let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        items.push(merge_row(&val));
+    for row in rows {
+        items.push(row.to_json());
     }
 
     Ok(json!({ "code": 0, "data": items }))
@@ -68,8 +69,8 @@ pub async fn db_bp_add(
     note: Option<String>,
     operator: Option<String>,
     data: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
     let id = new_id();
 
     let data_val = data.unwrap_or_else(|| {
@@ -90,18 +91,18 @@ pub async fn db_bp_add(
         "INSERT INTO blood_pressures (id, family_id, date, data) VALUES (?1, ?2, ?3, ?4)",
         (id.clone(), family_id, date, data_val),
     )
-    .await
-    .map_err(|e| e.to_string())?;
-
-    let mut rows = conn
-        .query("SELECT * FROM blood_pressures WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        Ok(json!({ "code": 0, "data": merge_row(&val) }))
+    .await?;
+
+    let row = query_model::<BloodPressure>(
+        &conn,
+        "SELECT * FROM blood_pressures WHERE id = ?1",
+        (id,),
+    )
+    .await?;
+    if let Some(row) = row {
+        Ok(json!({ "code": 0, "data": row.to_json() }))
     } else {
-        Err("Failed to create blood pressure record".to_string())
+        Err(AppError::storage("Failed to create blood pressure record"))
     }
 }
 
@@ -110,12 +111,16 @@ pub async fn db_bp_update(
     state: State<'_, TursoDb>,
     id: String,
     data: String,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
 
     // Update fixed columns from data JSON if present
     let parsed: JsonValue = serde_json::from_str(&data).unwrap_or(json!({}));
-    let mut sets = vec!["data = ?1".to_string(), "updated_at = datetime('now')".to_string()];
+    let mut sets = vec![
+        "data = ?1".to_string(),
+        "sync_status = 'pending'".to_string(),
+        "updated_at = datetime('now')".to_string(),
+    ];
     let mut params: Vec<TursoValue> = vec![TursoValue::Text(data.clone())];
     let mut param_idx = 2;
 
@@ -133,17 +138,18 @@ pub async fn db_bp_update(
 
     params.push(TursoValue::Text(id.clone()));
     let sql = format!("UPDATE blood_pressures SET {} WHERE id = ?{}", sets.join(", "), param_idx);
-    conn.execute(&sql, params).await.map_err(|e| e.to_string())?;
-
-    let mut rows = conn
-        .query("SELECT * FROM blood_pressures WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        Ok(json!({ "code": 0, "data": merge_row(&val) }))
+    conn.execute(&sql, params).await?;
+
+    let row = query_model::<BloodPressure>(
+        &conn,
+        "SELECT * FROM blood_pressures WHERE id = ?1",
+        (id,),
+    )
+    .await?;
+    if let Some(row) = row {
+        Ok(json!({ "code": 0, "data": row.to_json() }))
     } else {
-        Err("Record not found".to_string())
+        Err(AppError::not_found("Record not found"))
     }
 }
 
@@ -151,34 +157,12 @@ pub async fn db_bp_update(
 pub async fn db_bp_delete(
     state: State<'_, TursoDb>,
     id: String,
-) -> Result<(), String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<(), AppError> {
+    let conn = state.0.connect()?;
     conn.execute(
-        "UPDATE blood_pressures SET deleted = 1, updated_at = datetime('now') WHERE id = ?1",
+        "UPDATE blood_pressures SET deleted = 1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?1",
         (id,),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
     Ok(())
 }
-
-fn row_to_json(row: &turso::Row) -> Result<JsonValue, String> {
-    let mut map = serde_json::Map::new();
-    for i in 0..8 {
-        if let Ok(val) = row.get_value(i as usize) {
-            let key = match i {
-                0 => "id",
-                1 => "remote_id",
-                2 => "sync_status",
-                3 => "updated_at",
-                4 => "deleted",
-                5 => "family_id",
-                6 => "date",
-                7 => "data",
-                _ => continue,
-            };
-            map.insert(key.to_string(), super::turso_value_to_json(&val));
-        }
-    }
-    Ok(JsonValue::Object(map))
-}
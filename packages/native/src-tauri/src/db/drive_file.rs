@@ -1,13 +1,21 @@
+use serde::Deserialize;
 use serde_json::{json, Value as JsonValue};
 use tauri::State;
 use turso::Value as TursoValue;
 
-use super::{merge_row, new_id, TursoDb};
+use crate::command::s3_client::purge_objects;
+use crate::command::s3_presign::S3Credentials;
+use crate::command::s3_profile::{ProviderConfig, ProviderProfile};
+use crate::error::AppError;
+use super::search::{reindex, reindex_one};
+use super::sync_engine::purge_with_tombstones;
+use super::model::{query_model, query_models, DriveFile, Model};
+use super::{new_id, TursoDb};
 
 const DRIVE_TABLE: &str = "drive_files";
 
-fn merge_drive_row(row: &JsonValue) -> JsonValue {
-    let mut val = merge_row(row);
+fn merge_drive_row(row: &DriveFile) -> JsonValue {
+    let mut val = row.to_json();
     if let Some(obj) = val.as_object_mut() {
         // 固定列 snake_case → 前端 camelCase
         if let Some(parent) = obj.remove("parent_id") {
@@ -31,22 +39,14 @@ fn merge_drive_row(row: &JsonValue) -> JsonValue {
 async fn get_row_by_id(
     conn: &turso::Connection,
     id: &str,
-) -> Result<Option<JsonValue>, String> {
-    let mut rows = conn
-        .query(&format!("SELECT * FROM {} WHERE id = ?1", DRIVE_TABLE), (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        Ok(Some(row_to_json(&row)?))
-    } else {
-        Ok(None)
-    }
+) -> Result<Option<DriveFile>, AppError> {
+    query_model(conn, &format!("SELECT * FROM {} WHERE id = ?1", DRIVE_TABLE), (id,)).await
 }
 
 async fn build_breadcrumb(
     conn: &turso::Connection,
     parent_id: &str,
-) -> Result<Vec<JsonValue>, String> {
+) -> Result<Vec<JsonValue>, AppError> {
     let mut chain: Vec<JsonValue> = Vec::new();
     let mut current = parent_id.to_string();
     // 防环：上限 50 层
@@ -58,25 +58,17 @@ async fn build_breadcrumb(
             Some(v) => v,
             None => break,
         };
-        let deleted = row.get("deleted").and_then(|v| v.as_i64()).unwrap_or(0) != 0;
-        let kind = row.get("kind").and_then(|v| v.as_str()).unwrap_or("").to_string();
-        if deleted || kind != "folder" {
+        if row.deleted != 0 || row.kind.as_deref() != Some("folder") {
             break;
         }
-        let id = row.get("id").and_then(|v| v.as_str()).unwrap_or("").to_string();
-        let data = row.get("data").and_then(|v| v.as_str()).unwrap_or("{}");
-        let data_json: JsonValue = serde_json::from_str(data).unwrap_or(json!({}));
+        let data_json: JsonValue = serde_json::from_str(&row.data).unwrap_or(json!({}));
         let name = data_json
             .get("name")
             .and_then(|v| v.as_str())
             .unwrap_or("")
             .to_string();
-        chain.insert(0, json!({ "id": id, "name": name }));
-        current = row
-            .get("parent_id")
-            .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .to_string();
+        chain.insert(0, json!({ "id": row.id, "name": name }));
+        current = row.parent_id.unwrap_or_default();
     }
     Ok(chain)
 }
@@ -85,7 +77,7 @@ async fn build_breadcrumb(
 async fn collect_descendant_ids(
     conn: &turso::Connection,
     root_id: &str,
-) -> Result<Vec<String>, String> {
+) -> Result<Vec<String>, AppError> {
     let mut ids = vec![root_id.to_string()];
     let mut frontier = vec![root_id.to_string()];
     for _ in 0..50 {
@@ -105,12 +97,11 @@ async fn collect_descendant_ids(
             "SELECT id FROM {} WHERE deleted = 0 AND parent_id IN ({})",
             DRIVE_TABLE, placeholders
         );
-        let mut rows = conn.query(&sql, params).await.map_err(|e| e.to_string())?;
+        let mut rows = conn.query(&sql, params).await?;
         let mut next = Vec::new();
-        while let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
+        while let Some(row) = rows.next().await? {
             let id = row
-                .get_value(0)
-                .map_err(|e| e.to_string())?
+                .get_value(0)?
                 .as_text()
                 .map_or("".to_string(), |v| v.to_string());
             if !id.is_empty() {
@@ -127,25 +118,23 @@ async fn collect_descendant_ids(
 pub async fn db_drive_file_list(
     state: State<'_, TursoDb>,
     parent_id: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
     let parent = parent_id.unwrap_or_default();
 
-    let mut rows = conn
-        .query(
-            &format!(
-                "SELECT * FROM {} WHERE deleted = 0 AND parent_id = ?1 ORDER BY kind ASC, updated_at DESC",
-                DRIVE_TABLE
-            ),
-            (parent.clone(),),
-        )
-        .await
-        .map_err(|e| e.to_string())?;
+    let rows = query_models::<DriveFile>(
+        &conn,
+        &format!(
+            "SELECT * FROM {} WHERE deleted = 0 AND parent_id = ?1 ORDER BY kind ASC, updated_at DESC",
+            DRIVE_TABLE
+        ),
+        (parent.clone(),),
+    )
+    .await?;
 
     let mut items = Vec::new();
-    while let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        items.push(merge_drive_row(&val));
+    for row in rows {
+        items.push(merge_drive_row(&row));
     }
 
     let breadcrumb = build_breadcrumb(&conn, &parent).await?;
@@ -158,11 +147,11 @@ pub async fn db_drive_file_create_folder(
     state: State<'_, TursoDb>,
     name: String,
     parent_id: Option<String>,
-) -> Result<JsonValue, String> {
+) -> Result<JsonValue, AppError> {
     if name.trim().is_empty() {
-        return Err("name is required".to_string());
+        return Err(AppError::validation("name is required"));
     }
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+    let conn = state.0.connect()?;
     let id = new_id();
     let parent = parent_id.unwrap_or_default();
 
@@ -185,37 +174,47 @@ pub async fn db_drive_file_create_folder(
             TursoValue::Text(data),
         ],
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
+    reindex_one(&conn, DRIVE_TABLE, &id).await?;
 
     match get_row_by_id(&conn, &id).await? {
-        Some(val) => Ok(json!({ "code": 0, "data": merge_drive_row(&val) })),
-        None => Err("Failed to create folder".to_string()),
+        Some(file) => Ok(json!({ "code": 0, "data": merge_drive_row(&file) })),
+        None => Err(AppError::storage("Failed to create folder")),
     }
 }
 
-#[tauri::command]
-pub async fn db_drive_file_create(
-    state: State<'_, TursoDb>,
-    key: String,
-    name: String,
-    size: Option<i64>,
-    mime_type: Option<String>,
-    parent_id: Option<String>,
-) -> Result<JsonValue, String> {
-    if key.is_empty() || name.is_empty() {
-        return Err("key and name are required".to_string());
+/// Fields of a drive file row; an upload job can carry them in its `meta` and
+/// create the file once the object is stored.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewDriveFile {
+    pub name: String,
+    #[serde(default)]
+    pub size: Option<i64>,
+    #[serde(default)]
+    pub mime_type: Option<String>,
+    #[serde(default)]
+    pub parent_id: Option<String>,
+}
+
+/// Insert a file row pointing at `key` and return it.
+pub async fn insert_drive_file(
+    conn: &turso::Connection,
+    id: &str,
+    key: &str,
+    file: NewDriveFile,
+) -> Result<JsonValue, AppError> {
+    if key.is_empty() || file.name.is_empty() {
+        return Err(AppError::validation("key and name are required"));
     }
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
-    let id = new_id();
-    let parent = parent_id.unwrap_or_default();
+    let parent = file.parent_id.unwrap_or_default();
 
     let now = chrono::Utc::now().to_rfc3339();
     let data = json!({
-        "name": name,
+        "name": file.name,
         "key": key,
-        "size": size.unwrap_or(0),
-        "mimeType": mime_type.unwrap_or_default(),
+        "size": file.size.unwrap_or(0),
+        "mimeType": file.mime_type.unwrap_or_default(),
         "provider": "bitiful",
         "createdAt": now,
     })
@@ -227,54 +226,78 @@ pub async fn db_drive_file_create(
             DRIVE_TABLE
         ),
         vec![
-            TursoValue::Text(id.clone()),
+            TursoValue::Text(id.to_string()),
             TursoValue::Text(parent),
             TursoValue::Text(data),
         ],
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
+    reindex_one(conn, DRIVE_TABLE, id).await?;
 
-    match get_row_by_id(&conn, &id).await? {
-        Some(val) => Ok(json!({ "code": 0, "data": merge_drive_row(&val) })),
-        None => Err("Failed to create drive file".to_string()),
+    match get_row_by_id(conn, id).await? {
+        Some(file) => Ok(merge_drive_row(&file)),
+        None => Err(AppError::storage("Failed to create drive file")),
     }
 }
 
+#[tauri::command]
+pub async fn db_drive_file_create(
+    state: State<'_, TursoDb>,
+    key: String,
+    name: String,
+    size: Option<i64>,
+    mime_type: Option<String>,
+    parent_id: Option<String>,
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let file = insert_drive_file(
+        &conn,
+        &new_id(),
+        &key,
+        NewDriveFile {
+            name,
+            size,
+            mime_type,
+            parent_id,
+        },
+    )
+    .await?;
+    Ok(json!({ "code": 0, "data": file }))
+}
+
 #[tauri::command]
 pub async fn db_drive_file_rename(
     state: State<'_, TursoDb>,
     id: String,
     name: String,
-) -> Result<JsonValue, String> {
+) -> Result<JsonValue, AppError> {
     if name.trim().is_empty() {
-        return Err("name is required".to_string());
+        return Err(AppError::validation("name is required"));
     }
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+    let conn = state.0.connect()?;
 
     let row = match get_row_by_id(&conn, &id).await? {
         Some(v) => v,
-        None => return Err("Drive file not found".to_string()),
+        None => return Err(AppError::not_found("Drive file not found")),
     };
-    let data_str = row.get("data").and_then(|v| v.as_str()).unwrap_or("{}");
-    let mut data: JsonValue = serde_json::from_str(data_str).unwrap_or(json!({}));
+    let mut data: JsonValue = serde_json::from_str(&row.data).unwrap_or(json!({}));
     if let Some(obj) = data.as_object_mut() {
         obj.insert("name".to_string(), json!(name));
     }
 
     conn.execute(
         &format!(
-            "UPDATE {} SET data = ?1, updated_at = datetime('now') WHERE id = ?2",
+            "UPDATE {} SET data = ?1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?2",
             DRIVE_TABLE
         ),
         (data.to_string(), id.clone()),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
+    reindex_one(&conn, DRIVE_TABLE, &id).await?;
 
     match get_row_by_id(&conn, &id).await? {
-        Some(val) => Ok(json!({ "code": 0, "data": merge_drive_row(&val) })),
-        None => Err("Drive file not found".to_string()),
+        Some(file) => Ok(json!({ "code": 0, "data": merge_drive_row(&file) })),
+        None => Err(AppError::not_found("Drive file not found")),
     }
 }
 
@@ -283,53 +306,52 @@ pub async fn db_drive_file_move(
     state: State<'_, TursoDb>,
     id: String,
     parent_id: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
     let parent = parent_id.unwrap_or_default();
 
     let row = match get_row_by_id(&conn, &id).await? {
         Some(v) => v,
-        None => return Err("Drive file not found".to_string()),
+        None => return Err(AppError::not_found("Drive file not found")),
     };
 
     // 文件夹移动时校验目标不是自身或其后代
-    let kind = row.get("kind").and_then(|v| v.as_str()).unwrap_or("");
+    let kind = row.kind.as_deref().unwrap_or("");
     if kind == "folder" {
         if id == parent {
-            return Err("cannot move to itself".to_string());
+            return Err(AppError::validation("cannot move to itself"));
         }
         let descendants = collect_descendant_ids(&conn, &id).await?;
         if descendants.contains(&parent) {
-            return Err("cannot move to its own descendant".to_string());
+            return Err(AppError::validation("cannot move to its own descendant"));
         }
     }
 
     conn.execute(
         &format!(
-            "UPDATE {} SET parent_id = ?1, updated_at = datetime('now') WHERE id = ?2",
+            "UPDATE {} SET parent_id = ?1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?2",
             DRIVE_TABLE
         ),
         (parent, id.clone()),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
 
     match get_row_by_id(&conn, &id).await? {
-        Some(val) => Ok(json!({ "code": 0, "data": merge_drive_row(&val) })),
-        None => Err("Drive file not found".to_string()),
+        Some(file) => Ok(json!({ "code": 0, "data": merge_drive_row(&file) })),
+        None => Err(AppError::not_found("Drive file not found")),
     }
 }
 
 #[tauri::command]
-pub async fn db_drive_file_delete(state: State<'_, TursoDb>, id: String) -> Result<(), String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+pub async fn db_drive_file_delete(state: State<'_, TursoDb>, id: String) -> Result<(), AppError> {
+    let conn = state.0.connect()?;
 
     let row = match get_row_by_id(&conn, &id).await? {
         Some(v) => v,
-        None => return Err("Drive file not found".to_string()),
+        None => return Err(AppError::not_found("Drive file not found")),
     };
 
-    let kind = row.get("kind").and_then(|v| v.as_str()).unwrap_or("");
+    let kind = row.kind.as_deref().unwrap_or("");
     let ids = if kind == "folder" {
         collect_descendant_ids(&conn, &id).await?
     } else {
@@ -346,10 +368,11 @@ pub async fn db_drive_file_delete(state: State<'_, TursoDb>, id: String) -> Resu
         .collect::<Vec<_>>()
         .join(", ");
     let sql = format!(
-        "UPDATE {} SET deleted = 1, updated_at = datetime('now') WHERE id IN ({})",
+        "UPDATE {} SET deleted = 1, sync_status = 'pending', updated_at = datetime('now') WHERE id IN ({})",
         DRIVE_TABLE, placeholders
     );
-    conn.execute(&sql, params).await.map_err(|e| e.to_string())?;
+    conn.execute(&sql, params).await?;
+    reindex(&conn, DRIVE_TABLE, &ids).await?;
 
     Ok(())
 }
@@ -360,7 +383,7 @@ pub async fn db_drive_file_delete(state: State<'_, TursoDb>, id: String) -> Resu
 async fn collect_descendant_ids_for_trash(
     conn: &turso::Connection,
     root_id: &str,
-) -> Result<Vec<String>, String> {
+) -> Result<Vec<String>, AppError> {
     let mut ids = vec![root_id.to_string()];
     let mut frontier = vec![root_id.to_string()];
     for _ in 0..50 {
@@ -381,12 +404,11 @@ async fn collect_descendant_ids_for_trash(
             "SELECT id FROM {} WHERE parent_id IN ({})",
             DRIVE_TABLE, placeholders
         );
-        let mut rows = conn.query(&sql, params).await.map_err(|e| e.to_string())?;
+        let mut rows = conn.query(&sql, params).await?;
         let mut next = Vec::new();
-        while let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
+        while let Some(row) = rows.next().await? {
             let id = row
-                .get_value(0)
-                .map_err(|e| e.to_string())?
+                .get_value(0)?
                 .as_text()
                 .map_or("".to_string(), |v| v.to_string());
             if !id.is_empty() {
@@ -401,25 +423,23 @@ async fn collect_descendant_ids_for_trash(
 
 // 回收站列表：仅顶级软删项（被软删且父目录未被软删，避免后代重复展示）
 #[tauri::command]
-pub async fn db_drive_file_trash_list(state: State<'_, TursoDb>) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+pub async fn db_drive_file_trash_list(state: State<'_, TursoDb>) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
 
     // 拉取所有软删项，Rust 端过滤掉父目录也被软删的后代（避免子查询兼容性问题）
-    let mut rows = conn
-        .query(
-            &format!(
-                "SELECT * FROM {} WHERE deleted = 1 ORDER BY updated_at DESC",
-                DRIVE_TABLE
-            ),
-            (),
-        )
-        .await
-        .map_err(|e| e.to_string())?;
+    let rows = query_models::<DriveFile>(
+        &conn,
+        &format!(
+            "SELECT * FROM {} WHERE deleted = 1 ORDER BY updated_at DESC",
+            DRIVE_TABLE
+        ),
+        (),
+    )
+    .await?;
 
     let mut all: Vec<JsonValue> = Vec::new();
-    while let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        all.push(merge_drive_row(&val));
+    for row in rows {
+        all.push(merge_drive_row(&row));
     }
 
     // 收集所有软删项 id
@@ -451,15 +471,15 @@ pub async fn db_drive_file_trash_list(state: State<'_, TursoDb>) -> Result<JsonV
 
 // 恢复（递归恢复所有后代）
 #[tauri::command]
-pub async fn db_drive_file_restore(state: State<'_, TursoDb>, id: String) -> Result<(), String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+pub async fn db_drive_file_restore(state: State<'_, TursoDb>, id: String) -> Result<(), AppError> {
+    let conn = state.0.connect()?;
 
     let row = match get_row_by_id(&conn, &id).await? {
         Some(v) => v,
-        None => return Err("Drive file not found".to_string()),
+        None => return Err(AppError::not_found("Drive file not found")),
     };
 
-    let kind = row.get("kind").and_then(|v| v.as_str()).unwrap_or("");
+    let kind = row.kind.as_deref().unwrap_or("");
     let ids = if kind == "folder" {
         collect_descendant_ids_for_trash(&conn, &id).await?
     } else {
@@ -476,10 +496,11 @@ pub async fn db_drive_file_restore(state: State<'_, TursoDb>, id: String) -> Res
         .collect::<Vec<_>>()
         .join(", ");
     let sql = format!(
-        "UPDATE {} SET deleted = 0, updated_at = datetime('now') WHERE id IN ({})",
+        "UPDATE {} SET deleted = 0, sync_status = 'pending', updated_at = datetime('now') WHERE id IN ({})",
         DRIVE_TABLE, placeholders
     );
-    conn.execute(&sql, params).await.map_err(|e| e.to_string())?;
+    conn.execute(&sql, params).await?;
+    reindex(&conn, DRIVE_TABLE, &ids).await?;
 
     Ok(())
 }
@@ -495,15 +516,16 @@ pub async fn db_drive_file_purge(
     endpoint: Option<String>,
     access_key: Option<String>,
     secret_key: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+    profile: Option<ProviderConfig>,
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
 
     let row = match get_row_by_id(&conn, &id).await? {
         Some(v) => v,
-        None => return Err("Drive file not found".to_string()),
+        None => return Err(AppError::not_found("Drive file not found")),
     };
 
-    let kind = row.get("kind").and_then(|v| v.as_str()).unwrap_or("");
+    let kind = row.kind.as_deref().unwrap_or("");
     let ids = if kind == "folder" {
         collect_descendant_ids_for_trash(&conn, &id).await?
     } else {
@@ -526,8 +548,8 @@ pub async fn db_drive_file_purge(
             "SELECT data FROM {} WHERE id IN ({}) AND kind = 'file'",
             DRIVE_TABLE, placeholders
         );
-        let mut rows = conn.query(&sql, params).await.map_err(|e| e.to_string())?;
-        while let Some(r) = rows.next().await.map_err(|e| e.to_string())? {
+        let mut rows = conn.query(&sql, params).await?;
+        while let Some(r) = rows.next().await? {
             if let Ok(data_val) = r.get_value(0) {
                 if let Some(data_str) = data_val.as_text() {
                     if let Ok(data_json) = serde_json::from_str::<JsonValue>(data_str) {
@@ -543,51 +565,19 @@ pub async fn db_drive_file_purge(
     }
 
     // S3 对象删除（配置完整时）
-    let mut s3_failed = 0u32;
-    if let (Some(bucket), Some(region), Some(endpoint), Some(access_key), Some(secret_key)) =
-        (bucket, region, endpoint, access_key, secret_key)
-    {
-        if !bucket.is_empty()
-            && !endpoint.is_empty()
-            && !access_key.is_empty()
-            && !secret_key.is_empty()
-        {
-            let region_value = if region.is_empty() {
-                "us-east-1".to_string()
-            } else {
-                region
-            };
-            for key in &keys {
-                match crate::command::s3_presign::presign_delete_object_url(
-                    crate::command::s3_presign::PresignDeleteObjectParams {
-                        key,
-                        bucket: &bucket,
-                        region: &region_value,
-                        endpoint: &endpoint,
-                        access_key: &access_key,
-                        secret_key: &secret_key,
-                        expires_seconds: 3600,
-                    },
-                    chrono::Utc::now(),
-                ) {
-                    Ok(url) => {
-                        let client = reqwest::Client::new();
-                        match client.delete(&url).send().await {
-                            Ok(resp) if resp.status().is_success() => {}
-                            _ => {
-                                s3_failed += 1;
-                            }
-                        }
-                    }
-                    Err(_) => {
-                        s3_failed += 1;
-                    }
-                }
-            }
-        }
-    }
+    let s3_failed = match S3Credentials::from_parts(
+        bucket,
+        region,
+        endpoint,
+        access_key,
+        secret_key,
+        ProviderProfile::from_config(profile.as_ref()),
+    ) {
+        Some(credentials) => purge_objects(&credentials, &keys).await,
+        None => 0,
+    };
 
-    // DB 物理删除
+    // DB 物理删除（同一事务内写墓碑，供同步推送删除）
     let mut params: Vec<TursoValue> = Vec::new();
     let placeholders = ids
         .iter()
@@ -597,8 +587,8 @@ pub async fn db_drive_file_purge(
         })
         .collect::<Vec<_>>()
         .join(", ");
-    let sql = format!("DELETE FROM {} WHERE id IN ({})", DRIVE_TABLE, placeholders);
-    conn.execute(&sql, params).await.map_err(|e| e.to_string())?;
+    let condition = format!("id IN ({})", placeholders);
+    purge_with_tombstones(&conn, DRIVE_TABLE, &condition, params).await?;
 
     Ok(json!({
         "code": 0,
@@ -619,8 +609,9 @@ pub async fn db_drive_file_purge_all(
     endpoint: Option<String>,
     access_key: Option<String>,
     secret_key: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+    profile: Option<ProviderConfig>,
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
 
     // 收集所有软删 file 的 key
     let mut keys: Vec<String> = Vec::new();
@@ -633,9 +624,8 @@ pub async fn db_drive_file_purge_all(
                 ),
                 (),
             )
-            .await
-            .map_err(|e| e.to_string())?;
-        while let Some(r) = rows.next().await.map_err(|e| e.to_string())? {
+            .await?;
+        while let Some(r) = rows.next().await? {
             if let Ok(data_val) = r.get_value(0) {
                 if let Some(data_str) = data_val.as_text() {
                     if let Ok(data_json) = serde_json::from_str::<JsonValue>(data_str) {
@@ -651,57 +641,20 @@ pub async fn db_drive_file_purge_all(
     }
 
     // S3 对象删除（配置完整时）
-    let mut s3_failed = 0u32;
-    if let (Some(bucket), Some(region), Some(endpoint), Some(access_key), Some(secret_key)) =
-        (bucket, region, endpoint, access_key, secret_key)
-    {
-        if !bucket.is_empty()
-            && !endpoint.is_empty()
-            && !access_key.is_empty()
-            && !secret_key.is_empty()
-        {
-            let region_value = if region.is_empty() {
-                "us-east-1".to_string()
-            } else {
-                region
-            };
-            for key in &keys {
-                match crate::command::s3_presign::presign_delete_object_url(
-                    crate::command::s3_presign::PresignDeleteObjectParams {
-                        key,
-                        bucket: &bucket,
-                        region: &region_value,
-                        endpoint: &endpoint,
-                        access_key: &access_key,
-                        secret_key: &secret_key,
-                        expires_seconds: 3600,
-                    },
-                    chrono::Utc::now(),
-                ) {
-                    Ok(url) => {
-                        let client = reqwest::Client::new();
-                        match client.delete(&url).send().await {
-                            Ok(resp) if resp.status().is_success() => {}
-                            _ => {
-                                s3_failed += 1;
-                            }
-                        }
-                    }
-                    Err(_) => {
-                        s3_failed += 1;
-                    }
-                }
-            }
-        }
-    }
+    let s3_failed = match S3Credentials::from_parts(
+        bucket,
+        region,
+        endpoint,
+        access_key,
+        secret_key,
+        ProviderProfile::from_config(profile.as_ref()),
+    ) {
+        Some(credentials) => purge_objects(&credentials, &keys).await,
+        None => 0,
+    };
 
-    // DB 物理删除所有软删记录
-    conn.execute(
-        &format!("DELETE FROM {} WHERE deleted = 1", DRIVE_TABLE),
-        (),
-    )
-    .await
-    .map_err(|e| e.to_string())?;
+    // DB 物理删除所有软删记录（同一事务内写墓碑）
+    purge_with_tombstones(&conn, DRIVE_TABLE, "deleted = 1", Vec::new()).await?;
 
     Ok(json!({
         "code": 0,
@@ -712,23 +665,3 @@ pub async fn db_drive_file_purge_all(
         }
     }))
 }
-
-fn row_to_json(row: &turso::Row) -> Result<JsonValue, String> {
-    let mut map = serde_json::Map::new();
-    let keys = [
-        "id",
-        "remote_id",
-        "sync_status",
-        "updated_at",
-        "deleted",
-        "parent_id",
-        "kind",
-        "data",
-    ];
-    for (i, key) in keys.iter().enumerate() {
-        if let Ok(val) = row.get_value(i) {
-            map.insert(key.to_string(), super::turso_value_to_json(&val));
-        }
-    }
-    Ok(JsonValue::Object(map))
-}
@@ -1,7 +1,10 @@
 use serde_json::{json, Value as JsonValue};
 use tauri::State;
 
-use super::{merge_row, new_id, TursoDb};
+use crate::error::AppError;
+use super::model::{query_model, query_models, Memorial, Model};
+use super::search::reindex_one;
+use super::{new_id, TursoDb};
 
 fn normalize_memorial(mut val: JsonValue) -> JsonValue {
     if let Some(obj) = val.as_object_mut() {
@@ -26,30 +29,28 @@ fn normalize_memorial(mut val: JsonValue) -> JsonValue {
     val
 }
 
-fn merge_memorial_row(row: &JsonValue) -> JsonValue {
-    normalize_memorial(merge_row(row))
+fn merge_memorial_row(row: &Memorial) -> JsonValue {
+    normalize_memorial(row.to_json())
 }
 
-fn parse_memorial_payload(data: &str) -> Result<JsonValue, String> {
-    let parsed = serde_json::from_str::<JsonValue>(data).map_err(|e| e.to_string())?;
+fn parse_memorial_payload(data: &str) -> Result<JsonValue, AppError> {
+    let parsed = serde_json::from_str::<JsonValue>(data)?;
     Ok(normalize_memorial(parsed))
 }
 
 #[tauri::command]
-pub async fn db_memorial_list(state: State<'_, TursoDb>) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
-    let mut rows = conn
-        .query(
-            "SELECT * FROM memorials WHERE deleted = 0 ORDER BY updated_at DESC",
-            (),
-        )
-        .await
-        .map_err(|e| e.to_string())?;
+pub async fn db_memorial_list(state: State<'_, TursoDb>) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let rows = query_models::<Memorial>(
+        &conn,
+        "SELECT * FROM memorials WHERE deleted = 0 ORDER BY updated_at DESC",
+        (),
+    )
+    .await?;
 
     let mut items = Vec::new();
-    while let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        items.push(merge_memorial_row(&val));
+    for row in rows {
+        items.push(merge_memorial_row(&row));
     }
 
     Ok(json!({ "data": items }))
@@ -60,8 +61,8 @@ pub async fn db_memorial_create(
     state: State<'_, TursoDb>,
     id: Option<String>,
     data: String,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
     let mid = id.unwrap_or_else(new_id);
     let memorial_data = parse_memorial_payload(&data)?;
 
@@ -69,18 +70,19 @@ pub async fn db_memorial_create(
         "INSERT INTO memorials (id, data) VALUES (?1, ?2)",
         (mid.clone(), memorial_data.to_string()),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
+    reindex_one(&conn, "memorials", &mid).await?;
 
-    let mut rows = conn
-        .query("SELECT * FROM memorials WHERE id = ?1", (mid,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        Ok(merge_memorial_row(&val))
+    let row = query_model::<Memorial>(
+        &conn,
+        "SELECT * FROM memorials WHERE id = ?1",
+        (mid,),
+    )
+    .await?;
+    if let Some(row) = row {
+        Ok(merge_memorial_row(&row))
     } else {
-        Err("Failed to create memorial".to_string())
+        Err(AppError::storage("Failed to create memorial"))
     }
 }
 
@@ -89,8 +91,8 @@ pub async fn db_memorial_update(
     state: State<'_, TursoDb>,
     id: String,
     data: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
 
     if let Some(d) = data {
         let mut merged_data = get_memorial_data(&conn, &id)
@@ -110,41 +112,37 @@ pub async fn db_memorial_update(
         }
         merged_data = normalize_memorial(merged_data);
         conn.execute(
-            "UPDATE memorials SET data = ?1, updated_at = datetime('now') WHERE id = ?2",
+            "UPDATE memorials SET data = ?1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?2",
             (merged_data.to_string(), id.clone()),
         )
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+        reindex_one(&conn, "memorials", &id).await?;
     } else {
         // No data provided, nothing to update
     }
 
-    let mut rows = conn
-        .query("SELECT * FROM memorials WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        Ok(merge_memorial_row(&val))
+    let row = query_model::<Memorial>(&conn, "SELECT * FROM memorials WHERE id = ?1", (id,)).await?;
+    if let Some(row) = row {
+        Ok(merge_memorial_row(&row))
     } else {
-        Err("Memorial not found".to_string())
+        Err(AppError::not_found("Memorial not found"))
     }
 }
 
 #[tauri::command]
-pub async fn db_memorial_delete(state: State<'_, TursoDb>, id: String) -> Result<(), String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+pub async fn db_memorial_delete(state: State<'_, TursoDb>, id: String) -> Result<(), AppError> {
+    let conn = state.0.connect()?;
     conn.execute(
-        "UPDATE memorials SET deleted = 1, updated_at = datetime('now') WHERE id = ?1",
-        (id,),
+        "UPDATE memorials SET deleted = 1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?1",
+        (id.clone(),),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
+    reindex_one(&conn, "memorials", &id).await?;
     Ok(())
 }
 
 #[tauri::command]
-pub async fn db_memorial_covers() -> Result<JsonValue, String> {
+pub async fn db_memorial_covers() -> Result<JsonValue, AppError> {
     let covers = vec![
         "/memorial-covers/pink-clouds.jpg",
         "/memorial-covers/minimal-leaves.jpg",
@@ -161,39 +159,18 @@ pub async fn db_memorial_covers() -> Result<JsonValue, String> {
     Ok(json!({ "data": covers }))
 }
 
-async fn get_memorial_data(conn: &turso::Connection, id: &str) -> Result<JsonValue, String> {
+async fn get_memorial_data(conn: &turso::Connection, id: &str) -> Result<JsonValue, AppError> {
     let mut rows = conn
         .query("SELECT data FROM memorials WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
+        .await?;
+    if let Some(row) = rows.next().await? {
         let data_str = row
-            .get_value(0)
-            .map_err(|e| e.to_string())?
+            .get_value(0)?
             .as_text()
             .map_or("{}", |v| v)
             .to_string();
-        serde_json::from_str(&data_str).map_err(|e| e.to_string())
+        serde_json::from_str(&data_str).map_err(AppError::from)
     } else {
-        Err("Memorial not found".to_string())
-    }
-}
-
-fn row_to_json(row: &turso::Row) -> Result<JsonValue, String> {
-    let mut map = serde_json::Map::new();
-    for i in 0..6 {
-        if let Ok(val) = row.get_value(i as usize) {
-            let key = match i {
-                0 => "id",
-                1 => "remote_id",
-                2 => "sync_status",
-                3 => "updated_at",
-                4 => "deleted",
-                5 => "data",
-                _ => continue,
-            };
-            map.insert(key.to_string(), super::turso_value_to_json(&val));
-        }
+        Err(AppError::not_found("Memorial not found"))
     }
-    Ok(JsonValue::Object(map))
 }
@@ -0,0 +1,281 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use log::info;
+
+use crate::error::AppError;
+
+/// Data backfill that can't be expressed in plain SQL, run after the
+/// migration's statements inside the same transaction.
+pub type MigrationStep = for<'a> fn(
+    &'a turso::Connection,
+) -> Pin<Box<dyn Future<Output = Result<(), AppError>> + Send + 'a>>;
+
+/// A single forward-only schema change, applied once per database.
+///
+/// `schema_statements()` is the frozen baseline; anything that changes an
+/// existing table (new column, new default, data backfill) goes here instead,
+/// appended with the next version number.
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub statements: &'static [&'static str],
+    pub run: Option<MigrationStep>,
+}
+
+const SCHEMA_VERSION_CREATE_SQL: &str = "CREATE TABLE IF NOT EXISTS schema_version (
+    version INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    applied_at TEXT DEFAULT (datetime('now'))
+)";
+
+/// Ordered up-migrations. Versions must be strictly increasing and must never
+/// be edited once released — add a new entry instead.
+pub const MIGRATIONS: &[Migration] = &[
+    // 基线：schema_statements() 建出的表结构
+    Migration {
+        version: 1,
+        name: "baseline",
+        statements: &[],
+        run: None,
+    },
+    // 同步引擎：保存拉取游标等键值状态
+    Migration {
+        version: 2,
+        name: "sync_state",
+        statements: &["CREATE TABLE IF NOT EXISTS sync_state (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at TEXT DEFAULT (datetime('now'))
+        )"],
+        run: None,
+    },
+    // 物理删除的墓碑记录，推送到远端后按保留期清理；pushed 标记行是否推送过，决定是否写墓碑
+    Migration {
+        version: 3,
+        name: "tombstones",
+        statements: &[
+            "CREATE TABLE IF NOT EXISTS tombstones (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                entity_type TEXT NOT NULL,
+                entity_id TEXT NOT NULL,
+                remote_id TEXT,
+                deleted_at TEXT DEFAULT (datetime('now')),
+                pushed_at TEXT
+            )",
+            "CREATE INDEX IF NOT EXISTS idx_tombstones_entity ON tombstones(entity_type, entity_id)",
+        ],
+        run: Some(|conn| Box::pin(super::sync_engine::add_pushed_column(conn))),
+    },
+    // 相册关联按相册查询的索引；旧版 data.albumId 一次性回填到 photo_albums
+    Migration {
+        version: 4,
+        name: "photo_album_links",
+        statements: &["CREATE INDEX IF NOT EXISTS idx_photo_albums_album ON photo_albums(album_id)"],
+        run: Some(|conn| Box::pin(super::photo::backfill_album_links(conn))),
+    },
+    // 拍摄时间列：时间线排序与日期筛选改用它，而不是文件 mtime
+    Migration {
+        version: 5,
+        name: "photo_taken_at",
+        statements: &[
+            "ALTER TABLE photos ADD COLUMN taken_at TEXT",
+            "CREATE INDEX IF NOT EXISTS idx_photos_taken_at ON photos(deleted, taken_at, id)",
+        ],
+        run: Some(|conn| {
+            Box::pin(async move {
+                super::photo::backfill_taken_at(conn, true).await?;
+                Ok(())
+            })
+        }),
+    },
+    // 感知哈希列：相似照片检测按 phash 查找候选
+    Migration {
+        version: 6,
+        name: "photo_phash",
+        statements: &[
+            "ALTER TABLE photos ADD COLUMN phash TEXT",
+            "CREATE INDEX IF NOT EXISTS idx_photos_phash ON photos(deleted, phash)",
+        ],
+        run: None,
+    },
+    // 本地全文搜索：文档表与分词倒排表，建表后从现有数据重建一次
+    Migration {
+        version: 7,
+        name: "search_index",
+        statements: &[
+            "CREATE TABLE IF NOT EXISTS search_docs (
+                entity TEXT NOT NULL,
+                entity_id TEXT NOT NULL,
+                field TEXT NOT NULL,
+                weight INTEGER NOT NULL DEFAULT 1,
+                content TEXT NOT NULL,
+                PRIMARY KEY (entity, entity_id, field)
+            )",
+            "CREATE TABLE IF NOT EXISTS search_terms (
+                term TEXT NOT NULL,
+                entity TEXT NOT NULL,
+                entity_id TEXT NOT NULL,
+                field TEXT NOT NULL,
+                PRIMARY KEY (term, entity, entity_id, field)
+            )",
+            "CREATE INDEX IF NOT EXISTS idx_search_terms_doc ON search_terms(entity, entity_id)",
+        ],
+        run: Some(|conn| {
+            Box::pin(async move {
+                super::search::rebuild_index(conn).await?;
+                Ok(())
+            })
+        }),
+    },
+    // 回收站保留期：记录移入回收站的时间，已在回收站的照片从最后修改时间起算
+    Migration {
+        version: 8,
+        name: "photo_deleted_at",
+        statements: &[
+            "ALTER TABLE photos ADD COLUMN deleted_at TEXT",
+            "UPDATE photos SET deleted_at = COALESCE(updated_at, datetime('now')) WHERE deleted = 1",
+            "CREATE INDEX IF NOT EXISTS idx_photos_deleted_at ON photos(deleted, deleted_at)",
+        ],
+        run: None,
+    },
+    // 分片上传会话（含源文件修改时间，续传前核对）与已完成分片的 ETag，应用重启后据此续传
+    Migration {
+        version: 9,
+        name: "multipart_uploads",
+        statements: &[
+            "CREATE TABLE IF NOT EXISTS multipart_uploads (
+                upload_id TEXT PRIMARY KEY,
+                object_key TEXT NOT NULL,
+                bucket TEXT NOT NULL,
+                source_path TEXT NOT NULL,
+                file_size INTEGER NOT NULL,
+                source_mtime INTEGER,
+                part_size INTEGER NOT NULL,
+                created_at TEXT DEFAULT (datetime('now')),
+                updated_at TEXT DEFAULT (datetime('now'))
+            )",
+            "CREATE INDEX IF NOT EXISTS idx_multipart_uploads_key ON multipart_uploads(bucket, object_key)",
+            "CREATE TABLE IF NOT EXISTS multipart_parts (
+                upload_id TEXT NOT NULL,
+                part_number INTEGER NOT NULL,
+                etag TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                PRIMARY KEY (upload_id, part_number)
+            )",
+        ],
+        run: None,
+    },
+    // 持久化上传队列：应用重启后未完成的任务继续执行
+    Migration {
+        version: 10,
+        name: "upload_jobs",
+        statements: &[
+            "CREATE TABLE IF NOT EXISTS upload_jobs (
+                id TEXT PRIMARY KEY,
+                object_key TEXT NOT NULL,
+                source_path TEXT NOT NULL,
+                entity_type TEXT,
+                entity_id TEXT,
+                meta TEXT,
+                state TEXT NOT NULL DEFAULT 'pending',
+                attempts INTEGER NOT NULL DEFAULT 0,
+                last_error TEXT,
+                file_size INTEGER,
+                next_attempt_at TEXT,
+                created_at TEXT DEFAULT (datetime('now')),
+                updated_at TEXT DEFAULT (datetime('now'))
+            )",
+            "CREATE INDEX IF NOT EXISTS idx_upload_jobs_state ON upload_jobs(state, next_attempt_at)",
+        ],
+        run: None,
+    },
+];
+
+/// Highest schema version this build understands.
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Read the version recorded in `schema_version` (0 for a fresh or pre-migration database).
+pub async fn current_version(conn: &turso::Connection) -> Result<i64, AppError> {
+    conn.execute(SCHEMA_VERSION_CREATE_SQL, ())
+        .await?;
+    let mut rows = conn
+        .query("SELECT COALESCE(MAX(version), 0) FROM schema_version", ())
+        .await?;
+    if let Some(row) = rows.next().await? {
+        Ok(row
+            .get_value(0)?
+            .as_integer()
+            .copied()
+            .unwrap_or(0))
+    } else {
+        Ok(0)
+    }
+}
+
+/// Return the current version, or an error when the database was written by
+/// an app that knows migrations past `latest`.
+pub async fn ensure_supported(conn: &turso::Connection, latest: i64) -> Result<i64, AppError> {
+    let version = current_version(conn).await?;
+    // 数据库由更新版本的 App 写入过：拒绝继续，避免旧代码误写新结构
+    if version > latest {
+        return Err(AppError::validation(format!(
+            "Database schema version {} is newer than this app supports (latest known: {}). Please upgrade the app.",
+            version, latest
+        )));
+    }
+    Ok(version)
+}
+
+/// Apply every pending migration from [`MIGRATIONS`]. Returns the resulting version.
+pub async fn run_migrations(conn: &turso::Connection) -> Result<i64, AppError> {
+    apply_migrations(conn, MIGRATIONS).await
+}
+
+/// Apply the pending entries of `migrations` in order, each inside its own
+/// transaction. A failing migration is rolled back and stops the run, leaving
+/// the database at the last successfully applied version.
+pub async fn apply_migrations(
+    conn: &turso::Connection,
+    migrations: &[Migration],
+) -> Result<i64, AppError> {
+    let latest = migrations.last().map(|m| m.version).unwrap_or(0);
+    let start = ensure_supported(conn, latest).await?;
+    let mut current = start;
+
+    for migration in migrations.iter().filter(|m| m.version > start) {
+        info!(
+            "Applying migration {} ({})",
+            migration.version, migration.name
+        );
+        conn.execute("BEGIN", ()).await?;
+        if let Err(e) = apply_one(conn, migration).await {
+            let _ = conn.execute("ROLLBACK", ()).await;
+            return Err(AppError::storage(format!(
+                "Migration {} ({}) failed: {}",
+                migration.version, migration.name, e
+            )));
+        }
+        conn.execute("COMMIT", ()).await?;
+        current = migration.version;
+    }
+
+    Ok(current)
+}
+
+async fn apply_one(conn: &turso::Connection, migration: &Migration) -> Result<(), AppError> {
+    for stmt in migration.statements {
+        conn.execute(*stmt, ()).await?;
+    }
+    if let Some(run) = migration.run {
+        run(conn).await?;
+    }
+    conn.execute(
+        "INSERT INTO schema_version (version, name) VALUES (?1, ?2)",
+        (migration.version, migration.name),
+    )
+    .await?;
+    Ok(())
+}
@@ -4,16 +4,27 @@ use serde_json::{json, Value as JsonValue};
 use tauri::Manager;
 use turso::{Builder, Database};
 
+use crate::error::AppError;
+
 pub mod album;
 pub mod album_folder;
 pub mod asset;
+pub mod backup;
 pub mod blood_pressure;
 pub mod drive_file;
 pub mod family;
 pub mod memorial;
+pub mod migration;
+pub mod model;
+pub mod multipart;
 pub mod photo;
+pub mod portable;
+pub mod search;
+pub mod smart_album;
 pub mod sync;
+pub mod sync_engine;
 pub mod todo;
+pub mod upload_job;
 pub mod usage_record;
 pub mod weight;
 
@@ -21,11 +32,15 @@ pub mod weight;
 pub use album::*;
 pub use album_folder::*;
 pub use asset::*;
+pub use backup::*;
 pub use blood_pressure::*;
 pub use drive_file::*;
 pub use family::*;
 pub use memorial::*;
 pub use photo::*;
+pub use portable::*;
+pub use search::*;
+pub use smart_album::*;
 pub use sync::*;
 pub use todo::*;
 pub use usage_record::*;
@@ -37,17 +52,53 @@ pub struct CacheInfo {
     pub size: usize,
 }
 
-pub struct TursoDb(pub Database);
+pub struct TursoDb(pub AppDatabase);
 
-pub async fn init(app: &tauri::AppHandle) -> Result<(), String> {
-    let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-    std::fs::create_dir_all(&app_dir).map_err(|e| e.to_string())?;
+/// The opened database. Once [`AppDatabase::refuse`] records a fatal schema
+/// problem (e.g. a schema written by a newer app), every `connect` returns
+/// that error instead of handing out a connection.
+pub struct AppDatabase {
+    db: Database,
+    refused: std::sync::OnceLock<AppError>,
+}
+
+impl AppDatabase {
+    pub fn new(db: Database) -> Self {
+        AppDatabase {
+            db,
+            refused: std::sync::OnceLock::new(),
+        }
+    }
+
+    pub fn connect(&self) -> Result<turso::Connection, AppError> {
+        if let Some(e) = self.refused.get() {
+            return Err(e.clone());
+        }
+        Ok(self.db.connect()?)
+    }
+
+    /// 之后的所有连接请求都返回 `error`
+    pub fn refuse(&self, error: AppError) {
+        let _ = self.refused.set(error);
+    }
+}
+
+pub async fn init(app: &tauri::AppHandle) -> Result<(), AppError> {
+    let app_dir = app.path().app_data_dir()?;
+    std::fs::create_dir_all(&app_dir)?;
 
     let db_path = app_dir.join("echo_trails.db");
     let db_path_str = db_path.to_string_lossy().to_string();
 
     info!("Initializing Turso database at {}", db_path_str);
 
+    // 应用上次暂存的备份恢复，必须在打开数据库之前完成
+    match backup::apply_pending_restore(&db_path) {
+        Ok(true) => info!("Applied staged backup restore"),
+        Ok(false) => {}
+        Err(e) => log::error!("Failed to apply staged restore: {}", e),
+    }
+
     // Verify directory is accessible
     match std::fs::metadata(&app_dir) {
         Ok(meta) => info!("App data dir exists, permissions: {:?}", meta.permissions()),
@@ -74,14 +125,14 @@ pub async fn init(app: &tauri::AppHandle) -> Result<(), String> {
             Builder::new_local(&db_path_str)
                 .build()
                 .await
-                .map_err(|e| format!("Failed to build local db after recovery: {}", e))?
+                .map_err(|e| AppError::storage(format!("Failed to build local db after recovery: {}", e)))?
         }
     };
 
     // Register state IMMEDIATELY after db opens, before schema creation.
     // This ensures commands won't hit "state not managed" even if schema
     // creation has issues.
-    app.manage(TursoDb(db));
+    app.manage(TursoDb(AppDatabase::new(db)));
 
     // Schema creation — errors here are logged but don't prevent state from
     // being registered, so commands will get a better error message instead
@@ -95,6 +146,9 @@ pub async fn init(app: &tauri::AppHandle) -> Result<(), String> {
 }
 
 /// Returns all schema creation SQL statements (tables + indexes) in order.
+///
+/// This is the baseline schema and is kept as-is; later column or data
+/// changes are expressed as versioned entries in [`migration::MIGRATIONS`].
 pub fn schema_statements() -> &'static [&'static str] {
     &[
         // Legacy KV cache table
@@ -259,8 +313,8 @@ pub fn schema_statements() -> &'static [&'static str] {
 /// Execute schema creation on a given connection. Each statement is executed
 /// independently — a failure on one statement is logged but does NOT abort the
 /// rest, so a single bad statement won't prevent all other tables from being
-/// created.
-pub async fn create_schema_with_conn(conn: &turso::Connection) -> Result<(), String> {
+/// created. Pending migrations are applied afterwards.
+pub async fn create_schema_with_conn(conn: &turso::Connection) -> Result<(), AppError> {
     // PRAGMA journal_mode=WAL 返回一行，必须用 query 执行（turso 0.5.3 的 execute
     // 不允许返回行，否则报 "unexpected row during execution"）
     let _ = conn.query("PRAGMA journal_mode=WAL", ()).await;
@@ -276,25 +330,45 @@ pub async fn create_schema_with_conn(conn: &turso::Connection) -> Result<(), Str
         }
     }
 
+    match migration::run_migrations(conn).await {
+        Ok(version) => info!("Database schema at version {}", version),
+        Err(e) => {
+            log::error!("{}", e);
+            errors.push(e.to_string());
+        }
+    }
+
     if errors.is_empty() {
         info!("All tables and indexes created successfully");
         Ok(())
     } else {
-        Err(format!(
+        Err(AppError::storage(format!(
             "Schema creation completed with {} error(s): {}",
             errors.len(),
             errors.join("; ")
-        ))
+        )))
     }
 }
 
 /// Create all tables and indexes. Returns Ok on success, Err with details on failure.
-async fn create_schema(app: &tauri::AppHandle) -> Result<(), String> {
+async fn create_schema(app: &tauri::AppHandle) -> Result<(), AppError> {
     let state: tauri::State<'_, TursoDb> = app.state();
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+    check_schema_version(&state.0).await?;
+    let conn = state.0.connect()?;
     create_schema_with_conn(&conn).await
 }
 
+/// Refuse all further connections when the database was written by a newer
+/// app, so commands report that instead of touching an unknown schema.
+pub async fn check_schema_version(db: &AppDatabase) -> Result<(), AppError> {
+    let conn = db.connect()?;
+    if let Err(e) = migration::ensure_supported(&conn, migration::latest_version()).await {
+        db.refuse(e.clone());
+        return Err(e);
+    }
+    Ok(())
+}
+
 // ==================== Helper functions ====================
 
 /// Generate a new UUID v4 string
@@ -375,7 +449,103 @@ pub fn turso_value_to_json(val: &turso::Value) -> JsonValue {
     }
 }
 
-pub async fn ensure_album_folders_table(conn: &turso::Connection) -> Result<(), String> {
+/// Convert a serde_json::Value to a turso Value for binding.
+pub fn json_to_turso_value(value: &JsonValue) -> turso::Value {
+    match value {
+        JsonValue::Null => turso::Value::Null,
+        JsonValue::Bool(b) => turso::Value::Integer(if *b { 1 } else { 0 }),
+        JsonValue::Number(n) => match n.as_i64() {
+            Some(i) => turso::Value::Integer(i),
+            None => turso::Value::Real(n.as_f64().unwrap_or(0.0)),
+        },
+        JsonValue::String(s) => turso::Value::Text(s.clone()),
+        other => turso::Value::Text(other.to_string()),
+    }
+}
+
+/// Column names of `table`, in declaration order.
+pub async fn table_columns(conn: &turso::Connection, table: &str) -> Result<Vec<String>, AppError> {
+    let mut rows = conn
+        .query(&format!("PRAGMA table_info({})", table), ())
+        .await?;
+    let mut columns = Vec::new();
+    while let Some(row) = rows.next().await? {
+        if let Some(name) = row.get_value(1)?.as_text() {
+            columns.push(name.to_string());
+        }
+    }
+    Ok(columns)
+}
+
+/// Run a query and return each row as a column-name → value map.
+pub async fn query_maps(
+    conn: &turso::Connection,
+    sql: &str,
+    params: impl turso::IntoParams,
+) -> Result<Vec<serde_json::Map<String, JsonValue>>, AppError> {
+    let mut rows = conn.query(sql, params).await?;
+    let names = rows.column_names();
+    let mut result = Vec::new();
+    while let Some(row) = rows.next().await? {
+        let mut map = serde_json::Map::new();
+        for (i, name) in names.iter().enumerate() {
+            if let Ok(val) = row.get_value(i) {
+                map.insert(name.clone(), turso_value_to_json(&val));
+            }
+        }
+        result.push(map);
+    }
+    Ok(result)
+}
+
+/// Per-id outcome of a bulk command. The batch itself is all-or-nothing;
+/// `ok = false` only marks ids that were skipped (e.g. no such row).
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BatchItemResult {
+    pub id: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl BatchItemResult {
+    pub fn ok(id: &str) -> Self {
+        BatchItemResult { id: id.to_string(), ok: true, error: None }
+    }
+
+    pub fn skipped(id: &str, error: &str) -> Self {
+        BatchItemResult { id: id.to_string(), ok: false, error: Some(error.to_string()) }
+    }
+}
+
+/// Max bound parameters per `IN (...)` statement in bulk commands.
+pub const BATCH_CHUNK_SIZE: usize = 500;
+
+/// `?{start}, ?{start+1}, ...` for `count` parameters.
+pub fn placeholders(start: usize, count: usize) -> String {
+    (start..start + count)
+        .map(|i| format!("?{}", i))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Run `work` inside BEGIN/COMMIT; any error rolls the whole batch back.
+pub async fn in_transaction<T>(
+    conn: &turso::Connection,
+    work: impl std::future::Future<Output = Result<T, AppError>>,
+) -> Result<T, AppError> {
+    conn.execute("BEGIN", ()).await?;
+    let result = match work.await {
+        Ok(value) => conn.execute("COMMIT", ()).await.map(|_| value).map_err(AppError::from),
+        Err(e) => Err(e),
+    };
+    if result.is_err() {
+        let _ = conn.execute("ROLLBACK", ()).await;
+    }
+    result
+}
+
+pub async fn ensure_album_folders_table(conn: &turso::Connection) -> Result<(), AppError> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS album_folders (
             id TEXT PRIMARY KEY,
@@ -387,8 +557,7 @@ pub async fn ensure_album_folders_table(conn: &turso::Connection) -> Result<(),
         )",
         (),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
     Ok(())
 }
 
@@ -400,10 +569,9 @@ const KV_CACHE_CREATE_SQL: &str = "CREATE TABLE IF NOT EXISTS kv_cache (
     updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
 )";
 
-async fn ensure_kv_cache_table(conn: &turso::Connection) -> Result<(), String> {
+async fn ensure_kv_cache_table(conn: &turso::Connection) -> Result<(), AppError> {
     conn.execute(KV_CACHE_CREATE_SQL, ())
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
     Ok(())
 }
 
@@ -417,8 +585,8 @@ pub async fn db_set_cache(
     state: tauri::State<'_, TursoDb>,
     key: String,
     value: String,
-) -> Result<(), String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<(), AppError> {
+    let conn = state.0.connect()?;
     let sql = "INSERT INTO kv_cache (key, value, updated_at) VALUES (?1, ?2, CURRENT_TIMESTAMP)
          ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = CURRENT_TIMESTAMP";
     match conn.execute(sql, (key.clone(), value.clone())).await {
@@ -428,11 +596,10 @@ pub async fn db_set_cache(
             if is_missing_kv_cache_error(&msg) {
                 ensure_kv_cache_table(&conn).await?;
                 conn.execute(sql, (key, value))
-                    .await
-                    .map_err(|e| e.to_string())?;
+                    .await?;
                 Ok(())
             } else {
-                Err(msg)
+                Err(AppError::storage(msg))
             }
         }
     }
@@ -442,8 +609,8 @@ pub async fn db_set_cache(
 pub async fn db_get_cache(
     state: tauri::State<'_, TursoDb>,
     key: String,
-) -> Result<Option<String>, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<Option<String>, AppError> {
+    let conn = state.0.connect()?;
     let sql = "SELECT value FROM kv_cache WHERE key = ?1";
     let mut rows = match conn.query(sql, (key.clone(),)).await {
         Ok(r) => r,
@@ -451,15 +618,15 @@ pub async fn db_get_cache(
             let msg = e.to_string();
             if is_missing_kv_cache_error(&msg) {
                 ensure_kv_cache_table(&conn).await?;
-                conn.query(sql, (key,)).await.map_err(|e| e.to_string())?
+                conn.query(sql, (key,)).await?
             } else {
-                return Err(msg);
+                return Err(AppError::storage(msg));
             }
         }
     };
 
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row.get_value(0).map_err(|e| e.to_string())?;
+    if let Some(row) = rows.next().await? {
+        let val = row.get_value(0)?;
         if let Some(text) = val.as_text() {
             return Ok(Some(text.to_string()));
         }
@@ -471,8 +638,8 @@ pub async fn db_get_cache(
 #[tauri::command]
 pub async fn db_get_all_cache_info(
     state: tauri::State<'_, TursoDb>,
-) -> Result<Vec<CacheInfo>, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<Vec<CacheInfo>, AppError> {
+    let conn = state.0.connect()?;
     let sql = "SELECT key, LENGTH(value) as size FROM kv_cache";
     let mut rows = match conn.query(sql, ()).await {
         Ok(r) => r,
@@ -480,17 +647,17 @@ pub async fn db_get_all_cache_info(
             let msg = e.to_string();
             if is_missing_kv_cache_error(&msg) {
                 ensure_kv_cache_table(&conn).await?;
-                conn.query(sql, ()).await.map_err(|e| e.to_string())?
+                conn.query(sql, ()).await?
             } else {
-                return Err(msg);
+                return Err(AppError::storage(msg));
             }
         }
     };
 
     let mut result = Vec::new();
-    while let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let key = row.get_value(0).map_err(|e| e.to_string())?;
-        let size = row.get_value(1).map_err(|e| e.to_string())?;
+    while let Some(row) = rows.next().await? {
+        let key = row.get_value(0)?;
+        let size = row.get_value(1)?;
 
         if let (Some(k), Some(s)) = (key.as_text(), size.as_integer()) {
             result.push(CacheInfo {
@@ -506,8 +673,8 @@ pub async fn db_get_all_cache_info(
 pub async fn db_delete_cache(
     state: tauri::State<'_, TursoDb>,
     key: String,
-) -> Result<(), String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<(), AppError> {
+    let conn = state.0.connect()?;
     let sql = "DELETE FROM kv_cache WHERE key = ?1";
     match conn.execute(sql, (key.clone(),)).await {
         Ok(_) => Ok(()),
@@ -516,11 +683,10 @@ pub async fn db_delete_cache(
             if is_missing_kv_cache_error(&msg) {
                 ensure_kv_cache_table(&conn).await?;
                 conn.execute(sql, (key,))
-                    .await
-                    .map_err(|e| e.to_string())?;
+                    .await?;
                 Ok(())
             } else {
-                Err(msg)
+                Err(AppError::storage(msg))
             }
         }
     }
@@ -528,8 +694,8 @@ pub async fn db_delete_cache(
 
 /// 清空整个 kv_cache 表（用于切换模式/地址时清理残留缓存）
 #[tauri::command]
-pub async fn db_clear_all_cache(state: tauri::State<'_, TursoDb>) -> Result<(), String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+pub async fn db_clear_all_cache(state: tauri::State<'_, TursoDb>) -> Result<(), AppError> {
+    let conn = state.0.connect()?;
     let sql = "DELETE FROM kv_cache";
     match conn.execute(sql, ()).await {
         Ok(_) => Ok(()),
@@ -537,10 +703,10 @@ pub async fn db_clear_all_cache(state: tauri::State<'_, TursoDb>) -> Result<(),
             let msg = e.to_string();
             if is_missing_kv_cache_error(&msg) {
                 ensure_kv_cache_table(&conn).await?;
-                conn.execute(sql, ()).await.map_err(|e| e.to_string())?;
+                conn.execute(sql, ()).await?;
                 Ok(())
             } else {
-                Err(msg)
+                Err(AppError::storage(msg))
             }
         }
     }
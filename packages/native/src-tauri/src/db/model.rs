@@ -0,0 +1,188 @@
+//! Typed rows for every synced table.
+//!
+//! Fields mirror the SQL columns one-to-one; free-form attributes stay in the
+//! `data` JSON column. Rows are decoded by column name via [`query_models`],
+//! so `SELECT *` keeps working when migrations append columns.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value as JsonValue;
+
+use super::{merge_row, query_maps};
+use crate::error::AppError;
+
+/// A table row that can be decoded by column name and rendered in the
+/// frontend shape (`data` spread to the top level, `id` aliased as `_id`).
+pub trait Model: Serialize + DeserializeOwned {
+    const TABLE: &'static str;
+
+    fn to_json(&self) -> JsonValue {
+        merge_row(&serde_json::to_value(self).unwrap_or_default())
+    }
+}
+
+/// Run a query and decode every row into `T`, matching columns by name.
+pub async fn query_models<T: DeserializeOwned>(
+    conn: &turso::Connection,
+    sql: &str,
+    params: impl turso::IntoParams,
+) -> Result<Vec<T>, AppError> {
+    query_maps(conn, sql, params)
+        .await?
+        .into_iter()
+        .map(|map| Ok(serde_json::from_value(JsonValue::Object(map))?))
+        .collect()
+}
+
+/// Like [`query_models`] but returns only the first row.
+pub async fn query_model<T: DeserializeOwned>(
+    conn: &turso::Connection,
+    sql: &str,
+    params: impl turso::IntoParams,
+) -> Result<Option<T>, AppError> {
+    Ok(query_models(conn, sql, params).await?.into_iter().next())
+}
+
+// SQLite 列是动态类型：TEXT 列里可能存了数字，INTEGER 列可能为 NULL
+fn text<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    Ok(match Option::<JsonValue>::deserialize(d)? {
+        None | Some(JsonValue::Null) => None,
+        Some(JsonValue::String(s)) => Some(s),
+        Some(other) => Some(other.to_string()),
+    })
+}
+
+fn int<'de, D: Deserializer<'de>>(d: D) -> Result<i64, D::Error> {
+    Ok(match Option::<JsonValue>::deserialize(d)? {
+        Some(JsonValue::Number(n)) => n
+            .as_i64()
+            .unwrap_or_else(|| n.as_f64().unwrap_or(0.0) as i64),
+        Some(JsonValue::Bool(b)) => b as i64,
+        Some(JsonValue::String(s)) => s.trim().parse().unwrap_or(0),
+        _ => 0,
+    })
+}
+
+fn data<'de, D: Deserializer<'de>>(d: D) -> Result<String, D::Error> {
+    Ok(text(d)?.unwrap_or_else(|| "{}".to_string()))
+}
+
+fn default_data() -> String {
+    "{}".to_string()
+}
+
+// 每张表共有的列按 schema 顺序展开，额外列插在 deleted 与 data 之间
+macro_rules! model {
+    ($(#[$meta:meta])* $name:ident, $table:literal { $($(#[$fmeta:meta])* $field:ident : $ty:ty => $de:literal),* $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        pub struct $name {
+            pub id: String,
+            #[serde(default, deserialize_with = "text")]
+            pub remote_id: Option<String>,
+            #[serde(default, deserialize_with = "text")]
+            pub sync_status: Option<String>,
+            #[serde(default, deserialize_with = "text")]
+            pub updated_at: Option<String>,
+            #[serde(default, deserialize_with = "int")]
+            pub deleted: i64,
+            $(
+                $(#[$fmeta])*
+                #[serde(default, deserialize_with = $de)]
+                pub $field: $ty,
+            )*
+            #[serde(default = "default_data", deserialize_with = "data")]
+            pub data: String,
+        }
+
+        impl Model for $name {
+            const TABLE: &'static str = $table;
+        }
+    };
+}
+
+model!(
+    /// `photos` 照片/视频
+    Photo, "photos" {
+        is_liked: i64 => "int",
+        #[serde(rename = "type")]
+        kind: Option<String> => "text",
+        last_modified: Option<String> => "text",
+        md5: Option<String> => "text",
+        taken_at: Option<String> => "text",
+        phash: Option<String> => "text",
+        deleted_at: Option<String> => "text",
+    }
+);
+
+model!(
+    /// `albums` 相册
+    Album, "albums" {}
+);
+
+model!(
+    /// `album_folders` 相册文件夹
+    AlbumFolder, "album_folders" {}
+);
+
+model!(
+    /// `asset_categories` 资产分类
+    AssetCategory, "asset_categories" {}
+);
+
+model!(
+    /// `assets` 资产
+    Asset, "assets" {}
+);
+
+model!(
+    /// `memorials` 纪念日
+    Memorial, "memorials" {}
+);
+
+model!(
+    /// `families` 家庭成员
+    Family, "families" {
+        family_id: Option<String> => "text",
+    }
+);
+
+model!(
+    /// `weights` 体重记录
+    Weight, "weights" {
+        family_id: Option<String> => "text",
+    }
+);
+
+model!(
+    /// `blood_pressures` 血压记录
+    BloodPressure, "blood_pressures" {
+        family_id: Option<String> => "text",
+        date: Option<String> => "text",
+    }
+);
+
+model!(
+    /// `usage_records` 资产使用记录
+    UsageRecord, "usage_records" {
+        target_id: Option<String> => "text",
+        target_type: Option<String> => "text",
+        action_type: Option<String> => "text",
+    }
+);
+
+model!(
+    /// `todos` 四象限待办
+    Todo, "todos" {
+        completed: i64 => "int",
+        quadrant: i64 => "int",
+    }
+);
+
+model!(
+    /// `drive_files` 云盘文件/文件夹
+    DriveFile, "drive_files" {
+        parent_id: Option<String> => "text",
+        kind: Option<String> => "text",
+    }
+);
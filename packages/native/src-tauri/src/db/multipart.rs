@@ -0,0 +1,185 @@
+//! Persistent state of S3 multipart uploads.
+//!
+//! Every completed part's ETag is written as soon as S3 acknowledges it, so an
+//! upload interrupted by a network error or an app restart continues from the
+//! first missing part instead of starting over.
+
+use serde::Serialize;
+use serde_json::{Map, Value as JsonValue};
+
+use super::query_maps;
+use crate::error::AppError;
+
+/// One in-flight multipart upload of a local file to `bucket/object_key`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultipartUpload {
+    pub upload_id: String,
+    pub object_key: String,
+    pub bucket: String,
+    pub source_path: String,
+    pub file_size: u64,
+    /// Modification time of the source file (ms since epoch) when the upload
+    /// started; `None` when the platform did not report one.
+    pub source_mtime: Option<i64>,
+    pub part_size: u64,
+}
+
+impl MultipartUpload {
+    /// Number of parts the file is split into (at least one).
+    pub fn part_count(&self) -> u32 {
+        self.file_size.div_ceil(self.part_size).max(1) as u32
+    }
+
+    /// Byte range `(offset, len)` of `part_number` (1-based).
+    pub fn part_range(&self, part_number: u32) -> (u64, u64) {
+        let offset = (part_number as u64 - 1) * self.part_size;
+        (offset, self.part_size.min(self.file_size - offset))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadedPart {
+    pub part_number: u32,
+    pub etag: String,
+    pub size: u64,
+}
+
+fn text(row: &Map<String, JsonValue>, key: &str) -> String {
+    row.get(key)
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn int(row: &Map<String, JsonValue>, key: &str) -> u64 {
+    row.get(key).and_then(|v| v.as_i64()).unwrap_or(0).max(0) as u64
+}
+
+fn upload_from_row(row: &Map<String, JsonValue>) -> MultipartUpload {
+    MultipartUpload {
+        upload_id: text(row, "upload_id"),
+        object_key: text(row, "object_key"),
+        bucket: text(row, "bucket"),
+        source_path: text(row, "source_path"),
+        file_size: int(row, "file_size"),
+        source_mtime: row.get("source_mtime").and_then(|v| v.as_i64()),
+        part_size: int(row, "part_size"),
+    }
+}
+
+/// Most recent unfinished upload to `bucket/object_key`, if any.
+pub async fn find_upload(
+    conn: &turso::Connection,
+    bucket: &str,
+    object_key: &str,
+) -> Result<Option<MultipartUpload>, AppError> {
+    let rows = query_maps(
+        conn,
+        "SELECT * FROM multipart_uploads WHERE bucket = ?1 AND object_key = ?2 ORDER BY created_at DESC LIMIT 1",
+        (bucket, object_key),
+    )
+    .await?;
+    Ok(rows.first().map(upload_from_row))
+}
+
+pub async fn create_upload(
+    conn: &turso::Connection,
+    upload: &MultipartUpload,
+) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT INTO multipart_uploads (upload_id, object_key, bucket, source_path, file_size, source_mtime, part_size) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        (
+            upload.upload_id.clone(),
+            upload.object_key.clone(),
+            upload.bucket.clone(),
+            upload.source_path.clone(),
+            upload.file_size as i64,
+            upload.source_mtime,
+            upload.part_size as i64,
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Remember a part S3 has accepted; re-uploading a part replaces its ETag.
+pub async fn record_part(
+    conn: &turso::Connection,
+    upload_id: &str,
+    part: &UploadedPart,
+) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO multipart_parts (upload_id, part_number, etag, size) VALUES (?1, ?2, ?3, ?4)",
+        (
+            upload_id,
+            part.part_number as i64,
+            part.etag.as_str(),
+            part.size as i64,
+        ),
+    )
+    .await?;
+    conn.execute(
+        "UPDATE multipart_uploads SET updated_at = datetime('now') WHERE upload_id = ?1",
+        (upload_id,),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Completed parts of `upload_id` in part-number order.
+pub async fn list_parts(
+    conn: &turso::Connection,
+    upload_id: &str,
+) -> Result<Vec<UploadedPart>, AppError> {
+    let rows = query_maps(
+        conn,
+        "SELECT part_number, etag, size FROM multipart_parts WHERE upload_id = ?1 ORDER BY part_number",
+        (upload_id,),
+    )
+    .await?;
+    Ok(rows
+        .iter()
+        .map(|row| UploadedPart {
+            part_number: int(row, "part_number") as u32,
+            etag: text(row, "etag"),
+            size: int(row, "size"),
+        })
+        .collect())
+}
+
+/// Forget an upload after it was completed or aborted.
+pub async fn remove_upload(conn: &turso::Connection, upload_id: &str) -> Result<(), AppError> {
+    conn.execute(
+        "DELETE FROM multipart_parts WHERE upload_id = ?1",
+        (upload_id,),
+    )
+    .await?;
+    conn.execute(
+        "DELETE FROM multipart_uploads WHERE upload_id = ?1",
+        (upload_id,),
+    )
+    .await?;
+    Ok(())
+}
+
+/// All unfinished uploads with the number of bytes already on S3.
+pub async fn list_uploads(
+    conn: &turso::Connection,
+) -> Result<Vec<(MultipartUpload, u64)>, AppError> {
+    let rows = query_maps(
+        conn,
+        "SELECT u.upload_id, u.object_key, u.bucket, u.source_path, u.file_size, u.source_mtime, u.part_size,
+                COALESCE(SUM(p.size), 0) AS uploaded
+         FROM multipart_uploads u LEFT JOIN multipart_parts p ON p.upload_id = u.upload_id
+         GROUP BY u.upload_id, u.object_key, u.bucket, u.source_path, u.file_size, u.source_mtime, u.part_size, u.created_at
+         ORDER BY u.created_at DESC",
+        (),
+    )
+    .await?;
+    Ok(rows
+        .iter()
+        .map(|row| (upload_from_row(row), int(row, "uploaded")))
+        .collect())
+}
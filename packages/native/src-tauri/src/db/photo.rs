@@ -1,10 +1,24 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
+use chrono::NaiveDate;
+use serde::Deserialize;
 use serde_json::{json, Value as JsonValue};
 use tauri::State;
 use turso::Value as TursoValue;
 
-use super::{merge_row, new_id, TursoDb};
+use crate::command::metadata::parse_exif_datetime;
+use crate::command::phash;
+use crate::command::s3_client::purge_objects;
+use crate::command::s3_presign::S3Credentials;
+use crate::command::s3_profile::{ProviderConfig, ProviderProfile};
+use crate::error::AppError;
+use super::model::{query_model, query_models, Memorial, Model, Photo};
+use super::search::{reindex, reindex_one};
+use super::smart_album::{load_smart_rules, SmartRules};
+use super::sync_engine::{delete_with_tombstones, parse_timestamp};
+use super::{
+    in_transaction, new_id, placeholders, query_maps, BatchItemResult, TursoDb, BATCH_CHUNK_SIZE,
+};
 
 fn apply_live_photo_normalization(obj: &mut serde_json::Map<String, JsonValue>) {
     let is_live = obj
@@ -45,165 +59,297 @@ fn sanitize_photo_data_str(data: &str) -> String {
     }
 }
 
-fn merge_photo_row(row: &JsonValue) -> JsonValue {
-    normalize_photo(merge_row(row))
+// 显式拍摄时间写入 data.takenAt，后续重新推导时仍以它为准
+fn with_taken_at(data: Option<String>, taken_at: Option<String>) -> Option<String> {
+    let Some(taken_at) = taken_at.filter(|v| !v.is_empty()) else {
+        return data;
+    };
+    let mut value = data
+        .as_deref()
+        .and_then(|d| serde_json::from_str::<JsonValue>(d).ok())
+        .filter(|v| v.is_object())
+        .unwrap_or_else(|| json!({}));
+    value["takenAt"] = json!(taken_at);
+    Some(value.to_string())
 }
 
-#[tauri::command]
-pub async fn db_photo_list(
-    state: State<'_, TursoDb>,
-    page: Option<i64>,
-    page_size: Option<i64>,
-    liked_mode: Option<bool>,
-    album_id: Option<String>,
-    is_delete: Option<bool>,
-    type_filter: Option<String>,
-    start_date: Option<String>,
-    end_date: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
-    let page = page.unwrap_or(1);
-    let page_size = page_size.unwrap_or(30);
-    let offset = (page - 1) * page_size;
-
-    let mut conditions = vec!["deleted = ?1".to_string()];
-    let mut params: Vec<TursoValue> = vec![TursoValue::Integer(if is_delete.unwrap_or(false) {
-        1
-    } else {
-        0
-    })];
+fn merge_photo_row(row: &Photo) -> JsonValue {
+    normalize_photo(row.to_json())
+}
 
-    if liked_mode.unwrap_or(false) {
-        let param_idx = params.len() + 1;
-        conditions.push(format!("is_liked = ?{}", param_idx));
-        params.push(TursoValue::Integer(1));
-    }
+/// 照片列表与统计共用的筛选条件
+#[derive(Debug, Clone, Default)]
+pub struct PhotoFilter {
+    pub deleted: bool,
+    pub liked_only: bool,
+    pub album_id: Option<String>,
+    pub type_prefix: Option<String>,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    /// 智能相册规则；设置后 `album_id` 不再走 photo_albums
+    pub smart: Option<SmartRules>,
+}
 
-    if let Some(ref t) = type_filter {
-        let param_idx = params.len() + 1;
-        conditions.push(format!("type LIKE ?{}", param_idx));
-        params.push(TursoValue::Text(format!("{}%", t)));
+impl PhotoFilter {
+    /// `album_id` 指向智能相册时载入其规则
+    pub async fn load_smart_album(&mut self, conn: &turso::Connection) -> Result<(), AppError> {
+        if let Some(aid) = self.album_id.as_deref().filter(|_| self.smart.is_none()) {
+            self.smart = load_smart_rules(conn, aid).await?;
+        }
+        Ok(())
     }
 
-    if let Some(ref sd) = start_date {
-        if !sd.is_empty() {
-            let param_idx = params.len() + 1;
-            conditions.push(format!("last_modified >= ?{}", param_idx));
+    /// Build the WHERE clause (parameters numbered from `?1`).
+    pub fn where_clause(&self) -> (String, Vec<TursoValue>) {
+        let mut conditions = vec!["deleted = ?1".to_string()];
+        let mut params: Vec<TursoValue> = vec![TursoValue::Integer(self.deleted as i64)];
+
+        if self.liked_only {
+            params.push(TursoValue::Integer(1));
+            conditions.push(format!("is_liked = ?{}", params.len()));
+        }
+        if let Some(ref t) = self.type_prefix {
+            params.push(TursoValue::Text(format!("{}%", t)));
+            conditions.push(format!("type LIKE ?{}", params.len()));
+        }
+        if let Some(sd) = self.start_date.as_ref().filter(|v| !v.is_empty()) {
             params.push(TursoValue::Text(sd.clone()));
+            conditions.push(format!("taken_at >= ?{}", params.len()));
         }
-    }
-
-    if let Some(ref ed) = end_date {
-        if !ed.is_empty() {
-            let param_idx = params.len() + 1;
-            conditions.push(format!("last_modified <= ?{}", param_idx));
+        if let Some(ed) = self.end_date.as_ref().filter(|v| !v.is_empty()) {
             params.push(TursoValue::Text(ed.clone()));
+            conditions.push(format!("taken_at <= ?{}", params.len()));
+        }
+        // 智能相册按规则实时筛选；普通相册走 photo_albums 关联表（旧 data.albumId 已在迁移中回填）
+        if let Some(ref rules) = self.smart {
+            let condition = rules.condition(&mut params);
+            conditions.push(condition);
+        } else if let Some(ref aid) = self.album_id {
+            params.push(TursoValue::Text(aid.clone()));
+            conditions.push(format!(
+                "id IN (SELECT photo_id FROM photo_albums WHERE album_id = ?{})",
+                params.len()
+            ));
         }
-    }
 
-    let where_clause = conditions.join(" AND ");
+        (conditions.join(" AND "), params)
+    }
+}
 
-    if let Some(aid) = album_id {
-        let related_photo_ids = get_album_photo_id_set(&conn, &aid).await?;
-        let sql = format!(
-            "SELECT * FROM photos WHERE {} ORDER BY last_modified DESC",
-            where_clause
-        );
-        let mut rows = conn.query(&sql, params).await.map_err(|e| e.to_string())?;
-        let mut matched = Vec::new();
-        while let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-            let val = row_to_json(&row)?;
-            let item = merge_photo_row(&val);
-            if photo_matches_album(&item, &aid, &related_photo_ids) {
-                matched.push(item);
-            }
-        }
+/// Keyset position in the `(taken_at DESC, id DESC)` ordering, handed to
+/// the frontend as an opaque string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhotoCursor {
+    pub taken_at: Option<String>,
+    pub id: String,
+}
 
-        let total = matched.len() as i64;
-        let items: Vec<JsonValue> = matched
-            .into_iter()
-            .skip(offset as usize)
-            .take(page_size as usize)
-            .collect();
+impl PhotoCursor {
+    pub fn encode(&self) -> String {
+        json!([self.taken_at, self.id])
+            .to_string()
+            .bytes()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
 
-        return Ok(json!({ "data": items, "total": total }));
+    pub fn decode(cursor: &str) -> Result<Self, AppError> {
+        let invalid = || AppError::validation("Invalid photo cursor");
+        if !cursor.len().is_multiple_of(2) {
+            return Err(invalid());
+        }
+        let bytes = (0..cursor.len())
+            .step_by(2)
+            .map(|i| {
+                let pair = cursor.get(i..i + 2).ok_or_else(invalid)?;
+                u8::from_str_radix(pair, 16).map_err(|_| invalid())
+            })
+            .collect::<Result<Vec<u8>, AppError>>()?;
+        let (taken_at, id): (Option<String>, String) =
+            serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+        Ok(PhotoCursor { taken_at, id })
     }
 
-    // Count total
-    let count_sql = format!("SELECT COUNT(*) FROM photos WHERE {}", where_clause);
-    let total: i64 = {
-        let mut count_rows = conn
-            .query(&count_sql, params.clone())
-            .await
-            .map_err(|e| e.to_string())?;
-        if let Some(row) = count_rows.next().await.map_err(|e| e.to_string())? {
-            row.get_value(0)
-                .map_err(|e| e.to_string())?
-                .as_integer()
-                .copied()
-                .unwrap_or(0)
-        } else {
-            0
+    /// Condition selecting rows strictly after this cursor. NULL `taken_at`
+    /// sorts last under DESC, so it needs its own branch.
+    fn after_clause(&self, params: &mut Vec<TursoValue>) -> String {
+        params.push(TursoValue::Text(self.id.clone()));
+        let id_idx = params.len();
+        match &self.taken_at {
+            Some(lm) => {
+                params.push(TursoValue::Text(lm.clone()));
+                let lm_idx = params.len();
+                format!(
+                    "(taken_at < ?{lm} OR (taken_at = ?{lm} AND id < ?{id}) OR taken_at IS NULL)",
+                    lm = lm_idx,
+                    id = id_idx
+                )
+            }
+            None => format!("(taken_at IS NULL AND id < ?{})", id_idx),
         }
+    }
+}
+
+/// 照片列表：传 `cursor` 时按 (taken_at, id) 键集分页；
+/// 仅传 `page` 时兼容旧的页码分页。响应中的 `nextCursor` 为空表示已到末尾。
+#[tauri::command]
+pub async fn db_photo_list(
+    state: State<'_, TursoDb>,
+    page: Option<i64>,
+    page_size: Option<i64>,
+    cursor: Option<String>,
+    liked_mode: Option<bool>,
+    album_id: Option<String>,
+    is_delete: Option<bool>,
+    type_filter: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let mut filter = PhotoFilter {
+        deleted: is_delete.unwrap_or(false),
+        liked_only: liked_mode.unwrap_or(false),
+        album_id,
+        type_prefix: type_filter,
+        start_date,
+        end_date,
+        smart: None,
+    };
+    filter.load_smart_album(&conn).await?;
+    let cursor = cursor
+        .filter(|c| !c.is_empty())
+        .map(|c| PhotoCursor::decode(&c))
+        .transpose()?;
+    let page_size = page_size.unwrap_or(30).max(1);
+    let offset = match (&cursor, page) {
+        (None, Some(p)) if p > 1 => (p - 1) * page_size,
+        _ => 0,
     };
 
-    // Fetch page
-    let sql = format!(
-        "SELECT * FROM photos WHERE {} ORDER BY last_modified DESC LIMIT {} OFFSET {}",
-        where_clause, page_size, offset
-    );
-    let mut rows = conn.query(&sql, params).await.map_err(|e| e.to_string())?;
-    let mut items = Vec::new();
-    while let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        items.push(merge_photo_row(&val));
-    }
+    let page = list_photos_page(&conn, &filter, cursor.as_ref(), page_size, offset).await?;
+    let total = count_photos(&conn, &filter).await?;
+    let items: Vec<JsonValue> = page.photos.iter().map(merge_photo_row).collect();
 
-    Ok(json!({ "data": items, "total": total }))
+    Ok(json!({
+        "data": items,
+        "total": total,
+        "nextCursor": page.next_cursor.map(|c| c.encode()),
+    }))
 }
 
-async fn get_album_photo_id_set(
-    conn: &turso::Connection,
-    album_id: &str,
-) -> Result<HashSet<String>, String> {
-    let mut photo_ids = HashSet::new();
-    let mut rows = conn
-        .query(
-            "SELECT photo_id FROM photo_albums WHERE album_id = ?1",
-            (album_id.to_string(),),
-        )
-        .await
-        .map_err(|e| e.to_string())?;
+pub struct PhotoPage {
+    pub photos: Vec<Photo>,
+    pub next_cursor: Option<PhotoCursor>,
+}
 
-    while let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let photo_id = row
-            .get_value(0)
-            .map_err(|e| e.to_string())?
-            .as_text()
-            .map_or("", |v| v)
-            .to_string();
-        if !photo_id.is_empty() {
-            photo_ids.insert(photo_id);
-        }
+/// Fetch one page in `(taken_at DESC, id DESC)` order, starting after
+/// `cursor` (or at `offset` for legacy page-number paging).
+pub async fn list_photos_page(
+    conn: &turso::Connection,
+    filter: &PhotoFilter,
+    cursor: Option<&PhotoCursor>,
+    page_size: i64,
+    offset: i64,
+) -> Result<PhotoPage, AppError> {
+    let (mut where_clause, mut params) = filter.where_clause();
+    if let Some(c) = cursor {
+        where_clause = format!("{} AND {}", where_clause, c.after_clause(&mut params));
     }
+    // 多取一条判断是否还有下一页
+    let sql = format!(
+        "SELECT * FROM photos WHERE {} ORDER BY taken_at DESC, id DESC LIMIT {} OFFSET {}",
+        where_clause,
+        page_size + 1,
+        offset
+    );
+    let mut photos = query_models::<Photo>(conn, &sql, params).await?;
+    let has_more = photos.len() as i64 > page_size;
+    photos.truncate(page_size as usize);
+    let next_cursor = if has_more {
+        photos.last().map(|p| PhotoCursor {
+            taken_at: p.taken_at.clone(),
+            id: p.id.clone(),
+        })
+    } else {
+        None
+    };
+    Ok(PhotoPage { photos, next_cursor })
+}
 
-    Ok(photo_ids)
+pub async fn count_photos(conn: &turso::Connection, filter: &PhotoFilter) -> Result<i64, AppError> {
+    let (where_clause, params) = filter.where_clause();
+    let rows = query_maps(
+        conn,
+        &format!("SELECT COUNT(*) AS total FROM photos WHERE {}", where_clause),
+        params,
+    )
+    .await?;
+    Ok(rows
+        .first()
+        .and_then(|r| r.get("total"))
+        .and_then(|v| v.as_i64())
+        .unwrap_or(0))
 }
 
-fn photo_matches_album(
-    item: &JsonValue,
-    album_id: &str,
-    related_photo_ids: &HashSet<String>,
-) -> bool {
-    let photo_id = item.get("id").and_then(|v| v.as_str()).unwrap_or("");
-    if related_photo_ids.contains(photo_id) {
-        return true;
+/// Fields of a photo row to insert; the same shape `db_photo_add` takes, so an
+/// upload job can carry it in its `meta` and create the photo once the object
+/// is stored.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewPhoto {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub is_liked: Option<bool>,
+    #[serde(default, rename = "type")]
+    pub type_: Option<String>,
+    #[serde(default)]
+    pub last_modified: Option<String>,
+    #[serde(default)]
+    pub md5: Option<String>,
+    #[serde(default)]
+    pub taken_at: Option<String>,
+    #[serde(default)]
+    pub phash: Option<String>,
+    pub data: String,
+    #[serde(default)]
+    pub album_ids: Vec<String>,
+}
+
+/// Insert a photo row (plus album links and search index) and return it.
+pub async fn insert_photo(conn: &turso::Connection, photo: NewPhoto) -> Result<JsonValue, AppError> {
+    let photo_id = photo.id.unwrap_or_else(new_id);
+    let data = with_taken_at(Some(photo.data), photo.taken_at).unwrap_or_default();
+    let sanitized_data = sanitize_photo_data_str(&data);
+    let phash = photo.phash.map(|h| normalize_phash(&h)).transpose()?;
+
+    conn.execute(
+        "INSERT INTO photos (id, is_liked, type, last_modified, md5, phash, data) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        (
+            photo_id.clone(),
+            if photo.is_liked.unwrap_or(false) { 1 } else { 0 },
+            photo.type_.unwrap_or_default(),
+            photo.last_modified.unwrap_or_default(),
+            photo.md5.unwrap_or_default(),
+            phash,
+            sanitized_data.clone(),
+        ),
+    )
+    .await?;
+    link_legacy_album_ids(conn, &photo_id, &sanitized_data).await?;
+    if !photo.album_ids.is_empty() {
+        set_photo_albums(conn, &photo_id, &photo.album_ids).await?;
     }
+    refresh_taken_at(conn, &photo_id).await?;
+    reindex_one(conn, "photos", &photo_id).await?;
 
-    item.get("albumId")
-        .and_then(|v| v.as_array())
-        .map(|ids| ids.iter().any(|id| id.as_str() == Some(album_id)))
-        .unwrap_or(false)
+    // Return the created photo
+    let row = query_model::<Photo>(conn, "SELECT * FROM photos WHERE id = ?1", (photo_id,)).await?;
+    if let Some(row) = row {
+        Ok(merge_photo_row(&row))
+    } else {
+        Err(AppError::storage("Failed to retrieve created photo"))
+    }
 }
 
 #[tauri::command]
@@ -214,37 +360,26 @@ pub async fn db_photo_add(
     type_: Option<String>,
     last_modified: Option<String>,
     md5: Option<String>,
+    taken_at: Option<String>,
+    phash: Option<String>,
     data: String,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
-    let photo_id = id.unwrap_or_else(new_id);
-    let sanitized_data = sanitize_photo_data_str(&data);
-
-    conn.execute(
-        "INSERT INTO photos (id, is_liked, type, last_modified, md5, data) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        (
-            photo_id.clone(),
-            if is_liked.unwrap_or(false) { 1 } else { 0 },
-            type_.unwrap_or_default(),
-            last_modified.unwrap_or_default(),
-            md5.unwrap_or_default(),
-            sanitized_data,
-        ),
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    insert_photo(
+        &conn,
+        NewPhoto {
+            id,
+            is_liked,
+            type_,
+            last_modified,
+            md5,
+            taken_at,
+            phash,
+            data,
+            album_ids: vec![],
+        },
     )
     .await
-    .map_err(|e| e.to_string())?;
-
-    // Return the created photo
-    let mut rows = conn
-        .query("SELECT * FROM photos WHERE id = ?1", (photo_id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        Ok(merge_photo_row(&val))
-    } else {
-        Err("Failed to retrieve created photo".to_string())
-    }
 }
 
 #[tauri::command]
@@ -255,12 +390,18 @@ pub async fn db_photo_update(
     type_: Option<String>,
     last_modified: Option<String>,
     md5: Option<String>,
+    taken_at: Option<String>,
+    phash: Option<String>,
     data: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let data = with_taken_at(data, taken_at);
 
     // Build dynamic SET clause
-    let mut sets = vec!["updated_at = datetime('now')".to_string()];
+    let mut sets = vec![
+        "sync_status = 'pending'".to_string(),
+        "updated_at = datetime('now')".to_string(),
+    ];
     let mut params: Vec<TursoValue> = Vec::new();
     let mut param_idx = 1;
 
@@ -284,6 +425,11 @@ pub async fn db_photo_update(
         params.push(TursoValue::Text(m.clone()));
         param_idx += 1;
     }
+    if let Some(ref h) = phash {
+        sets.push(format!("phash = ?{}", param_idx));
+        params.push(TursoValue::Text(normalize_phash(h)?));
+        param_idx += 1;
+    }
     if let Some(ref d) = data {
         let mut merged_data = get_photo_data(&conn, &id)
             .await
@@ -300,6 +446,7 @@ pub async fn db_photo_update(
             }
         }
         let sanitized_data = sanitize_photo_data(merged_data).to_string();
+        link_legacy_album_ids(&conn, &id, &sanitized_data).await?;
         sets.push(format!("data = ?{}", param_idx));
         params.push(TursoValue::Text(sanitized_data));
         param_idx += 1;
@@ -312,18 +459,19 @@ pub async fn db_photo_update(
         param_idx
     );
     conn.execute(&sql, params)
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+    if data.is_some() || last_modified.is_some() {
+        refresh_taken_at(&conn, &id).await?;
+    }
+    if data.is_some() {
+        reindex_one(&conn, "photos", &id).await?;
+    }
 
-    let mut rows = conn
-        .query("SELECT * FROM photos WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        Ok(merge_photo_row(&val))
+    let row = query_model::<Photo>(&conn, "SELECT * FROM photos WHERE id = ?1", (id,)).await?;
+    if let Some(row) = row {
+        Ok(merge_photo_row(&row))
     } else {
-        Err("Photo not found".to_string())
+        Err(AppError::not_found("Photo not found"))
     }
 }
 
@@ -331,167 +479,886 @@ pub async fn db_photo_update(
 pub async fn db_photo_toggle_like(
     state: State<'_, TursoDb>,
     id: String,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
     let mut rows = conn
         .query("SELECT is_liked FROM photos WHERE id = ?1", (id.clone(),))
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
-    let current = if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        row.get_value(0)
-            .map_err(|e| e.to_string())?
+    let current = if let Some(row) = rows.next().await? {
+        row.get_value(0)?
             .as_integer()
             .copied()
             .unwrap_or(0)
     } else {
-        return Err("Photo not found".to_string());
+        return Err(AppError::not_found("Photo not found"));
     };
 
     conn.execute(
-        "UPDATE photos SET is_liked = ?1, updated_at = datetime('now') WHERE id = ?2",
+        "UPDATE photos SET is_liked = ?1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?2",
         (if current == 0 { 1 } else { 0 }, id.clone()),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
 
-    let mut rows = conn
-        .query("SELECT * FROM photos WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        Ok(merge_photo_row(&val))
+    let row = query_model::<Photo>(&conn, "SELECT * FROM photos WHERE id = ?1", (id,)).await?;
+    if let Some(row) = row {
+        Ok(merge_photo_row(&row))
     } else {
-        Err("Photo not found".to_string())
+        Err(AppError::not_found("Photo not found"))
     }
 }
 
+/// 批量移入回收站：单事务内完成，不存在的 id 在结果中标记为跳过
 #[tauri::command]
-pub async fn db_photo_delete(state: State<'_, TursoDb>, ids: Vec<String>) -> Result<(), String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
-    for id in ids {
-        conn.execute(
-            "UPDATE photos SET deleted = 1, updated_at = datetime('now') WHERE id = ?1",
-            (id,),
-        )
-        .await
-        .map_err(|e| e.to_string())?;
-    }
-    Ok(())
+pub async fn db_photo_delete(state: State<'_, TursoDb>, ids: Vec<String>) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let results = set_photos_deleted(&conn, &ids, true).await?;
+    Ok(json!({ "code": 0, "data": results }))
 }
 
+/// 批量从回收站恢复，语义同 `db_photo_delete`
 #[tauri::command]
-pub async fn db_photo_restore(state: State<'_, TursoDb>, ids: Vec<String>) -> Result<(), String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
-    for id in ids {
-        conn.execute(
-            "UPDATE photos SET deleted = 0, updated_at = datetime('now') WHERE id = ?1",
-            (id,),
+pub async fn db_photo_restore(state: State<'_, TursoDb>, ids: Vec<String>) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let results = set_photos_deleted(&conn, &ids, false).await?;
+    Ok(json!({ "code": 0, "data": results }))
+}
+
+pub async fn set_photos_deleted(
+    conn: &turso::Connection,
+    ids: &[String],
+    deleted: bool,
+) -> Result<Vec<BatchItemResult>, AppError> {
+    in_transaction(conn, async {
+        let mut found = HashSet::new();
+        for chunk in ids.chunks(BATCH_CHUNK_SIZE) {
+            let params = text_params(chunk);
+            let marks = placeholders(1, chunk.len());
+            let rows = query_maps(
+                conn,
+                &format!("SELECT id FROM photos WHERE id IN ({})", marks),
+                params.clone(),
+            )
+            .await?;
+            found.extend(rows.iter().filter_map(|r| r.get("id").and_then(|v| v.as_str()).map(String::from)));
+            conn.execute(
+                &format!(
+                    "UPDATE photos SET deleted = {}, deleted_at = {}, sync_status = 'pending', updated_at = datetime('now') WHERE id IN ({})",
+                    deleted as i64,
+                    if deleted { "datetime('now')" } else { "NULL" },
+                    marks
+                ),
+                params,
+            )
+            .await?;
+            reindex(conn, "photos", chunk).await?;
+        }
+        Ok(ids
+            .iter()
+            .map(|id| {
+                if found.contains(id) {
+                    BatchItemResult::ok(id)
+                } else {
+                    BatchItemResult::skipped(id, "Photo not found")
+                }
+            })
+            .collect())
+    })
+    .await
+}
+
+fn text_params(values: &[String]) -> Vec<TursoValue> {
+    values.iter().map(|v| TursoValue::Text(v.clone())).collect()
+}
+
+/// 回收站默认保留天数
+pub const DEFAULT_TRASH_RETENTION_DAYS: i64 = 30;
+
+/// 彻底删除回收站中的照片：S3 对象（含 Live 视频）、关联与行一并清理，S3 失败不阻塞 DB 清理
+#[tauri::command]
+pub async fn db_photo_purge(
+    state: State<'_, TursoDb>,
+    ids: Vec<String>,
+    bucket: Option<String>,
+    region: Option<String>,
+    endpoint: Option<String>,
+    access_key: Option<String>,
+    secret_key: Option<String>,
+    profile: Option<ProviderConfig>,
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let credentials = S3Credentials::from_parts(
+        bucket,
+        region,
+        endpoint,
+        access_key,
+        secret_key,
+        ProviderProfile::from_config(profile.as_ref()),
+    );
+    let purge = purge_photos(&conn, &ids, credentials.as_ref()).await?;
+    Ok(json!({
+        "code": 0,
+        "data": purge.results,
+        "message": purge.message(),
+    }))
+}
+
+/// 清理超过保留期的回收站照片；`retention_days` 为 0 时清空回收站
+#[tauri::command]
+pub async fn db_photo_purge_expired(
+    state: State<'_, TursoDb>,
+    retention_days: Option<i64>,
+    bucket: Option<String>,
+    region: Option<String>,
+    endpoint: Option<String>,
+    access_key: Option<String>,
+    secret_key: Option<String>,
+    profile: Option<ProviderConfig>,
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let credentials = S3Credentials::from_parts(
+        bucket,
+        region,
+        endpoint,
+        access_key,
+        secret_key,
+        ProviderProfile::from_config(profile.as_ref()),
+    );
+    let retention_days = retention_days.unwrap_or(DEFAULT_TRASH_RETENTION_DAYS);
+    let purge = purge_expired_photos(&conn, retention_days, credentials.as_ref()).await?;
+    Ok(json!({
+        "code": 0,
+        "data": { "purged": purge.purged() },
+        "message": purge.message(),
+    }))
+}
+
+/// Outcome of a trash purge.
+pub struct PhotoPurge {
+    pub results: Vec<BatchItemResult>,
+    /// S3 objects that could not be deleted; their rows are purged anyway.
+    pub s3_failed: u32,
+}
+
+impl PhotoPurge {
+    pub fn purged(&self) -> usize {
+        self.results.iter().filter(|r| r.ok).count()
+    }
+
+    fn message(&self) -> String {
+        if self.s3_failed > 0 {
+            format!("部分云端文件清理失败：{} 个", self.s3_failed)
+        } else {
+            "success".to_string()
+        }
+    }
+}
+
+// 照片在 S3 上的对象：原文件与 Live Photo 的视频部分
+fn photo_object_keys(data: &JsonValue) -> Vec<String> {
+    ["key", "liveVideoKey"]
+        .iter()
+        .filter_map(|field| data.get(*field).and_then(|v| v.as_str()))
+        .filter(|key| !key.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Permanently delete the trashed photos among `ids`: their S3 objects (when
+/// `credentials` is given), rows (with sync tombstones and search entries)
+/// and album links. Ids that are missing or not in the trash are skipped.
+pub async fn purge_photos(
+    conn: &turso::Connection,
+    ids: &[String],
+    credentials: Option<&S3Credentials>,
+) -> Result<PhotoPurge, AppError> {
+    let mut found = HashSet::new();
+    let mut keys = Vec::new();
+    for chunk in ids.chunks(BATCH_CHUNK_SIZE) {
+        let rows = query_maps(
+            conn,
+            &format!(
+                "SELECT id, data FROM photos WHERE id IN ({}) AND deleted = 1",
+                placeholders(1, chunk.len())
+            ),
+            text_params(chunk),
         )
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
+        for row in rows {
+            let Some(id) = row.get("id").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            let data = row
+                .get("data")
+                .and_then(|v| v.as_str())
+                .and_then(|v| serde_json::from_str::<JsonValue>(v).ok())
+                .unwrap_or(JsonValue::Null);
+            keys.extend(photo_object_keys(&data));
+            found.insert(id.to_string());
+        }
     }
-    Ok(())
+
+    let s3_failed = match credentials {
+        Some(credentials) => purge_objects(credentials, &keys).await,
+        None => 0,
+    };
+
+    let purge_ids: Vec<String> = ids.iter().filter(|id| found.contains(*id)).cloned().collect();
+    for chunk in purge_ids.chunks(BATCH_CHUNK_SIZE) {
+        // 再次确认仍在回收站：同时被恢复的照片不删；关联与行在同一事务内删除
+        let condition = format!("id IN ({}) AND deleted = 1", placeholders(1, chunk.len()));
+        in_transaction(conn, async {
+            delete_with_tombstones(conn, "photos", &condition, text_params(chunk)).await?;
+            conn.execute(
+                &format!(
+                    "DELETE FROM photo_albums WHERE photo_id IN ({}) AND photo_id NOT IN (SELECT id FROM photos)",
+                    placeholders(1, chunk.len())
+                ),
+                text_params(chunk),
+            )
+            .await?;
+            Ok(())
+        })
+        .await?;
+    }
+
+    let results = ids
+        .iter()
+        .map(|id| {
+            if found.contains(id) {
+                BatchItemResult::ok(id)
+            } else {
+                BatchItemResult::skipped(id, "Photo not in trash")
+            }
+        })
+        .collect();
+    Ok(PhotoPurge { results, s3_failed })
+}
+
+/// Purge photos that have been in the trash for more than `retention_days`.
+/// Rows trashed by sync (no `deleted_at`) count from their `updated_at`.
+pub async fn purge_expired_photos(
+    conn: &turso::Connection,
+    retention_days: i64,
+    credentials: Option<&S3Credentials>,
+) -> Result<PhotoPurge, AppError> {
+    if retention_days < 0 {
+        return Err(AppError::validation("retention_days must not be negative"));
+    }
+    let rows = query_maps(
+        conn,
+        "SELECT id FROM photos WHERE deleted = 1 AND datetime(COALESCE(deleted_at, updated_at)) <= datetime('now', ?1)",
+        (format!("-{} days", retention_days),),
+    )
+    .await?;
+    let ids: Vec<String> = rows
+        .iter()
+        .filter_map(|r| r.get("id").and_then(|v| v.as_str()).map(String::from))
+        .collect();
+    purge_photos(conn, &ids, credentials).await
 }
 
 #[tauri::command]
 pub async fn db_photo_check_duplicate(
     state: State<'_, TursoDb>,
     md5: String,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
-    let mut rows = conn
-        .query(
-            "SELECT * FROM photos WHERE md5 = ?1 AND deleted = 0 LIMIT 1",
-            (md5,),
-        )
-        .await
-        .map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let row = query_model::<Photo>(
+        &conn,
+        "SELECT * FROM photos WHERE md5 = ?1 AND deleted = 0 LIMIT 1",
+        (md5,),
+    )
+    .await?;
 
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        let merged = merge_photo_row(&val);
+    if let Some(row) = row {
+        let merged = merge_photo_row(&row);
         Ok(json!({ "isDuplicate": true, "existingPhoto": merged }))
     } else {
         Ok(json!({ "isDuplicate": false, "existingPhoto": null }))
     }
 }
 
+// 存储前统一为 16 位小写十六进制，空字符串视为清除
+fn normalize_phash(value: &str) -> Result<String, AppError> {
+    if value.is_empty() {
+        return Ok(String::new());
+    }
+    phash::parse_hex(&value.to_ascii_lowercase())
+        .map(phash::to_hex)
+        .ok_or_else(|| AppError::validation(format!("Invalid phash: {}", value)))
+}
+
+// 相似照片中保留哪一张：分辨率优先，其次文件大小，再次拍摄更早的
+fn photo_quality(photo: &JsonValue) -> (u64, u64) {
+    let num = |v: Option<&JsonValue>| {
+        v.and_then(|v| v.as_u64().or_else(|| v.as_f64().map(|f| f as u64)))
+            .unwrap_or(0)
+    };
+    let exif_num = |tag: &str| num(photo.get("exif").and_then(|e| e.get(tag)).and_then(|t| t.get("value")));
+    let mut pixels = num(photo.get("width")) * num(photo.get("height"));
+    if pixels == 0 {
+        pixels = exif_num("Image Width") * exif_num("Image Height");
+    }
+    (pixels, num(photo.get("size")))
+}
+
+/// Cluster non-deleted photos around a representative: every photo of a
+/// cluster is within `threshold` bits of the one to keep. Each cluster lists
+/// its photos best-first (largest resolution) with `keepId` pointing at the
+/// one to keep.
+pub async fn find_similar_photos(
+    conn: &turso::Connection,
+    threshold: u32,
+) -> Result<Vec<JsonValue>, AppError> {
+    let rows = query_models::<Photo>(
+        conn,
+        "SELECT * FROM photos WHERE deleted = 0 AND phash IS NOT NULL AND phash != '' ORDER BY taken_at IS NULL, taken_at, id",
+        (),
+    )
+    .await?;
+    let (rows, hashes): (Vec<&Photo>, Vec<u64>) = rows
+        .iter()
+        .filter_map(|row| Some((row, phash::parse_hex(row.phash.as_deref()?)?)))
+        .unzip();
+
+    let mut clusters = Vec::new();
+    for members in phash::cluster(&hashes, threshold) {
+        let mut remaining: Vec<(JsonValue, u64)> = members
+            .iter()
+            .map(|&i| (merge_photo_row(rows[i]), hashes[i]))
+            .collect();
+        // sort_by 是稳定排序：同等质量时保留拍摄更早的
+        remaining.sort_by_key(|(photo, _)| std::cmp::Reverse(photo_quality(photo)));
+        // 链式相近的候选按代表拆分：簇内每张都与保留的那张足够接近，其余继续成簇
+        while remaining.len() > 1 {
+            let keep = remaining.remove(0);
+            let keep_hash = keep.1;
+            let (close, rest): (Vec<_>, Vec<_>) = remaining
+                .into_iter()
+                .partition(|(_, hash)| phash::hamming(keep_hash, *hash) <= threshold);
+            remaining = rest;
+            if close.is_empty() {
+                continue;
+            }
+            let keep_id = keep.0.get("id").cloned().unwrap_or(JsonValue::Null);
+            let photos: Vec<JsonValue> = std::iter::once(keep)
+                .chain(close)
+                .map(|(mut photo, hash)| {
+                    photo["distance"] = json!(phash::hamming(keep_hash, hash));
+                    photo
+                })
+                .collect();
+            clusters.push(json!({ "keepId": keep_id, "count": photos.len(), "photos": photos }));
+        }
+    }
+    Ok(clusters)
+}
+
+/// 查找相似照片（感知哈希汉明距离不超过 threshold），按簇返回，最佳版本在前
 #[tauri::command]
-pub async fn db_photo_list_info(
+pub async fn db_photo_find_similar(
     state: State<'_, TursoDb>,
+    threshold: Option<u32>,
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let threshold = threshold.unwrap_or(phash::DEFAULT_THRESHOLD);
+    if threshold > 32 {
+        return Err(AppError::validation("threshold must be between 0 and 32"));
+    }
+    let clusters = find_similar_photos(&conn, threshold).await?;
+    Ok(json!({ "code": 0, "data": clusters }))
+}
+
+/// 处理一簇相似照片：保留一张（未指定时取分辨率最大的），与它足够相似的其余照片移入回收站
+#[tauri::command]
+pub async fn db_photo_resolve_similar(
+    state: State<'_, TursoDb>,
+    ids: Vec<String>,
+    keep_id: Option<String>,
+    threshold: Option<u32>,
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let threshold = threshold.unwrap_or(phash::DEFAULT_THRESHOLD);
+    if threshold > 32 {
+        return Err(AppError::validation("threshold must be between 0 and 32"));
+    }
+    let (kept_id, results) = resolve_similar_photos(&conn, &ids, keep_id.as_deref(), threshold).await?;
+    Ok(json!({ "code": 0, "data": { "keptId": kept_id, "trashed": results } }))
+}
+
+/// Keep one photo of `ids` and trash the others whose perceptual hash is
+/// within `threshold` of it. Members that are too far from the kept photo
+/// (or have no hash) are reported as skipped instead of being trashed.
+pub async fn resolve_similar_photos(
+    conn: &turso::Connection,
+    ids: &[String],
+    keep_id: Option<&str>,
+    threshold: u32,
+) -> Result<(String, Vec<BatchItemResult>), AppError> {
+    if keep_id.is_some_and(|id| !ids.iter().any(|i| i == id)) {
+        return Err(AppError::validation("keepId must be one of ids"));
+    }
+    let mut rows: Vec<(JsonValue, Option<u64>)> = Vec::new();
+    for chunk in ids.chunks(BATCH_CHUNK_SIZE) {
+        let chunk_rows = query_models::<Photo>(
+            conn,
+            &format!(
+                "SELECT * FROM photos WHERE id IN ({}) AND deleted = 0 ORDER BY taken_at IS NULL, taken_at, id",
+                placeholders(1, chunk.len())
+            ),
+            text_params(chunk),
+        )
+        .await?;
+        rows.extend(chunk_rows.iter().map(|row| {
+            (merge_photo_row(row), row.phash.as_deref().and_then(phash::parse_hex))
+        }));
+    }
+    let row_id = |photo: &JsonValue| photo.get("id").and_then(|v| v.as_str()).map(String::from);
+
+    let keep = match keep_id {
+        Some(id) => rows.iter().find(|(photo, _)| row_id(photo).as_deref() == Some(id)),
+        // max_by_key 取最后一个最大值，倒序遍历即同等质量时保留拍摄更早的
+        None => rows.iter().rev().max_by_key(|(photo, _)| photo_quality(photo)),
+    };
+    let (kept_id, keep_hash) = keep
+        .and_then(|(photo, hash)| Some((row_id(photo)?, *hash)))
+        .ok_or_else(|| AppError::not_found("No photo to keep"))?;
+
+    let similar: HashSet<String> = rows
+        .iter()
+        .filter(|(_, hash)| match (keep_hash, hash) {
+            (Some(keep), Some(hash)) => phash::hamming(keep, *hash) <= threshold,
+            _ => false,
+        })
+        .filter_map(|(photo, _)| row_id(photo))
+        .collect();
+    let found: HashSet<String> = rows.iter().filter_map(|(photo, _)| row_id(photo)).collect();
+    let others: Vec<&String> = ids.iter().filter(|id| **id != kept_id).collect();
+    let trash: Vec<String> = others
+        .iter()
+        .filter(|id| similar.contains(id.as_str()))
+        .map(|id| id.to_string())
+        .collect();
+    let mut trashed = set_photos_deleted(conn, &trash, true).await?.into_iter();
+    let results = others
+        .into_iter()
+        .map(|id| {
+            if similar.contains(id) {
+                trashed.next().unwrap_or_else(|| BatchItemResult::ok(id))
+            } else if found.contains(id) {
+                BatchItemResult::skipped(id, "Not similar to the kept photo")
+            } else {
+                BatchItemResult::skipped(id, "Photo not found")
+            }
+        })
+        .collect();
+    Ok((kept_id, results))
+}
+
+/// Bucket size for [`photo_timeline`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineGranularity {
+    Year,
+    Month,
+    Day,
+}
+
+impl TimelineGranularity {
+    pub fn parse(value: Option<&str>) -> Result<Self, AppError> {
+        match value.unwrap_or("month") {
+            "year" => Ok(Self::Year),
+            "month" => Ok(Self::Month),
+            "day" => Ok(Self::Day),
+            other => Err(AppError::validation(format!("Invalid timeline granularity: {}", other))),
+        }
+    }
+
+    fn strftime_format(self) -> &'static str {
+        match self {
+            Self::Year => "%Y",
+            Self::Month => "%Y-%m",
+            Self::Day => "%Y-%m-%d",
+        }
+    }
+}
+
+// 拼接封面字段用的分隔符（不会出现在 id / key / ISO 时间里）
+const COVER_SEP: char = '\u{1f}';
+// 无窗口函数：用定长 ISO 时间拼接 id/key 后取 MAX，即得到每组最新的一张
+const COVER_EXPR: &str =
+    "MAX(COALESCE(taken_at, '') || char(31) || id || char(31) || COALESCE(json_extract(data, '$.key'), ''))";
+
+fn parse_cover(cover: &str) -> JsonValue {
+    let mut parts = cover.splitn(3, COVER_SEP);
+    let taken_at = parts.next().filter(|v| !v.is_empty());
+    let id = parts.next().unwrap_or_default();
+    let key = parts.next().filter(|v| !v.is_empty()).unwrap_or(id);
+    json!({ "id": id, "key": key, "takenAt": taken_at })
+}
+
+/// Photo counts per year / month / day bucket with the newest photo of each
+/// bucket as its cover, newest bucket first. Buckets follow the user's local
+/// calendar (`taken_at` is stored in UTC). Photos without `taken_at` are
+/// grouped under a `null` bucket at the end.
+pub async fn photo_timeline(
+    conn: &turso::Connection,
+    filter: &PhotoFilter,
+    granularity: TimelineGranularity,
+    utc_offset_minutes: i64,
+) -> Result<Vec<JsonValue>, AppError> {
+    let (where_clause, mut params) = filter.where_clause();
+    params.push(TursoValue::Text(format!("{:+} minutes", utc_offset_minutes)));
+    let sql = format!(
+        "SELECT strftime('{format}', taken_at, ?{shift}) AS bucket, COUNT(*) AS count, {cover} AS cover
+         FROM photos WHERE {where_clause}
+         GROUP BY bucket
+         ORDER BY bucket IS NULL, bucket DESC",
+        format = granularity.strftime_format(),
+        shift = params.len(),
+        cover = COVER_EXPR,
+        where_clause = where_clause
+    );
+    let rows = query_maps(conn, &sql, params).await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let cover = row.get("cover").and_then(|v| v.as_str()).unwrap_or_default();
+            json!({
+                "bucket": row.get("bucket").cloned().unwrap_or(JsonValue::Null),
+                "count": row.get("count").and_then(|v| v.as_i64()).unwrap_or(0),
+                "cover": parse_cover(cover),
+            })
+        })
+        .collect())
+}
+
+/// Number of photos matching `filter` and the newest one as cover
+/// (`None` when nothing matches).
+pub async fn photo_count_and_cover(
+    conn: &turso::Connection,
+    filter: &PhotoFilter,
+) -> Result<(i64, Option<JsonValue>), AppError> {
+    let (where_clause, params) = filter.where_clause();
+    let sql = format!(
+        "SELECT COUNT(*) AS count, {} AS cover FROM photos WHERE {}",
+        COVER_EXPR, where_clause
+    );
+    let rows = query_maps(conn, &sql, params).await?;
+    let row = rows.first();
+    let count = row.and_then(|r| r.get("count")).and_then(|v| v.as_i64()).unwrap_or(0);
+    let cover = row
+        .and_then(|r| r.get("cover"))
+        .and_then(|v| v.as_str())
+        .filter(|_| count > 0)
+        .map(parse_cover);
+    Ok((count, cover))
+}
+
+/// Photo count and newest cover of every regular album in one grouped query
+/// over `photo_albums`, keyed by album id. Albums without live photos are
+/// absent from the map.
+pub async fn album_counts_and_covers(
+    conn: &turso::Connection,
+) -> Result<HashMap<String, (i64, Option<JsonValue>)>, AppError> {
+    let sql = format!(
+        "SELECT pa.album_id AS album_id, COUNT(*) AS count, {} AS cover
+         FROM photo_albums pa JOIN photos p ON p.id = pa.photo_id
+         WHERE p.deleted = 0
+         GROUP BY pa.album_id",
+        COVER_EXPR
+    );
+    let rows = query_maps(conn, &sql, ()).await?;
+    Ok(rows
+        .iter()
+        .filter_map(|row| {
+            let album_id = row.get("album_id")?.as_str()?.to_string();
+            let count = row.get("count").and_then(|v| v.as_i64()).unwrap_or(0);
+            let cover = row.get("cover").and_then(|v| v.as_str()).map(parse_cover);
+            Some((album_id, (count, cover)))
+        })
+        .collect())
+}
+
+/// 时间线：按年/月/日分组的照片数量与封面，用于滚动条与“跳到某月”
+#[tauri::command]
+pub async fn db_photo_timeline(
+    state: State<'_, TursoDb>,
+    granularity: Option<String>,
     liked_mode: Option<bool>,
     album_id: Option<String>,
     is_delete: Option<bool>,
     type_filter: Option<String>,
     start_date: Option<String>,
     end_date: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+    utc_offset_minutes: Option<i64>,
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let granularity = TimelineGranularity::parse(granularity.as_deref())?;
+    let mut filter = PhotoFilter {
+        deleted: is_delete.unwrap_or(false),
+        liked_only: liked_mode.unwrap_or(false),
+        album_id,
+        type_prefix: type_filter,
+        start_date,
+        end_date,
+        smart: None,
+    };
+    filter.load_smart_album(&conn).await?;
+    let buckets = photo_timeline(&conn, &filter, granularity, utc_offset_minutes.unwrap_or(0)).await?;
+    Ok(json!({ "code": 0, "data": buckets }))
+}
 
-    let mut conditions = vec!["deleted = ?1".to_string()];
-    let mut params: Vec<TursoValue> = vec![TursoValue::Integer(if is_delete.unwrap_or(false) {
-        1
-    } else {
-        0
-    })];
-    let mut param_idx = 2;
+/// Options for [`photos_on_this_day`].
+#[derive(Debug, Clone)]
+pub struct OnThisDayOptions {
+    /// The "today" to look back from, in the user's local calendar.
+    pub date: NaiveDate,
+    /// Also match up to this many days before and after.
+    pub window_days: i64,
+    pub exclude_screenshots: bool,
+    /// Add photos near each memorial shown on the album home.
+    pub include_memorials: bool,
+    /// Offset of the user's time zone; `taken_at` is stored in UTC.
+    pub utc_offset_minutes: i64,
+}
 
-    if liked_mode.unwrap_or(false) {
-        conditions.push(format!("is_liked = ?{}", param_idx));
-        params.push(TursoValue::Integer(1));
-        param_idx += 1;
+// 按文件名识别截图（Android / iOS / macOS / Windows 常见命名）
+const SCREENSHOT_NAME_PATTERNS: [&str; 6] = [
+    "%screenshot%",
+    "%screen shot%",
+    "%screen_shot%",
+    "%截屏%",
+    "%截图%",
+    "%屏幕快照%",
+];
+
+const MAX_ON_THIS_DAY_WINDOW: i64 = 15;
+
+/// `MM-DD` strings within `window` days of `month`/`day`. A leap year is used
+/// as the reference so Feb 29 stays reachable.
+fn month_days_around(month: u32, day: u32, window: i64) -> Vec<String> {
+    let Some(center) = NaiveDate::from_ymd_opt(2000, month, day) else {
+        return Vec::new();
+    };
+    let window = window.clamp(0, MAX_ON_THIS_DAY_WINDOW);
+    (-window..=window)
+        .filter_map(|offset| center.checked_add_signed(chrono::Duration::days(offset)))
+        .map(|d| d.format("%m-%d").to_string())
+        .collect()
+}
+
+/// `date` moved to `year`; Feb 29 falls back to Feb 28 in common years.
+fn same_day_in_year(date: NaiveDate, year: i32) -> Option<NaiveDate> {
+    use chrono::Datelike;
+    date.with_year(year)
+        .or_else(|| NaiveDate::from_ymd_opt(year, date.month(), 28))
+}
+
+async fn photos_on_month_days(
+    conn: &turso::Connection,
+    month_days: &[String],
+    until: Option<NaiveDate>,
+    options: &OnThisDayOptions,
+) -> Result<Vec<Photo>, AppError> {
+    if month_days.is_empty() {
+        return Ok(Vec::new());
     }
-    if let Some(ref t) = type_filter {
-        conditions.push(format!("type LIKE ?{}", param_idx));
-        params.push(TursoValue::Text(format!("{}%", t)));
-        param_idx += 1;
+    let shift = format!("{:+} minutes", options.utc_offset_minutes);
+    let mut params: Vec<TursoValue> = vec![TursoValue::Text(shift)];
+    let mut conditions = vec![
+        "deleted = 0".to_string(),
+        "taken_at IS NOT NULL".to_string(),
+        format!(
+            "strftime('%m-%d', taken_at, ?1) IN ({})",
+            placeholders(2, month_days.len())
+        ),
+    ];
+    params.extend(month_days.iter().map(|v| TursoValue::Text(v.clone())));
+    if let Some(until) = until {
+        params.push(TursoValue::Text(until.format("%Y-%m-%d").to_string()));
+        conditions.push(format!("strftime('%Y-%m-%d', taken_at, ?1) <= ?{}", params.len()));
     }
-    if let Some(ref sd) = start_date {
-        if !sd.is_empty() {
-            conditions.push(format!("last_modified >= ?{}", param_idx));
-            params.push(TursoValue::Text(sd.clone()));
-            param_idx += 1;
-        }
+    if options.exclude_screenshots {
+        let start = params.len() + 1;
+        params.extend(SCREENSHOT_NAME_PATTERNS.iter().map(|p| TursoValue::Text(p.to_string())));
+        let likes = (start..start + SCREENSHOT_NAME_PATTERNS.len())
+            .map(|i| format!("lower(COALESCE(json_extract(data, '$.name'), '')) LIKE ?{}", i))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        conditions.push(format!("NOT ({})", likes));
     }
-    if let Some(ref ed) = end_date {
-        if !ed.is_empty() {
-            conditions.push(format!("last_modified <= ?{}", param_idx));
-            params.push(TursoValue::Text(ed.clone()));
-            param_idx += 1;
+    let sql = format!(
+        "SELECT * FROM photos WHERE {} ORDER BY taken_at DESC, id DESC",
+        conditions.join(" AND ")
+    );
+    query_models::<Photo>(conn, &sql, params).await
+}
+
+/// Calendar date of `taken_at` in the user's time zone.
+fn local_date(taken_at: Option<&str>, utc_offset_minutes: i64) -> Option<NaiveDate> {
+    let utc = parse_timestamp(taken_at?)?;
+    Some((utc + chrono::Duration::minutes(utc_offset_minutes)).date_naive())
+}
+
+/// Year whose copy of `date` lies within `window` days of `local`. Near New
+/// Year this differs from `local`'s own year (Dec 31 belongs to the next
+/// year's Jan 2).
+fn anchor_year(local: NaiveDate, date: NaiveDate, window: i64) -> Option<i32> {
+    use chrono::Datelike;
+    [local.year() + 1, local.year(), local.year() - 1]
+        .into_iter()
+        .find(|year| {
+            same_day_in_year(date, *year)
+                .is_some_and(|anchor| (local - anchor).num_days().abs() <= window)
+        })
+}
+
+/// Photos taken on `options.date`'s month/day (± window) in earlier years,
+/// grouped by year newest first, plus optional per-memorial groups.
+pub async fn photos_on_this_day(
+    conn: &turso::Connection,
+    options: &OnThisDayOptions,
+) -> Result<JsonValue, AppError> {
+    use chrono::Datelike;
+
+    let window = options.window_days.clamp(0, MAX_ON_THIS_DAY_WINDOW);
+    let month_days = month_days_around(options.date.month(), options.date.day(), window);
+    // 按完整日期截止到“去年今日 + 窗口”，跨年窗口里今年年初前几天的照片不算往年
+    let until = same_day_in_year(options.date, options.date.year() - 1)
+        .and_then(|d| d.checked_add_signed(chrono::Duration::days(window)));
+    let photos = photos_on_month_days(conn, &month_days, until, options).await?;
+
+    let mut years: Vec<(i32, Vec<JsonValue>)> = Vec::new();
+    for photo in &photos {
+        let year = local_date(photo.taken_at.as_deref(), options.utc_offset_minutes)
+            .and_then(|local| anchor_year(local, options.date, window));
+        let Some(year) = year.filter(|y| *y < options.date.year()) else {
+            continue;
+        };
+        match years.last_mut() {
+            Some((y, items)) if *y == year => items.push(merge_photo_row(photo)),
+            _ => years.push((year, vec![merge_photo_row(photo)])),
         }
     }
-    if let Some(ref aid) = album_id {
-        conditions.push(format!(
-            "id IN (SELECT photo_id FROM photo_albums WHERE album_id = ?{})",
-            param_idx
-        ));
-        params.push(TursoValue::Text(aid.clone()));
-        #[allow(unused_assignments)]
-        {
-            param_idx += 1;
+    let years: Vec<JsonValue> = years
+        .into_iter()
+        .map(|(year, items)| {
+            json!({
+                "year": year,
+                "yearsAgo": options.date.year() - year,
+                "count": items.len(),
+                "photos": items,
+            })
+        })
+        .collect();
+
+    let mut memorials = Vec::new();
+    if options.include_memorials {
+        let rows = query_models::<Memorial>(conn, "SELECT * FROM memorials WHERE deleted = 0", ()).await?;
+        for row in rows {
+            let memorial = row.to_json();
+            let shown = memorial.get("showOnAlbumHome").and_then(|v| v.as_bool()).unwrap_or(false);
+            // 农历纪念日需要历法换算，暂不参与
+            let lunar = memorial.get("isLunar").and_then(|v| v.as_bool()).unwrap_or(false);
+            let date = memorial
+                .get("date")
+                .and_then(|v| v.as_str())
+                .and_then(|v| NaiveDate::parse_from_str(v, "%Y-%m-%d").ok());
+            let (true, false, Some(date)) = (shown, lunar, date) else {
+                continue;
+            };
+            let days = month_days_around(date.month(), date.day(), options.window_days);
+            let until = NaiveDate::from_ymd_opt(options.date.year(), 12, 31);
+            let photos = photos_on_month_days(conn, &days, until, options).await?;
+            if photos.is_empty() {
+                continue;
+            }
+            memorials.push(json!({
+                "memorial": {
+                    "id": row.id,
+                    "name": memorial.get("name").cloned().unwrap_or(JsonValue::Null),
+                    "date": memorial.get("date").cloned().unwrap_or(JsonValue::Null),
+                },
+                "count": photos.len(),
+                "photos": photos.iter().map(merge_photo_row).collect::<Vec<_>>(),
+            }));
         }
     }
 
-    let where_clause = conditions.join(" AND ");
+    Ok(json!({
+        "date": options.date.format("%Y-%m-%d").to_string(),
+        "years": years,
+        "memorials": memorials,
+    }))
+}
+
+/// 那年今日：往年同月同日（可前后放宽 N 天）的照片，按年分组
+#[tauri::command]
+pub async fn db_photo_on_this_day(
+    state: State<'_, TursoDb>,
+    date: Option<String>,
+    window_days: Option<i64>,
+    exclude_screenshots: Option<bool>,
+    include_memorials: Option<bool>,
+    utc_offset_minutes: Option<i64>,
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let now = chrono::Local::now();
+    let date = match date.filter(|v| !v.is_empty()) {
+        Some(d) => NaiveDate::parse_from_str(&d, "%Y-%m-%d")
+            .map_err(|_| AppError::validation(format!("Invalid date: {}", d)))?,
+        None => now.date_naive(),
+    };
+    let options = OnThisDayOptions {
+        date,
+        window_days: window_days.unwrap_or(0),
+        exclude_screenshots: exclude_screenshots.unwrap_or(true),
+        include_memorials: include_memorials.unwrap_or(false),
+        utc_offset_minutes: utc_offset_minutes
+            .unwrap_or_else(|| now.offset().local_minus_utc() as i64 / 60),
+    };
+    let data = photos_on_this_day(&conn, &options).await?;
+    Ok(json!({ "code": 0, "data": data }))
+}
+
+#[tauri::command]
+pub async fn db_photo_list_info(
+    state: State<'_, TursoDb>,
+    liked_mode: Option<bool>,
+    album_id: Option<String>,
+    is_delete: Option<bool>,
+    type_filter: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let mut filter = PhotoFilter {
+        deleted: is_delete.unwrap_or(false),
+        liked_only: liked_mode.unwrap_or(false),
+        album_id,
+        type_prefix: type_filter,
+        start_date,
+        end_date,
+        smart: None,
+    };
+    filter.load_smart_album(&conn).await?;
+    let (where_clause, params) = filter.where_clause();
 
     // Count and total size from data JSON
     let sql = format!(
         "SELECT COUNT(*), COALESCE(SUM(json_extract(data, '$.size')), 0) FROM photos WHERE {}",
         where_clause
     );
-    let mut rows = conn.query(&sql, params).await.map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
+    let mut rows = conn.query(&sql, params).await?;
+    if let Some(row) = rows.next().await? {
         let count = row
-            .get_value(0)
-            .map_err(|e| e.to_string())?
+            .get_value(0)?
             .as_integer()
             .copied()
             .unwrap_or(0);
         let total_size = row
-            .get_value(1)
-            .map_err(|e| e.to_string())?
+            .get_value(1)?
             .as_integer()
             .copied()
             .unwrap_or(0);
@@ -521,14 +1388,13 @@ pub async fn db_photo_add_album(
     state: State<'_, TursoDb>,
     photo_id: String,
     album_id: String,
-) -> Result<(), String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<(), AppError> {
+    let conn = state.0.connect()?;
     conn.execute(
         "INSERT OR IGNORE INTO photo_albums (photo_id, album_id) VALUES (?1, ?2)",
         (photo_id, album_id),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
     Ok(())
 }
 
@@ -537,8 +1403,8 @@ pub async fn db_photo_remove_album(
     state: State<'_, TursoDb>,
     photo_id: String,
     album_id: String,
-) -> Result<(), String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<(), AppError> {
+    let conn = state.0.connect()?;
     let album_ids = get_photo_album_ids(&conn, &photo_id)
         .await?
         .into_iter()
@@ -548,8 +1414,7 @@ pub async fn db_photo_remove_album(
         "DELETE FROM photo_albums WHERE photo_id = ?1 AND album_id = ?2",
         (photo_id.clone(), album_id),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
     update_photo_album_data(&conn, &photo_id, &album_ids).await?;
     Ok(())
 }
@@ -559,49 +1424,291 @@ pub async fn db_photo_set_albums(
     state: State<'_, TursoDb>,
     photo_id: String,
     album_ids: Vec<String>,
-) -> Result<(), String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<(), AppError> {
+    let conn = state.0.connect()?;
+    set_photo_albums(&conn, &photo_id, &album_ids).await
+}
+
+/// Replace the photo's album links with `album_ids`.
+pub async fn set_photo_albums(
+    conn: &turso::Connection,
+    photo_id: &str,
+    album_ids: &[String],
+) -> Result<(), AppError> {
     // Clear existing
-    conn.execute(
-        "DELETE FROM photo_albums WHERE photo_id = ?1",
-        (photo_id.clone(),),
-    )
-    .await
-    .map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM photo_albums WHERE photo_id = ?1", (photo_id,))
+        .await?;
     // Insert new
-    for aid in &album_ids {
+    for aid in album_ids {
         conn.execute(
             "INSERT OR IGNORE INTO photo_albums (photo_id, album_id) VALUES (?1, ?2)",
-            (photo_id.clone(), aid.clone()),
+            (photo_id, aid.as_str()),
         )
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
     }
-    update_photo_album_data(&conn, &photo_id, &album_ids).await?;
+    update_photo_album_data(conn, photo_id, album_ids).await?;
     Ok(())
 }
 
+/// 批量把照片加入相册（追加，不移除已有关联）：单事务完成，返回每张照片的结果
 #[tauri::command]
 pub async fn db_photos_set_albums(
     state: State<'_, TursoDb>,
     photo_ids: Vec<String>,
     album_ids: Vec<String>,
-) -> Result<(), String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
-    for pid in &photo_ids {
-        let mut merged_album_ids = get_photo_album_ids(&conn, pid).await?;
-        for aid in &album_ids {
-            conn.execute(
-                "INSERT OR IGNORE INTO photo_albums (photo_id, album_id) VALUES (?1, ?2)",
-                (pid.clone(), aid.clone()),
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let results = add_photos_to_albums(&conn, &photo_ids, &album_ids).await?;
+    Ok(json!({ "code": 0, "data": results }))
+}
+
+pub async fn add_photos_to_albums(
+    conn: &turso::Connection,
+    photo_ids: &[String],
+    album_ids: &[String],
+) -> Result<Vec<BatchItemResult>, AppError> {
+    in_transaction(conn, async {
+        let mut results = Vec::with_capacity(photo_ids.len());
+        for chunk in photo_ids.chunks(BATCH_CHUNK_SIZE) {
+            let params = text_params(chunk);
+            let marks = placeholders(1, chunk.len());
+
+            let mut photo_data: HashMap<String, JsonValue> = HashMap::new();
+            for row in query_maps(
+                conn,
+                &format!("SELECT id, data FROM photos WHERE id IN ({})", marks),
+                params.clone(),
             )
-            .await
-            .map_err(|e| e.to_string())?;
-            if !merged_album_ids.contains(aid) {
-                merged_album_ids.push(aid.clone());
+            .await?
+            {
+                let id = row.get("id").and_then(|v| v.as_str()).unwrap_or("").to_string();
+                let data = row
+                    .get("data")
+                    .and_then(|v| v.as_str())
+                    .and_then(|d| serde_json::from_str::<JsonValue>(d).ok())
+                    .unwrap_or_else(|| json!({}));
+                photo_data.insert(id, data);
+            }
+
+            let mut linked: HashMap<String, Vec<String>> = HashMap::new();
+            for row in query_maps(
+                conn,
+                &format!("SELECT photo_id, album_id FROM photo_albums WHERE photo_id IN ({})", marks),
+                params,
+            )
+            .await?
+            {
+                if let (Some(pid), Some(aid)) = (
+                    row.get("photo_id").and_then(|v| v.as_str()),
+                    row.get("album_id").and_then(|v| v.as_str()),
+                ) {
+                    linked.entry(pid.to_string()).or_default().push(aid.to_string());
+                }
             }
+
+            let mut links: Vec<(String, String)> = Vec::new();
+            for pid in chunk {
+                let Some(mut data) = photo_data.get(pid).cloned() else {
+                    results.push(BatchItemResult::skipped(pid, "Photo not found"));
+                    continue;
+                };
+                // 关联表 + data.albumId（旧数据）+ 新相册，保持顺序去重
+                let mut merged: Vec<String> = linked.remove(pid).unwrap_or_default();
+                let legacy = data
+                    .get("albumId")
+                    .and_then(|v| v.as_array())
+                    .map(|ids| ids.iter().filter_map(|v| v.as_str()).map(String::from).collect::<Vec<_>>())
+                    .unwrap_or_default();
+                for aid in legacy.into_iter().chain(album_ids.iter().cloned()) {
+                    if !aid.is_empty() && !merged.contains(&aid) {
+                        merged.push(aid);
+                    }
+                }
+                for aid in album_ids {
+                    links.push((pid.clone(), aid.clone()));
+                }
+                if let Some(obj) = data.as_object_mut() {
+                    obj.insert("albumId".to_string(), json!(merged));
+                } else {
+                    data = json!({ "albumId": merged });
+                }
+                conn.execute(
+                    "UPDATE photos SET data = ?1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?2",
+                    (data.to_string(), pid.clone()),
+                )
+                .await?;
+                results.push(BatchItemResult::ok(pid));
+            }
+            insert_photo_album_links(conn, &links).await?;
+        }
+        Ok(results)
+    })
+    .await
+}
+
+// 前端 EXIF 解析使用的同一组时间标签，按优先级排列
+const EXIF_DATE_TAGS: [&str; 4] = ["DateTimeOriginal", "DateTimeDigitized", "DateTime", "CreateDate"];
+
+/// Capture time for a photo row: `data.takenAt`, then the stored EXIF date,
+/// then `last_modified`. Returned in the same ISO format the frontend writes
+/// to `last_modified`, so both sort and compare as text.
+pub fn derive_taken_at(data: &JsonValue, last_modified: Option<&str>) -> Option<String> {
+    let iso = |dt: chrono::DateTime<chrono::Utc>| dt.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+    let explicit = data.get("takenAt").and_then(|v| match v {
+        JsonValue::Number(n) => n.as_i64().and_then(chrono::DateTime::from_timestamp_millis),
+        JsonValue::String(s) => parse_timestamp(s),
+        _ => None,
+    });
+    if let Some(dt) = explicit {
+        return Some(iso(dt));
+    }
+
+    let exif = data.get("exif");
+    let tag_text = |tag: &str| {
+        let item = exif?.get(tag)?;
+        item.get("description")
+            .or_else(|| item.get("value"))
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string())
+    };
+    let offset = tag_text("OffsetTimeOriginal");
+    for tag in EXIF_DATE_TAGS {
+        let parsed = tag_text(tag).and_then(|v| parse_exif_datetime(&v, offset.as_deref()));
+        if let Some(dt) = parsed.and_then(chrono::DateTime::from_timestamp_millis) {
+            return Some(iso(dt));
+        }
+    }
+
+    let last_modified = last_modified.filter(|v| !v.is_empty())?;
+    Some(parse_timestamp(last_modified).map(iso).unwrap_or_else(|| last_modified.to_string()))
+}
+
+/// Re-derive `taken_at` for one photo after its data or timestamp changed.
+pub async fn refresh_taken_at(conn: &turso::Connection, photo_id: &str) -> Result<(), AppError> {
+    if let Some(photo) = query_model::<Photo>(conn, "SELECT * FROM photos WHERE id = ?1", (photo_id,)).await? {
+        let data = serde_json::from_str::<JsonValue>(&photo.data).unwrap_or_default();
+        let taken_at = derive_taken_at(&data, photo.last_modified.as_deref());
+        if taken_at != photo.taken_at {
+            conn.execute(
+                "UPDATE photos SET taken_at = ?1 WHERE id = ?2",
+                (taken_at, photo_id.to_string()),
+            )
+            .await?;
         }
-        update_photo_album_data(&conn, pid, &merged_album_ids).await?;
+    }
+    Ok(())
+}
+
+/// Re-derive `taken_at` for every photo (or only those still missing it).
+/// Returns `(scanned, updated)`.
+pub async fn backfill_taken_at(
+    conn: &turso::Connection,
+    missing_only: bool,
+) -> Result<(usize, usize), AppError> {
+    let sql = if missing_only {
+        "SELECT * FROM photos WHERE taken_at IS NULL"
+    } else {
+        "SELECT * FROM photos"
+    };
+    let photos = query_models::<Photo>(conn, sql, ()).await?;
+    let mut updated = 0;
+    for photo in &photos {
+        let data = serde_json::from_str::<JsonValue>(&photo.data).unwrap_or_default();
+        let taken_at = derive_taken_at(&data, photo.last_modified.as_deref());
+        if taken_at != photo.taken_at {
+            conn.execute(
+                "UPDATE photos SET taken_at = ?1 WHERE id = ?2",
+                (taken_at, photo.id.clone()),
+            )
+            .await?;
+            updated += 1;
+        }
+    }
+    Ok((photos.len(), updated))
+}
+
+/// 重新从 EXIF / data.takenAt / last_modified 推导全部照片的拍摄时间
+#[tauri::command]
+pub async fn db_photo_backfill_taken_at(state: State<'_, TursoDb>) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let (scanned, updated) = in_transaction(&conn, backfill_taken_at(&conn, false)).await?;
+    Ok(json!({ "code": 0, "data": { "scanned": scanned, "updated": updated } }))
+}
+
+fn legacy_album_ids(data: &JsonValue) -> Vec<String> {
+    data.get("albumId")
+        .and_then(|v| v.as_array())
+        .map(|ids| {
+            ids.iter()
+                .filter_map(|v| v.as_str())
+                .filter(|v| !v.is_empty())
+                .map(|v| v.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Mirror a photo's legacy `data.albumId` into `photo_albums`, so list
+/// queries only need to look at the link table.
+pub async fn link_legacy_album_ids(
+    conn: &turso::Connection,
+    photo_id: &str,
+    data: &str,
+) -> Result<(), AppError> {
+    let data = serde_json::from_str::<JsonValue>(data).unwrap_or_default();
+    let links: Vec<(String, String)> = legacy_album_ids(&data)
+        .into_iter()
+        .map(|aid| (photo_id.to_string(), aid))
+        .collect();
+    insert_photo_album_links(conn, &links).await
+}
+
+/// One-off backfill of `photo_albums` from every photo's `data.albumId`.
+pub async fn backfill_album_links(conn: &turso::Connection) -> Result<(), AppError> {
+    let rows = query_maps(
+        conn,
+        "SELECT id, data FROM photos WHERE json_type(data, '$.albumId') = 'array'",
+        (),
+    )
+    .await?;
+    let mut links = Vec::new();
+    for row in rows {
+        let id = row.get("id").and_then(|v| v.as_str()).unwrap_or_default();
+        let data = row
+            .get("data")
+            .and_then(|v| v.as_str())
+            .and_then(|v| serde_json::from_str::<JsonValue>(v).ok())
+            .unwrap_or_default();
+        links.extend(
+            legacy_album_ids(&data)
+                .into_iter()
+                .map(|aid| (id.to_string(), aid)),
+        );
+    }
+    insert_photo_album_links(conn, &links).await
+}
+
+// 多行 INSERT OR IGNORE 批量写入关联
+async fn insert_photo_album_links(
+    conn: &turso::Connection,
+    links: &[(String, String)],
+) -> Result<(), AppError> {
+    for chunk in links.chunks(BATCH_CHUNK_SIZE / 2) {
+        let mut params = Vec::with_capacity(chunk.len() * 2);
+        let values = chunk
+            .iter()
+            .map(|(pid, aid)| {
+                params.push(TursoValue::Text(pid.clone()));
+                params.push(TursoValue::Text(aid.clone()));
+                format!("(?{}, ?{})", params.len() - 1, params.len())
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        conn.execute(
+            &format!("INSERT OR IGNORE INTO photo_albums (photo_id, album_id) VALUES {}", values),
+            params,
+        )
+        .await?;
     }
     Ok(())
 }
@@ -609,20 +1716,18 @@ pub async fn db_photos_set_albums(
 async fn get_photo_album_ids(
     conn: &turso::Connection,
     photo_id: &str,
-) -> Result<Vec<String>, String> {
+) -> Result<Vec<String>, AppError> {
     let mut album_ids = Vec::new();
     let mut rows = conn
         .query(
             "SELECT album_id FROM photo_albums WHERE photo_id = ?1",
             (photo_id.to_string(),),
         )
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
-    while let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
+    while let Some(row) = rows.next().await? {
         let album_id = row
-            .get_value(0)
-            .map_err(|e| e.to_string())?
+            .get_value(0)?
             .as_text()
             .map_or("", |v| v)
             .to_string();
@@ -646,21 +1751,19 @@ async fn get_photo_album_ids(
     Ok(album_ids)
 }
 
-async fn get_photo_data(conn: &turso::Connection, id: &str) -> Result<JsonValue, String> {
+async fn get_photo_data(conn: &turso::Connection, id: &str) -> Result<JsonValue, AppError> {
     let mut rows = conn
         .query("SELECT data FROM photos WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
+        .await?;
+    if let Some(row) = rows.next().await? {
         let data_str = row
-            .get_value(0)
-            .map_err(|e| e.to_string())?
+            .get_value(0)?
             .as_text()
             .map_or("{}", |v| v)
             .to_string();
-        serde_json::from_str(&data_str).map_err(|e| e.to_string())
+        serde_json::from_str(&data_str).map_err(AppError::from)
     } else {
-        Err("Photo not found".to_string())
+        Err(AppError::not_found("Photo not found"))
     }
 }
 
@@ -668,7 +1771,7 @@ async fn update_photo_album_data(
     conn: &turso::Connection,
     photo_id: &str,
     album_ids: &[String],
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     let mut data = get_photo_data(conn, photo_id)
         .await
         .unwrap_or_else(|_| json!({}));
@@ -678,11 +1781,10 @@ async fn update_photo_album_data(
         data = json!({ "albumId": album_ids });
     }
     conn.execute(
-        "UPDATE photos SET data = ?1, updated_at = datetime('now') WHERE id = ?2",
+        "UPDATE photos SET data = ?1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?2",
         (data.to_string(), photo_id.to_string()),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
     Ok(())
 }
 
@@ -699,29 +1801,3 @@ fn format_size(mut size: f64) -> String {
         format!("{:.2}{}", size, units[unit_idx])
     }
 }
-
-/// Helper: convert a turso Row to serde_json::Value
-fn row_to_json(row: &turso::Row) -> Result<JsonValue, String> {
-    let mut map = serde_json::Map::new();
-    // We know our table structure: id, remote_id, sync_status, updated_at, deleted, is_liked, type, last_modified, md5, data
-    // Iterate columns by index
-    for i in 0..10 {
-        if let Ok(val) = row.get_value(i as usize) {
-            let key = match i {
-                0 => "id",
-                1 => "remote_id",
-                2 => "sync_status",
-                3 => "updated_at",
-                4 => "deleted",
-                5 => "is_liked",
-                6 => "type",
-                7 => "last_modified",
-                8 => "md5",
-                9 => "data",
-                _ => continue,
-            };
-            map.insert(key.to_string(), super::turso_value_to_json(&val));
-        }
-    }
-    Ok(JsonValue::Object(map))
-}
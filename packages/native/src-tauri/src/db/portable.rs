@@ -0,0 +1,320 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::{json, Map, Value as JsonValue};
+use tauri::State;
+use turso::Value as TursoValue;
+
+use crate::error::AppError;
+use super::{json_to_turso_value, merge_row, query_maps, table_columns, TursoDb};
+
+/// Tables included in a portable export, in import order: `photo_albums`
+/// comes after both sides of the relation so ids can be remapped.
+pub const PORTABLE_TABLES: &[&str] = &[
+    "photos",
+    "albums",
+    "photo_albums",
+    "album_folders",
+    "assets",
+    "asset_categories",
+    "memorials",
+    "families",
+    "weights",
+    "blood_pressures",
+    "todos",
+    "drive_files",
+    "usage_records",
+];
+
+const RELATION_TABLE: &str = "photo_albums";
+
+// 导入报告中每张表最多保留的错误条数
+const MAX_ERRORS: usize = 20;
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableImportReport {
+    pub inserted: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub invalid: usize,
+    pub errors: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub tables: BTreeMap<String, TableImportReport>,
+}
+
+fn ndjson_path(dir: &Path, table: &str) -> std::path::PathBuf {
+    dir.join(format!("{}.ndjson", table))
+}
+
+/// Write every portable table to `{dir}/{table}.ndjson`, one `merge_row`
+/// document per line. Returns the row count per table.
+pub async fn export_ndjson(
+    conn: &turso::Connection,
+    dir: &Path,
+) -> Result<BTreeMap<String, usize>, AppError> {
+    std::fs::create_dir_all(dir)?;
+    let mut counts = BTreeMap::new();
+    for table in PORTABLE_TABLES {
+        let rows = query_maps(conn, &format!("SELECT * FROM {}", table), ()).await?;
+        let mut out =
+            BufWriter::new(File::create(ndjson_path(dir, table))?);
+        for row in &rows {
+            let doc = merge_row(&JsonValue::Object(row.clone()));
+            let line = serde_json::to_string(&doc)?;
+            out.write_all(line.as_bytes())?;
+            out.write_all(b"\n")?;
+        }
+        out.flush()?;
+        counts.insert(table.to_string(), rows.len());
+    }
+    Ok(counts)
+}
+
+/// Import the NDJSON files found in `dir`. Rows are upserted by `id`, falling
+/// back to `remote_id`; `photo_albums` links follow any id remapping. The
+/// whole import runs in one transaction and is rolled back when `dry_run`.
+pub async fn import_ndjson(
+    conn: &turso::Connection,
+    dir: &Path,
+    dry_run: bool,
+) -> Result<ImportReport, AppError> {
+    let mut report = ImportReport {
+        dry_run,
+        ..Default::default()
+    };
+
+    conn.execute("BEGIN", ()).await?;
+    let result = import_tables(conn, dir, &mut report).await;
+    if result.is_err() || dry_run {
+        let _ = conn.execute("ROLLBACK", ()).await;
+    } else {
+        conn.execute("COMMIT", ())
+            .await?;
+    }
+    result.map(|_| report)
+}
+
+async fn import_tables(
+    conn: &turso::Connection,
+    dir: &Path,
+    report: &mut ImportReport,
+) -> Result<(), AppError> {
+    // 导入 id → 本地 id（按 remote_id 匹配到已有行时两者不同）
+    let mut id_maps: HashMap<&str, HashMap<String, String>> = HashMap::new();
+
+    for table in PORTABLE_TABLES {
+        let path = ndjson_path(dir, table);
+        if !path.exists() {
+            continue;
+        }
+        let file = File::open(&path)?;
+        let columns = table_columns(conn, table).await?;
+        let mut table_report = TableImportReport::default();
+        let mut id_map = HashMap::new();
+
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let outcome = match serde_json::from_str::<JsonValue>(&line) {
+                Ok(JsonValue::Object(doc)) => {
+                    if *table == RELATION_TABLE {
+                        import_relation(conn, &doc, &id_maps).await
+                    } else {
+                        import_row(conn, table, &columns, doc, &mut id_map).await
+                    }
+                }
+                Ok(_) => Err(AppError::validation("expected a JSON object")),
+                Err(e) => Err(AppError::from(e)),
+            };
+            match outcome {
+                Ok(Upserted::Inserted) => table_report.inserted += 1,
+                Ok(Upserted::Updated) => table_report.updated += 1,
+                Ok(Upserted::Skipped) => table_report.skipped += 1,
+                Err(e) => {
+                    table_report.invalid += 1;
+                    if table_report.errors.len() < MAX_ERRORS {
+                        table_report
+                            .errors
+                            .push(format!("{}.ndjson:{}: {}", table, index + 1, e));
+                    }
+                }
+            }
+        }
+
+        // 旧导出文件没有 taken_at 列，导入后补齐
+        if *table == "photos" {
+            super::photo::backfill_taken_at(conn, true).await?;
+        }
+        super::search::rebuild_table(conn, table).await?;
+        id_maps.insert(table, id_map);
+        report.tables.insert(table.to_string(), table_report);
+    }
+    Ok(())
+}
+
+enum Upserted {
+    Inserted,
+    Updated,
+    Skipped,
+}
+
+fn text_field(doc: &Map<String, JsonValue>, key: &str) -> Option<String> {
+    doc.get(key)
+        .and_then(|v| v.as_str())
+        .filter(|v| !v.is_empty())
+        .map(|v| v.to_string())
+}
+
+async fn find_existing(
+    conn: &turso::Connection,
+    table: &str,
+    id: &str,
+    remote_id: Option<&str>,
+) -> Result<Option<String>, AppError> {
+    let mut rows = query_maps(
+        conn,
+        &format!("SELECT id FROM {} WHERE id = ?1", table),
+        (id,),
+    )
+    .await?;
+    if rows.is_empty() {
+        if let Some(rid) = remote_id {
+            rows = query_maps(
+                conn,
+                &format!("SELECT id FROM {} WHERE remote_id = ?1 LIMIT 1", table),
+                (rid,),
+            )
+            .await?;
+        }
+    }
+    Ok(rows.pop().and_then(|row| {
+        row.get("id")
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string())
+    }))
+}
+
+async fn import_row(
+    conn: &turso::Connection,
+    table: &str,
+    columns: &[String],
+    mut doc: Map<String, JsonValue>,
+    id_map: &mut HashMap<String, String>,
+) -> Result<Upserted, AppError> {
+    let alias = doc.remove("_id");
+    let id = text_field(&doc, "id")
+        .or_else(|| {
+            alias
+                .as_ref()
+                .and_then(|v| v.as_str())
+                .map(|v| v.to_string())
+        })
+        .ok_or_else(|| AppError::validation("missing id"))?;
+    doc.remove("id");
+    let remote_id = text_field(&doc, "remote_id");
+
+    let existing = find_existing(conn, table, &id, remote_id.as_deref()).await?;
+    let local_id = existing.clone().unwrap_or_else(|| id.clone());
+    id_map.insert(id, local_id.clone());
+
+    let mut names = Vec::new();
+    let mut params: Vec<TursoValue> = Vec::new();
+    let mut data = Map::new();
+    for (key, value) in &doc {
+        if key != "data" && columns.iter().any(|c| c == key) {
+            names.push(key.clone());
+            params.push(json_to_turso_value(value));
+        } else {
+            data.insert(key.clone(), value.clone());
+        }
+    }
+    names.push("data".to_string());
+    params.push(TursoValue::Text(JsonValue::Object(data).to_string()));
+
+    if existing.is_some() {
+        let sets = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| format!("{} = ?{}", name, i + 1))
+            .collect::<Vec<_>>()
+            .join(", ");
+        params.push(TursoValue::Text(local_id));
+        let sql = format!("UPDATE {} SET {} WHERE id = ?{}", table, sets, params.len());
+        conn.execute(&sql, params)
+            .await?;
+        Ok(Upserted::Updated)
+    } else {
+        names.push("id".to_string());
+        params.push(TursoValue::Text(local_id));
+        let placeholders = (1..=params.len())
+            .map(|i| format!("?{}", i))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sql = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            table,
+            names.join(", "),
+            placeholders
+        );
+        conn.execute(&sql, params)
+            .await?;
+        Ok(Upserted::Inserted)
+    }
+}
+
+async fn import_relation(
+    conn: &turso::Connection,
+    doc: &Map<String, JsonValue>,
+    id_maps: &HashMap<&str, HashMap<String, String>>,
+) -> Result<Upserted, AppError> {
+    let photo_id = text_field(doc, "photo_id").ok_or_else(|| AppError::validation("missing photo_id"))?;
+    let album_id = text_field(doc, "album_id").ok_or_else(|| AppError::validation("missing album_id"))?;
+    let remap = |table: &str, id: String| {
+        id_maps
+            .get(table)
+            .and_then(|m| m.get(&id).cloned())
+            .unwrap_or(id)
+    };
+    let inserted = conn
+        .execute(
+            "INSERT OR IGNORE INTO photo_albums (photo_id, album_id) VALUES (?1, ?2)",
+            (remap("photos", photo_id), remap("albums", album_id)),
+        )
+        .await?;
+    Ok(if inserted > 0 {
+        Upserted::Inserted
+    } else {
+        Upserted::Skipped
+    })
+}
+
+/// Export every entity table as NDJSON files into `dir`.
+#[tauri::command]
+pub async fn db_export_ndjson(state: State<'_, TursoDb>, dir: String) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let counts = export_ndjson(&conn, Path::new(&dir)).await?;
+    Ok(json!({ "code": 0, "data": counts }))
+}
+
+/// Import NDJSON files from `dir`; with `dry_run` only the report is produced.
+#[tauri::command]
+pub async fn db_import_ndjson(
+    state: State<'_, TursoDb>,
+    dir: String,
+    dry_run: Option<bool>,
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let report = import_ndjson(&conn, Path::new(&dir), dry_run.unwrap_or(false)).await?;
+    Ok(json!({ "code": 0, "data": report }))
+}
@@ -0,0 +1,654 @@
+//! Local full-text search over photos, albums, drive files, todos, memorials
+//! and assets.
+//!
+//! The bundled engine has no FTS5 and no triggers, so the index is a pair of
+//! plain tables kept current by the `db` modules: `search_docs` holds the
+//! searchable text per (entity, id, field) and `search_terms` is an inverted
+//! index over it. Latin words are indexed whole and matched by prefix; CJK text
+//! has no word boundaries, so every character and every adjacent pair is
+//! indexed and queries match on bigrams.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+use serde_json::{json, Value as JsonValue};
+use tauri::State;
+use turso::Value as TursoValue;
+
+use super::{placeholders, query_maps, TursoDb, BATCH_CHUNK_SIZE};
+use crate::error::AppError;
+
+/// A searchable table: `fields` are keys of its JSON `data` with their rank weight.
+pub struct SearchSource {
+    pub entity: &'static str,
+    pub table: &'static str,
+    pub fields: &'static [(&'static str, i64)],
+}
+
+pub const SEARCH_SOURCES: &[SearchSource] = &[
+    SearchSource {
+        entity: "photo",
+        table: "photos",
+        fields: &[("name", 3), ("description", 2)],
+    },
+    SearchSource {
+        entity: "album",
+        table: "albums",
+        fields: &[("name", 3), ("description", 2), ("tags", 2)],
+    },
+    SearchSource {
+        entity: "drive_file",
+        table: "drive_files",
+        fields: &[("name", 3)],
+    },
+    SearchSource {
+        entity: "todo",
+        table: "todos",
+        fields: &[("title", 3), ("note", 2)],
+    },
+    SearchSource {
+        entity: "memorial",
+        table: "memorials",
+        fields: &[("name", 3), ("description", 2)],
+    },
+    SearchSource {
+        entity: "asset",
+        table: "assets",
+        fields: &[("name", 3), ("description", 2)],
+    },
+];
+
+pub const DEFAULT_SEARCH_LIMIT: usize = 50;
+const MAX_TERM_CHARS: usize = 64;
+const SNIPPET_CHARS: usize = 60;
+const SNIPPET_LEAD: usize = 20;
+
+fn source_for_table(table: &str) -> Option<&'static SearchSource> {
+    SEARCH_SOURCES.iter().find(|s| s.table == table)
+}
+
+// ==================== 分词 ====================
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF      // 平假名/片假名
+        | 0x3400..=0x4DBF    // 扩展 A
+        | 0x4E00..=0x9FFF    // 基本汉字
+        | 0xAC00..=0xD7AF    // 谚文
+        | 0xF900..=0xFAFF
+        | 0x20000..=0x2FFFF)
+}
+
+enum Run {
+    Word(String),
+    Cjk(Vec<char>),
+}
+
+// 按字符类别切分：字母数字连成词，CJK 连成一段，其余字符都是分隔符
+fn runs(text: &str) -> Vec<Run> {
+    let mut out = Vec::new();
+    let mut current: Option<Run> = None;
+    for c in text.chars() {
+        let c = c.to_lowercase().next().unwrap_or(c);
+        match (&mut current, is_cjk(c), c.is_alphanumeric()) {
+            (Some(Run::Cjk(chars)), true, _) => chars.push(c),
+            (Some(Run::Word(word)), false, true) => word.push(c),
+            (_, cjk, alnum) => {
+                out.extend(current.take());
+                current = if cjk {
+                    Some(Run::Cjk(vec![c]))
+                } else if alnum {
+                    Some(Run::Word(c.to_string()))
+                } else {
+                    None
+                };
+            }
+        }
+    }
+    out.extend(current);
+    out
+}
+
+fn truncate(word: String) -> String {
+    if word.chars().count() > MAX_TERM_CHARS {
+        word.chars().take(MAX_TERM_CHARS).collect()
+    } else {
+        word
+    }
+}
+
+/// Terms stored in the index for `text`.
+pub fn index_terms(text: &str) -> Vec<String> {
+    let mut terms = HashSet::new();
+    for run in runs(text) {
+        match run {
+            Run::Word(word) => {
+                terms.insert(truncate(word));
+            }
+            Run::Cjk(chars) => {
+                for (i, c) in chars.iter().enumerate() {
+                    terms.insert(c.to_string());
+                    if let Some(next) = chars.get(i + 1) {
+                        terms.insert(format!("{}{}", c, next));
+                    }
+                }
+            }
+        }
+    }
+    let mut terms: Vec<String> = terms.into_iter().collect();
+    terms.sort();
+    terms
+}
+
+/// A query term; `prefix` terms also match longer indexed words.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QueryTerm {
+    pub term: String,
+    pub prefix: bool,
+}
+
+/// Terms every hit must contain: words by prefix, CJK by bigram (or the
+/// single character for a one-character run).
+pub fn query_terms(query: &str) -> Vec<QueryTerm> {
+    let mut terms: Vec<QueryTerm> = Vec::new();
+    let mut push = |term: String, prefix: bool| {
+        let term = QueryTerm { term, prefix };
+        if !terms.contains(&term) {
+            terms.push(term);
+        }
+    };
+    for run in runs(query) {
+        match run {
+            Run::Word(word) => push(truncate(word), true),
+            Run::Cjk(chars) if chars.len() == 1 => push(chars[0].to_string(), false),
+            Run::Cjk(chars) => {
+                for pair in chars.windows(2) {
+                    push(pair.iter().collect(), false);
+                }
+            }
+        }
+    }
+    terms
+}
+
+// 高亮用的原始片段：整词或整段 CJK
+fn needles(query: &str) -> Vec<Vec<char>> {
+    runs(query)
+        .into_iter()
+        .map(|run| match run {
+            Run::Word(word) => word.chars().collect(),
+            Run::Cjk(chars) => chars,
+        })
+        .collect()
+}
+
+// ==================== 索引维护 ====================
+
+// 字符串直接取；数组（如相册 tags）拼接为空格分隔的文本
+fn field_text(data: &JsonValue, key: &str) -> String {
+    match data.get(key) {
+        Some(JsonValue::String(s)) => s.trim().to_string(),
+        Some(JsonValue::Array(items)) => items
+            .iter()
+            .filter_map(|v| v.as_str())
+            .collect::<Vec<_>>()
+            .join(" "),
+        _ => String::new(),
+    }
+}
+
+async fn index_rows(
+    conn: &turso::Connection,
+    source: &SearchSource,
+    rows: &[serde_json::Map<String, JsonValue>],
+) -> Result<usize, AppError> {
+    let mut terms: Vec<(String, String, &'static str)> = Vec::new();
+    let mut indexed = 0;
+    for row in rows {
+        let Some(id) = row.get("id").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let data = row
+            .get("data")
+            .and_then(|v| v.as_str())
+            .and_then(|d| serde_json::from_str::<JsonValue>(d).ok())
+            .unwrap_or(JsonValue::Null);
+        for &(field, weight) in source.fields {
+            let content = field_text(&data, field);
+            if content.is_empty() {
+                continue;
+            }
+            conn.execute(
+                "INSERT OR REPLACE INTO search_docs (entity, entity_id, field, weight, content) VALUES (?1, ?2, ?3, ?4, ?5)",
+                (source.entity, id, field, weight, content.as_str()),
+            )
+            .await?;
+            terms.extend(
+                index_terms(&content)
+                    .into_iter()
+                    .map(|t| (t, id.to_string(), field)),
+            );
+        }
+        indexed += 1;
+    }
+    // 每行 4 个参数，多行 INSERT 分批写入
+    for chunk in terms.chunks(BATCH_CHUNK_SIZE / 4) {
+        let values = (0..chunk.len())
+            .map(|i| {
+                format!(
+                    "(?{}, ?{}, ?{}, ?{})",
+                    i * 4 + 1,
+                    i * 4 + 2,
+                    i * 4 + 3,
+                    i * 4 + 4
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut params = Vec::with_capacity(chunk.len() * 4);
+        for (term, id, field) in chunk {
+            params.push(TursoValue::Text(term.clone()));
+            params.push(TursoValue::Text(source.entity.to_string()));
+            params.push(TursoValue::Text(id.clone()));
+            params.push(TursoValue::Text(field.to_string()));
+        }
+        conn.execute(
+            &format!(
+                "INSERT OR IGNORE INTO search_terms (term, entity, entity_id, field) VALUES {}",
+                values
+            ),
+            params,
+        )
+        .await?;
+    }
+    Ok(indexed)
+}
+
+/// Re-read the given rows of `table` and rewrite their index entries. Rows
+/// that are missing or soft-deleted drop out of the index. Call after every
+/// write that can change searchable text or the `deleted` flag; tables that
+/// are not searchable are ignored.
+pub async fn reindex(
+    conn: &turso::Connection,
+    table: &str,
+    ids: &[String],
+) -> Result<(), AppError> {
+    let Some(source) = source_for_table(table) else {
+        return Ok(());
+    };
+    for chunk in ids.chunks(BATCH_CHUNK_SIZE) {
+        let marks = placeholders(2, chunk.len());
+        let mut params = vec![TursoValue::Text(source.entity.to_string())];
+        params.extend(chunk.iter().map(|id| TursoValue::Text(id.clone())));
+        for index_table in ["search_docs", "search_terms"] {
+            conn.execute(
+                &format!(
+                    "DELETE FROM {} WHERE entity = ?1 AND entity_id IN ({})",
+                    index_table, marks
+                ),
+                params.clone(),
+            )
+            .await?;
+        }
+        let rows = query_maps(
+            conn,
+            &format!(
+                "SELECT id, data FROM {} WHERE deleted = 0 AND id IN ({})",
+                source.table,
+                placeholders(1, chunk.len())
+            ),
+            params[1..].to_vec(),
+        )
+        .await?;
+        index_rows(conn, source, &rows).await?;
+    }
+    Ok(())
+}
+
+/// [`reindex`] for a single row.
+pub async fn reindex_one(conn: &turso::Connection, table: &str, id: &str) -> Result<(), AppError> {
+    reindex(conn, table, &[id.to_string()]).await
+}
+
+/// Drop index entries for the rows of `table` matching `condition`; used
+/// before hard deletes that bypass [`reindex`].
+pub async fn remove_where(
+    conn: &turso::Connection,
+    table: &str,
+    condition: &str,
+    params: Vec<TursoValue>,
+) -> Result<(), AppError> {
+    let Some(source) = source_for_table(table) else {
+        return Ok(());
+    };
+    for index_table in ["search_docs", "search_terms"] {
+        conn.execute(
+            &format!(
+                "DELETE FROM {} WHERE entity = '{}' AND entity_id IN (SELECT id FROM {} WHERE {})",
+                index_table, source.entity, source.table, condition
+            ),
+            params.clone(),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Rebuild the index of one table from scratch. Returns the rows indexed.
+pub async fn rebuild_table(conn: &turso::Connection, table: &str) -> Result<usize, AppError> {
+    let Some(source) = source_for_table(table) else {
+        return Ok(0);
+    };
+    for index_table in ["search_docs", "search_terms"] {
+        conn.execute(
+            &format!("DELETE FROM {} WHERE entity = ?1", index_table),
+            (source.entity,),
+        )
+        .await?;
+    }
+    let rows = query_maps(
+        conn,
+        &format!("SELECT id, data FROM {} WHERE deleted = 0", source.table),
+        (),
+    )
+    .await?;
+    index_rows(conn, source, &rows).await
+}
+
+/// Rebuild the whole index (migration backfill and manual repair).
+pub async fn rebuild_index(conn: &turso::Connection) -> Result<usize, AppError> {
+    let mut total = 0;
+    for source in SEARCH_SOURCES {
+        total += rebuild_table(conn, source.table).await?;
+    }
+    Ok(total)
+}
+
+// ==================== 查询 ====================
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub id: String,
+    /// 主字段（名称/标题）内容
+    pub title: String,
+    /// 片段来源字段
+    pub field: String,
+    pub snippet: String,
+    /// 片段内的高亮区间 `[start, end)`，单位为 UTF-16 码元（即 JS 字符串下标）
+    pub highlights: Vec<[usize; 2]>,
+    pub score: i64,
+}
+
+type DocKey = (String, String);
+
+/// Ranked hits for `query`. Every query term must appear somewhere in the
+/// document; the score sums, per term, the weight of the best field it hit,
+/// plus a bonus for fields containing the query as a phrase.
+pub async fn search(
+    conn: &turso::Connection,
+    query: &str,
+    types: Option<&[String]>,
+    limit: usize,
+) -> Result<Vec<SearchHit>, AppError> {
+    let terms = query_terms(query);
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+    let entities: Vec<&str> = SEARCH_SOURCES
+        .iter()
+        .map(|s| s.entity)
+        .filter(|e| types.is_none_or(|t| t.iter().any(|x| x == e)))
+        .collect();
+    if entities.is_empty() {
+        return Ok(Vec::new());
+    }
+    let weights: HashMap<(&str, &str), i64> = SEARCH_SOURCES
+        .iter()
+        .flat_map(|s| s.fields.iter().map(move |(f, w)| ((s.entity, *f), *w)))
+        .collect();
+
+    // 逐词取候选并求交集，同时累加该词命中字段的最高权重
+    let mut scores: Option<HashMap<DocKey, i64>> = None;
+    for term in &terms {
+        let mut params = vec![TursoValue::Text(term.term.clone())];
+        let term_cond = if term.prefix {
+            params.push(TursoValue::Text(format!("{}\u{10FFFF}", term.term)));
+            "term >= ?1 AND term < ?2"
+        } else {
+            "term = ?1"
+        };
+        let start = params.len() + 1;
+        params.extend(entities.iter().map(|e| TursoValue::Text(e.to_string())));
+        let rows = query_maps(
+            conn,
+            &format!(
+                "SELECT DISTINCT entity, entity_id, field FROM search_terms WHERE {} AND entity IN ({})",
+                term_cond,
+                placeholders(start, entities.len())
+            ),
+            params,
+        )
+        .await?;
+        let mut best: HashMap<DocKey, i64> = HashMap::new();
+        for row in &rows {
+            let text = |k: &str| {
+                row.get(k)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string()
+            };
+            let (entity, id, field) = (text("entity"), text("entity_id"), text("field"));
+            let weight = weights
+                .get(&(entity.as_str(), field.as_str()))
+                .copied()
+                .unwrap_or(1);
+            let slot = best.entry((entity, id)).or_insert(0);
+            *slot = (*slot).max(weight);
+        }
+        scores = Some(match scores {
+            None => best,
+            Some(prev) => prev
+                .into_iter()
+                .filter_map(|(key, score)| best.get(&key).map(|w| (key, score + w)))
+                .collect(),
+        });
+        if scores.as_ref().is_some_and(|s| s.is_empty()) {
+            return Ok(Vec::new());
+        }
+    }
+    let scores = scores.unwrap_or_default();
+
+    // 读取候选文档内容，计算短语加分与片段
+    let needles = needles(query);
+    let phrase: Vec<char> = query.trim().to_lowercase().chars().collect();
+    let mut by_entity: HashMap<&str, Vec<String>> = HashMap::new();
+    for (entity, id) in scores.keys() {
+        if let Some(e) = entities.iter().find(|e| **e == entity) {
+            by_entity.entry(e).or_default().push(id.clone());
+        }
+    }
+    let mut hits = Vec::new();
+    for (entity, ids) in by_entity {
+        for chunk in ids.chunks(BATCH_CHUNK_SIZE) {
+            let mut params = vec![TursoValue::Text(entity.to_string())];
+            params.extend(chunk.iter().map(|id| TursoValue::Text(id.clone())));
+            let rows = query_maps(
+                conn,
+                &format!(
+                    "SELECT entity_id, field, weight, content FROM search_docs WHERE entity = ?1 AND entity_id IN ({})",
+                    placeholders(2, chunk.len())
+                ),
+                params,
+            )
+            .await?;
+            let mut docs: HashMap<String, Vec<(String, i64, String)>> = HashMap::new();
+            for row in rows {
+                let text = |k: &str| {
+                    row.get(k)
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("")
+                        .to_string()
+                };
+                let weight = row.get("weight").and_then(|v| v.as_i64()).unwrap_or(1);
+                docs.entry(text("entity_id")).or_default().push((
+                    text("field"),
+                    weight,
+                    text("content"),
+                ));
+            }
+            for (id, mut fields) in docs {
+                fields.sort_by_key(|(_, weight, _)| -weight);
+                let mut score = scores
+                    .get(&(entity.to_string(), id.clone()))
+                    .copied()
+                    .unwrap_or(0);
+                let mut best: Option<(&str, Snippet)> = None;
+                for (field, weight, content) in &fields {
+                    let lowered = lower_chars(content);
+                    if phrase.len() > 1 && find(&lowered, &phrase, 0).is_some() {
+                        score += weight;
+                    }
+                    let snippet = make_snippet(content, &lowered, &needles);
+                    // 优先取有高亮的最高权重字段
+                    let replace = match &best {
+                        None => true,
+                        Some((_, current)) => {
+                            current.highlights.is_empty() && !snippet.highlights.is_empty()
+                        }
+                    };
+                    if replace {
+                        best = Some((field.as_str(), snippet));
+                    }
+                }
+                let Some((field, snippet)) = best else {
+                    continue;
+                };
+                hits.push(SearchHit {
+                    kind: entity.to_string(),
+                    id: id.clone(),
+                    title: fields[0].2.clone(),
+                    field: field.to_string(),
+                    snippet: snippet.text,
+                    highlights: snippet.highlights,
+                    score,
+                });
+            }
+        }
+    }
+    let order = |kind: &str| {
+        SEARCH_SOURCES
+            .iter()
+            .position(|s| s.entity == kind)
+            .unwrap_or(usize::MAX)
+    };
+    hits.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| order(&a.kind).cmp(&order(&b.kind)))
+            .then_with(|| a.title.cmp(&b.title))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    hits.truncate(limit);
+    Ok(hits)
+}
+
+struct Snippet {
+    text: String,
+    highlights: Vec<[usize; 2]>,
+}
+
+// 逐字符小写，保证与原文字符位置一一对应
+fn lower_chars(text: &str) -> Vec<char> {
+    text.chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect()
+}
+
+fn find(haystack: &[char], needle: &[char], from: usize) -> Option<usize> {
+    if needle.is_empty() || haystack.len() < needle.len() {
+        return None;
+    }
+    (from..=haystack.len() - needle.len()).find(|&i| haystack[i..i + needle.len()] == *needle)
+}
+
+/// Cut a window of `content` around the first match and mark every match in it.
+fn make_snippet(content: &str, lowered: &[char], needles: &[Vec<char>]) -> Snippet {
+    let chars: Vec<char> = content.chars().collect();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for needle in needles {
+        let mut from = 0;
+        while let Some(at) = find(lowered, needle, from) {
+            ranges.push((at, at + needle.len()));
+            from = at + needle.len();
+        }
+    }
+    ranges.sort();
+    // 合并重叠区间
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    let (start, end) = if chars.len() <= SNIPPET_CHARS {
+        (0, chars.len())
+    } else {
+        let first = merged.first().map(|r| r.0).unwrap_or(0);
+        let start = first
+            .saturating_sub(SNIPPET_LEAD)
+            .min(chars.len() - SNIPPET_CHARS);
+        (start, start + SNIPPET_CHARS)
+    };
+    let mut text = String::new();
+    if start > 0 {
+        text.push('…');
+    }
+    let utf16_offset = |text: &String| text.encode_utf16().count();
+    let mut highlights = Vec::new();
+    let mut pos = start;
+    for &(s, e) in merged.iter().filter(|(s, e)| *e > start && *s < end) {
+        let (s, e) = (s.max(start), e.min(end));
+        text.extend(&chars[pos..s]);
+        let begin = utf16_offset(&text);
+        text.extend(&chars[s..e]);
+        highlights.push([begin, utf16_offset(&text)]);
+        pos = e;
+    }
+    text.extend(&chars[pos..end]);
+    if end < chars.len() {
+        text.push('…');
+    }
+    Snippet { text, highlights }
+}
+
+/// 全局搜索：照片/相册/云盘文件/待办/纪念日/资产，按相关度排序
+#[tauri::command]
+pub async fn db_search(
+    state: State<'_, TursoDb>,
+    query: String,
+    types: Option<Vec<String>>,
+    limit: Option<usize>,
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let hits = search(
+        &conn,
+        &query,
+        types.as_deref(),
+        limit.unwrap_or(DEFAULT_SEARCH_LIMIT),
+    )
+    .await?;
+    Ok(json!({ "code": 0, "data": hits }))
+}
+
+/// 重建搜索索引（用于修复）
+#[tauri::command]
+pub async fn db_search_rebuild(state: State<'_, TursoDb>) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let indexed = super::in_transaction(&conn, rebuild_index(&conn)).await?;
+    Ok(json!({ "code": 0, "data": { "indexed": indexed } }))
+}
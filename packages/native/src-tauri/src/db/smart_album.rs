@@ -0,0 +1,264 @@
+//! Rule-based smart albums.
+//!
+//! A smart album stores its rules under `data.smart` instead of linking
+//! photos through `photo_albums`:
+//!
+//! ```json
+//! { "match": "all", "rules": [
+//!     { "field": "type", "value": "video" },
+//!     { "field": "year", "value": 2023 },
+//!     { "field": "size", "op": "gt", "value": 20971520 },
+//!     { "field": "location", "value": { "latitude": 30.27, "longitude": 120.15, "radiusKm": 30 } }
+//! ] }
+//! ```
+//!
+//! Rules compile to a SQL condition over `photos`, so membership, counts and
+//! covers are always evaluated against the current library.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use turso::Value as TursoValue;
+
+use crate::error::AppError;
+
+// 每纬度约 111.32 km
+const KM_PER_DEGREE: f64 = 111.32;
+const MAX_RULES: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    #[default]
+    All,
+    Any,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaKind {
+    Image,
+    Video,
+    Live,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompareOp {
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+impl CompareOp {
+    fn sql(self) -> &'static str {
+        match self {
+            Self::Gt => ">",
+            Self::Gte => ">=",
+            Self::Lt => "<",
+            Self::Lte => "<=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeoCircle {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub radius_km: f64,
+}
+
+/// One condition of a smart album.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "field", rename_all = "camelCase")]
+pub enum SmartRule {
+    /// `image` / `video` by MIME type, `live` for live photos.
+    Type {
+        value: MediaKind,
+    },
+    Liked {
+        value: bool,
+    },
+    /// Calendar year of `taken_at` (UTC, same as the date filters).
+    Year {
+        value: i32,
+    },
+    /// Compare `taken_at` with an ISO date or timestamp.
+    TakenAt {
+        op: CompareOp,
+        value: String,
+    },
+    /// File size in bytes.
+    Size {
+        op: CompareOp,
+        value: i64,
+    },
+    /// Within `radiusKm` of a point; needs `data.gps` recorded at import.
+    Location {
+        value: GeoCircle,
+    },
+    /// Case-insensitive substring of the file name.
+    Name {
+        value: String,
+    },
+}
+
+impl SmartRule {
+    fn validate(&self) -> Result<(), AppError> {
+        let invalid = |msg: &str| {
+            Err(AppError::validation(format!(
+                "Invalid smart album rule: {}",
+                msg
+            )))
+        };
+        match self {
+            Self::Year { value } if !(1000..=9999).contains(value) => invalid("year out of range"),
+            Self::TakenAt { value, .. } if value.trim().is_empty() => invalid("empty date"),
+            Self::Size { value, .. } if *value < 0 => invalid("negative size"),
+            Self::Location { value } => {
+                let ok = (-90.0..=90.0).contains(&value.latitude)
+                    && (-180.0..=180.0).contains(&value.longitude)
+                    && value.radius_km > 0.0
+                    && value.radius_km.is_finite();
+                if ok {
+                    Ok(())
+                } else {
+                    invalid("bad location")
+                }
+            }
+            Self::Name { value } if value.trim().is_empty() => invalid("empty name"),
+            _ => Ok(()),
+        }
+    }
+
+    fn condition(&self, params: &mut Vec<TursoValue>) -> String {
+        let mut bind = |value: TursoValue| {
+            params.push(value);
+            format!("?{}", params.len())
+        };
+        match self {
+            Self::Type {
+                value: MediaKind::Image,
+            } => {
+                format!(
+                    "type LIKE {}",
+                    bind(TursoValue::Text("image/%".to_string()))
+                )
+            }
+            Self::Type {
+                value: MediaKind::Video,
+            } => {
+                format!(
+                    "type LIKE {}",
+                    bind(TursoValue::Text("video/%".to_string()))
+                )
+            }
+            Self::Type {
+                value: MediaKind::Live,
+            } => "COALESCE(json_extract(data, '$.isLive'), 0) = 1".to_string(),
+            Self::Liked { value } => {
+                format!("is_liked = {}", bind(TursoValue::Integer(*value as i64)))
+            }
+            Self::Year { value } => {
+                let start = bind(TursoValue::Text(format!("{:04}", value)));
+                let end = bind(TursoValue::Text(format!("{:04}", value + 1)));
+                format!("(taken_at >= {} AND taken_at < {})", start, end)
+            }
+            Self::TakenAt { op, value } => {
+                format!(
+                    "taken_at {} {}",
+                    op.sql(),
+                    bind(TursoValue::Text(value.trim().to_string()))
+                )
+            }
+            Self::Size { op, value } => format!(
+                "COALESCE(json_extract(data, '$.size'), 0) {} {}",
+                op.sql(),
+                bind(TursoValue::Integer(*value))
+            ),
+            Self::Location { value } => {
+                // 等距矩形近似：经度差按中心纬度缩放后与纬度差一起算平面距离
+                let lat = bind(TursoValue::Real(value.latitude));
+                let lng = bind(TursoValue::Real(value.longitude));
+                let scale = bind(TursoValue::Real(value.latitude.to_radians().cos()));
+                let radius = value.radius_km / KM_PER_DEGREE;
+                let limit = bind(TursoValue::Real(radius * radius));
+                format!(
+                    "((json_extract(data, '$.gps.latitude') - {lat}) * (json_extract(data, '$.gps.latitude') - {lat}) \
+                     + ((json_extract(data, '$.gps.longitude') - {lng}) * {scale}) * ((json_extract(data, '$.gps.longitude') - {lng}) * {scale}) <= {limit})",
+                    lat = lat,
+                    lng = lng,
+                    scale = scale,
+                    limit = limit
+                )
+            }
+            Self::Name { value } => format!(
+                "instr(lower(COALESCE(json_extract(data, '$.name'), '')), {}) > 0",
+                bind(TursoValue::Text(value.trim().to_lowercase()))
+            ),
+        }
+    }
+}
+
+/// Rules of a smart album, combined with AND (`all`) or OR (`any`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SmartRules {
+    #[serde(default, rename = "match")]
+    pub match_mode: MatchMode,
+    pub rules: Vec<SmartRule>,
+}
+
+impl SmartRules {
+    pub fn parse(value: &JsonValue) -> Result<Self, AppError> {
+        let rules: SmartRules = serde_json::from_value(value.clone())
+            .map_err(|e| AppError::validation(format!("Invalid smart album rule: {}", e)))?;
+        if rules.rules.is_empty() || rules.rules.len() > MAX_RULES {
+            return Err(AppError::validation(format!(
+                "Smart album needs 1 to {} rules",
+                MAX_RULES
+            )));
+        }
+        for rule in &rules.rules {
+            rule.validate()?;
+        }
+        Ok(rules)
+    }
+
+    /// Rules stored in album data; `None` for a regular album.
+    pub fn from_album_data(data: &JsonValue) -> Result<Option<Self>, AppError> {
+        match data.get("smart") {
+            None | Some(JsonValue::Null) => Ok(None),
+            Some(value) => Self::parse(value).map(Some),
+        }
+    }
+
+    /// SQL condition over `photos`; parameters continue after `params`.
+    pub fn condition(&self, params: &mut Vec<TursoValue>) -> String {
+        let joiner = match self.match_mode {
+            MatchMode::All => " AND ",
+            MatchMode::Any => " OR ",
+        };
+        let parts: Vec<String> = self.rules.iter().map(|r| r.condition(params)).collect();
+        format!("({})", parts.join(joiner))
+    }
+}
+
+/// Load the rules of `album_id`, or `None` when it is missing or not smart.
+pub async fn load_smart_rules(
+    conn: &turso::Connection,
+    album_id: &str,
+) -> Result<Option<SmartRules>, AppError> {
+    let mut rows = conn
+        .query("SELECT data FROM albums WHERE id = ?1", (album_id,))
+        .await?;
+    let Some(row) = rows.next().await? else {
+        return Ok(None);
+    };
+    let data = row
+        .get_value(0)?
+        .as_text()
+        .and_then(|v| serde_json::from_str::<JsonValue>(v).ok())
+        .unwrap_or(JsonValue::Null);
+    SmartRules::from_album_data(&data)
+}
@@ -1,44 +1,66 @@
+use std::sync::OnceLock;
+
 use serde_json::{json, Value as JsonValue};
 use tauri::State;
-use turso::Value as TursoValue;
 
-use super::TursoDb;
+use crate::error::AppError;
+use super::sync_engine::{self, HttpSyncRemote, LastWriterWins, SyncOptions};
+use super::{migration::MIGRATIONS, query_maps, schema_statements, TursoDb};
+
+/// Registry of syncable entities: every table in the schema (baseline or
+/// migrations) that carries a `sync_status` column, in creation order.
+/// Derived from the DDL so a new table becomes syncable without touching the
+/// sync commands.
+pub fn sync_tables() -> &'static [&'static str] {
+    static TABLES: OnceLock<Vec<&'static str>> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let mut tables = Vec::new();
+        let statements = schema_statements()
+            .iter()
+            .chain(MIGRATIONS.iter().flat_map(|m| m.statements.iter()));
+        for stmt in statements {
+            if let Some(table) = syncable_table(stmt) {
+                if !tables.contains(&table) {
+                    tables.push(table);
+                }
+            }
+        }
+        tables
+    })
+}
+
+/// Whether `entity_type` names a syncable table.
+pub fn is_sync_table(entity_type: &str) -> bool {
+    sync_tables().contains(&entity_type)
+}
+
+/// Table name if the statement creates a table with (or adds) a `sync_status` column.
+fn syncable_table(stmt: &'static str) -> Option<&'static str> {
+    let stmt = stmt.trim_start();
+    if let Some(rest) = stmt.strip_prefix("CREATE TABLE IF NOT EXISTS ") {
+        let (name, body) = rest.split_once('(')?;
+        return body.contains("sync_status").then(|| name.trim());
+    }
+    // 迁移中通过 ADD COLUMN 追加同步字段的表
+    let rest = stmt.strip_prefix("ALTER TABLE ")?;
+    let (name, column) = rest.split_once(" ADD COLUMN ")?;
+    column.trim_start().starts_with("sync_status ").then(|| name.trim())
+}
 
 /// Get all records with pending sync status across all tables
 #[tauri::command]
 pub async fn db_get_pending_sync(
     state: State<'_, TursoDb>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
-    let tables = [
-        "photos", "albums", "album_folders", "asset_categories", "assets",
-        "memorials", "families", "weights", "blood_pressures", "usage_records",
-    ];
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
 
     let mut result = serde_json::Map::new();
-    for table in tables {
+    for table in sync_tables() {
         let sql = format!(
             "SELECT id, sync_status, updated_at, data FROM {} WHERE sync_status IN ('local', 'pending')",
             table
         );
-        let mut rows = conn.query(&sql, ()).await.map_err(|e| e.to_string())?;
-        let mut items = Vec::new();
-        while let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-            let mut map = serde_json::Map::new();
-            for i in 0..4 {
-                if let Ok(val) = row.get_value(i as usize) {
-                    let key = match i {
-                        0 => "id",
-                        1 => "sync_status",
-                        2 => "updated_at",
-                        3 => "data",
-                        _ => continue,
-                    };
-                    map.insert(key.to_string(), super::turso_value_to_json(&val));
-                }
-            }
-            items.push(JsonValue::Object(map));
-        }
+        let items = query_maps(&conn, &sql, ()).await?;
         if !items.is_empty() {
             result.insert(table.to_string(), json!(items));
         }
@@ -54,15 +76,11 @@ pub async fn db_mark_synced(
     entity_type: String,
     entity_id: String,
     remote_id: Option<String>,
-) -> Result<(), String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<(), AppError> {
+    let conn = state.0.connect()?;
 
-    let valid_tables = [
-        "photos", "albums", "album_folders", "asset_categories", "assets",
-        "memorials", "families", "weights", "blood_pressures", "usage_records",
-    ];
-    if !valid_tables.contains(&entity_type.as_str()) {
-        return Err(format!("Invalid entity type: {}", entity_type));
+    if !is_sync_table(&entity_type) {
+        return Err(AppError::validation(format!("Invalid entity type: {}", entity_type)));
     }
 
     if let Some(rid) = remote_id {
@@ -70,13 +88,13 @@ pub async fn db_mark_synced(
             "UPDATE {} SET sync_status = 'synced', remote_id = ?1, updated_at = datetime('now') WHERE id = ?2",
             entity_type
         );
-        conn.execute(&sql, (rid, entity_id.clone())).await.map_err(|e| e.to_string())?;
+        conn.execute(&sql, (rid, entity_id.clone())).await?;
     } else {
         let sql = format!(
             "UPDATE {} SET sync_status = 'synced', updated_at = datetime('now') WHERE id = ?1",
             entity_type
         );
-        conn.execute(&sql, (entity_id.clone(),)).await.map_err(|e| e.to_string())?;
+        conn.execute(&sql, (entity_id.clone(),)).await?;
     }
 
     // Log to sync_log
@@ -84,8 +102,27 @@ pub async fn db_mark_synced(
         "INSERT INTO sync_log (entity_type, entity_id, action, synced_at, status) VALUES (?1, ?2, 'sync', datetime('now'), 'success')",
         (entity_type, entity_id),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
 
     Ok(())
 }
+
+/// Run a full two-way sync against the remote server: pull changes after the
+/// stored cursor, resolve conflicts (last writer wins), then push pending rows.
+#[tauri::command]
+pub async fn db_sync_run(
+    state: State<'_, TursoDb>,
+    server_url: String,
+    token: String,
+    batch_size: Option<usize>,
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let remote = HttpSyncRemote::new(&server_url, &token);
+    let mut options = SyncOptions::default();
+    if let Some(size) = batch_size {
+        options.push_batch_size = size;
+    }
+
+    let report = sync_engine::run_sync(&conn, &remote, &LastWriterWins, &options).await?;
+    Ok(json!({ "code": 0, "data": report }))
+}
@@ -0,0 +1,797 @@
+use std::future::Future;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value as JsonValue};
+use turso::Value as TursoValue;
+
+use crate::error::AppError;
+use super::photo::{link_legacy_album_ids, refresh_taken_at};
+use super::search;
+use super::sync::sync_tables;
+use super::{in_transaction, json_to_turso_value, merge_row, new_id, query_maps, table_columns};
+
+/// Columns managed by the sync engine itself; everything else is payload.
+const META_COLUMNS: &[&str] = &["id", "remote_id", "sync_status", "updated_at", "deleted", "pushed"];
+
+const PULL_CURSOR_KEY: &str = "pull_cursor";
+
+/// One row travelling between the device and the remote server.
+/// `data` is the merged document (fixed columns + `data` JSON spread), the same
+/// shape the frontend sees, minus the sync metadata.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncChange {
+    pub entity_type: String,
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub remote_id: Option<String>,
+    pub updated_at: String,
+    #[serde(default)]
+    pub deleted: bool,
+    #[serde(default)]
+    pub data: Map<String, JsonValue>,
+}
+
+/// Server response for a single pushed change.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PushAck {
+    pub entity_type: String,
+    pub id: String,
+    #[serde(default)]
+    pub remote_id: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// A page of remote changes after a cursor.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PullPage {
+    #[serde(default)]
+    pub changes: Vec<SyncChange>,
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub has_more: bool,
+}
+
+/// Transport to the remote side. The app uses [`HttpSyncRemote`]; tests plug in
+/// an in-memory stand-in.
+pub trait SyncRemote: Send + Sync {
+    fn push(
+        &self,
+        changes: Vec<SyncChange>,
+    ) -> impl Future<Output = Result<Vec<PushAck>, AppError>> + Send;
+
+    fn pull(
+        &self,
+        cursor: Option<String>,
+        limit: usize,
+    ) -> impl Future<Output = Result<PullPage, AppError>> + Send;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    KeepLocal,
+    TakeRemote,
+}
+
+/// Decides who wins when a remote change hits a row that still has unpushed local edits.
+pub trait ConflictPolicy: Send + Sync {
+    fn resolve(&self, local: &SyncChange, remote: &SyncChange) -> Resolution;
+}
+
+/// Newer `updated_at` wins; ties (or unparsable timestamps) go to the remote.
+pub struct LastWriterWins;
+
+impl ConflictPolicy for LastWriterWins {
+    fn resolve(&self, local: &SyncChange, remote: &SyncChange) -> Resolution {
+        match (
+            parse_timestamp(&local.updated_at),
+            parse_timestamp(&remote.updated_at),
+        ) {
+            (Some(l), Some(r)) if l > r => Resolution::KeepLocal,
+            _ => Resolution::TakeRemote,
+        }
+    }
+}
+
+pub struct SyncOptions {
+    pub push_batch_size: usize,
+    pub pull_page_size: usize,
+    /// Pushed tombstones older than this many days are garbage-collected.
+    pub tombstone_retention_days: u32,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        SyncOptions {
+            push_batch_size: 100,
+            pull_page_size: 200,
+            tombstone_retention_days: 30,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    pub batch: String,
+    pub pulled: usize,
+    pub conflicts: usize,
+    pub pushed: usize,
+    pub push_failed: usize,
+    pub cursor: Option<String>,
+    pub tombstones_purged: usize,
+}
+
+/// Full round: pull remote changes first (so conflicts are resolved locally),
+/// then push whatever is still pending, including tombstones. Every step lands
+/// in `sync_log` under one `sync_batch` id. Once the round is confirmed,
+/// tombstones past the retention window are dropped.
+pub async fn run_sync<R: SyncRemote>(
+    conn: &turso::Connection,
+    remote: &R,
+    policy: &dyn ConflictPolicy,
+    options: &SyncOptions,
+) -> Result<SyncReport, AppError> {
+    let mut report = SyncReport {
+        batch: new_id(),
+        ..Default::default()
+    };
+    pull_changes(conn, remote, policy, options, &mut report).await?;
+    push_pending(conn, remote, options, &mut report).await?;
+    report.tombstones_purged =
+        purge_expired_tombstones(conn, options.tombstone_retention_days).await?;
+    Ok(report)
+}
+
+/// Pull every page after the stored cursor. Each page is applied together with
+/// its cursor in one transaction, so an interrupted pull resumes cleanly.
+pub async fn pull_changes<R: SyncRemote>(
+    conn: &turso::Connection,
+    remote: &R,
+    policy: &dyn ConflictPolicy,
+    options: &SyncOptions,
+    report: &mut SyncReport,
+) -> Result<(), AppError> {
+    let mut cursor = get_sync_state(conn, PULL_CURSOR_KEY).await?;
+    loop {
+        let page = remote.pull(cursor.clone(), options.pull_page_size).await?;
+
+        conn.execute("BEGIN", ()).await?;
+        let applied = apply_page(conn, &page, policy, &report.batch).await;
+        match applied {
+            Ok((pulled, conflicts)) => {
+                conn.execute("COMMIT", ())
+                    .await?;
+                report.pulled += pulled;
+                report.conflicts += conflicts;
+            }
+            Err(e) => {
+                let _ = conn.execute("ROLLBACK", ()).await;
+                return Err(e);
+            }
+        }
+
+        if !page.has_more || page.changes.is_empty() {
+            if page.cursor.is_some() {
+                cursor = page.cursor.clone();
+            }
+            break;
+        }
+        // 声明还有数据却没有推进游标：再请求只会拿到同一页
+        if page.cursor.is_none() || page.cursor == cursor {
+            return Err(AppError::network(format!(
+                "Sync server reported more changes without advancing the cursor ({})",
+                cursor.as_deref().unwrap_or("none")
+            )));
+        }
+        cursor = page.cursor.clone();
+    }
+    report.cursor = cursor;
+    Ok(())
+}
+
+async fn apply_page(
+    conn: &turso::Connection,
+    page: &PullPage,
+    policy: &dyn ConflictPolicy,
+    batch: &str,
+) -> Result<(usize, usize), AppError> {
+    let mut pulled = 0;
+    let mut conflicts = 0;
+    for change in &page.changes {
+        match apply_remote_change(conn, change, policy).await? {
+            Applied::Written => {
+                pulled += 1;
+                log_sync(conn, change, "pull", batch, "success", None).await?;
+            }
+            Applied::KeptLocal => {
+                conflicts += 1;
+                log_sync(conn, change, "pull", batch, "conflict", Some("kept local")).await?;
+            }
+            Applied::Skipped => {}
+        }
+    }
+    if let Some(cursor) = &page.cursor {
+        set_sync_state(conn, PULL_CURSOR_KEY, cursor).await?;
+    }
+    Ok((pulled, conflicts))
+}
+
+/// Outcome of applying a single remote change.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Applied {
+    Written,
+    KeptLocal,
+    Skipped,
+}
+
+/// Apply one remote change to its table, honouring the conflict policy when
+/// the local row has unpushed edits.
+pub async fn apply_remote_change(
+    conn: &turso::Connection,
+    change: &SyncChange,
+    policy: &dyn ConflictPolicy,
+) -> Result<Applied, AppError> {
+    let table = sync_table(&change.entity_type)?;
+    let remote_id = change
+        .remote_id
+        .clone()
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| AppError::validation(format!("Remote change for {} is missing remote_id", table)))?;
+
+    let local = find_local(conn, table, &remote_id, &change.id).await?;
+    let local_id = match &local {
+        Some((local_change, status)) => {
+            if status != "synced" && policy.resolve(local_change, change) == Resolution::KeepLocal {
+                // 本地胜出：仅记录 remote_id，保持 pending 等待下次推送
+                conn.execute(
+                    &format!("UPDATE {} SET remote_id = ?1 WHERE id = ?2", table),
+                    (remote_id, local_change.id.clone()),
+                )
+                .await?;
+                return Ok(Applied::KeptLocal);
+            }
+            local_change.id.clone()
+        }
+        None => {
+            // 远端删除但本地没有，或本地已彻底删除：不复活
+            if change.deleted || has_tombstone(conn, table, &remote_id, &change.id).await? {
+                return Ok(Applied::Skipped);
+            }
+            if change.id.is_empty() {
+                new_id()
+            } else {
+                change.id.clone()
+            }
+        }
+    };
+
+    let columns = table_columns(conn, table).await?;
+    let mut names: Vec<String> = Vec::new();
+    let mut params: Vec<TursoValue> = Vec::new();
+    let mut data = Map::new();
+    for (key, value) in &change.data {
+        if META_COLUMNS.contains(&key.as_str()) || key == "_id" || key == "data" {
+            continue;
+        }
+        if columns.iter().any(|c| c == key) {
+            names.push(key.clone());
+            params.push(json_to_turso_value(value));
+        } else {
+            data.insert(key.clone(), value.clone());
+        }
+    }
+    names.extend(
+        ["data", "deleted", "updated_at", "remote_id", "sync_status", "pushed"]
+            .iter()
+            .map(|v| v.to_string()),
+    );
+    let data = JsonValue::Object(data).to_string();
+    params.push(TursoValue::Text(data.clone()));
+    params.push(TursoValue::Integer(if change.deleted { 1 } else { 0 }));
+    params.push(TursoValue::Text(normalize_timestamp(&change.updated_at)));
+    params.push(TursoValue::Text(remote_id));
+    params.push(TursoValue::Text("synced".to_string()));
+    // 来自远端的行远端自然已知，之后彻底删除需要墓碑
+    params.push(TursoValue::Integer(1));
+
+    if local.is_some() {
+        let sets = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| format!("{} = ?{}", name, i + 1))
+            .collect::<Vec<_>>()
+            .join(", ");
+        params.push(TursoValue::Text(local_id.clone()));
+        let sql = format!("UPDATE {} SET {} WHERE id = ?{}", table, sets, params.len());
+        conn.execute(&sql, params)
+            .await?;
+    } else {
+        names.push("id".to_string());
+        params.push(TursoValue::Text(local_id.clone()));
+        let placeholders = (1..=params.len())
+            .map(|i| format!("?{}", i))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sql = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            table,
+            names.join(", "),
+            placeholders
+        );
+        conn.execute(&sql, params)
+            .await?;
+    }
+    // 远端照片可能仍只带旧的 data.albumId
+    if table == "photos" {
+        link_legacy_album_ids(conn, &local_id, &data).await?;
+        refresh_taken_at(conn, &local_id).await?;
+    }
+    search::reindex_one(conn, table, &local_id).await?;
+    Ok(Applied::Written)
+}
+
+/// Push all pending rows and then all unpushed tombstones in batches, marking
+/// the acknowledged ones synced. A row edited again while its batch was in
+/// flight stays pending.
+pub async fn push_pending<R: SyncRemote>(
+    conn: &turso::Connection,
+    remote: &R,
+    options: &SyncOptions,
+    report: &mut SyncReport,
+) -> Result<(), AppError> {
+    let pending = collect_pending(conn).await?;
+    push_batches(conn, remote, &pending, false, options, report).await?;
+    let tombstones = collect_tombstones(conn).await?;
+    push_batches(conn, remote, &tombstones, true, options, report).await
+}
+
+async fn push_batches<R: SyncRemote>(
+    conn: &turso::Connection,
+    remote: &R,
+    changes: &[SyncChange],
+    tombstones: bool,
+    options: &SyncOptions,
+    report: &mut SyncReport,
+) -> Result<(), AppError> {
+    for batch in changes.chunks(options.push_batch_size.max(1)) {
+        let acks = remote.push(batch.to_vec()).await?;
+        for ack in acks {
+            let Some(change) = batch
+                .iter()
+                .find(|c| c.id == ack.id && c.entity_type == ack.entity_type)
+            else {
+                continue;
+            };
+            if let Some(err) = ack.error.as_deref() {
+                report.push_failed += 1;
+                log_sync(conn, change, "push", &report.batch, "failed", Some(err)).await?;
+                continue;
+            }
+            if tombstones {
+                mark_tombstone_pushed(conn, change).await?;
+                report.pushed += 1;
+                log_sync(conn, change, "push", &report.batch, "success", None).await?;
+                continue;
+            }
+            mark_pushed(conn, change, ack.remote_id.as_deref()).await?;
+            report.pushed += 1;
+            log_sync(conn, change, "push", &report.batch, "success", None).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Record an acknowledged push. The row is marked synced only when it still
+/// holds exactly what was pushed; `updated_at` alone has one-second
+/// resolution, so an edit in the same second as the push would be lost.
+async fn mark_pushed(
+    conn: &turso::Connection,
+    change: &SyncChange,
+    remote_id: Option<&str>,
+) -> Result<(), AppError> {
+    let table = sync_table(&change.entity_type)?;
+    in_transaction(conn, async {
+        let current = query_maps(
+            conn,
+            &format!("SELECT * FROM {} WHERE id = ?1", table),
+            (change.id.as_str(),),
+        )
+        .await?
+        .pop()
+        .map(|row| row_to_change(table, row));
+        let unchanged = current.is_some_and(|row| {
+            row.updated_at == change.updated_at
+                && row.deleted == change.deleted
+                && row.data == change.data
+        });
+        let status = if unchanged { ", sync_status = 'synced'" } else { "" };
+        conn.execute(
+            &format!(
+                "UPDATE {} SET pushed = 1, remote_id = COALESCE(?1, remote_id){} WHERE id = ?2",
+                table, status
+            ),
+            (remote_id.filter(|v| !v.is_empty()), change.id.as_str()),
+        )
+        .await?;
+        Ok(())
+    })
+    .await
+}
+
+/// Every row with `sync_status IN ('local', 'pending')`, as push payloads.
+/// Commands that change a row's data set it back to `pending`.
+///
+/// `photo_albums` links are not synced; only the photo's own `data.albumId`
+/// goes along with the photo row.
+pub async fn collect_pending(conn: &turso::Connection) -> Result<Vec<SyncChange>, AppError> {
+    let mut changes = Vec::new();
+    for table in sync_tables() {
+        let sql = format!(
+            "SELECT * FROM {} WHERE sync_status IN ('local', 'pending') ORDER BY updated_at ASC",
+            table
+        );
+        for row in query_maps(conn, &sql, ()).await? {
+            changes.push(row_to_change(table, row));
+        }
+    }
+    Ok(changes)
+}
+
+fn row_to_change(table: &str, row: Map<String, JsonValue>) -> SyncChange {
+    let merged = merge_row(&JsonValue::Object(row));
+    let mut data = merged.as_object().cloned().unwrap_or_default();
+    let text = |v: Option<JsonValue>| v.and_then(|v| v.as_str().map(|s| s.to_string()));
+    let id = text(data.remove("id")).unwrap_or_default();
+    let remote_id = text(data.remove("remote_id")).filter(|v| !v.is_empty());
+    let updated_at = text(data.remove("updated_at")).unwrap_or_default();
+    let deleted = data.remove("deleted").and_then(|v| v.as_i64()).unwrap_or(0) != 0;
+    data.remove("sync_status");
+    data.remove("pushed");
+    data.remove("_id");
+    SyncChange {
+        entity_type: table.to_string(),
+        id,
+        remote_id,
+        updated_at,
+        deleted,
+        data,
+    }
+}
+
+async fn find_local(
+    conn: &turso::Connection,
+    table: &str,
+    remote_id: &str,
+    id: &str,
+) -> Result<Option<(SyncChange, String)>, AppError> {
+    let sql = format!(
+        "SELECT * FROM {} WHERE remote_id = ?1 OR id = ?2 ORDER BY (remote_id = ?1) DESC LIMIT 1",
+        table
+    );
+    let mut rows = query_maps(conn, &sql, (remote_id.to_string(), id.to_string())).await?;
+    Ok(rows.pop().map(|row| {
+        let status = row
+            .get("sync_status")
+            .and_then(|v| v.as_str())
+            .unwrap_or("local")
+            .to_string();
+        (row_to_change(table, row), status)
+    }))
+}
+
+// ==================== Tombstones ====================
+
+/// Record tombstones for rows about to be hard-deleted from `table`. Call this
+/// in the same transaction as the `DELETE` with the same condition. Only rows
+/// the remote knows about need one: rows pushed at least once (`pushed`, set
+/// on every ack even when it carried no `remote_id`) or rows that came from
+/// the remote. A never-pushed row needs none, even when a soft delete has
+/// already marked it `pending`.
+pub async fn record_tombstones(
+    conn: &turso::Connection,
+    table: &str,
+    condition: &str,
+    params: Vec<TursoValue>,
+) -> Result<(), AppError> {
+    let table = sync_table(table)?;
+    let sql = format!(
+        "INSERT INTO tombstones (entity_type, entity_id, remote_id) SELECT '{}', id, remote_id FROM {} WHERE ({}) AND (pushed = 1 OR remote_id IS NOT NULL)",
+        table, table, condition
+    );
+    conn.execute(&sql, params)
+        .await?;
+    Ok(())
+}
+
+/// Migration step: add the `pushed` marker to every sync table. Rows already
+/// synced or carrying a `remote_id` count as pushed.
+pub async fn add_pushed_column(conn: &turso::Connection) -> Result<(), AppError> {
+    for table in sync_tables() {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN pushed INTEGER DEFAULT 0", table),
+            (),
+        )
+        .await?;
+        conn.execute(
+            &format!(
+                "UPDATE {} SET pushed = 1 WHERE sync_status = 'synced' OR remote_id IS NOT NULL",
+                table
+            ),
+            (),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Hard-delete the rows of `table` matching `condition`, writing their
+/// tombstones in the same transaction.
+pub async fn purge_with_tombstones(
+    conn: &turso::Connection,
+    table: &str,
+    condition: &str,
+    params: Vec<TursoValue>,
+) -> Result<(), AppError> {
+    in_transaction(conn, delete_with_tombstones(conn, table, condition, params)).await
+}
+
+/// [`purge_with_tombstones`] without its own transaction, for callers that
+/// clean up related rows in the same one.
+pub async fn delete_with_tombstones(
+    conn: &turso::Connection,
+    table: &str,
+    condition: &str,
+    params: Vec<TursoValue>,
+) -> Result<(), AppError> {
+    record_tombstones(conn, table, condition, params.clone()).await?;
+    search::remove_where(conn, table, condition, params.clone()).await?;
+    conn.execute(
+        &format!("DELETE FROM {} WHERE {}", table, condition),
+        params,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Tombstones not yet acknowledged by the remote, as deleted push payloads.
+pub async fn collect_tombstones(conn: &turso::Connection) -> Result<Vec<SyncChange>, AppError> {
+    let rows = query_maps(
+        conn,
+        "SELECT entity_type, entity_id, remote_id, deleted_at FROM tombstones WHERE pushed_at IS NULL ORDER BY id ASC",
+        (),
+    )
+    .await?;
+    let text = |row: &Map<String, JsonValue>, key: &str| {
+        row.get(key).and_then(|v| v.as_str()).map(|v| v.to_string())
+    };
+    Ok(rows
+        .iter()
+        .map(|row| SyncChange {
+            entity_type: text(row, "entity_type").unwrap_or_default(),
+            id: text(row, "entity_id").unwrap_or_default(),
+            remote_id: text(row, "remote_id").filter(|v| !v.is_empty()),
+            updated_at: text(row, "deleted_at").unwrap_or_default(),
+            deleted: true,
+            data: Map::new(),
+        })
+        .collect())
+}
+
+async fn mark_tombstone_pushed(
+    conn: &turso::Connection,
+    change: &SyncChange,
+) -> Result<(), AppError> {
+    conn.execute(
+        "UPDATE tombstones SET pushed_at = datetime('now') WHERE entity_type = ?1 AND entity_id = ?2 AND pushed_at IS NULL",
+        (change.entity_type.clone(), change.id.clone()),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn has_tombstone(
+    conn: &turso::Connection,
+    table: &str,
+    remote_id: &str,
+    id: &str,
+) -> Result<bool, AppError> {
+    let mut rows = conn
+        .query(
+            "SELECT 1 FROM tombstones WHERE entity_type = ?1 AND (remote_id = ?2 OR entity_id = ?3) LIMIT 1",
+            (table, remote_id, id),
+        )
+        .await?;
+    Ok(rows.next().await?.is_some())
+}
+
+/// Drop tombstones the remote has acknowledged once they are older than
+/// `retention_days`. Returns how many were removed.
+pub async fn purge_expired_tombstones(
+    conn: &turso::Connection,
+    retention_days: u32,
+) -> Result<usize, AppError> {
+    let removed = conn
+        .execute(
+            "DELETE FROM tombstones WHERE pushed_at IS NOT NULL AND deleted_at < datetime('now', ?1)",
+            (format!("-{} days", retention_days),),
+        )
+        .await?;
+    Ok(removed as usize)
+}
+
+async fn log_sync(
+    conn: &turso::Connection,
+    change: &SyncChange,
+    action: &str,
+    batch: &str,
+    status: &str,
+    error: Option<&str>,
+) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT INTO sync_log (entity_type, entity_id, action, synced_at, sync_batch, status, error) VALUES (?1, ?2, ?3, datetime('now'), ?4, ?5, ?6)",
+        vec![
+            TursoValue::Text(change.entity_type.clone()),
+            TursoValue::Text(if change.id.is_empty() {
+                change.remote_id.clone().unwrap_or_default()
+            } else {
+                change.id.clone()
+            }),
+            TursoValue::Text(action.to_string()),
+            TursoValue::Text(batch.to_string()),
+            TursoValue::Text(status.to_string()),
+            error
+                .map(|e| TursoValue::Text(e.to_string()))
+                .unwrap_or(TursoValue::Null),
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+pub async fn get_sync_state(conn: &turso::Connection, key: &str) -> Result<Option<String>, AppError> {
+    let mut rows = conn
+        .query("SELECT value FROM sync_state WHERE key = ?1", (key,))
+        .await?;
+    if let Some(row) = rows.next().await? {
+        let val = row.get_value(0)?;
+        Ok(val.as_text().map(|v| v.to_string()))
+    } else {
+        Ok(None)
+    }
+}
+
+pub async fn set_sync_state(
+    conn: &turso::Connection,
+    key: &str,
+    value: &str,
+) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT INTO sync_state (key, value, updated_at) VALUES (?1, ?2, datetime('now'))
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+        (key, value),
+    )
+    .await?;
+    Ok(())
+}
+
+fn sync_table(entity_type: &str) -> Result<&'static str, AppError> {
+    sync_tables()
+        .iter()
+        .copied()
+        .find(|t| *t == entity_type)
+        .ok_or_else(|| AppError::validation(format!("Invalid entity type: {}", entity_type)))
+}
+
+/// Parse the timestamp flavours we meet: SQLite `datetime('now')`, RFC 3339
+/// from the server, and epoch seconds / milliseconds.
+pub fn parse_timestamp(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    if let Ok(n) = value.parse::<i64>() {
+        // 13 位视为毫秒，10 位视为秒
+        return if n > 100_000_000_000 {
+            chrono::DateTime::from_timestamp_millis(n)
+        } else {
+            chrono::DateTime::from_timestamp(n, 0)
+        };
+    }
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Some(dt.with_timezone(&chrono::Utc));
+    }
+    chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|dt| dt.and_utc())
+}
+
+/// Store remote timestamps in the same format as `datetime('now')` so local
+/// comparisons stay consistent.
+fn normalize_timestamp(value: &str) -> String {
+    parse_timestamp(value)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| value.to_string())
+}
+
+// ==================== HTTP remote ====================
+
+/// Remote server transport. Expects the server's `{ code, data }` envelope:
+/// - `POST {base}/sync/push` with `{ changes }` → `{ acks }`
+/// - `GET {base}/sync/pull?cursor=&limit=` → `{ changes, cursor, hasMore }`
+pub struct HttpSyncRemote {
+    client: reqwest::Client,
+    base_url: String,
+    token: String,
+}
+
+impl HttpSyncRemote {
+    pub fn new(base_url: &str, token: &str) -> Self {
+        HttpSyncRemote {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<JsonValue, AppError> {
+        let resp = request
+            .header("Authorization", format!("Bearer {}", self.token))
+            .send()
+            .await
+            .map_err(|e| AppError::network(format!("Sync request failed: {}", e)))?;
+        if !resp.status().is_success() {
+            return Err(AppError::network(format!(
+                "Sync request failed with status: {}",
+                resp.status()
+            ))
+            .with_details(json!({ "status": resp.status().as_u16() })));
+        }
+        let text = resp.text().await?;
+        let body: JsonValue = serde_json::from_str(&text)?;
+        if body.get("code").and_then(|v| v.as_i64()).unwrap_or(0) != 0 {
+            let message = body
+                .get("message")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown error");
+            return Err(AppError::network(format!("Sync rejected by server: {}", message)));
+        }
+        Ok(body.get("data").cloned().unwrap_or(JsonValue::Null))
+    }
+}
+
+impl SyncRemote for HttpSyncRemote {
+    async fn push(&self, changes: Vec<SyncChange>) -> Result<Vec<PushAck>, AppError> {
+        let body = json!({ "changes": changes }).to_string();
+        let data = self
+            .send(
+                self.client
+                    .post(format!("{}/sync/push", self.base_url))
+                    .header("Content-Type", "application/json")
+                    .body(body),
+            )
+            .await?;
+        Ok(serde_json::from_value(
+            data.get("acks").cloned().unwrap_or(json!([])),
+        )?)
+    }
+
+    async fn pull(&self, cursor: Option<String>, limit: usize) -> Result<PullPage, AppError> {
+        let mut url = reqwest::Url::parse(&format!("{}/sync/pull", self.base_url))
+            .map_err(|e| AppError::validation(format!("Invalid sync server URL: {}", e)))?;
+        {
+            let mut query = url.query_pairs_mut();
+            if let Some(c) = &cursor {
+                query.append_pair("cursor", c);
+            }
+            query.append_pair("limit", &limit.to_string());
+        }
+        let data = self.send(self.client.get(url)).await?;
+        serde_json::from_value(data).map_err(AppError::from)
+    }
+}
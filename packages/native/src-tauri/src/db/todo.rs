@@ -2,7 +2,10 @@ use serde_json::{json, Value as JsonValue};
 use tauri::State;
 use turso::Value as TursoValue;
 
-use super::{merge_row, new_id, TursoDb};
+use crate::error::AppError;
+use super::model::{query_model, query_models, Model, Todo};
+use super::search::reindex_one;
+use super::{new_id, TursoDb};
 
 fn normalize_todo(mut val: JsonValue) -> JsonValue {
     if let Some(obj) = val.as_object_mut() {
@@ -27,25 +30,23 @@ fn normalize_todo(mut val: JsonValue) -> JsonValue {
     val
 }
 
-fn merge_todo_row(row: &JsonValue) -> JsonValue {
-    normalize_todo(merge_row(row))
+fn merge_todo_row(row: &Todo) -> JsonValue {
+    normalize_todo(row.to_json())
 }
 
 #[tauri::command]
-pub async fn db_todo_list(state: State<'_, TursoDb>) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
-    let mut rows = conn
-        .query(
-            "SELECT * FROM todos WHERE deleted = 0 ORDER BY completed ASC, updated_at DESC",
-            (),
-        )
-        .await
-        .map_err(|e| e.to_string())?;
+pub async fn db_todo_list(state: State<'_, TursoDb>) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
+    let rows = query_models::<Todo>(
+        &conn,
+        "SELECT * FROM todos WHERE deleted = 0 ORDER BY completed ASC, updated_at DESC",
+        (),
+    )
+    .await?;
 
     let mut items = Vec::new();
-    while let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        items.push(merge_todo_row(&val));
+    for row in rows {
+        items.push(merge_todo_row(&row));
     }
 
     Ok(json!({ "code": 0, "data": items }))
@@ -58,15 +59,15 @@ pub async fn db_todo_create(
     note: Option<String>,
     quadrant: Option<i64>,
     due_date: Option<String>,
-) -> Result<JsonValue, String> {
+) -> Result<JsonValue, AppError> {
     if title.trim().is_empty() {
-        return Err("title is required".to_string());
+        return Err(AppError::validation("title is required"));
     }
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+    let conn = state.0.connect()?;
     let id = new_id();
     let quadrant_value = quadrant.unwrap_or(4);
     if !(1..=4).contains(&quadrant_value) {
-        return Err("quadrant is invalid".to_string());
+        return Err(AppError::validation("quadrant is invalid"));
     }
 
     let now = chrono::Utc::now().to_rfc3339();
@@ -87,18 +88,14 @@ pub async fn db_todo_create(
             TursoValue::Text(data),
         ],
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
+    reindex_one(&conn, "todos", &id).await?;
 
-    let mut rows = conn
-        .query("SELECT * FROM todos WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        Ok(json!({ "code": 0, "data": merge_todo_row(&val) }))
+    let row = query_model::<Todo>(&conn, "SELECT * FROM todos WHERE id = ?1", (id,)).await?;
+    if let Some(row) = row {
+        Ok(json!({ "code": 0, "data": merge_todo_row(&row) }))
     } else {
-        Err("Failed to create todo".to_string())
+        Err(AppError::storage("Failed to create todo"))
     }
 }
 
@@ -110,25 +107,23 @@ pub async fn db_todo_update(
     note: Option<String>,
     quadrant: Option<i64>,
     due_date: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
 
     // 读取现有 data 合并
     let mut rows = conn
         .query("SELECT data FROM todos WHERE id = ?1 AND deleted = 0", (id.clone(),))
-        .await
-        .map_err(|e| e.to_string())?;
-    let existing = match rows.next().await.map_err(|e| e.to_string())? {
+        .await?;
+    let existing = match rows.next().await? {
         Some(row) => {
             let data_str = row
-                .get_value(0)
-                .map_err(|e| e.to_string())?
+                .get_value(0)?
                 .as_text()
                 .map_or("{}", |v| v)
                 .to_string();
             serde_json::from_str::<JsonValue>(&data_str).unwrap_or(json!({}))
         }
-        None => return Err("Todo not found".to_string()),
+        None => return Err(AppError::not_found("Todo not found")),
     };
 
     let mut merged = existing;
@@ -146,68 +141,60 @@ pub async fn db_todo_update(
 
     if let Some(q) = quadrant {
         if !(1..=4).contains(&q) {
-            return Err("quadrant is invalid".to_string());
+            return Err(AppError::validation("quadrant is invalid"));
         }
         conn.execute(
-            "UPDATE todos SET quadrant = ?1, data = ?2, updated_at = datetime('now') WHERE id = ?3",
+            "UPDATE todos SET quadrant = ?1, data = ?2, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?3",
             vec![
                 TursoValue::Integer(q),
                 TursoValue::Text(merged.to_string()),
                 TursoValue::Text(id.clone()),
             ],
         )
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
     } else {
         conn.execute(
-            "UPDATE todos SET data = ?1, updated_at = datetime('now') WHERE id = ?2",
+            "UPDATE todos SET data = ?1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?2",
             (merged.to_string(), id.clone()),
         )
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
     }
+    reindex_one(&conn, "todos", &id).await?;
 
-    let mut rows = conn
-        .query("SELECT * FROM todos WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        Ok(json!({ "code": 0, "data": merge_todo_row(&val) }))
+    let row = query_model::<Todo>(&conn, "SELECT * FROM todos WHERE id = ?1", (id,)).await?;
+    if let Some(row) = row {
+        Ok(json!({ "code": 0, "data": merge_todo_row(&row) }))
     } else {
-        Err("Todo not found".to_string())
+        Err(AppError::not_found("Todo not found"))
     }
 }
 
 #[tauri::command]
-pub async fn db_todo_toggle(state: State<'_, TursoDb>, id: String) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+pub async fn db_todo_toggle(state: State<'_, TursoDb>, id: String) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
 
     let mut rows = conn
         .query(
             "SELECT completed, data FROM todos WHERE id = ?1 AND deleted = 0",
             (id.clone(),),
         )
-        .await
-        .map_err(|e| e.to_string())?;
-    let (completed, data_str) = match rows.next().await.map_err(|e| e.to_string())? {
+        .await?;
+    let (completed, data_str) = match rows.next().await? {
         Some(row) => {
             let completed = row
-                .get_value(0)
-                .map_err(|e| e.to_string())?
+                .get_value(0)?
                 .as_integer()
                 .copied()
                 .unwrap_or(0)
                 != 0;
             let data_str = row
-                .get_value(1)
-                .map_err(|e| e.to_string())?
+                .get_value(1)?
                 .as_text()
                 .map_or("{}", |v| v)
                 .to_string();
             (completed, data_str)
         }
-        None => return Err("Todo not found".to_string()),
+        None => return Err(AppError::not_found("Todo not found")),
     };
 
     let new_completed = !completed;
@@ -222,56 +209,31 @@ pub async fn db_todo_toggle(state: State<'_, TursoDb>, id: String) -> Result<Jso
     }
 
     conn.execute(
-        "UPDATE todos SET completed = ?1, data = ?2, updated_at = datetime('now') WHERE id = ?3",
+        "UPDATE todos SET completed = ?1, data = ?2, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?3",
         vec![
             TursoValue::Integer(if new_completed { 1 } else { 0 }),
             TursoValue::Text(data.to_string()),
             TursoValue::Text(id.clone()),
         ],
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
 
-    let mut rows = conn
-        .query("SELECT * FROM todos WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        Ok(json!({ "code": 0, "data": merge_todo_row(&val) }))
+    let row = query_model::<Todo>(&conn, "SELECT * FROM todos WHERE id = ?1", (id,)).await?;
+    if let Some(row) = row {
+        Ok(json!({ "code": 0, "data": merge_todo_row(&row) }))
     } else {
-        Err("Todo not found".to_string())
+        Err(AppError::not_found("Todo not found"))
     }
 }
 
 #[tauri::command]
-pub async fn db_todo_delete(state: State<'_, TursoDb>, id: String) -> Result<(), String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+pub async fn db_todo_delete(state: State<'_, TursoDb>, id: String) -> Result<(), AppError> {
+    let conn = state.0.connect()?;
     conn.execute(
-        "UPDATE todos SET deleted = 1, updated_at = datetime('now') WHERE id = ?1",
-        (id,),
+        "UPDATE todos SET deleted = 1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?1",
+        (id.clone(),),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
+    reindex_one(&conn, "todos", &id).await?;
     Ok(())
 }
-
-fn row_to_json(row: &turso::Row) -> Result<JsonValue, String> {
-    let mut map = serde_json::Map::new();
-    let keys = [
-        "id",
-        "remote_id",
-        "sync_status",
-        "updated_at",
-        "deleted",
-        "completed",
-        "quadrant",
-        "data",
-    ];
-    for (i, key) in keys.iter().enumerate() {
-        if let Ok(val) = row.get_value(i) {
-            map.insert(key.to_string(), super::turso_value_to_json(&val));
-        }
-    }
-    Ok(JsonValue::Object(map))
-}
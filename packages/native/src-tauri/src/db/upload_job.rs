@@ -0,0 +1,484 @@
+//! Persistent upload queue.
+//!
+//! Each row is one local file to upload to `object_key`. Jobs move through
+//! `pending → running → done`, with `paused`, `failed` and `cancelled` as the
+//! side exits. Workers only ever finish a job that is still `running`, so a
+//! pause or cancel issued mid-upload is never overwritten by the worker.
+//!
+//! A job with an `entity_type` carries the record to create in `meta`
+//! ([`NewPhoto`] or [`NewDriveFile`]); [`complete_job`] writes it in the same
+//! transaction that marks the job `done`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use turso::Value as TursoValue;
+
+use super::drive_file::{insert_drive_file, NewDriveFile};
+use super::photo::{insert_photo, NewPhoto};
+use super::{in_transaction, new_id, placeholders, query_maps};
+use crate::error::AppError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Pending,
+    Running,
+    Paused,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Paused => "paused",
+            Self::Done => "done",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, AppError> {
+        serde_json::from_value(JsonValue::String(value.to_string()))
+            .map_err(|_| AppError::validation(format!("Unknown upload state: {}", value)))
+    }
+}
+
+/// What the uploaded object belongs to; the record is created on `done`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadEntity {
+    Photo,
+    DriveFile,
+}
+
+impl UploadEntity {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Photo => "photo",
+            Self::DriveFile => "drive_file",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewUploadJob {
+    pub object_key: String,
+    pub source_path: String,
+    #[serde(default)]
+    pub entity_type: Option<UploadEntity>,
+    /// 缺省时入队生成，完成后的记录使用该 id
+    #[serde(default)]
+    pub entity_id: Option<String>,
+    /// 有 entity_type 时为待写入的记录字段，否则为前端自定义数据，原样返回
+    #[serde(default)]
+    pub meta: Option<JsonValue>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadJob {
+    pub id: String,
+    pub object_key: String,
+    pub source_path: String,
+    pub entity_type: Option<UploadEntity>,
+    pub entity_id: Option<String>,
+    pub meta: Option<JsonValue>,
+    pub state: JobState,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub file_size: Option<u64>,
+    pub next_attempt_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+// 未结束的任务：同一对象重复入队时复用
+const ACTIVE_STATES: [JobState; 4] = [
+    JobState::Pending,
+    JobState::Running,
+    JobState::Paused,
+    JobState::Failed,
+];
+
+fn text(row: &Map<String, JsonValue>, key: &str) -> Option<String> {
+    row.get(key).and_then(|v| v.as_str()).map(String::from)
+}
+
+// 以文本存储的枚举列
+fn text_enum<T: serde::de::DeserializeOwned>(row: &Map<String, JsonValue>, key: &str) -> Option<T> {
+    text(row, key).and_then(|v| serde_json::from_value(JsonValue::String(v)).ok())
+}
+
+fn job_from_row(row: &Map<String, JsonValue>) -> UploadJob {
+    UploadJob {
+        id: text(row, "id").unwrap_or_default(),
+        object_key: text(row, "object_key").unwrap_or_default(),
+        source_path: text(row, "source_path").unwrap_or_default(),
+        entity_type: text_enum(row, "entity_type"),
+        entity_id: text(row, "entity_id"),
+        meta: text(row, "meta").and_then(|v| serde_json::from_str(&v).ok()),
+        state: text_enum(row, "state").unwrap_or(JobState::Failed),
+        attempts: row.get("attempts").and_then(|v| v.as_i64()).unwrap_or(0) as u32,
+        last_error: text(row, "last_error"),
+        file_size: row
+            .get("file_size")
+            .and_then(|v| v.as_i64())
+            .map(|v| v as u64),
+        next_attempt_at: text(row, "next_attempt_at"),
+        created_at: text(row, "created_at").unwrap_or_default(),
+        updated_at: text(row, "updated_at").unwrap_or_default(),
+    }
+}
+
+fn state_list(states: &[JobState], start: usize) -> (String, Vec<TursoValue>) {
+    (
+        placeholders(start, states.len()),
+        states
+            .iter()
+            .map(|s| TursoValue::Text(s.as_str().to_string()))
+            .collect(),
+    )
+}
+
+pub async fn get_job(conn: &turso::Connection, id: &str) -> Result<Option<UploadJob>, AppError> {
+    let rows = query_maps(conn, "SELECT * FROM upload_jobs WHERE id = ?1", (id,)).await?;
+    Ok(rows.first().map(job_from_row))
+}
+
+/// Jobs in queue order; an empty `states` lists every job.
+pub async fn list_jobs(
+    conn: &turso::Connection,
+    states: &[JobState],
+) -> Result<Vec<UploadJob>, AppError> {
+    let rows = if states.is_empty() {
+        query_maps(
+            conn,
+            "SELECT * FROM upload_jobs ORDER BY created_at, rowid",
+            (),
+        )
+        .await?
+    } else {
+        let (marks, params) = state_list(states, 1);
+        query_maps(
+            conn,
+            &format!(
+                "SELECT * FROM upload_jobs WHERE state IN ({}) ORDER BY created_at, rowid",
+                marks
+            ),
+            params,
+        )
+        .await?
+    };
+    Ok(rows.iter().map(job_from_row).collect())
+}
+
+/// Add jobs to the queue. A key that already has an unfinished job returns
+/// that job instead of queueing the file twice.
+pub async fn enqueue_jobs(
+    conn: &turso::Connection,
+    jobs: &[NewUploadJob],
+) -> Result<Vec<UploadJob>, AppError> {
+    for job in jobs {
+        if job.object_key.trim().is_empty() || job.source_path.trim().is_empty() {
+            return Err(AppError::validation(
+                "Upload job needs an object key and a source path",
+            ));
+        }
+    }
+    let ids = in_transaction(conn, async {
+        let mut ids = Vec::with_capacity(jobs.len());
+        for job in jobs {
+            let (marks, mut params) = state_list(&ACTIVE_STATES, 2);
+            params.insert(0, TursoValue::Text(job.object_key.clone()));
+            let existing = query_maps(
+                conn,
+                &format!(
+                    "SELECT id FROM upload_jobs WHERE object_key = ?1 AND state IN ({}) LIMIT 1",
+                    marks
+                ),
+                params,
+            )
+            .await?;
+            if let Some(id) = existing.first().and_then(|row| text(row, "id")) {
+                ids.push(id);
+                continue;
+            }
+            let id = new_id();
+            let entity_id = match job.entity_type {
+                Some(_) => Some(job.entity_id.clone().unwrap_or_else(new_id)),
+                None => job.entity_id.clone(),
+            };
+            conn.execute(
+                "INSERT INTO upload_jobs (id, object_key, source_path, entity_type, entity_id, meta) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                (
+                    id.as_str(),
+                    job.object_key.as_str(),
+                    job.source_path.as_str(),
+                    job.entity_type.map(|e| e.as_str()),
+                    entity_id.as_deref(),
+                    job.meta.as_ref().map(|m| m.to_string()),
+                ),
+            )
+            .await?;
+            ids.push(id);
+        }
+        Ok(ids)
+    })
+    .await?;
+
+    let mut result = Vec::with_capacity(ids.len());
+    for id in ids {
+        if let Some(job) = get_job(conn, &id).await? {
+            result.push(job);
+        }
+    }
+    Ok(result)
+}
+
+/// Take the oldest due `pending` job and mark it `running` (one more
+/// attempt). The conditional update keeps concurrent workers from claiming
+/// the same job.
+pub async fn claim_next_job(conn: &turso::Connection) -> Result<Option<UploadJob>, AppError> {
+    loop {
+        let rows = query_maps(
+            conn,
+            "SELECT id FROM upload_jobs
+             WHERE state = 'pending' AND (next_attempt_at IS NULL OR next_attempt_at <= datetime('now'))
+             ORDER BY created_at, rowid LIMIT 1",
+            (),
+        )
+        .await?;
+        let Some(id) = rows.first().and_then(|row| text(row, "id")) else {
+            return Ok(None);
+        };
+        let claimed = conn
+            .execute(
+                "UPDATE upload_jobs SET state = 'running', attempts = attempts + 1, next_attempt_at = NULL, updated_at = datetime('now')
+                 WHERE id = ?1 AND state = 'pending'",
+                (id.as_str(),),
+            )
+            .await?;
+        if claimed == 1 {
+            return get_job(conn, &id).await;
+        }
+    }
+}
+
+async fn finish_running(
+    conn: &turso::Connection,
+    id: &str,
+    sql: &str,
+    params: Vec<TursoValue>,
+) -> Result<bool, AppError> {
+    let mut params = params;
+    params.insert(0, TursoValue::Text(id.to_string()));
+    let changed = conn
+        .execute(
+            &format!(
+                "UPDATE upload_jobs SET {}, updated_at = datetime('now') WHERE id = ?1 AND state = 'running'",
+                sql
+            ),
+            params,
+        )
+        .await?;
+    Ok(changed == 1)
+}
+
+/// `false` when the job was paused or cancelled while uploading.
+pub async fn mark_done(conn: &turso::Connection, id: &str) -> Result<bool, AppError> {
+    finish_running(conn, id, "state = 'done', last_error = NULL", vec![]).await
+}
+
+/// Mark an uploaded job `done` and create its entity record in one
+/// transaction. Returns the created record (`Null` for jobs without an
+/// entity), or `None` when the job was paused or cancelled meanwhile. A record
+/// that cannot be written rolls the job back to `running`.
+pub async fn complete_job(
+    conn: &turso::Connection,
+    job: &UploadJob,
+) -> Result<Option<JsonValue>, AppError> {
+    in_transaction(conn, async {
+        if !mark_done(conn, &job.id).await? {
+            return Ok(None);
+        }
+        let Some(entity_type) = job.entity_type else {
+            return Ok(Some(JsonValue::Null));
+        };
+        let id = job.entity_id.clone().unwrap_or_else(new_id);
+        let meta = job.meta.clone().unwrap_or(JsonValue::Null);
+        let invalid =
+            |e: serde_json::Error| AppError::validation(format!("Invalid upload record: {}", e));
+        let record = match entity_type {
+            UploadEntity::Photo => {
+                let mut photo: NewPhoto = serde_json::from_value(meta).map_err(invalid)?;
+                photo.id = Some(id);
+                insert_photo(conn, photo).await?
+            }
+            UploadEntity::DriveFile => {
+                let file: NewDriveFile = serde_json::from_value(meta).map_err(invalid)?;
+                insert_drive_file(conn, &id, &job.object_key, file).await?
+            }
+        };
+        Ok(Some(record))
+    })
+    .await
+}
+
+/// Back to `pending`, due again after `delay_seconds`.
+pub async fn mark_retry(
+    conn: &turso::Connection,
+    id: &str,
+    error: &str,
+    delay_seconds: u64,
+) -> Result<bool, AppError> {
+    finish_running(
+        conn,
+        id,
+        "state = 'pending', last_error = ?2, next_attempt_at = datetime('now', ?3)",
+        vec![
+            TursoValue::Text(error.to_string()),
+            TursoValue::Text(format!("+{} seconds", delay_seconds)),
+        ],
+    )
+    .await
+}
+
+pub async fn mark_failed(
+    conn: &turso::Connection,
+    id: &str,
+    error: &str,
+) -> Result<bool, AppError> {
+    finish_running(
+        conn,
+        id,
+        "state = 'failed', last_error = ?2",
+        vec![TursoValue::Text(error.to_string())],
+    )
+    .await
+}
+
+pub async fn set_file_size(conn: &turso::Connection, id: &str, size: u64) -> Result<(), AppError> {
+    conn.execute(
+        "UPDATE upload_jobs SET file_size = ?2 WHERE id = ?1",
+        (id, size as i64),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Move jobs currently in one of `from` to `to` and return them in their new
+/// state. `ids = None` applies to every job in `from`.
+async fn transition(
+    conn: &turso::Connection,
+    ids: Option<&[String]>,
+    from: &[JobState],
+    to: JobState,
+) -> Result<Vec<UploadJob>, AppError> {
+    let (marks, mut params) = state_list(from, 1);
+    let mut sql = format!("SELECT id FROM upload_jobs WHERE state IN ({})", marks);
+    if let Some(ids) = ids {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+        sql.push_str(&format!(
+            " AND id IN ({})",
+            placeholders(params.len() + 1, ids.len())
+        ));
+        params.extend(ids.iter().map(|id| TursoValue::Text(id.clone())));
+    }
+    let matched: Vec<String> = query_maps(conn, &sql, params)
+        .await?
+        .iter()
+        .filter_map(|row| text(row, "id"))
+        .collect();
+
+    // 手动恢复视为重新开始：清空重试计数与退避时间
+    let reset = if to == JobState::Pending {
+        ", attempts = 0, next_attempt_at = NULL"
+    } else {
+        ""
+    };
+    let mut result = Vec::with_capacity(matched.len());
+    for id in matched {
+        let (marks, mut params) = state_list(from, 3);
+        params.insert(0, TursoValue::Text(to.as_str().to_string()));
+        params.insert(0, TursoValue::Text(id.clone()));
+        let changed = conn
+            .execute(
+                &format!(
+                    "UPDATE upload_jobs SET state = ?2{}, updated_at = datetime('now') WHERE id = ?1 AND state IN ({})",
+                    reset, marks
+                ),
+                params,
+            )
+            .await?;
+        if changed == 1 {
+            if let Some(job) = get_job(conn, &id).await? {
+                result.push(job);
+            }
+        }
+    }
+    Ok(result)
+}
+
+pub async fn pause_jobs(
+    conn: &turso::Connection,
+    ids: Option<&[String]>,
+) -> Result<Vec<UploadJob>, AppError> {
+    transition(
+        conn,
+        ids,
+        &[JobState::Pending, JobState::Running],
+        JobState::Paused,
+    )
+    .await
+}
+
+/// Paused and failed jobs go back to `pending` with a fresh retry budget.
+pub async fn resume_jobs(
+    conn: &turso::Connection,
+    ids: Option<&[String]>,
+) -> Result<Vec<UploadJob>, AppError> {
+    transition(
+        conn,
+        ids,
+        &[JobState::Paused, JobState::Failed],
+        JobState::Pending,
+    )
+    .await
+}
+
+pub async fn cancel_jobs(
+    conn: &turso::Connection,
+    ids: Option<&[String]>,
+) -> Result<Vec<UploadJob>, AppError> {
+    transition(conn, ids, &ACTIVE_STATES, JobState::Cancelled).await
+}
+
+/// Jobs left `running` by a previous process go back to `pending`; their
+/// attempt is not counted.
+pub async fn requeue_interrupted(conn: &turso::Connection) -> Result<u64, AppError> {
+    let changed = conn
+        .execute(
+            "UPDATE upload_jobs SET state = 'pending', attempts = MAX(attempts - 1, 0), updated_at = datetime('now') WHERE state = 'running'",
+            (),
+        )
+        .await?;
+    Ok(changed)
+}
+
+/// Delete `done` and `cancelled` jobs.
+pub async fn clear_finished(conn: &turso::Connection) -> Result<u64, AppError> {
+    let changed = conn
+        .execute(
+            "DELETE FROM upload_jobs WHERE state IN ('done', 'cancelled')",
+            (),
+        )
+        .await?;
+    Ok(changed)
+}
@@ -2,7 +2,9 @@ use serde_json::{json, Value as JsonValue};
 use tauri::State;
 use turso::Value as TursoValue;
 
-use super::{merge_row, new_id, TursoDb};
+use crate::error::AppError;
+use super::model::{query_model, query_models, Model, Weight};
+use super::{new_id, TursoDb};
 
 #[tauri::command]
 pub async fn db_weight_list(
@@ -12,8 +14,8 @@ pub async fn db_weight_list(
     page_size: Option<i64>,
     start_time: Option<String>,
     end_time: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
     let page = page.unwrap_or(1);
     let page_size = page_size.unwrap_or(30);
     let offset = (page - 1) * page_size;
@@ -45,11 +47,10 @@ pub async fn db_weight_list(
         where_clause, page_size, offset
     );
 
-    let mut rows = conn.query(&sql, params).await.map_err(|e| e.to_string())?;
+    let rows = query_models::<Weight>(&conn, &sql, params).await?;
     let mut items = Vec::new();
-    while let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        items.push(merge_row(&val));
+    for row in rows {
+        items.push(row.to_json());
     }
 
     Ok(json!({ "code": 0, "data": items }))
@@ -64,8 +65,8 @@ pub async fn db_weight_add(
     family_id: String,
     operator: Option<String>,
     data: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
     let id = new_id();
 
     let data_val = data.unwrap_or_else(|| {
@@ -82,18 +83,13 @@ pub async fn db_weight_add(
         "INSERT INTO weights (id, family_id, data) VALUES (?1, ?2, ?3)",
         (id.clone(), family_id, data_val),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
 
-    let mut rows = conn
-        .query("SELECT * FROM weights WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        Ok(json!({ "code": 0, "data": merge_row(&val) }))
+    let row = query_model::<Weight>(&conn, "SELECT * FROM weights WHERE id = ?1", (id,)).await?;
+    if let Some(row) = row {
+        Ok(json!({ "code": 0, "data": row.to_json() }))
     } else {
-        Err("Failed to create weight record".to_string())
+        Err(AppError::storage("Failed to create weight record"))
     }
 }
 
@@ -105,38 +101,32 @@ pub async fn db_weight_update(
     date: Option<String>,
     tips: Option<String>,
     data: Option<String>,
-) -> Result<JsonValue, String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<JsonValue, AppError> {
+    let conn = state.0.connect()?;
 
     if let Some(d) = data {
         conn.execute(
-            "UPDATE weights SET data = ?1, updated_at = datetime('now') WHERE id = ?2",
+            "UPDATE weights SET data = ?1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?2",
             (d, id.clone()),
         )
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
     } else {
         let mut existing = get_weight_data(&conn, &id).await?;
         if let Some(w) = weight { existing["weight"] = json!(w); }
         if let Some(d) = date { existing["date"] = json!(d); }
         if let Some(t) = tips { existing["tips"] = json!(t); }
         conn.execute(
-            "UPDATE weights SET data = ?1, updated_at = datetime('now') WHERE id = ?2",
+            "UPDATE weights SET data = ?1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?2",
             (existing.to_string(), id.clone()),
         )
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
     }
 
-    let mut rows = conn
-        .query("SELECT * FROM weights WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let val = row_to_json(&row)?;
-        Ok(json!({ "code": 0, "data": merge_row(&val) }))
+    let row = query_model::<Weight>(&conn, "SELECT * FROM weights WHERE id = ?1", (id,)).await?;
+    if let Some(row) = row {
+        Ok(json!({ "code": 0, "data": row.to_json() }))
     } else {
-        Err("Weight record not found".to_string())
+        Err(AppError::not_found("Weight record not found"))
     }
 }
 
@@ -144,46 +134,24 @@ pub async fn db_weight_update(
 pub async fn db_weight_delete(
     state: State<'_, TursoDb>,
     id: String,
-) -> Result<(), String> {
-    let conn = state.0.connect().map_err(|e| e.to_string())?;
+) -> Result<(), AppError> {
+    let conn = state.0.connect()?;
     conn.execute(
-        "UPDATE weights SET deleted = 1, updated_at = datetime('now') WHERE id = ?1",
+        "UPDATE weights SET deleted = 1, sync_status = 'pending', updated_at = datetime('now') WHERE id = ?1",
         (id,),
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
     Ok(())
 }
 
-async fn get_weight_data(conn: &turso::Connection, id: &str) -> Result<JsonValue, String> {
+async fn get_weight_data(conn: &turso::Connection, id: &str) -> Result<JsonValue, AppError> {
     let mut rows = conn
         .query("SELECT data FROM weights WHERE id = ?1", (id,))
-        .await
-        .map_err(|e| e.to_string())?;
-    if let Some(row) = rows.next().await.map_err(|e| e.to_string())? {
-        let data_str = row.get_value(0).map_err(|e| e.to_string())?.as_text().map_or("{}", |v| v).to_string();
-        serde_json::from_str(&data_str).map_err(|e| e.to_string())
+        .await?;
+    if let Some(row) = rows.next().await? {
+        let data_str = row.get_value(0)?.as_text().map_or("{}", |v| v).to_string();
+        serde_json::from_str(&data_str).map_err(AppError::from)
     } else {
-        Err("Weight record not found".to_string())
-    }
-}
-
-fn row_to_json(row: &turso::Row) -> Result<JsonValue, String> {
-    let mut map = serde_json::Map::new();
-    for i in 0..7 {
-        if let Ok(val) = row.get_value(i as usize) {
-            let key = match i {
-                0 => "id",
-                1 => "remote_id",
-                2 => "sync_status",
-                3 => "updated_at",
-                4 => "deleted",
-                5 => "family_id",
-                6 => "data",
-                _ => continue,
-            };
-            map.insert(key.to_string(), super::turso_value_to_json(&val));
-        }
+        Err(AppError::not_found("Weight record not found"))
     }
-    Ok(JsonValue::Object(map))
 }
@@ -0,0 +1,159 @@
+use serde::Serialize;
+use serde_json::{json, Value as JsonValue};
+
+/// Stable error kinds the frontend can branch on instead of matching message text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum ErrorCode {
+    NotFound,
+    Validation,
+    Conflict,
+    Storage,
+    Network,
+    S3Status,
+    /// Uploaded bytes don't match the checksum the client sent.
+    ChecksumMismatch,
+    Io,
+}
+
+/// Error returned by every `db::*` and `command::*` function.
+/// Serialized to the frontend as `{ code, message, details? }`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<JsonValue>,
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        AppError {
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NotFound, message)
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Validation, message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Conflict, message)
+    }
+
+    pub fn storage(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Storage, message)
+    }
+
+    pub fn network(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Network, message)
+    }
+
+    pub fn io(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Io, message)
+    }
+
+    /// Non-success HTTP status from S3; the status is kept in `details.status`.
+    pub fn s3_status(status: u16, message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::S3Status, message).with_details(json!({ "status": status }))
+    }
+
+    /// S3 rejected the payload digest or returned an ETag for different bytes;
+    /// `details` carries `expected` / `actual` when known.
+    pub fn checksum_mismatch(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::ChecksumMismatch, message)
+    }
+
+    pub fn with_details(mut self, details: JsonValue) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// HTTP status of an [`ErrorCode::S3Status`] error (`details.status`).
+    pub fn s3_status_code(&self) -> Option<u16> {
+        if self.code != ErrorCode::S3Status {
+            return None;
+        }
+        self.details
+            .as_ref()
+            .and_then(|d| d.get("status"))
+            .and_then(|s| s.as_u64())
+            .and_then(|s| u16::try_from(s).ok())
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<turso::Error> for AppError {
+    fn from(e: turso::Error) -> Self {
+        AppError::storage(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::io(e.to_string())
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        AppError::network(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::validation(e.to_string())
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(e: tauri::Error) -> Self {
+        AppError::io(e.to_string())
+    }
+}
+
+#[cfg(target_os = "android")]
+impl From<jni::errors::Error> for AppError {
+    fn from(e: jni::errors::Error) -> Self {
+        AppError::io(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_code_message_and_optional_details() {
+        let plain = serde_json::to_value(AppError::not_found("photo not found")).unwrap();
+        assert_eq!(plain, json!({ "code": "NotFound", "message": "photo not found" }));
+
+        let s3 = serde_json::to_value(AppError::s3_status(403, "denied")).unwrap();
+        assert_eq!(
+            s3,
+            json!({ "code": "S3Status", "message": "denied", "details": { "status": 403 } })
+        );
+    }
+
+    #[test]
+    fn reads_s3_status_from_details() {
+        assert_eq!(AppError::s3_status(404, "gone").s3_status_code(), Some(404));
+        let detailed = AppError::s3_status(404, "gone")
+            .with_details(json!({ "status": 404, "code": "NoSuchUpload" }));
+        assert_eq!(detailed.s3_status_code(), Some(404));
+        assert_eq!(AppError::network("offline").s3_status_code(), None);
+    }
+}
@@ -1,11 +1,15 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+// Tauri commands take their invoke arguments as plain parameters
+#![allow(clippy::too_many_arguments)]
 use log::{info, LevelFilter};
+use tauri::Manager;
 use tauri_plugin_log::{Target, TargetKind};
 
-mod command;
+pub mod command;
 use command::*;
 
 pub mod db;
+pub mod error;
 use db::*;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -37,6 +41,9 @@ pub fn run() {
                 app.handle().plugin(tauri_plugin_updater::Builder::new().build())?;
                 app.handle().plugin(tauri_plugin_process::init())?;
             }
+            let thumbnail_dir = app.path().app_cache_dir()?.join("thumbnails");
+            app.manage(command::thumbnail::ThumbnailCache::new(thumbnail_dir));
+            app.manage(command::upload_queue::UploadQueue::default());
             tauri::async_runtime::block_on(async {
                 match db::init(app.handle()).await {
                     Ok(()) => info!("Database initialized successfully"),
@@ -60,6 +67,21 @@ pub fn run() {
             open_apk,
             check_update,
             get_file_info,
+            command::multipart::upload_file_multipart,
+            command::multipart::abort_multipart_upload,
+            command::multipart::multipart_upload_list,
+            command::upload_queue::upload_queue_start,
+            command::upload_queue::upload_queue_enqueue,
+            command::upload_queue::upload_queue_list,
+            command::upload_queue::upload_queue_pause,
+            command::upload_queue::upload_queue_resume,
+            command::upload_queue::upload_queue_cancel,
+            command::upload_queue::upload_queue_clear,
+            command::phash::compute_phash,
+            command::thumbnail::get_thumbnail,
+            command::thumbnail::thumbnail_cache_usage,
+            command::thumbnail::thumbnail_cache_clear,
+            command::thumbnail::thumbnail_cache_set_budget,
             parse_live_photo,
             // Legacy KV cache
             db_set_cache,
@@ -74,8 +96,15 @@ pub fn run() {
             db_photo_toggle_like,
             db_photo_delete,
             db_photo_restore,
+            db_photo_purge,
+            db_photo_purge_expired,
             db_photo_check_duplicate,
             db_photo_list_info,
+            db_photo_timeline,
+            db_photo_on_this_day,
+            db_photo_find_similar,
+            db_photo_resolve_similar,
+            db_photo_backfill_taken_at,
             db_photo_add_album,
             db_photo_remove_album,
             db_photo_set_albums,
@@ -148,6 +177,16 @@ pub fn run() {
             // Sync
             db_get_pending_sync,
             db_mark_synced,
+            db_sync_run,
+            // Backup
+            db_backup_create,
+            db_backup_restore,
+            // Portable export / import
+            db_export_ndjson,
+            db_import_ndjson,
+            // Search
+            db_search,
+            db_search_rebuild,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
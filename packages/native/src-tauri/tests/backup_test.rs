@@ -0,0 +1,194 @@
+mod common;
+
+use common::{query_i64, remove_db_files, TempDb};
+use tauri_app_lib::db::{backup, migration};
+
+fn temp_path(prefix: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("{}_{}", prefix, uuid::Uuid::new_v4()))
+}
+
+async fn seed(conn: &turso::Connection) {
+    for i in 0..3 {
+        conn.execute(
+            "INSERT INTO todos (id, data) VALUES (?1, '{\"title\":\"todo\"}')",
+            (format!("t{}", i),),
+        )
+        .await
+        .unwrap();
+    }
+    conn.execute("INSERT INTO albums (id, data) VALUES ('a1', '{}')", ())
+        .await
+        .unwrap();
+}
+
+/// 备份 → 暂存恢复 → 启动时替换：数据一致，旧库保留为回滚副本
+#[tokio::test]
+async fn test_backup_and_restore_round_trip() {
+    let source = TempDb::with_schema("echo_trails_backup_src").await;
+    seed(&source.conn).await;
+    let archive = temp_path("echo_trails_backup.etb");
+
+    let manifest = backup::create_backup(&source.conn, &source.path, &archive, "1.2.3")
+        .await
+        .unwrap();
+    assert_eq!(manifest.app_version, "1.2.3");
+    assert_eq!(manifest.schema_version, migration::latest_version());
+    assert_eq!(manifest.tables.get("todos"), Some(&3));
+    assert_eq!(manifest.tables.get("albums"), Some(&1));
+    assert_eq!(
+        backup::read_manifest(&archive).unwrap().sha256,
+        manifest.sha256
+    );
+
+    // 目标设备上已有一个不同内容的库
+    let target = TempDb::with_schema("echo_trails_backup_dst").await;
+    target
+        .conn
+        .execute("INSERT INTO todos (id, data) VALUES ('other', '{}')", ())
+        .await
+        .unwrap();
+    let target_path = target.path.clone();
+    backup::stage_restore(&archive, &target_path).await.unwrap();
+    assert!(backup::staged_restore_path(&target_path).exists());
+    // 模拟 App 退出：关闭连接但保留库文件
+    drop(target);
+
+    assert!(backup::apply_pending_restore(&target_path).unwrap());
+    assert!(!backup::staged_restore_path(&target_path).exists());
+    let rollback = backup::rollback_path(&target_path);
+    assert!(rollback.exists());
+    assert!(!backup::apply_pending_restore(&target_path).unwrap());
+
+    let db = turso::Builder::new_local(&target_path.to_string_lossy())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    assert_eq!(query_i64(&conn, "SELECT COUNT(*) FROM todos").await, 3);
+    assert_eq!(
+        query_i64(&conn, "SELECT COUNT(*) FROM todos WHERE id = 'other'").await,
+        0
+    );
+    drop(conn);
+    drop(db);
+
+    source.cleanup();
+    remove_db_files(&target_path);
+    remove_db_files(&rollback);
+    let _ = std::fs::remove_file(&archive);
+}
+
+/// 损坏的备份在暂存前被拒绝，不留下暂存文件
+#[tokio::test]
+async fn test_corrupted_backup_is_rejected() {
+    let source = TempDb::with_schema("echo_trails_backup_corrupt").await;
+    seed(&source.conn).await;
+    let archive = temp_path("echo_trails_backup_corrupt.etb");
+    backup::create_backup(&source.conn, &source.path, &archive, "1.0.0")
+        .await
+        .unwrap();
+
+    let mut bytes = std::fs::read(&archive).unwrap();
+    // 跳过 magic + format + db_len，篡改数据库内容
+    bytes[20 + 200] ^= 0xff;
+    std::fs::write(&archive, &bytes).unwrap();
+
+    let target_path = temp_path("echo_trails_backup_corrupt_dst.db");
+    let err = backup::stage_restore(&archive, &target_path)
+        .await
+        .unwrap_err();
+    assert!(err.message.contains("checksum mismatch"), "{}", err);
+    assert!(!backup::staged_restore_path(&target_path).exists());
+
+    std::fs::write(&archive, b"not a backup").unwrap();
+    let err = backup::read_manifest(&archive).unwrap_err();
+    assert!(err.message.contains("Not an Echo Trails backup"), "{}", err);
+
+    source.cleanup();
+    let _ = std::fs::remove_file(&archive);
+}
+
+/// 更高 schema 版本的备份无法在当前 App 恢复
+#[tokio::test]
+async fn test_backup_from_newer_schema_is_rejected() {
+    let source = TempDb::with_schema("echo_trails_backup_newer").await;
+    source
+        .conn
+        .execute(
+            "INSERT INTO schema_version (version, name) VALUES (?1, 'from_the_future')",
+            (migration::latest_version() + 1,),
+        )
+        .await
+        .unwrap();
+    let archive = temp_path("echo_trails_backup_newer.etb");
+    backup::create_backup(&source.conn, &source.path, &archive, "9.9.9")
+        .await
+        .unwrap();
+
+    let err = backup::read_manifest(&archive).unwrap_err();
+    assert!(err.message.contains("newer than this app supports"), "{}", err);
+
+    source.cleanup();
+    let _ = std::fs::remove_file(&archive);
+}
+
+/// 清单长度字段超出上限时直接拒绝，不按该长度分配内存
+#[tokio::test]
+async fn test_oversized_manifest_length_is_rejected() {
+    let source = TempDb::with_schema("echo_trails_backup_manifest_len").await;
+    let archive = temp_path("echo_trails_backup_manifest_len.etb");
+    let manifest = backup::create_backup(&source.conn, &source.path, &archive, "1.0.0")
+        .await
+        .unwrap();
+
+    let mut bytes = std::fs::read(&archive).unwrap();
+    let len_offset = 20 + manifest.db_size as usize;
+    bytes[len_offset..len_offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    std::fs::write(&archive, &bytes).unwrap();
+
+    let err = backup::read_manifest(&archive).unwrap_err();
+    assert!(err.message.contains("manifest is too large"), "{}", err);
+
+    source.cleanup();
+    let _ = std::fs::remove_file(&archive);
+}
+
+/// 备份期间另一个连接持续写入：清单行数与归档中的数据库一致，可以恢复
+#[tokio::test]
+async fn test_backup_during_concurrent_writes_stays_consistent() {
+    let source = TempDb::with_schema("echo_trails_backup_concurrent").await;
+    seed(&source.conn).await;
+    let archive = temp_path("echo_trails_backup_concurrent.etb");
+
+    let writer = source.db.connect().unwrap();
+    let stop = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let stop_writer = stop.clone();
+    let handle = tokio::spawn(async move {
+        let mut i = 0;
+        while !stop_writer.load(std::sync::atomic::Ordering::Relaxed) {
+            // 写锁被备份占用时可能失败，忽略即可
+            let _ = writer
+                .execute(
+                    "INSERT INTO todos (id, data) VALUES (?1, '{}')",
+                    (format!("w{}", i),),
+                )
+                .await;
+            i += 1;
+            tokio::task::yield_now().await;
+        }
+    });
+
+    for _ in 0..3 {
+        backup::create_backup(&source.conn, &source.path, &archive, "1.0.0")
+            .await
+            .unwrap();
+        let target_path = temp_path("echo_trails_backup_concurrent_dst.db");
+        backup::stage_restore(&archive, &target_path).await.unwrap();
+        remove_db_files(&backup::staged_restore_path(&target_path));
+    }
+    stop.store(true, std::sync::atomic::Ordering::Relaxed);
+    handle.await.unwrap();
+
+    source.cleanup();
+    let _ = std::fs::remove_file(&archive);
+}
@@ -0,0 +1,171 @@
+mod common;
+
+use common::{query_i64, TempDb};
+use tauri_app_lib::db::{self, album, photo};
+use tauri_app_lib::error::AppError;
+
+async fn seed_photos(conn: &turso::Connection, count: usize) -> Vec<String> {
+    let ids: Vec<String> = (0..count).map(|i| format!("p{}", i)).collect();
+    for chunk in ids.chunks(200) {
+        let values = chunk
+            .iter()
+            .map(|id| format!("('{}', '{{}}')", id))
+            .collect::<Vec<_>>()
+            .join(", ");
+        conn.execute(
+            &format!("INSERT INTO photos (id, data) VALUES {}", values),
+            (),
+        )
+        .await
+        .unwrap();
+    }
+    ids
+}
+
+/// 上千条的批量软删/恢复一次完成，缺失的 id 单独标记
+#[tokio::test]
+async fn test_bulk_delete_and_restore_report_per_id() {
+    let tmp = TempDb::with_schema("echo_trails_batch_delete").await;
+    let mut ids = seed_photos(&tmp.conn, 1200).await;
+    ids.push("missing".to_string());
+
+    let results = photo::set_photos_deleted(&tmp.conn, &ids, true)
+        .await
+        .unwrap();
+    assert_eq!(results.len(), 1201);
+    assert!(results[..1200].iter().all(|r| r.ok));
+    assert!(!results[1200].ok);
+    assert_eq!(results[1200].id, "missing");
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            "SELECT COUNT(*) FROM photos WHERE deleted = 1 AND sync_status = 'pending'"
+        )
+        .await,
+        1200
+    );
+
+    photo::set_photos_deleted(&tmp.conn, &ids[..10], false)
+        .await
+        .unwrap();
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT COUNT(*) FROM photos WHERE deleted = 0").await,
+        10
+    );
+
+    tmp.cleanup();
+}
+
+/// 事务中任一语句失败则整批回滚
+#[tokio::test]
+async fn test_transaction_rolls_back_on_failure() {
+    let tmp = TempDb::with_schema("echo_trails_batch_rollback").await;
+    seed_photos(&tmp.conn, 3).await;
+
+    let conn = &tmp.conn;
+    let result: Result<(), AppError> = db::in_transaction(conn, async {
+        conn.execute("UPDATE photos SET deleted = 1", ()).await?;
+        conn.execute("UPDATE no_such_table SET x = 1", ()).await?;
+        Ok(())
+    })
+    .await;
+    assert!(result.is_err());
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT COUNT(*) FROM photos WHERE deleted = 1").await,
+        0
+    );
+
+    tmp.cleanup();
+}
+
+/// 批量加入相册：追加关联并合并旧的 data.albumId
+#[tokio::test]
+async fn test_add_photos_to_albums_merges_existing_links() {
+    let tmp = TempDb::with_schema("echo_trails_batch_albums").await;
+    tmp.conn
+        .execute(
+            "INSERT INTO photos (id, data) VALUES ('p1', '{\"albumId\":[\"legacy\"]}'), ('p2', '{}')",
+            (),
+        )
+        .await
+        .unwrap();
+    tmp.conn
+        .execute(
+            "INSERT INTO photo_albums (photo_id, album_id) VALUES ('p2', 'a0')",
+            (),
+        )
+        .await
+        .unwrap();
+
+    let photo_ids = vec!["p1".to_string(), "p2".to_string(), "nope".to_string()];
+    let album_ids = vec!["a1".to_string(), "a2".to_string()];
+    let results = photo::add_photos_to_albums(&tmp.conn, &photo_ids, &album_ids)
+        .await
+        .unwrap();
+    assert_eq!(results.iter().filter(|r| r.ok).count(), 2);
+    assert_eq!(results[2].error.as_deref(), Some("Photo not found"));
+
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT COUNT(*) FROM photo_albums").await,
+        5
+    );
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            "SELECT json_array_length(data, '$.albumId') FROM photos WHERE id = 'p1'"
+        )
+        .await,
+        3
+    );
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            "SELECT json_array_length(data, '$.albumId') FROM photos WHERE id = 'p2'"
+        )
+        .await,
+        3
+    );
+
+    tmp.cleanup();
+}
+
+/// 批量移动相册到文件夹，空 folder 视为移出
+#[tokio::test]
+async fn test_set_albums_folder() {
+    let tmp = TempDb::with_schema("echo_trails_batch_folder").await;
+    tmp.conn
+        .execute(
+            "INSERT INTO albums (id, data) VALUES ('a1', '{\"name\":\"A\"}'), ('a2', '{\"folderId\":\"old\"}')",
+            (),
+        )
+        .await
+        .unwrap();
+
+    let ids = vec!["a1".to_string(), "a2".to_string(), "a3".to_string()];
+    let results = album::set_albums_folder(&tmp.conn, &ids, Some("f1"))
+        .await
+        .unwrap();
+    assert_eq!(results.iter().filter(|r| r.ok).count(), 2);
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            "SELECT COUNT(*) FROM albums WHERE json_extract(data, '$.folderId') = 'f1'"
+        )
+        .await,
+        2
+    );
+
+    album::set_albums_folder(&tmp.conn, &ids, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            "SELECT COUNT(*) FROM albums WHERE json_extract(data, '$.folderId') IS NOT NULL"
+        )
+        .await,
+        0
+    );
+
+    tmp.cleanup();
+}
@@ -0,0 +1,157 @@
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+
+/// 临时数据库：测试结束时调用 `cleanup` 删除 db/wal/shm 文件
+pub struct TempDb {
+    pub path: PathBuf,
+    pub db: turso::Database,
+    pub conn: turso::Connection,
+}
+
+impl TempDb {
+    pub async fn open(prefix: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("{}_{}.db", prefix, uuid::Uuid::new_v4()));
+        remove_db_files(&path);
+        let db = turso::Builder::new_local(&path.to_string_lossy())
+            .build()
+            .await
+            .expect("Failed to build test db");
+        let conn = db.connect().expect("Failed to connect");
+        TempDb { path, db, conn }
+    }
+
+    /// 打开并执行完整的 schema 创建（含迁移）
+    pub async fn with_schema(prefix: &str) -> Self {
+        let tmp = Self::open(prefix).await;
+        tauri_app_lib::db::create_schema_with_conn(&tmp.conn)
+            .await
+            .expect("create_schema_with_conn failed");
+        tmp
+    }
+
+    pub fn cleanup(self) {
+        let path = self.path.clone();
+        drop(self.conn);
+        drop(self.db);
+        remove_db_files(&path);
+    }
+}
+
+pub fn remove_db_files(path: &std::path::Path) {
+    let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_file(format!("{}-wal", path.display()));
+    let _ = std::fs::remove_file(format!("{}-shm", path.display()));
+}
+
+/// 执行单值查询（COUNT 等），返回第一行第一列的整数
+pub async fn query_i64(conn: &turso::Connection, sql: &str) -> i64 {
+    let mut rows = conn.query(sql, ()).await.expect("query failed");
+    match rows.next().await.expect("row fetch failed") {
+        Some(row) => row
+            .get_value(0)
+            .expect("column missing")
+            .as_integer()
+            .copied()
+            .unwrap_or(0),
+        None => 0,
+    }
+}
+
+/// 测试用 HTTP 请求（只解析 S3 模拟需要的部分）
+pub struct HttpRequest {
+    pub method: String,
+    /// 路径加查询串
+    pub target: String,
+    /// 小写名称
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn query(&self) -> &str {
+        self.target.split_once('?').map(|(_, q)| q).unwrap_or("")
+    }
+}
+
+/// 本地 HTTP 服务：每个连接处理一个请求，`handler` 返回完整的原始响应报文
+pub async fn spawn_http_server<F>(handler: F) -> String
+where
+    F: Fn(HttpRequest) -> String + Send + Sync + 'static,
+{
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handler = std::sync::Arc::new(handler);
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 64 * 1024];
+                let header_end = loop {
+                    let n = socket.read(&mut chunk).await.unwrap_or(0);
+                    if n == 0 {
+                        return;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                    if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break pos + 4;
+                    }
+                };
+                let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+                let mut lines = head.lines();
+                let mut request_line = lines.next().unwrap_or_default().split(' ');
+                let method = request_line.next().unwrap_or_default().to_string();
+                let target = request_line.next().unwrap_or_default().to_string();
+                let headers: Vec<(String, String)> = lines
+                    .filter_map(|l| l.split_once(':'))
+                    .map(|(n, v)| (n.trim().to_ascii_lowercase(), v.trim().to_string()))
+                    .collect();
+                let content_length: usize = headers
+                    .iter()
+                    .find(|(n, _)| n == "content-length")
+                    .and_then(|(_, v)| v.parse().ok())
+                    .unwrap_or(0);
+                while buf.len() < header_end + content_length {
+                    let n = socket.read(&mut chunk).await.unwrap_or(0);
+                    if n == 0 {
+                        break;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+                let response = handler(HttpRequest {
+                    method,
+                    target,
+                    headers,
+                    body: buf.split_off(header_end),
+                });
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+            });
+        }
+    });
+    format!("http://{}", addr)
+}
+
+/// 带 `Connection: close` 的原始响应报文
+pub fn http_response(status: &str, headers: &[(&str, &str)], body: &str) -> String {
+    let mut response = format!("HTTP/1.1 {}\r\n", status);
+    for (name, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    ));
+    response
+}
@@ -0,0 +1,376 @@
+use std::io::Cursor;
+
+use tauri_app_lib::command::metadata::{self, MediaMetadata};
+
+// 2023-05-01 10:00:00 +08:00
+const TAKEN_AT_MS: i64 = 1_682_906_400_000;
+
+struct Entry {
+    tag: u16,
+    kind: u16,
+    count: u32,
+    data: Vec<u8>,
+}
+
+fn ascii(tag: u16, value: &str) -> Entry {
+    let mut data = value.as_bytes().to_vec();
+    data.push(0);
+    Entry {
+        tag,
+        kind: 2,
+        count: data.len() as u32,
+        data,
+    }
+}
+
+fn short(tag: u16, value: u16) -> Entry {
+    Entry {
+        tag,
+        kind: 3,
+        count: 1,
+        data: value.to_be_bytes().to_vec(),
+    }
+}
+
+fn rationals(tag: u16, values: &[(u32, u32)]) -> Entry {
+    let data = values
+        .iter()
+        .flat_map(|(n, d)| n.to_be_bytes().into_iter().chain(d.to_be_bytes()))
+        .collect();
+    Entry {
+        tag,
+        kind: 5,
+        count: values.len() as u32,
+        data,
+    }
+}
+
+/// 构造大端 TIFF：IFD0 + 可选的 Exif / GPS 子 IFD
+fn tiff(mut ifd0: Vec<Entry>, exif: Vec<Entry>, gps: Vec<Entry>) -> Vec<u8> {
+    let ifd_size = |n: usize| 2 + 12 * n + 4;
+    let extra = (!exif.is_empty()) as usize + (!gps.is_empty()) as usize;
+    let ifd0_at = 8;
+    let exif_at = ifd0_at + ifd_size(ifd0.len() + extra);
+    let gps_at = exif_at
+        + if exif.is_empty() {
+            0
+        } else {
+            ifd_size(exif.len())
+        };
+    if !exif.is_empty() {
+        ifd0.push(Entry {
+            tag: 0x8769,
+            kind: 4,
+            count: 1,
+            data: (exif_at as u32).to_be_bytes().to_vec(),
+        });
+    }
+    if !gps.is_empty() {
+        ifd0.push(Entry {
+            tag: 0x8825,
+            kind: 4,
+            count: 1,
+            data: (gps_at as u32).to_be_bytes().to_vec(),
+        });
+    }
+    let mut data_at = gps_at
+        + if gps.is_empty() {
+            0
+        } else {
+            ifd_size(gps.len())
+        };
+
+    let mut out = b"MM\0\x2a".to_vec();
+    out.extend((ifd0_at as u32).to_be_bytes());
+    let mut heap: Vec<u8> = Vec::new();
+    for ifd in [&ifd0, &exif, &gps] {
+        if ifd.is_empty() {
+            continue;
+        }
+        out.extend((ifd.len() as u16).to_be_bytes());
+        for e in ifd.iter() {
+            out.extend(e.tag.to_be_bytes());
+            out.extend(e.kind.to_be_bytes());
+            out.extend(e.count.to_be_bytes());
+            if e.data.len() <= 4 {
+                let mut inline = e.data.clone();
+                inline.resize(4, 0);
+                out.extend(inline);
+            } else {
+                out.extend((data_at as u32).to_be_bytes());
+                data_at += e.data.len();
+                heap.extend(&e.data);
+            }
+        }
+        out.extend(0u32.to_be_bytes());
+    }
+    out.extend(heap);
+    out
+}
+
+fn camera_exif() -> Vec<u8> {
+    tiff(
+        vec![
+            ascii(0x010F, "Apple"),
+            ascii(0x0110, "iPhone 15"),
+            short(0x0112, 6),
+        ],
+        vec![
+            ascii(0x9003, "2023:05:01 10:00:00"),
+            ascii(0x9011, "+08:00"),
+        ],
+        vec![
+            ascii(1, "N"),
+            rationals(2, &[(37, 1), (30, 1), (0, 1)]),
+            ascii(3, "W"),
+            rationals(4, &[(122, 1), (15, 1), (0, 1)]),
+            rationals(6, &[(105, 10)]),
+        ],
+    )
+}
+
+fn read(bytes: Vec<u8>) -> MediaMetadata {
+    metadata::read(&mut Cursor::new(bytes)).unwrap()
+}
+
+fn bx(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+    out.extend(kind);
+    out.extend(body);
+    out
+}
+
+fn full(version: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = vec![version, 0, 0, 0];
+    out.extend(body);
+    out
+}
+
+fn assert_camera(meta: &MediaMetadata) {
+    assert_eq!(meta.make.as_deref(), Some("Apple"));
+    assert_eq!(meta.model.as_deref(), Some("iPhone 15"));
+    assert_eq!(meta.taken_at, Some(TAKEN_AT_MS));
+    let gps = meta.gps.unwrap();
+    assert!((gps.latitude - 37.5).abs() < 1e-9);
+    assert!((gps.longitude + 122.25).abs() < 1e-9);
+    assert!((gps.altitude.unwrap() - 10.5).abs() < 1e-9);
+}
+
+/// JPEG：SOF 尺寸 + EXIF 方向、拍摄时间（带时区）、相机与 GPS
+#[test]
+fn test_jpeg_exif_and_orientation() {
+    let exif = [b"Exif\0\0".to_vec(), camera_exif()].concat();
+    let mut file = vec![0xFF, 0xD8, 0xFF, 0xE1];
+    file.extend(((exif.len() + 2) as u16).to_be_bytes());
+    file.extend(exif);
+    // SOF0: precision, height, width, components
+    file.extend([0xFF, 0xC0, 0x00, 0x08, 8, 0x0B, 0xD0, 0x0F, 0xC0, 3]);
+    file.extend([0xFF, 0xDA, 0x00, 0x02, 0xFF, 0xD9]);
+
+    let meta = read(file);
+    assert_eq!((meta.width, meta.height), (4032, 3024));
+    assert_eq!(meta.orientation, Some(6));
+    assert_eq!(meta.display_size(), (3024, 4032));
+    assert_camera(&meta);
+}
+
+/// PNG / GIF / WebP 的像素尺寸
+#[test]
+fn test_png_gif_webp_dimensions() {
+    let mut ihdr = 640u32.to_be_bytes().to_vec();
+    ihdr.extend(480u32.to_be_bytes());
+    ihdr.extend([8, 6, 0, 0, 0]);
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    for (kind, body) in [
+        (b"IHDR", ihdr),
+        (b"eXIf", camera_exif()),
+        (b"IEND", Vec::new()),
+    ] {
+        png.extend((body.len() as u32).to_be_bytes());
+        png.extend(kind);
+        png.extend(body);
+        png.extend([0; 4]);
+    }
+    let meta = read(png);
+    assert_eq!(meta.display_size(), (480, 640));
+    assert_camera(&meta);
+
+    let gif = [b"GIF89a".to_vec(), vec![0x20, 0x03, 0x58, 0x02, 0, 0, 0]].concat();
+    assert_eq!(read(gif).display_size(), (800, 600));
+
+    // VP8X: flags + reserved, 然后 24 位的 width-1 / height-1
+    let vp8x = [0u8, 0, 0, 0, 0x7F, 0x07, 0, 0x37, 0x04, 0];
+    let mut webp = b"RIFF\0\0\0\0WEBPVP8X".to_vec();
+    webp.extend((vp8x.len() as u32).to_le_bytes());
+    webp.extend(vp8x);
+    assert_eq!(read(webp).display_size(), (1920, 1080));
+}
+
+/// MP4/MOV：旋转矩阵、时长，以及 Apple keys/ilst 中的拍摄时间与位置
+#[test]
+fn test_quicktime_video_metadata() {
+    let mut mvhd = vec![0u8; 4];
+    mvhd.extend(3_000_000_000u32.to_be_bytes()); // creation (1904 纪元)
+    mvhd.extend(0u32.to_be_bytes());
+    mvhd.extend(600u32.to_be_bytes());
+    mvhd.extend(6000u32.to_be_bytes());
+    mvhd.extend([0; 80]);
+
+    let mut tkhd = vec![0u8; 40];
+    for v in [0u32, 0x0001_0000, 0, 0xFFFF_0000, 0, 0, 0, 0, 0x4000_0000] {
+        tkhd.extend(v.to_be_bytes());
+    }
+    tkhd.extend((1920u32 << 16).to_be_bytes());
+    tkhd.extend((1080u32 << 16).to_be_bytes());
+
+    let keys_list = [
+        "com.apple.quicktime.make",
+        "com.apple.quicktime.creationdate",
+        "com.apple.quicktime.location.ISO6709",
+    ];
+    let mut keys = 3u32.to_be_bytes().to_vec();
+    for key in keys_list {
+        keys.extend(((key.len() + 8) as u32).to_be_bytes());
+        keys.extend(b"mdta");
+        keys.extend(key.as_bytes());
+    }
+    let values = [
+        "Apple",
+        "2023-05-01T10:00:00+0800",
+        "+37.5000-122.2500+010.500/",
+    ];
+    let mut ilst = Vec::new();
+    for (i, value) in values.iter().enumerate() {
+        let data = bx(
+            b"data",
+            &[&[0u8, 0, 0, 1, 0, 0, 0, 0][..], value.as_bytes()].concat(),
+        );
+        ilst.extend(bx(&((i + 1) as u32).to_be_bytes(), &data));
+    }
+    let meta_box = bx(
+        b"meta",
+        &[bx(b"keys", &full(0, &keys)), bx(b"ilst", &ilst)].concat(),
+    );
+
+    let moov = bx(
+        b"moov",
+        &[
+            bx(b"mvhd", &mvhd),
+            bx(b"trak", &bx(b"tkhd", &tkhd)),
+            meta_box,
+        ]
+        .concat(),
+    );
+    let file = [
+        bx(b"ftyp", b"qt  \0\0\0\0qt  "),
+        bx(b"mdat", &[0; 16]),
+        moov,
+    ]
+    .concat();
+
+    let meta = read(file);
+    assert_eq!((meta.width, meta.height), (1920, 1080));
+    assert_eq!(meta.rotation, Some(90));
+    assert_eq!(meta.display_size(), (1080, 1920));
+    assert_eq!(meta.duration_ms, Some(10_000));
+    assert_eq!(meta.taken_at, Some(TAKEN_AT_MS));
+    assert_eq!(meta.make.as_deref(), Some("Apple"));
+    let gps = meta.gps.unwrap();
+    assert!((gps.latitude - 37.5).abs() < 1e-9);
+    assert!((gps.longitude + 122.25).abs() < 1e-9);
+}
+
+/// HEIC：主图 ispe 尺寸 + 通过 iloc 定位的 Exif 项
+#[test]
+fn test_heic_primary_item_and_exif() {
+    let exif_block = [vec![0, 0, 0, 6], b"Exif\0\0".to_vec(), camera_exif()].concat();
+    let build = |exif_offset: u32| {
+        let pitm = bx(b"pitm", &full(0, &1u16.to_be_bytes()));
+        let infe = bx(b"infe", &full(2, &[0, 2, 0, 0, b'E', b'x', b'i', b'f']));
+        let iinf = bx(
+            b"iinf",
+            &full(0, &[&1u16.to_be_bytes()[..], &infe].concat()),
+        );
+        let mut iloc = vec![0x44, 0x00];
+        iloc.extend(1u16.to_be_bytes());
+        iloc.extend(2u16.to_be_bytes()); // item_ID
+        iloc.extend(0u16.to_be_bytes()); // data_reference_index
+        iloc.extend(1u16.to_be_bytes()); // extent_count
+        iloc.extend(exif_offset.to_be_bytes());
+        iloc.extend((exif_block.len() as u32).to_be_bytes());
+        let iloc = bx(b"iloc", &full(0, &iloc));
+        let ispe = bx(
+            b"ispe",
+            &full(
+                0,
+                &[&4032u32.to_be_bytes()[..], &3024u32.to_be_bytes()].concat(),
+            ),
+        );
+        let ipco = bx(b"ipco", &ispe);
+        let ipma = bx(b"ipma", &full(0, &[0, 0, 0, 1, 0, 1, 1, 0x81]));
+        let iprp = bx(b"iprp", &[ipco, ipma].concat());
+        let meta = bx(b"meta", &full(0, &[pitm, iinf, iloc, iprp].concat()));
+        [bx(b"ftyp", b"heic\0\0\0\0mif1heic"), meta].concat()
+    };
+    let head_len = build(0).len() as u32;
+    let file = [build(head_len + 8), bx(b"mdat", &exif_block)].concat();
+
+    let meta = read(file);
+    assert_eq!((meta.width, meta.height), (4032, 3024));
+    assert_eq!(meta.orientation, Some(6));
+    assert_camera(&meta);
+}
+
+/// 无法识别或截断的文件不报错，返回空元数据
+#[test]
+fn test_unknown_or_truncated_input() {
+    assert_eq!(read(b"hello world".to_vec()), MediaMetadata::default());
+    assert_eq!(read(vec![0xFF, 0xD8, 0xFF, 0xE1, 0xFF]).width, 0);
+}
+
+/// 64 位 largesize 声明超大尺寸（接近 u64::MAX）时不溢出、不 panic
+#[test]
+fn test_huge_largesize_boxes() {
+    fn large(kind: &[u8; 4], size: u64, body: &[u8]) -> Vec<u8> {
+        let mut out = 1u32.to_be_bytes().to_vec();
+        out.extend(kind);
+        out.extend(size.to_be_bytes());
+        out.extend(body);
+        out
+    }
+    let ftyp = bx(b"ftyp", b"qt  \0\0\0\0");
+
+    // 简单的 xorshift，生成可复现的伪随机尺寸
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    let mut sizes = vec![u64::MAX, u64::MAX - 7, u64::MAX - 16, 1 << 63, 16];
+    for _ in 0..200 {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        sizes.push(state | (1 << 63));
+    }
+
+    for size in sizes {
+        let payload = size.to_le_bytes();
+        let inputs = [
+            // 顶层
+            [ftyp.clone(), large(b"mdat", size, &payload)].concat(),
+            // moov 内
+            [
+                ftyp.clone(),
+                bx(b"moov", &large(b"trak", size, &payload)),
+            ]
+            .concat(),
+            // HEIF meta（full box）内
+            [
+                ftyp.clone(),
+                bx(b"meta", &full(0, &large(b"iinf", size, &payload))),
+            ]
+            .concat(),
+        ];
+        for input in inputs {
+            let meta = read(input);
+            assert_eq!(meta.width, 0, "largesize {}", size);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use common::{query_i64, TempDb};
+use tauri_app_lib::db::migration::{self, Migration};
+use tauri_app_lib::db::{check_schema_version, AppDatabase};
+use tauri_app_lib::error::ErrorCode;
+
+/// 全新数据库建表后应处于最新版本，重复执行不重复应用
+#[tokio::test]
+async fn test_fresh_database_reaches_latest_version() {
+    let tmp = TempDb::with_schema("echo_trails_migration_fresh").await;
+
+    let version = migration::current_version(&tmp.conn).await.unwrap();
+    assert_eq!(version, migration::latest_version());
+
+    let again = migration::run_migrations(&tmp.conn).await.unwrap();
+    assert_eq!(again, version);
+    let applied = query_i64(&tmp.conn, "SELECT COUNT(*) FROM schema_version").await;
+    assert_eq!(applied, migration::MIGRATIONS.len() as i64);
+    // 列表按 taken_at 排序：只有 taken_at 索引，不建 last_modified 键集索引
+    let index_count = |name: &str| {
+        format!(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name = '{}'",
+            name
+        )
+    };
+    assert_eq!(query_i64(&tmp.conn, &index_count("idx_photos_keyset")).await, 0);
+    assert_eq!(query_i64(&tmp.conn, &index_count("idx_photos_taken_at")).await, 1);
+
+    tmp.cleanup();
+}
+
+/// 失败的迁移整体回滚，版本停留在上一个成功的迁移
+#[tokio::test]
+async fn test_failed_migration_rolls_back() {
+    let tmp = TempDb::open("echo_trails_migration_rollback").await;
+    let migrations = [
+        Migration {
+            version: 1,
+            name: "create_notes",
+            statements: &["CREATE TABLE notes (id TEXT PRIMARY KEY)"],
+            run: None,
+        },
+        Migration {
+            version: 2,
+            name: "broken",
+            statements: &[
+                "ALTER TABLE notes ADD COLUMN title TEXT",
+                "INSERT INTO missing_table (id) VALUES ('x')",
+            ],
+            run: None,
+        },
+    ];
+
+    let err = migration::apply_migrations(&tmp.conn, &migrations)
+        .await
+        .unwrap_err();
+    assert_eq!(err.code, ErrorCode::Storage);
+    assert!(err.message.contains("Migration 2 (broken) failed"), "{}", err);
+    assert_eq!(migration::current_version(&tmp.conn).await.unwrap(), 1);
+
+    // 第二个迁移的 ADD COLUMN 也应被回滚
+    let title_cols = query_i64(
+        &tmp.conn,
+        "SELECT COUNT(*) FROM pragma_table_info('notes') WHERE name = 'title'",
+    )
+    .await;
+    assert_eq!(title_cols, 0);
+
+    tmp.cleanup();
+}
+
+/// 数据库版本高于当前 App 所知版本时拒绝迁移
+#[tokio::test]
+async fn test_newer_database_is_rejected() {
+    let tmp = TempDb::with_schema("echo_trails_migration_newer").await;
+    let future = migration::latest_version() + 1;
+    tmp.conn
+        .execute(
+            "INSERT INTO schema_version (version, name) VALUES (?1, 'from_the_future')",
+            (future,),
+        )
+        .await
+        .unwrap();
+
+    let err = migration::run_migrations(&tmp.conn).await.unwrap_err();
+    assert_eq!(err.code, ErrorCode::Validation);
+    assert!(err.message.contains("newer than this app supports"), "{}", err);
+
+    tmp.cleanup();
+}
+
+/// 数据库版本过新时，之后的所有连接都返回该错误
+#[tokio::test]
+async fn test_newer_database_refuses_connections() {
+    let tmp = TempDb::with_schema("echo_trails_migration_refuse").await;
+    let db = AppDatabase::new(tmp.db.clone());
+    check_schema_version(&db).await.unwrap();
+    assert!(db.connect().is_ok());
+
+    tmp.conn
+        .execute(
+            "INSERT INTO schema_version (version, name) VALUES (?1, 'from_the_future')",
+            (migration::latest_version() + 1,),
+        )
+        .await
+        .unwrap();
+    let err = check_schema_version(&db).await.unwrap_err();
+    assert_eq!(err.code, ErrorCode::Validation);
+    let refused = db.connect().err().unwrap();
+    assert_eq!(refused, err);
+
+    tmp.cleanup();
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use common::TempDb;
+use serde_json::json;
+use tauri_app_lib::db::model::{query_model, query_models, Model, Photo, Todo, UsageRecord};
+
+/// 按列名解码，输出与原 merge_row 形状一致：data 展开、id 同时作为 _id
+#[tokio::test]
+async fn test_photo_decodes_by_name_and_keeps_json_shape() {
+    let tmp = TempDb::with_schema("echo_trails_model_photo").await;
+    tmp.conn
+        .execute(
+            "INSERT INTO photos (id, remote_id, is_liked, type, last_modified, md5, data)
+             VALUES ('p1', 'srv-1', 1, 'image', '1700000000000', 'abc', '{\"key\":\"a.jpg\",\"width\":10}')",
+            (),
+        )
+        .await
+        .unwrap();
+
+    let photo: Photo = query_model(&tmp.conn, "SELECT * FROM photos WHERE id = ?1", ("p1",))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(photo.kind.as_deref(), Some("image"));
+    assert_eq!(photo.is_liked, 1);
+
+    let value = photo.to_json();
+    assert_eq!(value["_id"], "p1");
+    assert_eq!(value["id"], "p1");
+    assert_eq!(value["remote_id"], "srv-1");
+    assert_eq!(value["type"], "image");
+    assert_eq!(value["last_modified"], "1700000000000");
+    assert_eq!(value["deleted"], 0);
+    assert_eq!(value["key"], "a.jpg");
+    assert_eq!(value["width"], 10);
+    assert!(value.get("data").is_none());
+
+    tmp.cleanup();
+}
+
+/// 迁移追加列、或列顺序与结构体不同时仍能正确解码
+#[tokio::test]
+async fn test_decoder_ignores_column_order_and_extra_columns() {
+    let tmp = TempDb::with_schema("echo_trails_model_order").await;
+    tmp.conn
+        .execute("ALTER TABLE todos ADD COLUMN extra TEXT", ())
+        .await
+        .unwrap();
+    tmp.conn
+        .execute(
+            "INSERT INTO todos (id, completed, quadrant, data, extra) VALUES ('t1', 1, 2, '{\"title\":\"x\"}', 'y')",
+            (),
+        )
+        .await
+        .unwrap();
+
+    let todos: Vec<Todo> = query_models(
+        &tmp.conn,
+        "SELECT data, quadrant, id, extra, completed FROM todos",
+        (),
+    )
+    .await
+    .unwrap();
+    assert_eq!(todos.len(), 1);
+    assert_eq!(todos[0].id, "t1");
+    assert_eq!(todos[0].completed, 1);
+    assert_eq!(todos[0].quadrant, 2);
+    assert_eq!(todos[0].to_json()["title"], "x");
+
+    tmp.cleanup();
+}
+
+/// usage_records 的 deleted 列不再错位到 target_id
+#[tokio::test]
+async fn test_usage_record_columns_are_not_shifted() {
+    let tmp = TempDb::with_schema("echo_trails_model_usage").await;
+    tmp.conn
+        .execute(
+            "INSERT INTO usage_records (id, target_id, target_type, action_type, data)
+             VALUES ('u1', 'asset-1', 'asset', 'use', '{}')",
+            (),
+        )
+        .await
+        .unwrap();
+
+    let records: Vec<UsageRecord> = query_models(&tmp.conn, "SELECT * FROM usage_records", ())
+        .await
+        .unwrap();
+    let value = records[0].to_json();
+    assert_eq!(value["target_id"], json!("asset-1"));
+    assert_eq!(value["target_type"], json!("asset"));
+    assert_eq!(value["action_type"], json!("use"));
+    assert_eq!(value["deleted"], json!(0));
+
+    tmp.cleanup();
+}
@@ -0,0 +1,315 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use chrono::{TimeZone, Utc};
+use common::{http_response, query_i64, spawn_http_server, TempDb};
+use tauri_app_lib::command::multipart::{part_size_for, upload_multipart, MIN_PART_SIZE};
+use tauri_app_lib::command::s3_presign::{
+    complete_multipart_upload_body, presign_upload_part_url, xml_tag_text, PresignMultipartParams,
+    S3Credentials,
+};
+use tauri_app_lib::command::s3_profile::ProviderProfile;
+use tauri_app_lib::db::multipart::{self, MultipartUpload, UploadedPart};
+
+const MB: u64 = 1024 * 1024;
+
+/// 极简 S3 模拟：记录请求，`fail_part` 指定的分片第一次返回 500
+#[derive(Default)]
+struct FakeS3 {
+    requests: Vec<(String, String)>,
+    complete_body: Option<String>,
+    fail_part: Option<u32>,
+}
+
+async fn spawn_fake_s3(state: Arc<Mutex<FakeS3>>) -> String {
+    spawn_http_server(move |req| {
+        let part_number: Option<u32> = req
+            .query()
+            .split('&')
+            .find_map(|p| p.strip_prefix("partNumber="))
+            .and_then(|v| v.parse().ok());
+        let mut s = state.lock().unwrap();
+        s.requests.push((req.method.clone(), req.target.clone()));
+        let xml = [("Content-Type", "application/xml")];
+        match (req.method.as_str(), part_number) {
+            ("POST", _) if req.query().contains("uploads=") => http_response(
+                "200 OK",
+                &xml,
+                "<InitiateMultipartUploadResult><UploadId>upload-1</UploadId></InitiateMultipartUploadResult>",
+            ),
+            ("PUT", Some(n)) if s.fail_part == Some(n) => {
+                s.fail_part = None;
+                http_response("500 Internal Server Error", &[], "")
+            }
+            ("PUT", Some(n)) => {
+                http_response("200 OK", &[("ETag", &format!("\"etag-{}\"", n))], "")
+            }
+            ("POST", _) => {
+                s.complete_body = Some(String::from_utf8_lossy(&req.body).to_string());
+                http_response(
+                    "200 OK",
+                    &xml,
+                    "<CompleteMultipartUploadResult><ETag>\"final\"</ETag></CompleteMultipartUploadResult>",
+                )
+            }
+            _ => http_response("204 No Content", &[], ""),
+        }
+    })
+    .await
+}
+
+fn credentials(endpoint: &str) -> S3Credentials {
+    S3Credentials::from_parts(
+        Some("bucket".to_string()),
+        Some("us-east-1".to_string()),
+        Some(endpoint.to_string()),
+        Some("ak".to_string()),
+        Some("sk".to_string()),
+        ProviderProfile::default(),
+    )
+    .unwrap()
+}
+
+/// 分片失败后重试只上传缺失分片，Complete 携带全部 ETag，完成后清除记录
+#[tokio::test]
+async fn test_upload_resumes_from_last_part() {
+    let tmp = TempDb::with_schema("echo_trails_multipart_resume").await;
+    let state = Arc::new(Mutex::new(FakeS3 {
+        fail_part: Some(2),
+        ..Default::default()
+    }));
+    let endpoint = spawn_fake_s3(state.clone()).await;
+    let creds = credentials(&endpoint);
+    let client = reqwest::Client::new();
+
+    let source = std::env::temp_dir().join(format!("echo_trails_mp_{}.bin", uuid::Uuid::new_v4()));
+    std::fs::write(&source, vec![7u8; (12 * MB) as usize]).unwrap();
+    let source_path = source.to_string_lossy().to_string();
+    let open = || async { tokio::fs::File::open(&source).await.unwrap() };
+
+    let err = upload_multipart(
+        &tmp.conn,
+        &client,
+        &creds,
+        "video/a.mp4",
+        &source_path,
+        open().await,
+        Some(MIN_PART_SIZE),
+        |_, _| {},
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.details, Some(serde_json::json!({ "status": 500 })));
+    let pending = multipart::find_upload(&tmp.conn, "bucket", "video/a.mp4")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(pending.upload_id, "upload-1");
+    assert_eq!(pending.part_count(), 3);
+    let parts = multipart::list_parts(&tmp.conn, "upload-1").await.unwrap();
+    assert_eq!(parts.len(), 1);
+    assert_eq!(parts[0].etag, "\"etag-1\"");
+
+    // 续传：进度从已完成的 5 MiB 开始
+    state.lock().unwrap().requests.clear();
+    let mut progress = Vec::new();
+    upload_multipart(
+        &tmp.conn,
+        &client,
+        &creds,
+        "video/a.mp4",
+        &source_path,
+        open().await,
+        Some(MIN_PART_SIZE),
+        |done, total| progress.push((done, total)),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        progress,
+        vec![(5 * MB, 12 * MB), (10 * MB, 12 * MB), (12 * MB, 12 * MB)]
+    );
+
+    let (requests, body) = {
+        let s = state.lock().unwrap();
+        (s.requests.clone(), s.complete_body.clone().unwrap())
+    };
+    let methods: Vec<&str> = requests.iter().map(|(m, _)| m.as_str()).collect();
+    assert_eq!(methods, vec!["PUT", "PUT", "POST"]);
+    assert!(requests[0].1.contains("partNumber=2"));
+    assert!(requests[1].1.contains("partNumber=3"));
+    for n in 1..=3 {
+        assert!(
+            body.contains(&format!(
+                "<PartNumber>{}</PartNumber><ETag>&quot;etag-{}&quot;</ETag>",
+                n, n
+            )),
+            "{}",
+            body
+        );
+    }
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT COUNT(*) FROM multipart_uploads").await,
+        0
+    );
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT COUNT(*) FROM multipart_parts").await,
+        0
+    );
+
+    let _ = std::fs::remove_file(&source);
+    tmp.cleanup();
+}
+
+/// 源文件大小不变但修改时间变了：丢弃旧会话重新上传全部分片
+#[tokio::test]
+async fn test_modified_source_restarts_upload() {
+    let tmp = TempDb::with_schema("echo_trails_multipart_mtime").await;
+    let state = Arc::new(Mutex::new(FakeS3 {
+        fail_part: Some(2),
+        ..Default::default()
+    }));
+    let endpoint = spawn_fake_s3(state.clone()).await;
+    let creds = credentials(&endpoint);
+    let client = reqwest::Client::new();
+
+    let source = std::env::temp_dir().join(format!("echo_trails_mp_{}.bin", uuid::Uuid::new_v4()));
+    std::fs::write(&source, vec![7u8; (12 * MB) as usize]).unwrap();
+    let source_path = source.to_string_lossy().to_string();
+    let open = || async { tokio::fs::File::open(&source).await.unwrap() };
+    let upload = |file| {
+        upload_multipart(
+            &tmp.conn,
+            &client,
+            &creds,
+            "video/a.mp4",
+            &source_path,
+            file,
+            Some(MIN_PART_SIZE),
+            |_, _| {},
+        )
+    };
+
+    upload(open().await).await.unwrap_err();
+    let pending = multipart::find_upload(&tmp.conn, "bucket", "video/a.mp4")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(pending.source_mtime.is_some());
+
+    // 同样大小的新内容，修改时间前移一小时
+    std::fs::write(&source, vec![9u8; (12 * MB) as usize]).unwrap();
+    let modified = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+    std::fs::File::options()
+        .write(true)
+        .open(&source)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+
+    state.lock().unwrap().requests.clear();
+    upload(open().await).await.unwrap();
+    let requests = state.lock().unwrap().requests.clone();
+    let methods: Vec<&str> = requests.iter().map(|(m, _)| m.as_str()).collect();
+    // 中止旧会话、新建会话、重传 3 个分片、Complete
+    assert_eq!(methods, vec!["DELETE", "POST", "PUT", "PUT", "PUT", "POST"]);
+    assert!(requests[0].1.contains("uploadId=upload-1"));
+    assert!(requests[2].1.contains("partNumber=1"));
+
+    let _ = std::fs::remove_file(&source);
+    tmp.cleanup();
+}
+
+/// 会话记录：分片进度汇总、同分片重传覆盖 ETag
+#[tokio::test]
+async fn test_session_records() {
+    let tmp = TempDb::with_schema("echo_trails_multipart_db").await;
+    let upload = MultipartUpload {
+        upload_id: "u1".to_string(),
+        object_key: "k".to_string(),
+        bucket: "b".to_string(),
+        source_path: "/tmp/x".to_string(),
+        file_size: 11 * MB,
+        source_mtime: Some(1_700_000_000_000),
+        part_size: 5 * MB,
+    };
+    assert_eq!(upload.part_count(), 3);
+    assert_eq!(upload.part_range(3), (10 * MB, MB));
+    multipart::create_upload(&tmp.conn, &upload).await.unwrap();
+    for (n, etag) in [(2, "e2"), (1, "e1"), (2, "e2b")] {
+        let part = UploadedPart {
+            part_number: n,
+            etag: etag.to_string(),
+            size: 5 * MB,
+        };
+        multipart::record_part(&tmp.conn, "u1", &part)
+            .await
+            .unwrap();
+    }
+    let parts = multipart::list_parts(&tmp.conn, "u1").await.unwrap();
+    let etags: Vec<&str> = parts.iter().map(|p| p.etag.as_str()).collect();
+    assert_eq!(etags, vec!["e1", "e2b"]);
+
+    let uploads = multipart::list_uploads(&tmp.conn).await.unwrap();
+    assert_eq!(uploads, vec![(upload.clone(), 10 * MB)]);
+    assert_eq!(
+        multipart::find_upload(&tmp.conn, "b", "k").await.unwrap(),
+        Some(upload)
+    );
+    multipart::remove_upload(&tmp.conn, "u1").await.unwrap();
+    assert!(multipart::find_upload(&tmp.conn, "b", "k")
+        .await
+        .unwrap()
+        .is_none());
+
+    tmp.cleanup();
+}
+
+/// 分片大小下限与 10000 片上限；预签名 URL 与 XML 辅助函数
+#[test]
+fn test_part_size_and_presign_helpers() {
+    assert_eq!(part_size_for(MB, None), 8 * MB);
+    assert_eq!(part_size_for(MB, Some(MB)), MIN_PART_SIZE);
+    assert_eq!(part_size_for(100_000 * MB, None), 10 * MB);
+
+    let params = PresignMultipartParams {
+        key: "a b.mp4",
+        bucket: "bucket",
+        region: "us-east-1",
+        endpoint: "https://s3.example.com",
+        access_key: "ak",
+        secret_key: "sk",
+        expires_seconds: 3600,
+        profile: &ProviderProfile::default(),
+    };
+    let now = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let url = presign_upload_part_url(&params, "id/+=", 7, now).unwrap();
+    assert!(
+        url.starts_with("https://s3.example.com/bucket/a%20b.mp4?"),
+        "{}",
+        url
+    );
+    assert!(url.contains("partNumber=7"));
+    assert!(url.contains("uploadId=id%2F%2B%3D"));
+    assert!(url.contains("X-Amz-Signature="));
+    assert_eq!(
+        url,
+        presign_upload_part_url(&params, "id/+=", 7, now).unwrap()
+    );
+    assert_ne!(
+        url,
+        presign_upload_part_url(&params, "id/+=", 8, now).unwrap()
+    );
+
+    let body = complete_multipart_upload_body(&[(2, "b".to_string()), (1, "a&".to_string())]);
+    assert_eq!(
+        body,
+        "<CompleteMultipartUpload xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\"><Part><PartNumber>1</PartNumber><ETag>a&amp;</ETag></Part><Part><PartNumber>2</PartNumber><ETag>b</ETag></Part></CompleteMultipartUpload>"
+    );
+    assert_eq!(
+        xml_tag_text("<R><UploadId>x&amp;y</UploadId></R>", "UploadId").as_deref(),
+        Some("x&y")
+    );
+    assert!(xml_tag_text("<R/>", "UploadId").is_none());
+}
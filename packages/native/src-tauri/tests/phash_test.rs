@@ -0,0 +1,234 @@
+mod common;
+
+use common::{query_i64, TempDb};
+use image::{DynamicImage, ImageFormat, RgbImage};
+use tauri_app_lib::command::phash;
+use tauri_app_lib::db::photo;
+
+/// 带渐变和色块的测试图，seed 不同则结构不同
+fn sample_image(width: u32, height: u32, seed: u32) -> DynamicImage {
+    let img = RgbImage::from_fn(width, height, |x, y| {
+        let (fx, fy) = (x * 256 / width, y * 256 / height);
+        let v = match seed {
+            0 => (fx + fy) / 2,
+            1 => 255 - fx,
+            _ => ((fx / 32 + fy / 32) % 2) * 255,
+        };
+        let block = if fx > 96 && fx < 160 && fy > 64 && fy < 128 {
+            200
+        } else {
+            0
+        };
+        image::Rgb([v as u8, (v as u8).wrapping_add(block), (fy as u8) / 2])
+    });
+    DynamicImage::ImageRgb8(img)
+}
+
+/// 缩放、JPEG 重压缩后的副本与原图距离很小，不同图距离很大
+#[test]
+fn test_dhash_tolerates_resize_and_recompression() {
+    let original = sample_image(640, 480, 0);
+    let mut jpeg = Vec::new();
+    original
+        .resize_exact(200, 150, image::imageops::FilterType::Lanczos3)
+        .write_to(&mut std::io::Cursor::new(&mut jpeg), ImageFormat::Jpeg)
+        .unwrap();
+    let copy = image::load_from_memory(&jpeg).unwrap();
+
+    let a = phash::dhash(&original);
+    let b = phash::dhash(&copy);
+    assert!(
+        phash::hamming(a, b) <= 4,
+        "distance {}",
+        phash::hamming(a, b)
+    );
+
+    let other = phash::dhash(&sample_image(640, 480, 1));
+    assert!(phash::hamming(a, other) > 16);
+    let other = phash::dhash(&sample_image(640, 480, 2));
+    assert!(phash::hamming(a, other) > 16);
+
+    assert_eq!(phash::parse_hex(&phash::to_hex(a)), Some(a));
+    assert_eq!(phash::parse_hex("xyz"), None);
+}
+
+/// EXIF 方向 6 的原图与已旋转像素的副本哈希一致
+#[test]
+fn test_apply_orientation_matches_baked_rotation() {
+    let upright = sample_image(300, 200, 0).rotate90();
+    let stored = sample_image(300, 200, 0);
+    assert_eq!(
+        phash::dhash(&upright),
+        phash::dhash(&phash::apply_orientation(stored, 6))
+    );
+}
+
+/// 聚类：链式相近的哈希并入同一簇，远离的不并入
+#[test]
+fn test_cluster_groups_within_threshold() {
+    let base = 0x0f0f_f0f0_1234_abcdu64;
+    let hashes = vec![
+        base,
+        base ^ 0b111,          // 距 base 3
+        base ^ 0b11_1111_1000,// 距 base 7，距上一个 10
+        !base,                 // 远离
+        0,
+        1, // 距 0 为 1
+    ];
+    let clusters = phash::cluster(&hashes, 7);
+    assert_eq!(clusters, vec![vec![0, 1, 2], vec![4, 5]]);
+
+    let clusters = phash::cluster(&hashes, 3);
+    assert_eq!(clusters, vec![vec![0, 1], vec![4, 5]]);
+    assert!(phash::cluster(&hashes, 0).is_empty());
+}
+
+/// 查找相似照片并保留分辨率最大的版本，其余移入回收站
+#[tokio::test]
+async fn test_find_and_resolve_similar_photos() {
+    let tmp = TempDb::with_schema("echo_trails_phash").await;
+    let base = 0x8888_4444_2222_1111u64;
+    let rows = [
+        (
+            "small",
+            base ^ 1,
+            r#"{"width":800,"height":600,"size":100}"#,
+            0,
+        ),
+        (
+            "large",
+            base,
+            r#"{"width":4000,"height":3000,"size":900}"#,
+            0,
+        ),
+        (
+            "mid",
+            base ^ 0b11,
+            r#"{"width":1920,"height":1440,"size":400}"#,
+            0,
+        ),
+        ("other", !base, r#"{"width":4000,"height":3000}"#, 0),
+        ("trashed", base, r#"{"width":8000,"height":6000}"#, 1),
+    ];
+    for (id, hash, data, deleted) in rows {
+        tmp.conn
+            .execute(
+                "INSERT INTO photos (id, phash, deleted, data) VALUES (?1, ?2, ?3, ?4)",
+                (id, phash::to_hex(hash), deleted, data),
+            )
+            .await
+            .unwrap();
+    }
+
+    let clusters = photo::find_similar_photos(&tmp.conn, 6).await.unwrap();
+    assert_eq!(clusters.len(), 1);
+    let cluster = &clusters[0];
+    assert_eq!(cluster["keepId"], "large");
+    let ids: Vec<&str> = cluster["photos"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, vec!["large", "mid", "small"]);
+    assert_eq!(cluster["photos"][2]["distance"], 1);
+
+    let ids: Vec<String> = ids.iter().map(|s| s.to_string()).collect();
+    let (kept, results) = photo::resolve_similar_photos(&tmp.conn, &ids, None, 6)
+        .await
+        .unwrap();
+    assert_eq!(kept, "large");
+    assert_eq!(results.len(), 2);
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT COUNT(*) FROM photos WHERE deleted = 1").await,
+        3
+    );
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT deleted FROM photos WHERE id = 'large'").await,
+        0
+    );
+    assert!(photo::find_similar_photos(&tmp.conn, 6)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // 显式指定的保留项必须属于该簇
+    let err = photo::resolve_similar_photos(&tmp.conn, &ids, Some("other"), 6).await;
+    assert!(err.is_err());
+
+    tmp.cleanup();
+}
+
+/// 链式相近的照片按保留项拆簇：离保留项超过阈值的不并入，也不会被移入回收站
+#[tokio::test]
+async fn test_similar_clusters_stay_within_threshold_of_kept_photo() {
+    let tmp = TempDb::with_schema("echo_trails_phash_chain").await;
+    let base = 0x0f0f_f0f0_1234_abcdu64;
+    let rows = [
+        ("best", base, r#"{"width":4000,"height":3000}"#),
+        // 距 best 5
+        ("near", base ^ 0x1f, r#"{"width":1920,"height":1440}"#),
+        // 距 near 5，距 best 10
+        ("far", base ^ 0x3ff, r#"{"width":800,"height":600}"#),
+        // 距 far 1，距 best 11
+        ("far2", base ^ 0x7ff, r#"{"width":640,"height":480}"#),
+    ];
+    for (id, hash, data) in rows {
+        tmp.conn
+            .execute(
+                "INSERT INTO photos (id, phash, data) VALUES (?1, ?2, ?3)",
+                (id, phash::to_hex(hash), data),
+            )
+            .await
+            .unwrap();
+    }
+
+    let clusters = photo::find_similar_photos(&tmp.conn, 6).await.unwrap();
+    let summary: Vec<(&str, Vec<&str>)> = clusters
+        .iter()
+        .map(|c| {
+            (
+                c["keepId"].as_str().unwrap(),
+                c["photos"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|p| p["id"].as_str().unwrap())
+                    .collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![("best", vec!["best", "near"]), ("far", vec!["far", "far2"])]
+    );
+
+    // 调用方传入整条链时，只回收与保留项足够接近的
+    let ids: Vec<String> = ["best", "near", "far", "missing"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let (kept, results) = photo::resolve_similar_photos(&tmp.conn, &ids, None, 6)
+        .await
+        .unwrap();
+    assert_eq!(kept, "best");
+    let outcome: Vec<(&str, bool)> = results.iter().map(|r| (r.id.as_str(), r.ok)).collect();
+    assert_eq!(
+        outcome,
+        vec![("near", true), ("far", false), ("missing", false)]
+    );
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            "SELECT COUNT(*) FROM photos WHERE deleted = 1 AND id = 'near'"
+        )
+        .await,
+        1
+    );
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT COUNT(*) FROM photos WHERE deleted = 1").await,
+        1
+    );
+
+    tmp.cleanup();
+}
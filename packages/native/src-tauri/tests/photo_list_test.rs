@@ -0,0 +1,427 @@
+mod common;
+
+use common::{query_i64, TempDb};
+use serde_json::{json, Value as JsonValue};
+use tauri_app_lib::db::migration::{self, MIGRATIONS};
+use tauri_app_lib::db::photo::{self, PhotoCursor, PhotoFilter, TimelineGranularity};
+use tauri_app_lib::db::schema_statements;
+use tauri_app_lib::error::ErrorCode;
+
+async fn collect_all(
+    conn: &turso::Connection,
+    filter: &PhotoFilter,
+    page_size: i64,
+) -> Vec<String> {
+    let mut ids = Vec::new();
+    let mut cursor: Option<PhotoCursor> = None;
+    loop {
+        let page = photo::list_photos_page(conn, filter, cursor.as_ref(), page_size, 0)
+            .await
+            .unwrap();
+        ids.extend(page.photos.iter().map(|p| p.id.clone()));
+        match page.next_cursor {
+            // 经过编码/解码往返，模拟前端回传
+            Some(c) => cursor = Some(PhotoCursor::decode(&c.encode()).unwrap()),
+            None => break,
+        }
+    }
+    ids
+}
+
+/// 游标翻页：同一拍摄时间与空拍摄时间的照片既不重复也不遗漏
+#[tokio::test]
+async fn test_cursor_pages_cover_ties_without_duplicates() {
+    let tmp = TempDb::with_schema("echo_trails_photo_cursor").await;
+    let values = (0..25)
+        .map(|i| {
+            let lm = match i % 5 {
+                0 => "NULL".to_string(),
+                n => format!("'17000000000{:02}'", n),
+            };
+            format!("('p{:02}', {}, '{{}}')", i, lm)
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    tmp.conn
+        .execute(
+            &format!(
+                "INSERT INTO photos (id, last_modified, data) VALUES {}",
+                values
+            ),
+            (),
+        )
+        .await
+        .unwrap();
+
+    let filter = PhotoFilter::default();
+    let ids = collect_all(&tmp.conn, &filter, 4).await;
+    assert_eq!(ids.len(), 25);
+    let mut unique = ids.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len(), 25);
+    // 最新的在前，空时间戳排在最后
+    assert_eq!(ids[0], "p24");
+    assert_eq!(ids[24], "p00");
+    assert_eq!(photo::count_photos(&tmp.conn, &filter).await.unwrap(), 25);
+
+    tmp.cleanup();
+}
+
+/// 相册筛选只依赖 photo_albums，迁移会回填旧的 data.albumId
+#[tokio::test]
+async fn test_album_filter_uses_links_backfilled_by_migration() {
+    let tmp = TempDb::open("echo_trails_photo_album_backfill").await;
+    for stmt in schema_statements() {
+        tmp.conn.execute(*stmt, ()).await.unwrap();
+    }
+    migration::apply_migrations(&tmp.conn, &MIGRATIONS[..3])
+        .await
+        .unwrap();
+    tmp.conn
+        .execute(
+            "INSERT INTO photos (id, last_modified, data) VALUES
+             ('p1', '1', '{\"albumId\":[\"a1\",\"a2\"]}'),
+             ('p2', '2', '{\"albumId\":[]}'),
+             ('p3', '3', '{}')",
+            (),
+        )
+        .await
+        .unwrap();
+    tmp.conn
+        .execute(
+            "INSERT INTO photo_albums (photo_id, album_id) VALUES ('p3', 'a1')",
+            (),
+        )
+        .await
+        .unwrap();
+
+    migration::run_migrations(&tmp.conn).await.unwrap();
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT COUNT(*) FROM photo_albums").await,
+        3
+    );
+
+    let filter = PhotoFilter {
+        album_id: Some("a1".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(collect_all(&tmp.conn, &filter, 1).await, vec!["p3", "p1"]);
+    assert_eq!(photo::count_photos(&tmp.conn, &filter).await.unwrap(), 2);
+
+    tmp.cleanup();
+}
+
+/// 非法游标返回校验错误
+#[tokio::test]
+async fn test_invalid_cursor_is_rejected() {
+    for cursor in ["zz", "abc", "7b7d"] {
+        let err = PhotoCursor::decode(cursor).unwrap_err();
+        assert_eq!(err.code, ErrorCode::Validation);
+    }
+    let cursor = PhotoCursor {
+        taken_at: None,
+        id: "p1".to_string(),
+    };
+    assert_eq!(PhotoCursor::decode(&cursor.encode()).unwrap(), cursor);
+}
+
+/// 拍摄时间优先级：data.takenAt > EXIF > last_modified
+#[test]
+fn test_derive_taken_at_priority() {
+    let exif = json!({
+        "exif": {
+            "DateTimeOriginal": { "description": "2023:05:01 10:00:00" },
+            "OffsetTimeOriginal": { "description": "+08:00" }
+        }
+    });
+    assert_eq!(
+        photo::derive_taken_at(&exif, Some("2024-01-01T00:00:00.000Z")).as_deref(),
+        Some("2023-05-01T02:00:00.000Z")
+    );
+
+    let explicit = json!({ "takenAt": 1682906400000i64, "exif": exif["exif"] });
+    assert_eq!(
+        photo::derive_taken_at(&explicit, None).as_deref(),
+        Some("2023-05-01T02:00:00.000Z")
+    );
+
+    assert_eq!(
+        photo::derive_taken_at(&json!({}), Some("1700000000000")).as_deref(),
+        Some("2023-11-14T22:13:20.000Z")
+    );
+    assert_eq!(
+        photo::derive_taken_at(&json!({}), Some("2024-02-03T04:05:06.789Z")).as_deref(),
+        Some("2024-02-03T04:05:06.789Z")
+    );
+    assert_eq!(photo::derive_taken_at(&json!({}), None), None);
+}
+
+/// 迁移为旧数据补齐 taken_at，日期筛选与排序都按拍摄时间
+#[tokio::test]
+async fn test_taken_at_backfill_drives_order_and_date_filter() {
+    let tmp = TempDb::open("echo_trails_photo_taken_at").await;
+    for stmt in schema_statements() {
+        tmp.conn.execute(*stmt, ()).await.unwrap();
+    }
+    migration::apply_migrations(&tmp.conn, &MIGRATIONS[..4])
+        .await
+        .unwrap();
+    // p1 拷贝时 mtime 被刷新，但 EXIF 记录的是更早的拍摄时间
+    tmp.conn
+        .execute(
+            "INSERT INTO photos (id, last_modified, data) VALUES
+             ('p1', '2024-06-01T00:00:00.000Z', '{\"exif\":{\"DateTimeOriginal\":{\"description\":\"2020:01:02 03:04:05\"},\"OffsetTimeOriginal\":{\"description\":\"+00:00\"}}}'),
+             ('p2', '2022-03-04T00:00:00.000Z', '{}')",
+            (),
+        )
+        .await
+        .unwrap();
+
+    migration::run_migrations(&tmp.conn).await.unwrap();
+    let filter = PhotoFilter::default();
+    assert_eq!(collect_all(&tmp.conn, &filter, 10).await, vec!["p2", "p1"]);
+
+    let filter = PhotoFilter {
+        start_date: Some("2020-01-01T00:00:00.000Z".to_string()),
+        end_date: Some("2020-12-31T23:59:59.999Z".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(collect_all(&tmp.conn, &filter, 10).await, vec!["p1"]);
+
+    // 数据被外部改写后，回填命令重新推导
+    tmp.conn
+        .execute(
+            "UPDATE photos SET data = '{\"takenAt\":\"2019-05-05T00:00:00.000Z\"}' WHERE id = 'p2'",
+            (),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        photo::backfill_taken_at(&tmp.conn, true).await.unwrap(),
+        (0, 0)
+    );
+    assert_eq!(
+        photo::backfill_taken_at(&tmp.conn, false).await.unwrap(),
+        (2, 1)
+    );
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            "SELECT COUNT(*) FROM photos WHERE id = 'p2' AND taken_at = '2019-05-05T00:00:00.000Z'"
+        )
+        .await,
+        1
+    );
+
+    tmp.cleanup();
+}
+
+/// 时间线按月分组：数量、每组最新一张作封面，遵循相同筛选条件
+#[tokio::test]
+async fn test_timeline_buckets_with_covers() {
+    let tmp = TempDb::with_schema("echo_trails_photo_timeline").await;
+    tmp.conn
+        .execute(
+            "INSERT INTO photos (id, taken_at, type, is_liked, data) VALUES
+             ('a', '2023-05-01T02:00:00.000Z', 'image/jpeg', 1, '{\"key\":\"a.jpg\"}'),
+             ('b', '2023-05-20T02:00:00.000Z', 'image/jpeg', 0, '{\"key\":\"b.jpg\"}'),
+             ('c', '2023-04-02T02:00:00.000Z', 'video/mp4', 1, '{}'),
+             ('d', '2022-12-31T23:00:00.000Z', 'image/png', 1, '{\"key\":\"d.png\"}'),
+             ('e', NULL, 'image/jpeg', 0, '{}')",
+            (),
+        )
+        .await
+        .unwrap();
+
+    let filter = PhotoFilter::default();
+    let months = photo::photo_timeline(&tmp.conn, &filter, TimelineGranularity::Month, 0)
+        .await
+        .unwrap();
+    let summary: Vec<(JsonValue, i64, JsonValue)> = months
+        .iter()
+        .map(|b| {
+            (
+                b["bucket"].clone(),
+                b["count"].as_i64().unwrap(),
+                b["cover"]["key"].clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (json!("2023-05"), 2, json!("b.jpg")),
+            (json!("2023-04"), 1, json!("c")),
+            (json!("2022-12"), 1, json!("d.png")),
+            (JsonValue::Null, 1, json!("e")),
+        ]
+    );
+    assert_eq!(months[0]["cover"]["takenAt"], "2023-05-20T02:00:00.000Z");
+
+    let liked_images = PhotoFilter {
+        liked_only: true,
+        type_prefix: Some("image".to_string()),
+        ..Default::default()
+    };
+    let years = photo::photo_timeline(&tmp.conn, &liked_images, TimelineGranularity::Year, 0)
+        .await
+        .unwrap();
+    assert_eq!(years.len(), 2);
+    assert_eq!(years[0]["bucket"], "2023");
+    assert_eq!(years[0]["cover"]["id"], "a");
+
+    // 按用户时区分组：UTC+8 下 d 拍摄于 2023-01-01
+    let local_days = photo::photo_timeline(&tmp.conn, &liked_images, TimelineGranularity::Day, 480)
+        .await
+        .unwrap();
+    let buckets: Vec<&JsonValue> = local_days.iter().map(|b| &b["bucket"]).collect();
+    assert_eq!(buckets, vec!["2023-05-01", "2023-01-01"]);
+    let local_years =
+        photo::photo_timeline(&tmp.conn, &liked_images, TimelineGranularity::Year, 480)
+            .await
+            .unwrap();
+    assert_eq!(local_years.len(), 1);
+    assert_eq!(local_years[0]["count"], 2);
+
+    assert!(TimelineGranularity::parse(Some("week")).is_err());
+
+    tmp.cleanup();
+}
+
+/// 那年今日：按本地日期匹配往年同日 ± 窗口，排除截图与今年，纪念日单独分组
+#[tokio::test]
+async fn test_on_this_day_groups_by_year_and_memorial() {
+    let tmp = TempDb::with_schema("echo_trails_photo_on_this_day").await;
+    tmp.conn
+        .execute(
+            "INSERT INTO photos (id, taken_at, deleted, data) VALUES
+             ('y22', '2022-04-30T23:30:00.000Z', 0, '{\"name\":\"IMG_1.jpg\"}'),
+             ('y21', '2021-05-02T04:00:00.000Z', 0, '{\"name\":\"IMG_2.jpg\"}'),
+             ('shot', '2021-05-01T04:00:00.000Z', 0, '{\"name\":\"Screenshot_20210501.png\"}'),
+             ('gone', '2020-05-01T04:00:00.000Z', 1, '{}'),
+             ('now', '2024-05-01T04:00:00.000Z', 0, '{}'),
+             ('far', '2021-05-09T04:00:00.000Z', 0, '{}'),
+             ('wed', '2019-10-01T04:00:00.000Z', 0, '{}')",
+            (),
+        )
+        .await
+        .unwrap();
+    tmp.conn
+        .execute(
+            "INSERT INTO memorials (id, data) VALUES
+             ('m1', '{\"name\":\"婚礼\",\"date\":\"2019-10-01\",\"showOnAlbumHome\":true}'),
+             ('m2', '{\"name\":\"隐藏\",\"date\":\"2021-05-09\",\"showOnAlbumHome\":false}')",
+            (),
+        )
+        .await
+        .unwrap();
+
+    let mut options = photo::OnThisDayOptions {
+        date: chrono::NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(),
+        window_days: 1,
+        exclude_screenshots: true,
+        include_memorials: true,
+        utc_offset_minutes: 480,
+    };
+    let data = photo::photos_on_this_day(&tmp.conn, &options)
+        .await
+        .unwrap();
+    let years: Vec<(i64, Vec<String>)> = data["years"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|y| {
+            (
+                y["year"].as_i64().unwrap(),
+                y["photos"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|p| p["id"].as_str().unwrap().to_string())
+                    .collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        years,
+        vec![
+            (2022, vec!["y22".to_string()]),
+            (2021, vec!["y21".to_string()])
+        ]
+    );
+    assert_eq!(data["years"][0]["yearsAgo"], 2);
+    let memorials = data["memorials"].as_array().unwrap();
+    assert_eq!(memorials.len(), 1);
+    assert_eq!(memorials[0]["memorial"]["id"], "m1");
+    assert_eq!(memorials[0]["photos"][0]["id"], "wed");
+
+    options.window_days = 0;
+    options.exclude_screenshots = false;
+    options.include_memorials = false;
+    let data = photo::photos_on_this_day(&tmp.conn, &options)
+        .await
+        .unwrap();
+    let ids: Vec<&str> = data["years"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .flat_map(|y| y["photos"].as_array().unwrap())
+        .map(|p| p["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, vec!["y22", "shot"]);
+    assert!(data["memorials"].as_array().unwrap().is_empty());
+
+    tmp.cleanup();
+}
+
+/// 跨年窗口按完整日期归入往年：去年底几天前的照片不算，前年底的照片归入去年
+#[tokio::test]
+async fn test_on_this_day_window_across_new_year() {
+    let tmp = TempDb::with_schema("echo_trails_photo_on_this_day_new_year").await;
+    tmp.conn
+        .execute(
+            "INSERT INTO photos (id, taken_at, deleted, data) VALUES
+             ('recent', '2023-12-31T10:00:00.000Z', 0, '{}'),
+             ('jan', '2023-01-03T10:00:00.000Z', 0, '{}'),
+             ('dec', '2022-12-31T10:00:00.000Z', 0, '{}'),
+             ('old', '2021-01-01T10:00:00.000Z', 0, '{}')",
+            (),
+        )
+        .await
+        .unwrap();
+
+    let options = photo::OnThisDayOptions {
+        date: chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+        window_days: 3,
+        exclude_screenshots: false,
+        include_memorials: false,
+        utc_offset_minutes: 0,
+    };
+    let data = photo::photos_on_this_day(&tmp.conn, &options)
+        .await
+        .unwrap();
+    let years: Vec<(i64, i64, Vec<&str>)> = data["years"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|y| {
+            (
+                y["year"].as_i64().unwrap(),
+                y["yearsAgo"].as_i64().unwrap(),
+                y["photos"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|p| p["id"].as_str().unwrap())
+                    .collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        years,
+        vec![(2023, 1, vec!["jan", "dec"]), (2021, 3, vec!["old"])]
+    );
+
+    tmp.cleanup();
+}
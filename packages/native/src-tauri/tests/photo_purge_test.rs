@@ -0,0 +1,197 @@
+mod common;
+
+use common::{query_i64, TempDb};
+use tauri_app_lib::command::s3_presign::S3Credentials;
+use tauri_app_lib::command::s3_profile::ProviderProfile;
+use tauri_app_lib::db::photo;
+use tauri_app_lib::db::search;
+use tauri_app_lib::error::ErrorCode;
+
+async fn seed(tmp: &TempDb) {
+    tmp.conn
+        .execute(
+            "INSERT INTO photos (id, remote_id, sync_status, data) VALUES
+             ('p1', 'r1', 'synced', '{\"name\":\"sunset.jpg\",\"key\":\"a/p1.jpg\"}'),
+             ('p2', NULL, 'local', '{\"name\":\"live.heic\",\"key\":\"a/p2.heic\",\"isLive\":true,\"liveVideoKey\":\"a/p2.mov\"}'),
+             ('p3', NULL, 'local', '{\"name\":\"keep.jpg\",\"key\":\"a/p3.jpg\"}')",
+            (),
+        )
+        .await
+        .unwrap();
+    tmp.conn
+        .execute(
+            "INSERT INTO photo_albums (photo_id, album_id) VALUES ('p1', 'a1'), ('p2', 'a1'), ('p3', 'a1')",
+            (),
+        )
+        .await
+        .unwrap();
+    search::rebuild_index(&tmp.conn).await.unwrap();
+}
+
+fn ids(values: &[&str]) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+/// 移入回收站记录时间，恢复时清空
+#[tokio::test]
+async fn test_trash_tracks_deleted_at() {
+    let tmp = TempDb::with_schema("echo_trails_trash_deleted_at").await;
+    seed(&tmp).await;
+
+    photo::set_photos_deleted(&tmp.conn, &ids(&["p1", "p2"]), true)
+        .await
+        .unwrap();
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            "SELECT COUNT(*) FROM photos WHERE deleted = 1 AND deleted_at IS NOT NULL"
+        )
+        .await,
+        2
+    );
+    photo::set_photos_deleted(&tmp.conn, &ids(&["p2"]), false)
+        .await
+        .unwrap();
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            "SELECT COUNT(*) FROM photos WHERE id = 'p2' AND deleted_at IS NULL"
+        )
+        .await,
+        1
+    );
+
+    tmp.cleanup();
+}
+
+/// 彻底删除只处理回收站中的照片：行、相册关联、搜索索引一并清理
+#[tokio::test]
+async fn test_purge_removes_rows_links_and_index() {
+    let tmp = TempDb::with_schema("echo_trails_photo_purge").await;
+    seed(&tmp).await;
+    photo::set_photos_deleted(&tmp.conn, &ids(&["p1", "p2"]), true)
+        .await
+        .unwrap();
+
+    let purge = photo::purge_photos(&tmp.conn, &ids(&["p1", "p2", "p3", "missing"]), None)
+        .await
+        .unwrap();
+    let ok: Vec<(&str, bool)> = purge
+        .results
+        .iter()
+        .map(|r| (r.id.as_str(), r.ok))
+        .collect();
+    assert_eq!(
+        ok,
+        vec![
+            ("p1", true),
+            ("p2", true),
+            ("p3", false),
+            ("missing", false)
+        ]
+    );
+    assert_eq!(purge.purged(), 2);
+    assert_eq!(purge.s3_failed, 0);
+
+    assert_eq!(query_i64(&tmp.conn, "SELECT COUNT(*) FROM photos").await, 1);
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT COUNT(*) FROM photo_albums").await,
+        1
+    );
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            "SELECT COUNT(*) FROM search_docs WHERE entity_id IN ('p1', 'p2')"
+        )
+        .await,
+        0
+    );
+    // 已同步的照片留下墓碑供同步推送删除；从未同步的 p2 不需要
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            "SELECT COUNT(*) FROM tombstones WHERE entity_type = 'photos' AND entity_id = 'p1'"
+        )
+        .await,
+        1
+    );
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT COUNT(*) FROM tombstones").await,
+        1
+    );
+
+    tmp.cleanup();
+}
+
+/// 保留期：只清理超期的回收站照片；无 deleted_at 的按 updated_at 计算；S3 失败计数但不阻塞
+#[tokio::test]
+async fn test_purge_expired_reports_s3_failures() {
+    let tmp = TempDb::with_schema("echo_trails_photo_purge_expired").await;
+    seed(&tmp).await;
+    tmp.conn
+        .execute(
+            "UPDATE photos SET deleted = 1, deleted_at = datetime('now', '-40 days') WHERE id = 'p2'",
+            (),
+        )
+        .await
+        .unwrap();
+    tmp.conn
+        .execute(
+            "UPDATE photos SET deleted = 1, deleted_at = datetime('now', '-3 days') WHERE id = 'p3'",
+            (),
+        )
+        .await
+        .unwrap();
+    tmp.conn
+        .execute(
+            "UPDATE photos SET deleted = 1, deleted_at = NULL, updated_at = '2020-01-01T00:00:00.000Z' WHERE id = 'p1'",
+            (),
+        )
+        .await
+        .unwrap();
+
+    // 不可达的端点：批量删除请求失败，整批对象（含 Live 视频）都记为失败
+    let credentials = S3Credentials::from_parts(
+        Some("bucket".to_string()),
+        Some(String::new()),
+        Some("http://127.0.0.1:9".to_string()),
+        Some("ak".to_string()),
+        Some("sk".to_string()),
+        ProviderProfile::default(),
+    )
+    .unwrap();
+    // 空 region 取服务商默认值（默认 Bitiful）
+    assert_eq!(credentials.region, "us-east-1");
+    let purge = photo::purge_expired_photos(
+        &tmp.conn,
+        photo::DEFAULT_TRASH_RETENTION_DAYS,
+        Some(&credentials),
+    )
+    .await
+    .unwrap();
+    assert_eq!(purge.purged(), 2);
+    assert_eq!(purge.s3_failed, 3);
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT COUNT(*) FROM photos WHERE id = 'p3'").await,
+        1
+    );
+
+    // 0 天即清空回收站
+    let purge = photo::purge_expired_photos(&tmp.conn, 0, None)
+        .await
+        .unwrap();
+    assert_eq!(purge.purged(), 1);
+    assert_eq!(query_i64(&tmp.conn, "SELECT COUNT(*) FROM photos").await, 0);
+
+    let err = photo::purge_expired_photos(&tmp.conn, -1, None)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(err.code, ErrorCode::Validation);
+    assert!(
+        S3Credentials::from_parts(None, None, None, None, None, ProviderProfile::default())
+            .is_none()
+    );
+
+    tmp.cleanup();
+}
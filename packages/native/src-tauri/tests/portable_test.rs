@@ -0,0 +1,172 @@
+mod common;
+
+use common::{query_i64, TempDb};
+use tauri_app_lib::db::portable;
+
+fn temp_dir(prefix: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("{}_{}", prefix, uuid::Uuid::new_v4()))
+}
+
+async fn seed(conn: &turso::Connection) {
+    conn.execute(
+        "INSERT INTO photos (id, remote_id, type, md5, data) VALUES
+         ('p1', 'srv-p1', 'image', 'md5-1', '{\"key\":\"photos/1.jpg\",\"width\":100}'),
+         ('p2', NULL, 'image', 'md5-2', '{\"key\":\"photos/2.jpg\"}')",
+        (),
+    )
+    .await
+    .unwrap();
+    conn.execute(
+        "INSERT INTO albums (id, data) VALUES ('a1', '{\"name\":\"Trip\"}')",
+        (),
+    )
+    .await
+    .unwrap();
+    conn.execute(
+        "INSERT INTO photo_albums (photo_id, album_id) VALUES ('p1', 'a1'), ('p2', 'a1')",
+        (),
+    )
+    .await
+    .unwrap();
+    conn.execute(
+        "INSERT INTO todos (id, quadrant, data) VALUES ('t1', 2, '{\"title\":\"Call\"}')",
+        (),
+    )
+    .await
+    .unwrap();
+}
+
+/// 导出为 merge_row 形状，导入到空库后数据与关联完整
+#[tokio::test]
+async fn test_export_then_import_round_trip() {
+    let source = TempDb::with_schema("echo_trails_portable_src").await;
+    seed(&source.conn).await;
+    let dir = temp_dir("echo_trails_portable_export");
+
+    let counts = portable::export_ndjson(&source.conn, &dir).await.unwrap();
+    assert_eq!(counts.get("photos"), Some(&2));
+    assert_eq!(counts.get("photo_albums"), Some(&2));
+    assert_eq!(counts.len(), portable::PORTABLE_TABLES.len());
+
+    let photos = std::fs::read_to_string(dir.join("photos.ndjson")).unwrap();
+    let first: serde_json::Value = serde_json::from_str(photos.lines().next().unwrap()).unwrap();
+    assert_eq!(first["_id"], first["id"]);
+    assert!(first.get("data").is_none());
+    assert!(first.get("key").is_some());
+
+    let target = TempDb::with_schema("echo_trails_portable_dst").await;
+    let report = portable::import_ndjson(&target.conn, &dir, false)
+        .await
+        .unwrap();
+    assert_eq!(report.tables["photos"].inserted, 2);
+    assert_eq!(report.tables["photo_albums"].inserted, 2);
+    assert_eq!(
+        query_i64(
+            &target.conn,
+            "SELECT COUNT(*) FROM photos WHERE id = 'p1' AND md5 = 'md5-1' AND json_extract(data, '$.width') = 100"
+        )
+        .await,
+        1
+    );
+    assert_eq!(
+        query_i64(
+            &target.conn,
+            "SELECT COUNT(*) FROM todos WHERE quadrant = 2"
+        )
+        .await,
+        1
+    );
+
+    // 再次导入：更新而非重复插入
+    let again = portable::import_ndjson(&target.conn, &dir, false)
+        .await
+        .unwrap();
+    assert_eq!(again.tables["photos"].updated, 2);
+    assert_eq!(again.tables["photo_albums"].skipped, 2);
+    assert_eq!(
+        query_i64(&target.conn, "SELECT COUNT(*) FROM photos").await,
+        2
+    );
+
+    source.cleanup();
+    target.cleanup();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// 按 remote_id 匹配到本地已有行时，photo_albums 关联跟随本地 id
+#[tokio::test]
+async fn test_import_matches_remote_id_and_remaps_relations() {
+    let source = TempDb::with_schema("echo_trails_portable_remap_src").await;
+    seed(&source.conn).await;
+    let dir = temp_dir("echo_trails_portable_remap");
+    portable::export_ndjson(&source.conn, &dir).await.unwrap();
+
+    let target = TempDb::with_schema("echo_trails_portable_remap_dst").await;
+    target
+        .conn
+        .execute(
+            "INSERT INTO photos (id, remote_id, data) VALUES ('local-p1', 'srv-p1', '{}')",
+            (),
+        )
+        .await
+        .unwrap();
+
+    let report = portable::import_ndjson(&target.conn, &dir, false)
+        .await
+        .unwrap();
+    assert_eq!(report.tables["photos"].updated, 1);
+    assert_eq!(report.tables["photos"].inserted, 1);
+    assert_eq!(
+        query_i64(&target.conn, "SELECT COUNT(*) FROM photos").await,
+        2
+    );
+    assert_eq!(
+        query_i64(
+            &target.conn,
+            "SELECT COUNT(*) FROM photo_albums WHERE photo_id = 'local-p1' AND album_id = 'a1'"
+        )
+        .await,
+        1
+    );
+
+    source.cleanup();
+    target.cleanup();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// dry-run 只出报告不落库；非法行计入 invalid
+#[tokio::test]
+async fn test_dry_run_reports_without_writing() {
+    let source = TempDb::with_schema("echo_trails_portable_dry_src").await;
+    seed(&source.conn).await;
+    let dir = temp_dir("echo_trails_portable_dry");
+    portable::export_ndjson(&source.conn, &dir).await.unwrap();
+    let todos = dir.join("todos.ndjson");
+    let mut content = std::fs::read_to_string(&todos).unwrap();
+    content.push_str("{not json}\n[1,2]\n");
+    std::fs::write(&todos, content).unwrap();
+
+    let target = TempDb::with_schema("echo_trails_portable_dry_dst").await;
+    let report = portable::import_ndjson(&target.conn, &dir, true)
+        .await
+        .unwrap();
+    assert!(report.dry_run);
+    assert_eq!(report.tables["photos"].inserted, 2);
+    assert_eq!(report.tables["todos"].inserted, 1);
+    assert_eq!(report.tables["todos"].invalid, 2);
+    assert_eq!(report.tables["todos"].errors.len(), 2);
+    assert!(report.tables["todos"].errors[0].starts_with("todos.ndjson:2:"));
+
+    assert_eq!(
+        query_i64(&target.conn, "SELECT COUNT(*) FROM photos").await,
+        0
+    );
+    assert_eq!(
+        query_i64(&target.conn, "SELECT COUNT(*) FROM photo_albums").await,
+        0
+    );
+
+    source.cleanup();
+    target.cleanup();
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,289 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use common::{http_response, spawn_http_server, HttpRequest};
+use tauri_app_lib::command::s3_client::{
+    copy_object, delete_objects, delete_objects_body, get_object_range, head_object,
+    list_all_objects, purge_objects, DeleteError,
+};
+use tauri_app_lib::command::s3_presign::{PayloadChecksum, S3Credentials, SigV4Signer};
+use tauri_app_lib::command::s3_profile::ProviderProfile;
+use tauri_app_lib::error::ErrorCode;
+
+fn credentials(endpoint: &str) -> S3Credentials {
+    S3Credentials::from_parts(
+        Some("bucket".to_string()),
+        Some("us-east-1".to_string()),
+        Some(endpoint.to_string()),
+        Some("ak".to_string()),
+        Some("sk".to_string()),
+        ProviderProfile::default(),
+    )
+    .unwrap()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            out.push(u8::from_str_radix(&value[i + 1..i + 3], 16).unwrap());
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).unwrap()
+}
+
+/// 按服务端视角重新计算签名，校验 Authorization 覆盖了收到的请求头
+fn assert_signed(req: &HttpRequest) {
+    let authorization = req.header("authorization").expect("missing authorization");
+    let signed_headers = authorization
+        .split("SignedHeaders=")
+        .nth(1)
+        .and_then(|rest| rest.split(',').next())
+        .unwrap();
+    let headers: Vec<(&str, String)> = signed_headers
+        .split(';')
+        .map(|name| (name, req.header(name).unwrap_or_default().to_string()))
+        .collect();
+    let query: Vec<(String, String)> = req
+        .query()
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(name), percent_decode(value))
+        })
+        .collect();
+    let query: Vec<(&str, String)> = query
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.clone()))
+        .collect();
+    let now =
+        NaiveDateTime::parse_from_str(req.header("x-amz-date").unwrap(), "%Y%m%dT%H%M%SZ").unwrap();
+    let headers: Vec<(&str, String)> = headers
+        .into_iter()
+        .filter(|(name, _)| *name != "x-amz-date")
+        .collect();
+    let expected = SigV4Signer::s3("ak", "sk", "us-east-1").sign_headers(
+        &req.method,
+        req.target.split('?').next().unwrap(),
+        &query,
+        &headers,
+        req.header("x-amz-content-sha256").unwrap(),
+        Utc.from_utc_datetime(&now),
+    );
+    assert_eq!(expected[1].1, authorization);
+}
+
+/// HeadObject 解析响应头，404 返回 None；请求带有效的 header 签名
+#[tokio::test]
+async fn test_head_object() {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let seen = requests.clone();
+    let endpoint = spawn_http_server(move |req| {
+        assert_signed(&req);
+        let found = req.target == "/bucket/photos/a%20b.jpg";
+        seen.lock().unwrap().push(req.method.clone());
+        if found {
+            http_response(
+                "200 OK",
+                &[("ETag", "\"abc\""), ("Content-Type", "image/jpeg")],
+                "",
+            )
+        } else {
+            http_response("404 Not Found", &[], "")
+        }
+    })
+    .await;
+    let creds = credentials(&endpoint);
+    let client = reqwest::Client::new();
+
+    let head = head_object(&client, &creds, "photos/a b.jpg")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(head.etag.as_deref(), Some("\"abc\""));
+    assert_eq!(head.content_type.as_deref(), Some("image/jpeg"));
+    assert!(head_object(&client, &creds, "missing.jpg")
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(*requests.lock().unwrap(), vec!["HEAD", "HEAD"]);
+}
+
+/// ListObjectsV2 跟随 continuation token 翻页
+#[tokio::test]
+async fn test_list_all_objects_follows_continuation() {
+    let endpoint = spawn_http_server(|req| {
+        assert_signed(&req);
+        assert!(req.target.starts_with("/bucket?"), "{}", req.target);
+        assert!(req.query().contains("list-type=2"));
+        assert!(req.query().contains("prefix=photos%2F"));
+        let xml = [("Content-Type", "application/xml")];
+        if req.query().contains("continuation-token=next%2B%2F%3D") {
+            http_response(
+                "200 OK",
+                &xml,
+                "<ListBucketResult><IsTruncated>false</IsTruncated><Contents><Key>photos/c.jpg</Key><Size>3</Size></Contents></ListBucketResult>",
+            )
+        } else {
+            http_response(
+                "200 OK",
+                &xml,
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?><ListBucketResult><IsTruncated>true</IsTruncated><NextContinuationToken>next+/=</NextContinuationToken><Contents><Key>photos/a&amp;b.jpg</Key><Size>1</Size><ETag>&quot;e1&quot;</ETag></Contents><Contents><Key>photos/b.jpg</Key><Size>2</Size></Contents></ListBucketResult>",
+            )
+        }
+    })
+    .await;
+
+    let objects = list_all_objects(
+        &reqwest::Client::new(),
+        &credentials(&endpoint),
+        Some("photos/"),
+    )
+    .await
+    .unwrap();
+    let keys: Vec<(&str, u64)> = objects.iter().map(|o| (o.key.as_str(), o.size)).collect();
+    assert_eq!(
+        keys,
+        vec![
+            ("photos/a&b.jpg", 1),
+            ("photos/b.jpg", 2),
+            ("photos/c.jpg", 3)
+        ]
+    );
+    assert_eq!(objects[0].etag.as_deref(), Some("\"e1\""));
+}
+
+/// CopyObject 签入 x-amz-copy-source；200 响应中的 <Error> 视为失败
+#[tokio::test]
+async fn test_copy_object() {
+    let endpoint = spawn_http_server(|req| {
+        assert_signed(&req);
+        assert_eq!(req.method, "PUT");
+        assert_eq!(
+            req.header("x-amz-copy-source"),
+            Some("/bucket/src/a%20b.jpg")
+        );
+        if req.target == "/bucket/dst/ok.jpg" {
+            http_response(
+                "200 OK",
+                &[],
+                "<CopyObjectResult><ETag>&quot;copied&quot;</ETag></CopyObjectResult>",
+            )
+        } else {
+            http_response("200 OK", &[], "<Error><Code>InternalError</Code></Error>")
+        }
+    })
+    .await;
+    let creds = credentials(&endpoint);
+    let client = reqwest::Client::new();
+
+    let etag = copy_object(&client, &creds, "src/a b.jpg", "dst/ok.jpg")
+        .await
+        .unwrap();
+    assert_eq!(etag.as_deref(), Some("\"copied\""));
+    let err = copy_object(&client, &creds, "src/a b.jpg", "dst/fail.jpg")
+        .await
+        .unwrap_err();
+    assert_eq!(err.code, ErrorCode::S3Status);
+    assert!(err.message.contains("InternalError"), "{}", err.message);
+}
+
+/// DeleteObjects 每批最多 1000 个，带 Content-MD5，返回逐个失败的 key
+#[tokio::test]
+async fn test_delete_objects_in_batches() {
+    let batches = Arc::new(Mutex::new(Vec::new()));
+    let seen = batches.clone();
+    let endpoint = spawn_http_server(move |req| {
+        assert_signed(&req);
+        assert_eq!(req.target, "/bucket?delete=");
+        let body = String::from_utf8(req.body.clone()).unwrap();
+        let md5 = md5::compute(&req.body);
+        let expected = PayloadChecksum::Md5(&format!("{:x}", md5))
+            .header_value()
+            .unwrap();
+        assert_eq!(req.header("content-md5"), Some(expected.as_str()));
+        seen.lock().unwrap().push(body.matches("<Object>").count());
+        let errors = if body.contains("<Key>k0</Key>") {
+            "<Error><Key>k0</Key><Code>AccessDenied</Code><Message>Access Denied</Message></Error>"
+        } else {
+            ""
+        };
+        http_response(
+            "200 OK",
+            &[],
+            &format!("<DeleteResult>{}</DeleteResult>", errors),
+        )
+    })
+    .await;
+
+    let keys: Vec<String> = (0..1001).map(|i| format!("k{}", i)).collect();
+    let errors = delete_objects(&reqwest::Client::new(), &credentials(&endpoint), &keys)
+        .await
+        .unwrap();
+    assert_eq!(*batches.lock().unwrap(), vec![1000, 1]);
+    assert_eq!(
+        errors,
+        vec![DeleteError {
+            key: "k0".to_string(),
+            code: "AccessDenied".to_string(),
+            message: Some("Access Denied".to_string()),
+        }]
+    );
+    // 回收站清理同样按批删除，只统计失败个数
+    assert_eq!(purge_objects(&credentials(&endpoint), &keys).await, 1);
+    assert_eq!(*batches.lock().unwrap(), vec![1000, 1, 1000, 1]);
+    assert_eq!(
+        delete_objects_body(&["a&b".to_string()]),
+        "<Delete xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\"><Quiet>true</Quiet><Object><Key>a&amp;b</Key></Object></Delete>"
+    );
+}
+
+/// GetObject 携带签名的 Range 头，返回对应字节；非法范围直接拒绝
+#[tokio::test]
+async fn test_get_object_range() {
+    let endpoint = spawn_http_server(|req| {
+        assert_signed(&req);
+        match req.header("range") {
+            Some("bytes=2-5") => http_response("206 Partial Content", &[], "2345"),
+            Some("bytes=8-") => http_response("206 Partial Content", &[], "89"),
+            _ => http_response(
+                "416 Requested Range Not Satisfiable",
+                &[],
+                "<Error><Code>InvalidRange</Code></Error>",
+            ),
+        }
+    })
+    .await;
+    let creds = credentials(&endpoint);
+    let client = reqwest::Client::new();
+
+    assert_eq!(
+        get_object_range(&client, &creds, "a.txt", 2, Some(5))
+            .await
+            .unwrap(),
+        b"2345"
+    );
+    assert_eq!(
+        get_object_range(&client, &creds, "a.txt", 8, None)
+            .await
+            .unwrap(),
+        b"89"
+    );
+    let err = get_object_range(&client, &creds, "a.txt", 20, Some(30))
+        .await
+        .unwrap_err();
+    assert_eq!(err.details, Some(serde_json::json!({ "status": 416 })));
+    let err = get_object_range(&client, &creds, "a.txt", 5, Some(2))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code, ErrorCode::Validation);
+}
@@ -0,0 +1,233 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use chrono::{TimeZone, Utc};
+use common::{http_response, spawn_http_server};
+use tauri_app_lib::command::s3_client::head_object;
+use tauri_app_lib::command::s3_presign::{
+    presign_get_object_url, presign_put_object_url, presign_upload_part_url, PayloadChecksum,
+    PresignGetObjectParams, PresignMultipartParams, PresignPutObjectParams, S3Credentials,
+};
+use tauri_app_lib::command::s3_profile::{
+    AddressingStyle, ProviderConfig, ProviderProfile, S3Provider,
+};
+use tauri_app_lib::error::ErrorCode;
+
+fn put_url(endpoint: &str, bucket: &str, profile: &ProviderProfile) -> Result<String, ErrorCode> {
+    presign_put_object_url(
+        PresignPutObjectParams {
+            key: "photos/a b.jpg",
+            bucket,
+            region: "us-east-1",
+            endpoint,
+            access_key: "ak",
+            secret_key: "sk",
+            expires_seconds: 3600,
+            checksum: None,
+            profile,
+        },
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+    )
+    .map_err(|e| e.code)
+}
+
+/// 各服务商的默认寻址方式、region 与校验支持；配置项覆盖默认值
+#[test]
+fn test_provider_defaults_and_overrides() {
+    let cases = [
+        (
+            S3Provider::Bitiful,
+            "https://s3.bitiful.net",
+            AddressingStyle::Path,
+            "us-east-1",
+        ),
+        (
+            S3Provider::R2,
+            "https://acc.r2.cloudflarestorage.com",
+            AddressingStyle::Path,
+            "auto",
+        ),
+        (
+            S3Provider::Oss,
+            "oss-cn-shanghai.aliyuncs.com",
+            AddressingStyle::VirtualHosted,
+            "cn-shanghai",
+        ),
+        (
+            S3Provider::Minio,
+            "http://127.0.0.1:9000",
+            AddressingStyle::Path,
+            "us-east-1",
+        ),
+        (
+            S3Provider::B2,
+            "https://s3.eu-central-003.backblazeb2.com",
+            AddressingStyle::VirtualHosted,
+            "eu-central-003",
+        ),
+    ];
+    for (provider, endpoint, style, region) in cases {
+        let profile = ProviderProfile::for_provider(provider);
+        assert_eq!(profile.addressing_style, style, "{:?}", provider);
+        assert_eq!(profile.default_region(endpoint), region, "{:?}", provider);
+        assert_eq!(profile.resolve_region(" ", endpoint), region);
+        assert_eq!(profile.resolve_region("eu-west-1", endpoint), "eu-west-1");
+    }
+    assert_eq!(ProviderProfile::default().provider, S3Provider::Bitiful);
+    assert_eq!(
+        ProviderProfile::from_config(None),
+        ProviderProfile::default()
+    );
+
+    let config: ProviderConfig = serde_json::from_value(serde_json::json!({
+        "provider": "oss",
+        "addressingStyle": "path",
+        "pathPrefix": "/gateway/",
+    }))
+    .unwrap();
+    let profile = ProviderProfile::from_config(Some(&config));
+    assert_eq!(profile.provider, S3Provider::Oss);
+    assert_eq!(profile.addressing_style, AddressingStyle::Path);
+    assert_eq!(profile.path_prefix, "gateway");
+
+    // 关闭 Content-MD5 后不签入校验头
+    let profile = ProviderProfile::default();
+    assert_eq!(
+        profile.md5_checksum(Some("abc")),
+        Some(PayloadChecksum::Md5("abc"))
+    );
+    assert_eq!(profile.md5_checksum(Some("")), None);
+    let config: ProviderConfig = serde_json::from_value(serde_json::json!({
+        "provider": "r2",
+        "contentMd5": false,
+    }))
+    .unwrap();
+    let profile = ProviderProfile::from_config(Some(&config));
+    assert!(!profile.content_md5);
+    assert_eq!(profile.md5_checksum(Some("abc")), None);
+
+    // 凭据的空 region 按服务商补全
+    let credentials = S3Credentials::from_parts(
+        Some("bucket".to_string()),
+        Some(String::new()),
+        Some("https://acc.r2.cloudflarestorage.com".to_string()),
+        Some("ak".to_string()),
+        Some("sk".to_string()),
+        ProviderProfile::for_provider(S3Provider::R2),
+    )
+    .unwrap();
+    assert_eq!(credentials.region, "auto");
+}
+
+/// 预签名 URL 按配置使用 path-style / 虚拟主机风格及路径前缀
+#[test]
+fn test_presign_honours_profile() {
+    let oss = ProviderProfile::for_provider(S3Provider::Oss);
+    let virtual_url = put_url("https://oss-cn-hangzhou.aliyuncs.com", "my-bucket", &oss).unwrap();
+    assert!(
+        virtual_url.starts_with("https://my-bucket.oss-cn-hangzhou.aliyuncs.com/photos/a%20b.jpg?"),
+        "{}",
+        virtual_url
+    );
+    // 桶名不符合 DNS 规则时不能用虚拟主机风格
+    assert_eq!(
+        put_url("https://oss-cn-hangzhou.aliyuncs.com", "My_Bucket", &oss),
+        Err(ErrorCode::Validation)
+    );
+
+    let mut path_oss = oss.clone();
+    path_oss.addressing_style = AddressingStyle::Path;
+    let path_url = put_url(
+        "https://oss-cn-hangzhou.aliyuncs.com",
+        "my-bucket",
+        &path_oss,
+    )
+    .unwrap();
+    assert!(
+        path_url.starts_with("https://oss-cn-hangzhou.aliyuncs.com/my-bucket/photos/a%20b.jpg?")
+    );
+    // host 参与签名，两种风格签名不同
+    let signature = |url: &str| url.split("X-Amz-Signature=").nth(1).unwrap().to_string();
+    assert_ne!(signature(&virtual_url), signature(&path_url));
+
+    // endpoint 自带路径与配置前缀依次拼在对象路径前
+    let minio = ProviderProfile::from_config(Some(&ProviderConfig {
+        provider: Some(S3Provider::Minio),
+        addressing_style: None,
+        path_prefix: Some("s3 api".to_string()),
+        content_md5: None,
+    }));
+    let url = presign_get_object_url(
+        PresignGetObjectParams {
+            key: "a.jpg",
+            bucket: "bucket",
+            region: "us-east-1",
+            endpoint: "https://example.com/minio",
+            access_key: "ak",
+            secret_key: "sk",
+            expires_seconds: 60,
+            profile: &minio,
+        },
+        Utc::now(),
+    )
+    .unwrap();
+    assert!(
+        url.starts_with("https://example.com/minio/s3%20api/bucket/a.jpg?"),
+        "{}",
+        url
+    );
+
+    let b2 = ProviderProfile::for_provider(S3Provider::B2);
+    let url = presign_upload_part_url(
+        &PresignMultipartParams {
+            key: "video/a.mp4",
+            bucket: "media",
+            region: "us-west-004",
+            endpoint: "s3.us-west-004.backblazeb2.com",
+            access_key: "ak",
+            secret_key: "sk",
+            expires_seconds: 60,
+            profile: &b2,
+        },
+        "upload-1",
+        1,
+        Utc::now(),
+    )
+    .unwrap();
+    assert!(url.starts_with("https://media.s3.us-west-004.backblazeb2.com/video/a.mp4?"));
+}
+
+/// header 签名的请求同样带上路径前缀
+#[tokio::test]
+async fn test_client_uses_path_prefix() {
+    let targets = Arc::new(Mutex::new(Vec::new()));
+    let seen = targets.clone();
+    let endpoint = spawn_http_server(move |req| {
+        seen.lock().unwrap().push(req.target.clone());
+        http_response("200 OK", &[("ETag", "\"e\"")], "")
+    })
+    .await;
+    let profile = ProviderProfile::from_config(Some(&ProviderConfig {
+        provider: Some(S3Provider::Minio),
+        addressing_style: None,
+        path_prefix: Some("s3".to_string()),
+        content_md5: None,
+    }));
+    let credentials = S3Credentials::from_parts(
+        Some("bucket".to_string()),
+        Some(String::new()),
+        Some(format!("{}/gateway", endpoint)),
+        Some("ak".to_string()),
+        Some("sk".to_string()),
+        profile,
+    )
+    .unwrap();
+    assert_eq!(credentials.region, "us-east-1");
+
+    let head = head_object(&reqwest::Client::new(), &credentials, "a.jpg")
+        .await
+        .unwrap();
+    assert!(head.is_some());
+    assert_eq!(*targets.lock().unwrap(), vec!["/gateway/s3/bucket/a.jpg"]);
+}
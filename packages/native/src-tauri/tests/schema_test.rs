@@ -95,6 +95,9 @@ async fn test_create_schema_creates_all_tables() {
         "todos",
         "drive_files",
         "sync_log",
+        "schema_version",
+        "sync_state",
+        "tombstones",
     ];
 
     let mut missing: Vec<&str> = Vec::new();
@@ -0,0 +1,234 @@
+mod common;
+
+use common::{query_i64, TempDb};
+use tauri_app_lib::db::search::{self, QueryTerm};
+use turso::Value as TursoValue;
+
+async fn insert(tmp: &TempDb, table: &str, id: &str, data: &str) {
+    tmp.conn
+        .execute(
+            &format!("INSERT INTO {} (id, data) VALUES (?1, ?2)", table),
+            (id, data),
+        )
+        .await
+        .unwrap();
+    search::reindex(&tmp.conn, table, &[id.to_string()])
+        .await
+        .unwrap();
+}
+
+/// 分词：字母数字按词、CJK 按单字与相邻二字
+#[test]
+fn test_tokenizer() {
+    let terms = search::index_terms("IMG_2024 海边日落.JPG");
+    for t in ["img", "2024", "jpg", "海", "海边", "边日", "日落", "落"] {
+        assert!(terms.contains(&t.to_string()), "missing {}", t);
+    }
+    assert!(!terms.contains(&"海边日".to_string()));
+
+    let q = search::query_terms("海边日落 Sun");
+    let expect = |term: &str, prefix| QueryTerm {
+        term: term.to_string(),
+        prefix,
+    };
+    assert_eq!(
+        q,
+        vec![
+            expect("海边", false),
+            expect("边日", false),
+            expect("日落", false),
+            expect("sun", true)
+        ]
+    );
+    assert_eq!(search::query_terms("猫"), vec![expect("猫", false)]);
+    assert!(search::query_terms(" ,. ").is_empty());
+}
+
+/// 跨实体检索：名称命中优先于描述命中，带片段高亮，可按类型过滤
+#[tokio::test]
+async fn test_search_ranks_typed_hits() {
+    let tmp = TempDb::with_schema("echo_trails_search").await;
+    insert(
+        &tmp,
+        "photos",
+        "p1",
+        r#"{"name":"海边日落.jpg","description":"厦门"}"#,
+    )
+    .await;
+    insert(
+        &tmp,
+        "photos",
+        "p2",
+        r#"{"name":"IMG_0001.jpg","description":"和家人在海边散步"}"#,
+    )
+    .await;
+    insert(
+        &tmp,
+        "albums",
+        "a1",
+        r#"{"name":"旅行","description":"","tags":["海边","夏天"]}"#,
+    )
+    .await;
+    insert(
+        &tmp,
+        "todos",
+        "t1",
+        r#"{"title":"Buy sunscreen","note":"去海边之前"}"#,
+    )
+    .await;
+    insert(&tmp, "drive_files", "d1", r#"{"name":"report.pdf"}"#).await;
+    insert(&tmp, "memorials", "m1", r#"{"name":"第一次看海"}"#).await;
+    insert(
+        &tmp,
+        "assets",
+        "s1",
+        r#"{"name":"相机","description":"Sunny 16"}"#,
+    )
+    .await;
+
+    let hits = search::search(&tmp.conn, "海边", None, 50).await.unwrap();
+    let ids: Vec<(&str, &str)> = hits
+        .iter()
+        .map(|h| (h.kind.as_str(), h.id.as_str()))
+        .collect();
+    assert_eq!(ids[0], ("photo", "p1"));
+    assert_eq!(ids.len(), 4);
+    assert!(ids.contains(&("album", "a1")));
+    assert!(ids.contains(&("todo", "t1")));
+
+    let p2 = hits.iter().find(|h| h.id == "p2").unwrap();
+    assert_eq!(p2.title, "IMG_0001.jpg");
+    assert_eq!(p2.field, "description");
+    assert_eq!(p2.snippet, "和家人在海边散步");
+    assert_eq!(p2.highlights, vec![[4, 6]]);
+
+    // 英文按前缀匹配，大小写无关
+    let hits = search::search(&tmp.conn, "SUN", None, 50).await.unwrap();
+    let ids: Vec<&str> = hits.iter().map(|h| h.id.as_str()).collect();
+    assert_eq!(ids, vec!["t1", "s1"]);
+    assert_eq!(hits[0].snippet, "Buy sunscreen");
+    assert_eq!(hits[0].highlights, vec![[4, 7]]);
+
+    let types = vec!["asset".to_string()];
+    let hits = search::search(&tmp.conn, "sun", Some(&types), 50)
+        .await
+        .unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].kind, "asset");
+
+    // 所有词都要命中
+    assert!(search::search(&tmp.conn, "海边 report", None, 50)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        search::search(&tmp.conn, "海边", None, 2)
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+
+    tmp.cleanup();
+}
+
+/// 长文本截取命中位置附近的窗口，高亮偏移按 UTF-16 计算
+#[tokio::test]
+async fn test_snippet_window() {
+    let tmp = TempDb::with_schema("echo_trails_search_snippet").await;
+    let note = format!("{}😀关键字{}", "前".repeat(40), "后".repeat(40));
+    insert(
+        &tmp,
+        "todos",
+        "t1",
+        &serde_json::json!({ "title": "x", "note": note }).to_string(),
+    )
+    .await;
+
+    let hits = search::search(&tmp.conn, "关键字", None, 10).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    let hit = &hits[0];
+    assert_eq!(hit.field, "note");
+    assert!(hit.snippet.starts_with('…') && hit.snippet.ends_with('…'));
+    let utf16: Vec<u16> = hit.snippet.encode_utf16().collect();
+    let [start, end] = hit.highlights[0];
+    assert_eq!(String::from_utf16(&utf16[start..end]).unwrap(), "关键字");
+
+    tmp.cleanup();
+}
+
+/// 更新、软删除、物理删除与全量重建都能保持索引一致
+#[tokio::test]
+async fn test_index_follows_writes() {
+    let tmp = TempDb::with_schema("echo_trails_search_writes").await;
+    insert(&tmp, "memorials", "m1", r#"{"name":"结婚纪念日"}"#).await;
+    insert(&tmp, "memorials", "m2", r#"{"name":"生日"}"#).await;
+    assert_eq!(
+        search::search(&tmp.conn, "纪念", None, 10)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+
+    tmp.conn
+        .execute(
+            "UPDATE memorials SET data = '{\"name\":\"恋爱纪念日\"}' WHERE id = 'm2'",
+            (),
+        )
+        .await
+        .unwrap();
+    search::reindex(&tmp.conn, "memorials", &["m2".to_string()])
+        .await
+        .unwrap();
+    assert_eq!(
+        search::search(&tmp.conn, "纪念", None, 10)
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+    assert!(search::search(&tmp.conn, "生日", None, 10)
+        .await
+        .unwrap()
+        .is_empty());
+
+    tmp.conn
+        .execute("UPDATE memorials SET deleted = 1 WHERE id = 'm1'", ())
+        .await
+        .unwrap();
+    search::reindex(&tmp.conn, "memorials", &["m1".to_string()])
+        .await
+        .unwrap();
+    let hits = search::search(&tmp.conn, "纪念", None, 10).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].id, "m2");
+
+    search::remove_where(
+        &tmp.conn,
+        "memorials",
+        "id = ?1",
+        vec![TursoValue::Text("m2".to_string())],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            "SELECT COUNT(*) FROM search_terms WHERE entity_id = 'm2'"
+        )
+        .await,
+        0
+    );
+
+    // 全量重建：只收录未删除的行
+    tmp.conn
+        .execute("DELETE FROM search_terms", ())
+        .await
+        .unwrap();
+    assert_eq!(search::rebuild_index(&tmp.conn).await.unwrap(), 1);
+    let hits = search::search(&tmp.conn, "恋爱", None, 10).await.unwrap();
+    assert_eq!(hits.len(), 1);
+
+    tmp.cleanup();
+}
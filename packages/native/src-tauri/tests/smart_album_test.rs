@@ -0,0 +1,240 @@
+mod common;
+
+use common::TempDb;
+use serde_json::json;
+use tauri_app_lib::db::photo::{self, PhotoFilter};
+use tauri_app_lib::db::smart_album::SmartRules;
+use tauri_app_lib::error::ErrorCode;
+
+const MB: i64 = 1024 * 1024;
+
+async fn seed(tmp: &TempDb) {
+    let hangzhou = json!({ "latitude": 30.25, "longitude": 120.17 });
+    let shanghai = json!({ "latitude": 31.23, "longitude": 121.47 });
+    let photos = [
+        (
+            "v1",
+            "2023-03-01T00:00:00.000Z",
+            "video/mp4",
+            0,
+            json!({ "key": "v1.mp4", "size": 30 * MB }),
+        ),
+        (
+            "v2",
+            "2022-12-31T23:00:00.000Z",
+            "video/mp4",
+            1,
+            json!({ "key": "v2.mp4", "size": 5 * MB }),
+        ),
+        (
+            "i1",
+            "2023-06-01T00:00:00.000Z",
+            "image/jpeg",
+            1,
+            json!({ "key": "i1.jpg", "size": MB, "name": "West_Lake.jpg", "gps": hangzhou }),
+        ),
+        (
+            "i2",
+            "2023-07-01T00:00:00.000Z",
+            "image/jpeg",
+            0,
+            json!({ "key": "i2.jpg", "size": MB, "gps": hangzhou }),
+        ),
+        (
+            "i3",
+            "2023-08-01T00:00:00.000Z",
+            "image/heic",
+            1,
+            json!({ "key": "i3.heic", "size": 3 * MB, "gps": shanghai, "isLive": true, "liveVideoKey": "i3.mov" }),
+        ),
+        (
+            "i4",
+            "2024-01-01T00:00:00.000Z",
+            "image/png",
+            1,
+            json!({ "size": 25 * MB }),
+        ),
+    ];
+    for (id, taken_at, type_, liked, data) in photos {
+        tmp.conn
+            .execute(
+                "INSERT INTO photos (id, taken_at, type, is_liked, data) VALUES (?1, ?2, ?3, ?4, ?5)",
+                (id, taken_at, type_, liked, data.to_string()),
+            )
+            .await
+            .unwrap();
+    }
+}
+
+async fn ids_for(tmp: &TempDb, smart: serde_json::Value) -> Vec<String> {
+    let filter = PhotoFilter {
+        smart: Some(SmartRules::parse(&smart).unwrap()),
+        ..Default::default()
+    };
+    let page = photo::list_photos_page(&tmp.conn, &filter, None, 50, 0)
+        .await
+        .unwrap();
+    page.photos.into_iter().map(|p| p.id).collect()
+}
+
+/// 各类规则在 SQL 中求值：类型、年份、收藏、位置、大小、文件名，以及 any 组合
+#[tokio::test]
+async fn test_rules_filter_photos() {
+    let tmp = TempDb::with_schema("echo_trails_smart_rules").await;
+    seed(&tmp).await;
+
+    let videos_2023 = json!({ "rules": [
+        { "field": "type", "value": "video" },
+        { "field": "year", "value": 2023 },
+    ] });
+    assert_eq!(ids_for(&tmp, videos_2023).await, vec!["v1"]);
+
+    let liked_in_hangzhou = json!({ "rules": [
+        { "field": "liked", "value": true },
+        { "field": "location", "value": { "latitude": 30.27, "longitude": 120.15, "radiusKm": 30 } },
+    ] });
+    assert_eq!(ids_for(&tmp, liked_in_hangzhou).await, vec!["i1"]);
+
+    let live = json!({ "rules": [{ "field": "type", "value": "live" }] });
+    assert_eq!(ids_for(&tmp, live).await, vec!["i3"]);
+
+    let large = json!({ "rules": [{ "field": "size", "op": "gt", "value": 20 * MB }] });
+    assert_eq!(ids_for(&tmp, large).await, vec!["i4", "v1"]);
+
+    let any = json!({ "match": "any", "rules": [
+        { "field": "name", "value": "west_lake" },
+        { "field": "takenAt", "op": "lt", "value": "2023-01-01" },
+    ] });
+    assert_eq!(ids_for(&tmp, any).await, vec!["i1", "v2"]);
+
+    // 与其它筛选条件叠加
+    let filter = PhotoFilter {
+        liked_only: true,
+        type_prefix: Some("image".to_string()),
+        smart: Some(
+            SmartRules::parse(&json!({ "rules": [{ "field": "year", "value": 2023 }] })).unwrap(),
+        ),
+        ..Default::default()
+    };
+    assert_eq!(photo::count_photos(&tmp.conn, &filter).await.unwrap(), 2);
+
+    tmp.cleanup();
+}
+
+/// 相册 data.smart 决定走规则还是 photo_albums；数量与封面按规则实时计算
+#[tokio::test]
+async fn test_album_id_resolves_rules() {
+    let tmp = TempDb::with_schema("echo_trails_smart_album").await;
+    seed(&tmp).await;
+    let smart =
+        json!({ "name": "2023", "smart": { "rules": [{ "field": "year", "value": 2023 }] } });
+    tmp.conn
+        .execute(
+            "INSERT INTO albums (id, data) VALUES ('s1', ?1), ('m1', '{\"name\":\"manual\"}')",
+            (smart.to_string(),),
+        )
+        .await
+        .unwrap();
+    tmp.conn
+        .execute(
+            "INSERT INTO photo_albums (photo_id, album_id) VALUES ('v2', 'm1'), ('v2', 's1')",
+            (),
+        )
+        .await
+        .unwrap();
+
+    let mut filter = PhotoFilter {
+        album_id: Some("s1".to_string()),
+        ..Default::default()
+    };
+    filter.load_smart_album(&tmp.conn).await.unwrap();
+    assert!(filter.smart.is_some());
+    let (count, cover) = photo::photo_count_and_cover(&tmp.conn, &filter)
+        .await
+        .unwrap();
+    assert_eq!(count, 4);
+    let cover = cover.unwrap();
+    assert_eq!(cover["id"], "i3");
+    assert_eq!(cover["key"], "i3.heic");
+
+    // 新照片无需关联即出现在智能相册中
+    tmp.conn
+        .execute(
+            "INSERT INTO photos (id, taken_at, data) VALUES ('n1', '2023-12-01T00:00:00.000Z', '{}')",
+            (),
+        )
+        .await
+        .unwrap();
+    let (count, cover) = photo::photo_count_and_cover(&tmp.conn, &filter)
+        .await
+        .unwrap();
+    assert_eq!(count, 5);
+    assert_eq!(cover.unwrap()["key"], "n1");
+
+    let mut manual = PhotoFilter {
+        album_id: Some("m1".to_string()),
+        ..Default::default()
+    };
+    manual.load_smart_album(&tmp.conn).await.unwrap();
+    assert!(manual.smart.is_none());
+    assert_eq!(
+        photo::photo_count_and_cover(&tmp.conn, &manual)
+            .await
+            .unwrap()
+            .0,
+        1
+    );
+
+    // 相册列表的分组统计与逐个按 filter 统计一致
+    let linked = photo::album_counts_and_covers(&tmp.conn).await.unwrap();
+    let (count, cover) = &linked["m1"];
+    assert_eq!(*count, 1);
+    assert_eq!(cover.as_ref().unwrap()["key"], "v2.mp4");
+    tmp.conn
+        .execute("UPDATE photos SET deleted = 1 WHERE id = 'v2'", ())
+        .await
+        .unwrap();
+    let linked = photo::album_counts_and_covers(&tmp.conn).await.unwrap();
+    assert!(!linked.contains_key("m1"));
+    tmp.conn
+        .execute("UPDATE photos SET deleted = 0 WHERE id = 'v2'", ())
+        .await
+        .unwrap();
+
+    let empty = PhotoFilter {
+        deleted: true,
+        ..filter
+    };
+    assert_eq!(
+        photo::photo_count_and_cover(&tmp.conn, &empty)
+            .await
+            .unwrap(),
+        (0, None)
+    );
+
+    tmp.cleanup();
+}
+
+/// 非法规则返回校验错误
+#[test]
+fn test_invalid_rules() {
+    let cases = [
+        json!({ "rules": [] }),
+        json!({ "rules": [{ "field": "color", "value": "red" }] }),
+        json!({ "rules": [{ "field": "type", "value": "audio" }] }),
+        json!({ "rules": [{ "field": "size", "op": "eq", "value": 1 }] }),
+        json!({ "rules": [{ "field": "size", "op": "gt", "value": -1 }] }),
+        json!({ "rules": [{ "field": "location", "value": { "latitude": 91, "longitude": 0, "radiusKm": 1 } }] }),
+        json!({ "match": "none", "rules": [{ "field": "liked", "value": true }] }),
+    ];
+    for case in cases {
+        let err = SmartRules::parse(&case).unwrap_err();
+        assert_eq!(err.code, ErrorCode::Validation, "{}", case);
+    }
+    assert!(SmartRules::from_album_data(&json!({ "name": "x" }))
+        .unwrap()
+        .is_none());
+    assert!(SmartRules::from_album_data(&json!({ "smart": null }))
+        .unwrap()
+        .is_none());
+}
@@ -0,0 +1,111 @@
+use tauri_app_lib::command::sniff;
+
+fn ftyp(major: &[u8; 4], compatible: &[&[u8; 4]]) -> Vec<u8> {
+    let mut body = major.to_vec();
+    body.extend([0, 0, 0, 0]);
+    for brand in compatible {
+        body.extend(*brand);
+    }
+    let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+    out.extend(b"ftyp");
+    out.extend(body);
+    out
+}
+
+fn tiff_with_tag(tag: u16, value: &[u8]) -> Vec<u8> {
+    let mut out = b"II*\0\x08\0\0\0\x01\0".to_vec();
+    out.extend(tag.to_le_bytes());
+    out.extend(2u16.to_le_bytes());
+    out.extend((value.len() as u32).to_le_bytes());
+    out.extend(26u32.to_le_bytes());
+    out.extend(0u32.to_le_bytes());
+    out.extend(value);
+    out
+}
+
+/// 常见图片、RAW、视频、音频容器按文件头识别
+#[test]
+fn test_sniff_signatures() {
+    let cases: Vec<(Vec<u8>, &str)> = vec![
+        (vec![0xFF, 0xD8, 0xFF, 0xE1], "image/jpeg"),
+        (b"\x89PNG\r\n\x1a\n\0\0".to_vec(), "image/png"),
+        (b"GIF89a\x01\0".to_vec(), "image/gif"),
+        (b"RIFF\0\0\0\0WEBPVP8 ".to_vec(), "image/webp"),
+        (b"RIFF\0\0\0\0AVI LIST".to_vec(), "video/x-msvideo"),
+        (b"RIFF\0\0\0\0WAVEfmt ".to_vec(), "audio/wav"),
+        (ftyp(b"heic", &[b"mif1", b"heic"]), "image/heic"),
+        (ftyp(b"mif1", &[b"mif1", b"heic"]), "image/heic"),
+        (ftyp(b"mif1", &[b"mif1"]), "image/heif"),
+        (ftyp(b"avif", &[b"mif1"]), "image/avif"),
+        (ftyp(b"crx ", &[b"crx "]), "image/x-canon-cr3"),
+        (ftyp(b"qt  ", &[b"qt  "]), "video/quicktime"),
+        (ftyp(b"isom", &[b"isom", b"mp41"]), "video/mp4"),
+        (ftyp(b"mp42", &[b"isom", b"M4V "]), "video/x-m4v"),
+        (ftyp(b"3gp4", &[b"isom"]), "video/3gpp"),
+        (ftyp(b"M4A ", &[b"isom"]), "audio/mp4"),
+        (
+            b"\x1a\x45\xdf\xa3\x9f\x42\x82\x84webm".to_vec(),
+            "video/webm",
+        ),
+        (
+            b"\x1a\x45\xdf\xa3\x9f\x42\x82\x88matroska".to_vec(),
+            "video/x-matroska",
+        ),
+        (b"OggS\0\x02\0\0\0\0\0\0\0\0OpusHead".to_vec(), "audio/opus"),
+        (
+            b"OggS\0\x02\0\0\0\0\0\0\0\0\x01vorbis".to_vec(),
+            "audio/ogg",
+        ),
+        (b"fLaC\0\0\0\x22".to_vec(), "audio/flac"),
+        (b"ID3\x04\0\0".to_vec(), "audio/mpeg"),
+        (vec![0xFF, 0xFB, 0x90, 0x64], "audio/mpeg"),
+        (vec![0xFF, 0xF1, 0x50, 0x80], "audio/aac"),
+        (b"FUJIFILMCCD-RAW 0201".to_vec(), "image/x-fuji-raf"),
+        (b"IIU\0\x08\0\0\0".to_vec(), "image/x-panasonic-rw2"),
+        (b"II*\0\x10\0\0\0CR\x02\0".to_vec(), "image/x-canon-cr2"),
+        (tiff_with_tag(0xC612, &[1, 4, 0, 0]), "image/x-adobe-dng"),
+        (
+            tiff_with_tag(0x010F, b"NIKON CORPORATION\0"),
+            "image/x-nikon-nef",
+        ),
+        (tiff_with_tag(0x010F, b"SONY\0\0\0\0"), "image/x-sony-arw"),
+        (tiff_with_tag(0x010F, b"Canon\0\0\0"), "image/tiff"),
+    ];
+    for (bytes, expected) in cases {
+        assert_eq!(
+            sniff::sniff(&bytes),
+            Some(expected),
+            "{:?}",
+            &bytes[..bytes.len().min(16)]
+        );
+    }
+    assert_eq!(sniff::sniff(b"plain text"), None);
+    assert_eq!(sniff::sniff(&[]), None);
+}
+
+/// 扩展名错误或缺失时以内容为准，未知内容才回退到扩展名
+#[test]
+fn test_detect_file_prefers_content_over_extension() {
+    let dir = std::env::temp_dir().join(format!("echo_trails_sniff_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mislabeled = dir.join("IMG_0001.jpg");
+    std::fs::write(&mislabeled, ftyp(b"heic", &[b"mif1"])).unwrap();
+    assert_eq!(sniff::detect_file(&mislabeled).unwrap(), Some("image/heic"));
+
+    let no_ext = dir.join("download");
+    std::fs::write(&no_ext, ftyp(b"qt  ", &[])).unwrap();
+    assert_eq!(
+        sniff::detect_file(&no_ext).unwrap(),
+        Some("video/quicktime")
+    );
+
+    let unknown = dir.join("clip.mkv");
+    std::fs::write(&unknown, b"not a real header").unwrap();
+    assert_eq!(
+        sniff::detect_file(&unknown).unwrap(),
+        Some("video/x-matroska")
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
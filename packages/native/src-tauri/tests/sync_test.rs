@@ -0,0 +1,768 @@
+mod common;
+
+use std::sync::Mutex;
+
+use common::{query_i64, TempDb};
+use serde_json::{json, Map};
+use tauri_app_lib::db::{album, photo};
+use tauri_app_lib::error::{AppError, ErrorCode};
+use tauri_app_lib::db::sync_engine::{
+    self, ConflictPolicy, LastWriterWins, PullPage, PushAck, Resolution, SyncChange, SyncOptions,
+    SyncRemote,
+};
+
+/// 本地替身服务器：记录收到的推送，按游标分页返回预置的变更
+#[derive(Default)]
+struct FakeRemote {
+    pushed: Mutex<Vec<SyncChange>>,
+    feed: Vec<SyncChange>,
+    page_size: usize,
+}
+
+impl FakeRemote {
+    fn with_feed(feed: Vec<SyncChange>, page_size: usize) -> Self {
+        FakeRemote {
+            feed,
+            page_size,
+            ..Default::default()
+        }
+    }
+}
+
+impl SyncRemote for FakeRemote {
+    async fn push(&self, changes: Vec<SyncChange>) -> Result<Vec<PushAck>, AppError> {
+        let acks = changes
+            .iter()
+            .map(|c| PushAck {
+                entity_type: c.entity_type.clone(),
+                id: c.id.clone(),
+                remote_id: Some(c.remote_id.clone().unwrap_or_else(|| format!("r-{}", c.id))),
+                error: None,
+            })
+            .collect();
+        self.pushed.lock().unwrap().extend(changes);
+        Ok(acks)
+    }
+
+    async fn pull(&self, cursor: Option<String>, _limit: usize) -> Result<PullPage, AppError> {
+        let start: usize = cursor.and_then(|c| c.parse().ok()).unwrap_or(0);
+        let size = self.page_size.max(1);
+        let end = (start + size).min(self.feed.len());
+        Ok(PullPage {
+            changes: self.feed[start.min(end)..end].to_vec(),
+            cursor: Some(end.to_string()),
+            has_more: end < self.feed.len(),
+        })
+    }
+}
+
+fn remote_album(remote_id: &str, name: &str, updated_at: &str) -> SyncChange {
+    let mut data = Map::new();
+    data.insert("name".to_string(), json!(name));
+    data.insert("description".to_string(), json!("from server"));
+    SyncChange {
+        entity_type: "albums".to_string(),
+        id: String::new(),
+        remote_id: Some(remote_id.to_string()),
+        updated_at: updated_at.to_string(),
+        deleted: false,
+        data,
+    }
+}
+
+async fn query_text(conn: &turso::Connection, sql: &str) -> Option<String> {
+    let mut rows = conn.query(sql, ()).await.unwrap();
+    let row = rows.next().await.unwrap()?;
+    row.get_value(0).unwrap().as_text().map(|v| v.to_string())
+}
+
+/// 推送：pending 行标记为 synced 并写入 remote_id，sync_log 按批次记录
+#[tokio::test]
+async fn test_push_marks_rows_synced_and_logs_batch() {
+    let tmp = TempDb::with_schema("echo_trails_sync_push").await;
+    for i in 0..3 {
+        tmp.conn
+            .execute(
+                "INSERT INTO albums (id, sync_status, data) VALUES (?1, 'pending', ?2)",
+                (
+                    format!("a{}", i),
+                    json!({ "name": format!("Album {}", i), "color": "red" }).to_string(),
+                ),
+            )
+            .await
+            .unwrap();
+    }
+
+    let remote = FakeRemote::default();
+    let options = SyncOptions {
+        push_batch_size: 2,
+        ..Default::default()
+    };
+    let report = sync_engine::run_sync(&tmp.conn, &remote, &LastWriterWins, &options)
+        .await
+        .unwrap();
+
+    assert_eq!(report.pushed, 3);
+    let pushed = remote.pushed.lock().unwrap().clone();
+    assert_eq!(pushed.len(), 3);
+    assert_eq!(pushed[0].data.get("color"), Some(&json!("red")));
+    assert_eq!(pushed[0].data.get("name"), Some(&json!("Album 0")));
+
+    let pending = query_i64(
+        &tmp.conn,
+        "SELECT COUNT(*) FROM albums WHERE sync_status != 'synced'",
+    )
+    .await;
+    assert_eq!(pending, 0);
+    assert_eq!(
+        query_text(&tmp.conn, "SELECT remote_id FROM albums WHERE id = 'a1'").await,
+        Some("r-a1".to_string())
+    );
+    let logged = query_i64(
+        &tmp.conn,
+        &format!(
+            "SELECT COUNT(*) FROM sync_log WHERE action = 'push' AND status = 'success' AND sync_batch = '{}'",
+            report.batch
+        ),
+    )
+    .await;
+    assert_eq!(logged, 3);
+
+    tmp.cleanup();
+}
+
+/// 拉取：按页插入/更新并推进游标，再次同步从游标继续
+#[tokio::test]
+async fn test_pull_applies_pages_and_advances_cursor() {
+    let tmp = TempDb::with_schema("echo_trails_sync_pull").await;
+    tmp.conn
+        .execute(
+            "INSERT INTO albums (id, data, remote_id, sync_status, updated_at) VALUES ('local-1', '{\"name\":\"Old\"}', 'srv-1', 'synced', '2024-01-01 00:00:00')",
+            (),
+        )
+        .await
+        .unwrap();
+
+    let feed = vec![
+        remote_album("srv-1", "Renamed", "2024-02-01T00:00:00Z"),
+        remote_album("srv-2", "New", "2024-02-02T00:00:00Z"),
+        remote_album("srv-3", "Another", "2024-02-03T00:00:00Z"),
+    ];
+    let remote = FakeRemote::with_feed(feed, 2);
+    let report =
+        sync_engine::run_sync(&tmp.conn, &remote, &LastWriterWins, &SyncOptions::default())
+            .await
+            .unwrap();
+
+    assert_eq!(report.pulled, 3);
+    assert_eq!(report.cursor.as_deref(), Some("3"));
+    assert_eq!(
+        sync_engine::get_sync_state(&tmp.conn, "pull_cursor")
+            .await
+            .unwrap(),
+        Some("3".to_string())
+    );
+    assert_eq!(
+        query_text(
+            &tmp.conn,
+            "SELECT json_extract(data, '$.name') FROM albums WHERE id = 'local-1'"
+        )
+        .await,
+        Some("Renamed".to_string())
+    );
+    assert_eq!(
+        query_text(
+            &tmp.conn,
+            "SELECT updated_at FROM albums WHERE id = 'local-1'"
+        )
+        .await,
+        Some("2024-02-01 00:00:00".to_string())
+    );
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            "SELECT COUNT(*) FROM albums WHERE sync_status = 'synced'"
+        )
+        .await,
+        3
+    );
+    // 拉取的行不应被再次推送
+    assert!(remote.pushed.lock().unwrap().is_empty());
+
+    let again = sync_engine::run_sync(&tmp.conn, &remote, &LastWriterWins, &SyncOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(again.pulled, 0);
+
+    tmp.cleanup();
+}
+
+/// 冲突：本地更新更晚时保留本地并保持待推送，随后推送出去
+#[tokio::test]
+async fn test_last_writer_wins_keeps_newer_local_row() {
+    let tmp = TempDb::with_schema("echo_trails_sync_lww").await;
+    tmp.conn
+        .execute(
+            "INSERT INTO albums (id, data, remote_id, sync_status, updated_at) VALUES ('mine', '{\"name\":\"Local edit\"}', 'srv-1', 'pending', '2024-03-01 00:00:00'),
+             ('stale', '{\"name\":\"Stale edit\"}', 'srv-2', 'pending', '2024-01-01 00:00:00')",
+            (),
+        )
+        .await
+        .unwrap();
+
+    let feed = vec![
+        remote_album("srv-1", "Server edit", "2024-02-01T00:00:00Z"),
+        remote_album("srv-2", "Server wins", "2024-02-01T00:00:00Z"),
+    ];
+    let remote = FakeRemote::with_feed(feed, 10);
+    let report =
+        sync_engine::run_sync(&tmp.conn, &remote, &LastWriterWins, &SyncOptions::default())
+            .await
+            .unwrap();
+
+    assert_eq!(report.conflicts, 1);
+    assert_eq!(report.pulled, 1);
+    assert_eq!(
+        query_text(
+            &tmp.conn,
+            "SELECT json_extract(data, '$.name') FROM albums WHERE id = 'mine'"
+        )
+        .await,
+        Some("Local edit".to_string())
+    );
+    assert_eq!(
+        query_text(
+            &tmp.conn,
+            "SELECT json_extract(data, '$.name') FROM albums WHERE id = 'stale'"
+        )
+        .await,
+        Some("Server wins".to_string())
+    );
+    let pushed = remote.pushed.lock().unwrap().clone();
+    assert_eq!(pushed.len(), 1);
+    assert_eq!(pushed[0].id, "mine");
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            "SELECT COUNT(*) FROM sync_log WHERE status = 'conflict'"
+        )
+        .await,
+        1
+    );
+
+    tmp.cleanup();
+}
+
+/// 自定义策略：始终保留本地
+struct AlwaysLocal;
+
+impl ConflictPolicy for AlwaysLocal {
+    fn resolve(&self, _local: &SyncChange, _remote: &SyncChange) -> Resolution {
+        Resolution::KeepLocal
+    }
+}
+
+#[tokio::test]
+async fn test_custom_conflict_policy_is_respected() {
+    let tmp = TempDb::with_schema("echo_trails_sync_policy").await;
+    tmp.conn
+        .execute(
+            "INSERT INTO albums (id, data, remote_id, sync_status, updated_at) VALUES ('mine', '{\"name\":\"Old local\"}', 'srv-1', 'pending', '2020-01-01 00:00:00')",
+            (),
+        )
+        .await
+        .unwrap();
+
+    let remote = FakeRemote::with_feed(
+        vec![remote_album(
+            "srv-1",
+            "Newer server",
+            "2024-02-01T00:00:00Z",
+        )],
+        10,
+    );
+    let report = sync_engine::run_sync(&tmp.conn, &remote, &AlwaysLocal, &SyncOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(report.conflicts, 1);
+    assert_eq!(
+        query_text(
+            &tmp.conn,
+            "SELECT json_extract(data, '$.name') FROM albums WHERE id = 'mine'"
+        )
+        .await,
+        Some("Old local".to_string())
+    );
+
+    tmp.cleanup();
+}
+
+/// 同步注册表：由建表语句推导，包含 todos / drive_files，排除非同步表
+#[test]
+fn test_sync_registry_is_derived_from_schema() {
+    let tables = tauri_app_lib::db::sync_tables();
+    for expected in [
+        "photos",
+        "albums",
+        "album_folders",
+        "asset_categories",
+        "assets",
+        "memorials",
+        "families",
+        "weights",
+        "blood_pressures",
+        "usage_records",
+        "todos",
+        "drive_files",
+    ] {
+        assert!(tables.contains(&expected), "missing {}", expected);
+    }
+    for excluded in [
+        "kv_cache",
+        "photo_albums",
+        "sync_log",
+        "sync_state",
+        "schema_version",
+    ] {
+        assert!(!tables.contains(&excluded), "unexpected {}", excluded);
+    }
+}
+
+#[tokio::test]
+async fn test_todos_and_drive_files_are_pushed() {
+    let tmp = TempDb::with_schema("echo_trails_sync_registry").await;
+    tmp.conn
+        .execute(
+            "INSERT INTO todos (id, quadrant, data) VALUES ('t1', 1, '{\"title\":\"Pay bills\"}')",
+            (),
+        )
+        .await
+        .unwrap();
+    tmp.conn
+        .execute(
+            "INSERT INTO drive_files (id, parent_id, kind, data) VALUES ('f1', NULL, 'folder', '{\"name\":\"Docs\"}')",
+            (),
+        )
+        .await
+        .unwrap();
+
+    let remote = FakeRemote::default();
+    let report =
+        sync_engine::run_sync(&tmp.conn, &remote, &LastWriterWins, &SyncOptions::default())
+            .await
+            .unwrap();
+
+    assert_eq!(report.pushed, 2);
+    let pushed = remote.pushed.lock().unwrap().clone();
+    let todo = pushed.iter().find(|c| c.entity_type == "todos").unwrap();
+    assert_eq!(todo.data.get("title"), Some(&json!("Pay bills")));
+    assert_eq!(todo.data.get("quadrant"), Some(&json!(1)));
+    let folder = pushed
+        .iter()
+        .find(|c| c.entity_type == "drive_files")
+        .unwrap();
+    assert_eq!(folder.data.get("kind"), Some(&json!("folder")));
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            "SELECT COUNT(*) FROM todos WHERE sync_status = 'synced'"
+        )
+        .await,
+        1
+    );
+
+    tmp.cleanup();
+}
+
+/// 墓碑：物理删除写入墓碑并推送，仅本地存在过的行不写；远端旧变更不会复活已删除行
+#[tokio::test]
+async fn test_purge_writes_tombstones_that_sync_pushes() {
+    let tmp = TempDb::with_schema("echo_trails_sync_tombstone").await;
+    tmp.conn
+        .execute(
+            "INSERT INTO drive_files (id, remote_id, sync_status, kind, deleted) VALUES
+             ('synced-file', 'srv-9', 'synced', 'file', 1),
+             ('local-file', NULL, 'local', 'file', 1),
+             ('kept-file', 'srv-10', 'synced', 'file', 0)",
+            (),
+        )
+        .await
+        .unwrap();
+
+    sync_engine::purge_with_tombstones(&tmp.conn, "drive_files", "deleted = 1", Vec::new())
+        .await
+        .unwrap();
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT COUNT(*) FROM drive_files").await,
+        1
+    );
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT COUNT(*) FROM tombstones").await,
+        1
+    );
+
+    // 远端仍带着旧版本：不应复活
+    let mut stale = remote_album("srv-9", "Resurrected", "2024-02-01T00:00:00Z");
+    stale.entity_type = "drive_files".to_string();
+    let remote = FakeRemote::with_feed(vec![stale], 10);
+    let report =
+        sync_engine::run_sync(&tmp.conn, &remote, &LastWriterWins, &SyncOptions::default())
+            .await
+            .unwrap();
+
+    assert_eq!(report.pulled, 0);
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT COUNT(*) FROM drive_files").await,
+        1
+    );
+    let pushed = remote.pushed.lock().unwrap().clone();
+    assert_eq!(pushed.len(), 1);
+    assert_eq!(pushed[0].id, "synced-file");
+    assert_eq!(pushed[0].remote_id.as_deref(), Some("srv-9"));
+    assert!(pushed[0].deleted);
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            "SELECT COUNT(*) FROM tombstones WHERE pushed_at IS NOT NULL"
+        )
+        .await,
+        1
+    );
+    // 保留期内的墓碑不清理
+    assert_eq!(report.tombstones_purged, 0);
+
+    tmp.cleanup();
+}
+
+#[tokio::test]
+async fn test_expired_tombstones_are_collected_after_sync() {
+    let tmp = TempDb::with_schema("echo_trails_sync_tombstone_gc").await;
+    tmp.conn
+        .execute(
+            "INSERT INTO tombstones (entity_type, entity_id, remote_id, deleted_at, pushed_at) VALUES
+             ('todos', 'old-pushed', 'srv-1', '2000-01-01 00:00:00', '2000-01-02 00:00:00'),
+             ('todos', 'recent-pushed', 'srv-2', datetime('now'), datetime('now'))",
+            (),
+        )
+        .await
+        .unwrap();
+    tmp.conn
+        .execute(
+            "INSERT INTO tombstones (entity_type, entity_id, remote_id, deleted_at) VALUES
+             ('todos', 'old-unpushed', 'srv-3', '2000-01-01 00:00:00')",
+            (),
+        )
+        .await
+        .unwrap();
+
+    let remote = FakeRemote::default();
+    let report =
+        sync_engine::run_sync(&tmp.conn, &remote, &LastWriterWins, &SyncOptions::default())
+            .await
+            .unwrap();
+
+    // 未推送的旧墓碑本轮推送后才满足清理条件
+    assert_eq!(remote.pushed.lock().unwrap().len(), 1);
+    assert_eq!(report.tombstones_purged, 2);
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT COUNT(*) FROM tombstones").await,
+        1
+    );
+
+    tmp.cleanup();
+}
+
+/// 软删除标记为待同步并以 deleted 推送
+#[tokio::test]
+async fn test_soft_deleted_rows_are_pushed_as_deleted() {
+    let tmp = TempDb::with_schema("echo_trails_sync_soft_delete").await;
+    tmp.conn
+        .execute(
+            "INSERT INTO todos (id, remote_id, sync_status, data) VALUES ('t1', 'srv-1', 'synced', '{}')",
+            (),
+        )
+        .await
+        .unwrap();
+    tmp.conn
+        .execute(
+            "UPDATE todos SET deleted = 1, sync_status = 'pending', updated_at = datetime('now') WHERE id = 't1'",
+            (),
+        )
+        .await
+        .unwrap();
+
+    let remote = FakeRemote::default();
+    sync_engine::run_sync(&tmp.conn, &remote, &LastWriterWins, &SyncOptions::default())
+        .await
+        .unwrap();
+
+    let pushed = remote.pushed.lock().unwrap().clone();
+    assert_eq!(pushed.len(), 1);
+    assert!(pushed[0].deleted);
+    assert_eq!(pushed[0].remote_id.as_deref(), Some("srv-1"));
+
+    tmp.cleanup();
+}
+
+/// 已同步的行再次编辑后重新标记为 pending，下一轮同步推送新内容
+#[tokio::test]
+async fn test_edit_after_sync_is_pushed() {
+    let tmp = TempDb::with_schema("echo_trails_sync_edit_again").await;
+    tmp.conn
+        .execute(
+            "INSERT INTO albums (id, sync_status, data) VALUES ('a1', 'pending', ?1)",
+            (json!({ "name": "Trip", "folderId": "f1" }).to_string(),),
+        )
+        .await
+        .unwrap();
+    tmp.conn
+        .execute(
+            "INSERT INTO photos (id, sync_status, data) VALUES ('p1', 'pending', '{}')",
+            (),
+        )
+        .await
+        .unwrap();
+
+    let remote = FakeRemote::default();
+    sync_engine::run_sync(&tmp.conn, &remote, &LastWriterWins, &SyncOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(remote.pushed.lock().unwrap().len(), 2);
+    remote.pushed.lock().unwrap().clear();
+
+    album::set_albums_folder(&tmp.conn, &["a1".to_string()], None)
+        .await
+        .unwrap();
+    photo::add_photos_to_albums(&tmp.conn, &["p1".to_string()], &["a1".to_string()])
+        .await
+        .unwrap();
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            "SELECT COUNT(*) FROM albums WHERE sync_status = 'pending'"
+        )
+        .await,
+        1
+    );
+
+    let report =
+        sync_engine::run_sync(&tmp.conn, &remote, &LastWriterWins, &SyncOptions::default())
+            .await
+            .unwrap();
+    assert_eq!(report.pushed, 2);
+    let pushed = remote.pushed.lock().unwrap().clone();
+    let album = pushed.iter().find(|c| c.entity_type == "albums").unwrap();
+    assert_eq!(album.remote_id.as_deref(), Some("r-a1"));
+    assert_eq!(album.data.get("folderId"), None);
+    let photo = pushed.iter().find(|c| c.entity_type == "photos").unwrap();
+    assert_eq!(photo.data.get("albumId"), Some(&json!(["a1"])));
+
+    tmp.cleanup();
+}
+
+/// 始终返回 hasMore 且游标不再前进的服务器
+struct StuckRemote;
+
+impl SyncRemote for StuckRemote {
+    async fn push(&self, _changes: Vec<SyncChange>) -> Result<Vec<PushAck>, AppError> {
+        Ok(Vec::new())
+    }
+
+    async fn pull(&self, cursor: Option<String>, _limit: usize) -> Result<PullPage, AppError> {
+        Ok(PullPage {
+            changes: vec![remote_album("srv-1", "Loop", "2024-01-01T00:00:00Z")],
+            cursor: cursor.or(Some("c1".to_string())),
+            has_more: true,
+        })
+    }
+}
+
+/// 服务端声明 hasMore 却不推进游标时报错，而不是无限循环
+#[tokio::test]
+async fn test_pull_stops_when_cursor_does_not_advance() {
+    let tmp = TempDb::with_schema("echo_trails_sync_stuck_cursor").await;
+
+    let options = SyncOptions::default();
+    let err = sync_engine::run_sync(&tmp.conn, &StuckRemote, &LastWriterWins, &options)
+        .await
+        .unwrap_err();
+    assert_eq!(err.code, ErrorCode::Network);
+    // 第一页仍已应用，游标停在服务端最后给出的位置
+    assert_eq!(
+        sync_engine::get_sync_state(&tmp.conn, "pull_cursor").await.unwrap(),
+        Some("c1".to_string())
+    );
+
+    tmp.cleanup();
+}
+
+/// 从未同步过的文件即使软删除后变为 pending，彻底删除时也不写墓碑
+#[tokio::test]
+async fn test_purging_never_synced_drive_file_writes_no_tombstone() {
+    let tmp = TempDb::with_schema("echo_trails_sync_tombstone_unsynced").await;
+    tmp.conn
+        .execute(
+            "INSERT INTO drive_files (id, sync_status, kind) VALUES ('draft', 'local', 'file')",
+            (),
+        )
+        .await
+        .unwrap();
+    tmp.conn
+        .execute(
+            "UPDATE drive_files SET deleted = 1, sync_status = 'pending', updated_at = datetime('now') WHERE id = 'draft'",
+            (),
+        )
+        .await
+        .unwrap();
+
+    sync_engine::purge_with_tombstones(&tmp.conn, "drive_files", "deleted = 1", Vec::new())
+        .await
+        .unwrap();
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT COUNT(*) FROM drive_files").await,
+        0
+    );
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT COUNT(*) FROM tombstones").await,
+        0
+    );
+
+    tmp.cleanup();
+}
+
+/// 确认推送但不返回 remote_id 的服务器
+struct AckWithoutId;
+
+impl SyncRemote for AckWithoutId {
+    async fn push(&self, changes: Vec<SyncChange>) -> Result<Vec<PushAck>, AppError> {
+        Ok(changes
+            .iter()
+            .map(|c| PushAck {
+                entity_type: c.entity_type.clone(),
+                id: c.id.clone(),
+                remote_id: None,
+                error: None,
+            })
+            .collect())
+    }
+
+    async fn pull(&self, _cursor: Option<String>, _limit: usize) -> Result<PullPage, AppError> {
+        Ok(PullPage::default())
+    }
+}
+
+/// 推送过但没有 remote_id 的行，再次编辑后彻底删除仍写墓碑
+#[tokio::test]
+async fn test_purging_pushed_row_without_remote_id_writes_tombstone() {
+    let tmp = TempDb::with_schema("echo_trails_sync_tombstone_no_remote_id").await;
+    tmp.conn
+        .execute(
+            "INSERT INTO drive_files (id, sync_status, kind) VALUES ('doc', 'local', 'file')",
+            (),
+        )
+        .await
+        .unwrap();
+    sync_engine::run_sync(&tmp.conn, &AckWithoutId, &LastWriterWins, &SyncOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            "SELECT COUNT(*) FROM drive_files WHERE sync_status = 'synced' AND remote_id IS NULL"
+        )
+        .await,
+        1
+    );
+
+    tmp.conn
+        .execute(
+            "UPDATE drive_files SET deleted = 1, sync_status = 'pending', updated_at = datetime('now') WHERE id = 'doc'",
+            (),
+        )
+        .await
+        .unwrap();
+    sync_engine::purge_with_tombstones(&tmp.conn, "drive_files", "deleted = 1", Vec::new())
+        .await
+        .unwrap();
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            "SELECT COUNT(*) FROM tombstones WHERE entity_type = 'drive_files' AND entity_id = 'doc'"
+        )
+        .await,
+        1
+    );
+
+    tmp.cleanup();
+}
+
+/// 推送途中（同一秒内）再次编辑行的服务器：updated_at 不变，只有内容变化
+struct EditingRemote {
+    conn: turso::Connection,
+}
+
+impl SyncRemote for EditingRemote {
+    async fn push(&self, changes: Vec<SyncChange>) -> Result<Vec<PushAck>, AppError> {
+        self.conn
+            .execute(
+                "UPDATE albums SET data = '{\"name\":\"Renamed\"}', sync_status = 'pending' WHERE id = 'a1'",
+                (),
+            )
+            .await
+            .unwrap();
+        Ok(changes
+            .iter()
+            .map(|c| PushAck {
+                entity_type: c.entity_type.clone(),
+                id: c.id.clone(),
+                remote_id: Some(format!("r-{}", c.id)),
+                error: None,
+            })
+            .collect())
+    }
+
+    async fn pull(&self, _cursor: Option<String>, _limit: usize) -> Result<PullPage, AppError> {
+        Ok(PullPage::default())
+    }
+}
+
+/// 推送期间同一秒内的编辑不会被确认覆盖：行保持 pending，下一轮推送新内容
+#[tokio::test]
+async fn test_edit_in_same_second_as_push_stays_pending() {
+    let tmp = TempDb::with_schema("echo_trails_sync_same_second").await;
+    tmp.conn
+        .execute(
+            "INSERT INTO albums (id, sync_status, updated_at, data) VALUES ('a1', 'pending', '2024-05-01 08:00:00', '{\"name\":\"Trip\"}')",
+            (),
+        )
+        .await
+        .unwrap();
+
+    let remote = EditingRemote {
+        conn: tmp.db.connect().unwrap(),
+    };
+    let report = sync_engine::run_sync(&tmp.conn, &remote, &LastWriterWins, &SyncOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(report.pushed, 1);
+    assert_eq!(
+        query_text(&tmp.conn, "SELECT sync_status FROM albums WHERE id = 'a1'").await,
+        Some("pending".to_string())
+    );
+    // 确认仍记录 remote_id 与推送标记
+    assert_eq!(
+        query_text(&tmp.conn, "SELECT remote_id FROM albums WHERE id = 'a1'").await,
+        Some("r-a1".to_string())
+    );
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT pushed FROM albums WHERE id = 'a1'").await,
+        1
+    );
+
+    let pending = sync_engine::collect_pending(&tmp.conn).await.unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].data.get("name"), Some(&json!("Renamed")));
+
+    tmp.cleanup();
+}
@@ -0,0 +1,132 @@
+use std::path::{Path, PathBuf};
+
+use image::{DynamicImage, RgbImage};
+use tauri_app_lib::command::thumbnail::{ThumbnailCache, ThumbnailFormat};
+use tauri_app_lib::error::ErrorCode;
+
+fn temp_dir(prefix: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("{}_{}", prefix, uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write_source(dir: &Path, name: &str, width: u32, height: u32, seed: u8) -> PathBuf {
+    let img = RgbImage::from_fn(width, height, |x, y| {
+        image::Rgb([(x % 256) as u8 ^ seed, (y % 256) as u8, seed])
+    });
+    let path = dir.join(name);
+    DynamicImage::ImageRgb8(img).save(&path).unwrap();
+    path
+}
+
+fn md5_of(n: u8) -> String {
+    format!("{:02x}{}", n, "0".repeat(30))
+}
+
+/// 首次生成、二次命中；按最长边等比缩小，不放大小图
+#[test]
+fn test_generate_then_hit() {
+    let dir = temp_dir("echo_trails_thumb");
+    let source = write_source(&dir, "a.png", 1200, 600, 1);
+    let small = write_source(&dir, "b.png", 100, 80, 2);
+    let cache = ThumbnailCache::new(dir.join("thumbnails"));
+
+    let first = cache
+        .get_or_create(&source, &md5_of(1), 256, ThumbnailFormat::Jpeg)
+        .unwrap();
+    assert!(!first.hit);
+    assert_eq!((first.width, first.height), (256, 128));
+    assert!(first.path.ends_with(&format!("{}_256.jpg", md5_of(1))));
+    assert_eq!(image::image_dimensions(&first.path).unwrap(), (256, 128));
+
+    let again = cache
+        .get_or_create(&source, &md5_of(1), 256, ThumbnailFormat::Jpeg)
+        .unwrap();
+    assert!(again.hit);
+    assert_eq!(again.path, first.path);
+
+    // 小图不放大，只转码
+    let unscaled = cache
+        .get_or_create(&small, &md5_of(2), 512, ThumbnailFormat::Jpeg)
+        .unwrap();
+    assert_eq!((unscaled.width, unscaled.height), (100, 80));
+    assert!(unscaled.path.ends_with(&format!("{}_512.jpg", md5_of(2))));
+
+    let usage = cache.usage().unwrap();
+    assert_eq!(usage.count, 2);
+    assert_eq!(usage.bytes, first.bytes + unscaled.bytes);
+
+    // WebP 走有损编码（VP8 而非无损的 VP8L），体积与 JPEG 相当
+    let webp = cache
+        .get_or_create(&source, &md5_of(3), 256, ThumbnailFormat::WebP)
+        .unwrap();
+    assert!(webp.path.ends_with(&format!("{}_256.webp", md5_of(3))));
+    assert_eq!(image::image_dimensions(&webp.path).unwrap(), (256, 128));
+    let header = std::fs::read(&webp.path).unwrap();
+    assert_eq!(&header[12..16], b"VP8 ");
+    assert!(webp.bytes < first.bytes * 2);
+
+    let usage = cache.usage().unwrap();
+    assert_eq!(usage.count, 3);
+    assert_eq!(ThumbnailFormat::parse(Some("WebP")).unwrap(), ThumbnailFormat::WebP);
+    assert_eq!(ThumbnailFormat::parse(None).unwrap(), ThumbnailFormat::Jpeg);
+    assert!(ThumbnailFormat::parse(Some("avif")).is_err());
+
+    let err = cache
+        .get_or_create(&source, &md5_of(1), 300, ThumbnailFormat::Jpeg)
+        .unwrap_err();
+    assert_eq!(err.code, ErrorCode::Validation);
+    let err = cache
+        .get_or_create(&source, "../../etc/passwd", 256, ThumbnailFormat::Jpeg)
+        .unwrap_err();
+    assert_eq!(err.code, ErrorCode::Validation);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// 超出预算时淘汰最久未访问的缩略图；预算与访问顺序在重启后保留
+#[test]
+fn test_lru_eviction_and_clear() {
+    let dir = temp_dir("echo_trails_thumb_lru");
+    let sources: Vec<PathBuf> = (0..3)
+        .map(|i| write_source(&dir, &format!("{}.png", i), 600, 600, 7))
+        .collect();
+    let cache = ThumbnailCache::new(dir.join("thumbnails"));
+
+    let a = cache
+        .get_or_create(&sources[0], &md5_of(10), 256, ThumbnailFormat::Jpeg)
+        .unwrap();
+    let b = cache
+        .get_or_create(&sources[1], &md5_of(11), 256, ThumbnailFormat::Jpeg)
+        .unwrap();
+    // 三张内容相同、大小一致；预算只够两张，访问 a 使 b 成为最久未用
+    cache.set_budget(a.bytes + b.bytes + 16).unwrap();
+    assert!(
+        cache
+            .get_or_create(&sources[0], &md5_of(10), 256, ThumbnailFormat::Jpeg)
+            .unwrap()
+            .hit
+    );
+    let c = cache
+        .get_or_create(&sources[2], &md5_of(12), 256, ThumbnailFormat::Jpeg)
+        .unwrap();
+
+    assert!(Path::new(&a.path).exists());
+    assert!(!Path::new(&b.path).exists());
+    assert!(Path::new(&c.path).exists());
+    let usage = cache.usage().unwrap();
+    assert_eq!(usage.count, 2);
+    assert!(usage.bytes <= usage.budget_bytes);
+
+    let reopened = ThumbnailCache::new(dir.join("thumbnails"));
+    let usage = reopened.usage().unwrap();
+    assert_eq!(usage.count, 2);
+    assert_eq!(usage.budget_bytes, a.bytes + b.bytes + 16);
+
+    let freed = reopened.clear().unwrap();
+    assert_eq!(freed.count, 2);
+    assert_eq!(reopened.usage().unwrap().count, 0);
+    assert!(!Path::new(&a.path).exists());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
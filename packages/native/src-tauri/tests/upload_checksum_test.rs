@@ -0,0 +1,162 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use common::{http_response, spawn_http_server};
+use tauri_app_lib::command::s3_presign::PayloadChecksum;
+use tauri_app_lib::command::upload::put_file;
+use tauri_app_lib::error::ErrorCode;
+
+const CONTENT: &[u8] = b"hello echo trails";
+
+fn content_md5() -> String {
+    format!("{:x}", md5::compute(CONTENT))
+}
+
+async fn source_file() -> (std::path::PathBuf, tokio::fs::File) {
+    let path =
+        std::env::temp_dir().join(format!("echo_trails_checksum_{}.bin", uuid::Uuid::new_v4()));
+    std::fs::write(&path, CONTENT).unwrap();
+    let file = tokio::fs::File::open(&path).await.unwrap();
+    (path, file)
+}
+
+/// 携带 Content-MD5，ETag 与文件 MD5 一致时成功
+#[tokio::test]
+async fn test_put_sends_content_md5_and_accepts_matching_etag() {
+    let md5 = content_md5();
+    let seen = Arc::new(Mutex::new(None));
+    let server_seen = seen.clone();
+    let etag = format!("\"{}\"", md5);
+    let url = spawn_http_server(move |req| {
+        *server_seen.lock().unwrap() = req.header("content-md5").map(String::from);
+        http_response("200 OK", &[("ETag", &etag)], "")
+    })
+    .await;
+
+    let (path, file) = source_file().await;
+    let checksum = PayloadChecksum::Md5(&md5);
+    put_file(
+        &reqwest::Client::new(),
+        &url,
+        file,
+        CONTENT.len() as u64,
+        Some(checksum),
+        |_, _| {},
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        seen.lock().unwrap().clone(),
+        Some(checksum.header_value().unwrap())
+    );
+
+    let _ = std::fs::remove_file(path);
+}
+
+/// ETag 与 MD5 不一致、或 S3 返回 BadDigest 时报 ChecksumMismatch
+#[tokio::test]
+async fn test_put_reports_checksum_mismatch() {
+    let md5 = content_md5();
+    let client = reqwest::Client::new();
+
+    let url = spawn_http_server(|_| {
+        http_response(
+            "200 OK",
+            &[("ETag", "\"0cc175b9c0f1b6a831c399e269772661\"")],
+            "",
+        )
+    })
+    .await;
+    let (path, file) = source_file().await;
+    let err = put_file(
+        &client,
+        &url,
+        file,
+        CONTENT.len() as u64,
+        Some(PayloadChecksum::Md5(&md5)),
+        |_, _| {},
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.code, ErrorCode::ChecksumMismatch);
+    assert_eq!(err.details.unwrap()["expected"], md5.as_str());
+
+    let url = spawn_http_server(|_| {
+        http_response(
+            "400 Bad Request",
+            &[("Content-Type", "application/xml")],
+            "<Error><Code>BadDigest</Code><Message>The Content-MD5 you specified did not match what we received.</Message></Error>",
+        )
+    })
+    .await;
+    let file = tokio::fs::File::open(&path).await.unwrap();
+    let err = put_file(
+        &client,
+        &url,
+        file,
+        CONTENT.len() as u64,
+        Some(PayloadChecksum::Md5(&md5)),
+        |_, _| {},
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.code, ErrorCode::ChecksumMismatch);
+
+    // 其它 4xx 仍是普通的 S3 状态错误
+    let url = spawn_http_server(|_| {
+        http_response(
+            "403 Forbidden",
+            &[],
+            "<Error><Code>AccessDenied</Code></Error>",
+        )
+    })
+    .await;
+    let file = tokio::fs::File::open(&path).await.unwrap();
+    let err = put_file(
+        &client,
+        &url,
+        file,
+        CONTENT.len() as u64,
+        Some(PayloadChecksum::Md5(&md5)),
+        |_, _| {},
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.code, ErrorCode::S3Status);
+
+    let _ = std::fs::remove_file(path);
+}
+
+/// 无法比较的 ETag（分片或加密对象）及未要求校验时不做比对
+#[tokio::test]
+async fn test_put_skips_uncomparable_etag() {
+    let md5 = content_md5();
+    let client = reqwest::Client::new();
+    let url = spawn_http_server(|_| {
+        http_response(
+            "200 OK",
+            &[("ETag", "\"0cc175b9c0f1b6a831c399e269772661-2\"")],
+            "",
+        )
+    })
+    .await;
+
+    let (path, file) = source_file().await;
+    put_file(
+        &client,
+        &url,
+        file,
+        CONTENT.len() as u64,
+        Some(PayloadChecksum::Md5(&md5)),
+        |_, _| {},
+    )
+    .await
+    .unwrap();
+    let file = tokio::fs::File::open(&path).await.unwrap();
+    put_file(&client, &url, file, CONTENT.len() as u64, None, |_, _| {})
+        .await
+        .unwrap();
+
+    let _ = std::fs::remove_file(path);
+}
@@ -0,0 +1,306 @@
+mod common;
+
+use common::{query_i64, TempDb};
+use serde_json::json;
+use tauri_app_lib::command::upload_queue::{
+    backoff_seconds, finish_job, is_retryable, MAX_ATTEMPTS,
+};
+use tauri_app_lib::db::upload_job::{self, JobState, NewUploadJob, UploadEntity};
+use tauri_app_lib::error::{AppError, ErrorCode};
+
+fn new_job(key: &str) -> NewUploadJob {
+    NewUploadJob {
+        object_key: key.to_string(),
+        source_path: format!("/tmp/{}", key),
+        entity_type: Some(UploadEntity::Photo),
+        entity_id: None,
+        meta: Some(json!({ "name": key })),
+    }
+}
+
+/// 入队去重、按入队顺序领取、已领取的任务不会再次被领取
+#[tokio::test]
+async fn test_enqueue_and_claim_in_order() {
+    let tmp = TempDb::with_schema("echo_trails_upload_queue_claim").await;
+    let jobs = upload_job::enqueue_jobs(&tmp.conn, &[new_job("a.jpg"), new_job("b.jpg")])
+        .await
+        .unwrap();
+    assert_eq!(jobs.len(), 2);
+    assert_eq!(jobs[0].state, JobState::Pending);
+    assert_eq!(jobs[0].entity_type, Some(UploadEntity::Photo));
+    assert_eq!(jobs[0].meta, Some(json!({ "name": "a.jpg" })));
+
+    // 同一 key 未完成时复用已有任务
+    let again = upload_job::enqueue_jobs(&tmp.conn, &[new_job("a.jpg")])
+        .await
+        .unwrap();
+    assert_eq!(again[0].id, jobs[0].id);
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT COUNT(*) FROM upload_jobs").await,
+        2
+    );
+
+    let first = upload_job::claim_next_job(&tmp.conn)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(first.id, jobs[0].id);
+    assert_eq!(first.state, JobState::Running);
+    assert_eq!(first.attempts, 1);
+    let second = upload_job::claim_next_job(&tmp.conn)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(second.id, jobs[1].id);
+    assert!(upload_job::claim_next_job(&tmp.conn)
+        .await
+        .unwrap()
+        .is_none());
+
+    assert!(upload_job::mark_done(&tmp.conn, &first.id).await.unwrap());
+    // 完成后同一 key 可再次入队
+    let fresh = upload_job::enqueue_jobs(&tmp.conn, &[new_job("a.jpg")])
+        .await
+        .unwrap();
+    assert_ne!(fresh[0].id, first.id);
+
+    let err = upload_job::enqueue_jobs(&tmp.conn, &[new_job("")])
+        .await
+        .unwrap_err();
+    assert_eq!(err.code, ErrorCode::Validation);
+
+    tmp.cleanup();
+}
+
+/// 失败重试进入退避，未到期不会被领取；重启后 running 任务重新排队
+#[tokio::test]
+async fn test_retry_backoff_and_requeue() {
+    let tmp = TempDb::with_schema("echo_trails_upload_queue_retry").await;
+    upload_job::enqueue_jobs(&tmp.conn, &[new_job("a.jpg"), new_job("b.jpg")])
+        .await
+        .unwrap();
+
+    let a = upload_job::claim_next_job(&tmp.conn)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(upload_job::mark_retry(&tmp.conn, &a.id, "timeout", 60)
+        .await
+        .unwrap());
+    let a = upload_job::get_job(&tmp.conn, &a.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(a.state, JobState::Pending);
+    assert_eq!(a.last_error.as_deref(), Some("timeout"));
+    assert!(a.next_attempt_at.is_some());
+
+    // a 仍在退避中，先领取 b
+    let b = upload_job::claim_next_job(&tmp.conn)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(b.object_key, "b.jpg");
+    assert!(upload_job::claim_next_job(&tmp.conn)
+        .await
+        .unwrap()
+        .is_none());
+
+    // 模拟应用重启：上传中的 b 重新排队且不计入本次尝试
+    assert_eq!(upload_job::requeue_interrupted(&tmp.conn).await.unwrap(), 1);
+    let b = upload_job::get_job(&tmp.conn, &b.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!((b.state, b.attempts), (JobState::Pending, 0));
+
+    let b = upload_job::claim_next_job(&tmp.conn)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(upload_job::mark_failed(&tmp.conn, &b.id, "forbidden")
+        .await
+        .unwrap());
+    let failed = upload_job::list_jobs(&tmp.conn, &[JobState::Failed])
+        .await
+        .unwrap();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].last_error.as_deref(), Some("forbidden"));
+
+    tmp.cleanup();
+}
+
+/// 暂停/恢复/取消：上传中被暂停的任务不会被 worker 改回完成
+#[tokio::test]
+async fn test_pause_resume_cancel() {
+    let tmp = TempDb::with_schema("echo_trails_upload_queue_control").await;
+    let jobs = upload_job::enqueue_jobs(
+        &tmp.conn,
+        &[new_job("a.jpg"), new_job("b.jpg"), new_job("c.jpg")],
+    )
+    .await
+    .unwrap();
+    let running = upload_job::claim_next_job(&tmp.conn)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let paused = upload_job::pause_jobs(&tmp.conn, None).await.unwrap();
+    assert_eq!(paused.len(), 3);
+    assert!(paused.iter().all(|j| j.state == JobState::Paused));
+    assert!(!upload_job::mark_done(&tmp.conn, &running.id).await.unwrap());
+    assert!(upload_job::claim_next_job(&tmp.conn)
+        .await
+        .unwrap()
+        .is_none());
+
+    let resumed = upload_job::resume_jobs(&tmp.conn, Some(std::slice::from_ref(&running.id)))
+        .await
+        .unwrap();
+    assert_eq!(resumed.len(), 1);
+    assert_eq!(
+        (resumed[0].state, resumed[0].attempts),
+        (JobState::Pending, 0)
+    );
+
+    let cancelled = upload_job::cancel_jobs(
+        &tmp.conn,
+        Some(&[jobs[1].id.clone(), "missing".to_string()]),
+    )
+    .await
+    .unwrap();
+    assert_eq!(cancelled.len(), 1);
+    assert_eq!(cancelled[0].state, JobState::Cancelled);
+    // 已取消的任务不能再恢复
+    assert!(
+        upload_job::resume_jobs(&tmp.conn, Some(std::slice::from_ref(&jobs[1].id)))
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    assert_eq!(upload_job::clear_finished(&tmp.conn).await.unwrap(), 1);
+    let states: Vec<JobState> = upload_job::list_jobs(&tmp.conn, &[])
+        .await
+        .unwrap()
+        .iter()
+        .map(|j| j.state)
+        .collect();
+    assert_eq!(states, vec![JobState::Pending, JobState::Paused]);
+    assert!(JobState::parse("unknown").is_err());
+
+    tmp.cleanup();
+}
+
+/// 完成任务时在同一事务内写入照片 / 文件记录；记录无效时任务失败，暂停的任务不落库
+#[tokio::test]
+async fn test_finish_job_writes_entity() {
+    let tmp = TempDb::with_schema("echo_trails_upload_queue_entity").await;
+    let photo = NewUploadJob {
+        meta: Some(json!({
+            "type": "image/jpeg",
+            "md5": "abc",
+            "data": "{\"name\":\"a.jpg\",\"key\":\"a.jpg\"}",
+            "albumIds": ["album-1"],
+        })),
+        ..new_job("a.jpg")
+    };
+    let file = NewUploadJob {
+        entity_type: Some(UploadEntity::DriveFile),
+        entity_id: Some("file-1".to_string()),
+        meta: Some(json!({ "name": "doc.pdf", "size": 12, "parentId": "dir" })),
+        ..new_job("drive/doc.pdf")
+    };
+    let jobs = upload_job::enqueue_jobs(
+        &tmp.conn,
+        &[photo, file, new_job("bad.jpg"), new_job("paused.jpg")],
+    )
+    .await
+    .unwrap();
+    // 未指定时入队即生成记录 id，前端可据此对应
+    let photo_id = jobs[0].entity_id.clone().unwrap();
+    assert_eq!(jobs[1].entity_id.as_deref(), Some("file-1"));
+
+    let mut claimed = Vec::new();
+    while let Some(job) = upload_job::claim_next_job(&tmp.conn).await.unwrap() {
+        claimed.push(job);
+    }
+    assert_eq!(claimed.len(), 4);
+    upload_job::pause_jobs(&tmp.conn, Some(std::slice::from_ref(&claimed[3].id)))
+        .await
+        .unwrap();
+
+    let record = finish_job(&tmp.conn, &claimed[0], Ok(()))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(record["id"], json!(photo_id));
+    assert_eq!(record["name"], json!("a.jpg"));
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            &format!(
+                "SELECT COUNT(*) FROM photo_albums WHERE photo_id = '{}' AND album_id = 'album-1'",
+                photo_id
+            )
+        )
+        .await,
+        1
+    );
+
+    let record = finish_job(&tmp.conn, &claimed[1], Ok(()))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(record["id"], json!("file-1"));
+    assert_eq!(record["key"], json!("drive/doc.pdf"));
+    assert_eq!(record["parentId"], json!("dir"));
+
+    // meta 缺少 data：对象已上传但记录无法写入
+    assert!(finish_job(&tmp.conn, &claimed[2], Ok(()))
+        .await
+        .unwrap()
+        .is_none());
+    let bad = upload_job::get_job(&tmp.conn, &claimed[2].id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(bad.state, JobState::Failed);
+    assert!(bad.last_error.unwrap().contains("Invalid upload record"));
+
+    assert!(finish_job(&tmp.conn, &claimed[3], Ok(()))
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(query_i64(&tmp.conn, "SELECT COUNT(*) FROM photos").await, 1);
+    assert_eq!(
+        query_i64(&tmp.conn, "SELECT COUNT(*) FROM drive_files").await,
+        1
+    );
+    assert_eq!(
+        query_i64(
+            &tmp.conn,
+            "SELECT COUNT(*) FROM upload_jobs WHERE state = 'done'"
+        )
+        .await,
+        2
+    );
+
+    tmp.cleanup();
+}
+
+/// 指数退避与可重试错误分类
+#[test]
+fn test_retry_policy() {
+    let delays: Vec<u64> = (1..=MAX_ATTEMPTS).map(backoff_seconds).collect();
+    assert_eq!(delays, vec![2, 4, 8, 16, 32]);
+    assert_eq!(backoff_seconds(30), 300);
+
+    assert!(is_retryable(&AppError::network("timeout")));
+    assert!(is_retryable(&AppError::s3_status(503, "slow down")));
+    assert!(is_retryable(&AppError::s3_status(429, "too many")));
+    assert!(is_retryable(&AppError::checksum_mismatch("bad digest")));
+    assert!(!is_retryable(&AppError::s3_status(403, "forbidden")));
+    assert!(!is_retryable(&AppError::io("missing file")));
+    assert!(!is_retryable(&AppError::validation("bad config")));
+}